@@ -0,0 +1,47 @@
+/*
+ * C interface of the superdo sudoku solver.
+ *
+ * Build the library with
+ *   cargo rustc --release --lib --features ffi --crate-type cdylib
+ * (or --crate-type staticlib) and link against libsuperdo.
+ *
+ * Puzzles are NUL-terminated strings of 81 digits, 0 or '.' for an empty cell.
+ * Grids are written to `out` as 81 digits followed by a NUL, so `len` must be
+ * at least 82.
+ */
+
+#ifndef SUPERDO_H
+#define SUPERDO_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define SUPERDO_OK 0
+/* Null pointer, malformed puzzle, conflicting givens or unknown difficulty */
+#define SUPERDO_INVALID (-1)
+/* The puzzle has no solution, or no puzzle could be generated */
+#define SUPERDO_NO_SOLUTION (-2)
+/* `out` is null or shorter than 82 bytes */
+#define SUPERDO_BUFFER_TOO_SMALL (-3)
+
+/* Solve a standard sudoku and write its first solution to `out`. */
+int superdo_solve(const char *puzzle, char *out, size_t len);
+
+/* Count the solutions of a standard sudoku, stopping at `cap`.
+ * Returns SUPERDO_INVALID for an invalid puzzle. */
+long long superdo_count(const char *puzzle, size_t cap);
+
+/* Generate a standard sudoku with a unique solution and write it to `out`.
+ * `difficulty` ranges from 0 (easy) to 4 (evil); the same seed always gives
+ * the same puzzle. */
+int superdo_generate(int difficulty, uint64_t seed, char *out, size_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SUPERDO_H */
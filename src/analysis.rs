@@ -0,0 +1,208 @@
+//! 题目与解的结构分析
+
+use std::sync::Arc;
+
+use crate::board::{Grid, SudokuBoard};
+use crate::constraint::Cell;
+use crate::solver::solutions;
+use crate::variant::Variant;
+
+/// 寻找不可避免集时每组数字最多列出的解的个数
+const UNAVOIDABLE_CAP: usize = 200;
+
+/// 盘面的几何对称
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridSymmetry {
+    /// 绕中心旋转 180 度
+    Rotate180,
+    /// 绕中心旋转 90 度
+    Rotate90,
+    /// 左右镜像
+    Mirror,
+    /// 上下翻转
+    Flip,
+    /// 沿主对角线翻转
+    Diagonal,
+    /// 沿副对角线翻转
+    AntiDiagonal,
+}
+
+impl GridSymmetry {
+    /// 所有几何对称
+    pub const ALL: [GridSymmetry; 6] = [
+        GridSymmetry::Rotate180,
+        GridSymmetry::Rotate90,
+        GridSymmetry::Mirror,
+        GridSymmetry::Flip,
+        GridSymmetry::Diagonal,
+        GridSymmetry::AntiDiagonal,
+    ];
+
+    /// 输出时使用的名称
+    pub fn name(self) -> &'static str {
+        match self {
+            GridSymmetry::Rotate180 => "rotate-180",
+            GridSymmetry::Rotate90 => "rotate-90",
+            GridSymmetry::Mirror => "mirror",
+            GridSymmetry::Flip => "flip",
+            GridSymmetry::Diagonal => "diagonal",
+            GridSymmetry::AntiDiagonal => "anti-diagonal",
+        }
+    }
+
+    /// 位置在变换后的位置
+    pub fn map(self, (row, col): Cell) -> Cell {
+        match self {
+            GridSymmetry::Rotate180 => (8 - row, 8 - col),
+            GridSymmetry::Rotate90 => (col, 8 - row),
+            GridSymmetry::Mirror => (row, 8 - col),
+            GridSymmetry::Flip => (8 - row, col),
+            GridSymmetry::Diagonal => (col, row),
+            GridSymmetry::AntiDiagonal => (8 - col, 8 - row),
+        }
+    }
+}
+
+/// 给定数字的位置具有的对称
+pub fn pattern_symmetries(grid: &Grid) -> Vec<GridSymmetry> {
+    GridSymmetry::ALL
+        .into_iter()
+        .filter(|sym| {
+            (0..81).all(|i| {
+                let (row, col) = (i / 9, i % 9);
+                let (r, c) = sym.map((row, col));
+                (grid[row][col] == 0) == (grid[r][c] == 0)
+            })
+        })
+        .collect()
+}
+
+/// 盘面在重新编号数字后具有的对称：存在数字的一一对应，使变换后的盘面与原盘面相同
+pub fn grid_symmetries(grid: &Grid) -> Vec<GridSymmetry> {
+    pattern_symmetries(grid)
+        .into_iter()
+        .filter(|sym| {
+            let mut digits = [0; 10];
+            let mut used = [false; 10];
+            (0..81).all(|i| {
+                let (row, col) = (i / 9, i % 9);
+                let (r, c) = sym.map((row, col));
+                let (from, to) = (grid[row][col] as usize, grid[r][c] as usize);
+                if from == 0 {
+                    return true;
+                }
+                if digits[from] == 0 {
+                    if used[to] {
+                        return false;
+                    }
+                    digits[from] = to;
+                    used[to] = true;
+                }
+                digits[from] == to
+            })
+        })
+        .collect()
+}
+
+/// 解中不超过 max_size 个位置的极小不可避免集，按大小排序。
+///
+/// 不可避免集中的位置全部空着而其余位置都给出时，题目有多个解，因此唯一解的题目在每个不可避免集中
+/// 至少有一个给定数字。依次空出两种或三种数字所在的位置，其余的解与原解不同的位置即为不可避免集
+pub fn unavoidable_sets(solution: &Grid, max_size: usize) -> Vec<Vec<Cell>> {
+    let variant = Arc::new(Variant::standard());
+    let mut found: Vec<Vec<Cell>> = vec![];
+    let mut subsets: Vec<Vec<u32>> = vec![];
+    for a in 1..10 {
+        for b in a + 1..10 {
+            subsets.push(vec![a, b]);
+            for c in b + 1..10 {
+                subsets.push(vec![a, b, c]);
+            }
+        }
+    }
+    for digits in subsets {
+        let masked = solution.map(|row| row.map(|d| if digits.contains(&d) { 0 } else { d }));
+        let board = SudokuBoard::new_with(&masked, variant.clone());
+        for other in solutions(&board, UNAVOIDABLE_CAP) {
+            let cells: Vec<Cell> = (0..81)
+                .map(|i| (i / 9, i % 9))
+                .filter(|&(r, c)| other[r][c] != solution[r][c])
+                .collect();
+            if !cells.is_empty() && cells.len() <= max_size && !found.contains(&cells) {
+                found.push(cells);
+            }
+        }
+    }
+    // 只保留极小的集合
+    found.sort_by_key(|set| set.len());
+    let mut minimal: Vec<Vec<Cell>> = vec![];
+    for set in found {
+        if !minimal
+            .iter()
+            .any(|m| m.iter().all(|cell| set.contains(cell)))
+        {
+            minimal.push(set);
+        }
+    }
+    minimal
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{grid_symmetries, pattern_symmetries, unavoidable_sets, GridSymmetry};
+    use crate::board::{parse_grid, SudokuBoard};
+    use crate::solver::count_solutions;
+    use crate::variant::Variant;
+
+    #[test]
+    fn test_symmetries() {
+        let puzzle = parse_grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        )
+        .unwrap();
+        assert_eq!(pattern_symmetries(&puzzle), [GridSymmetry::Rotate180]);
+        assert!(grid_symmetries(&puzzle).is_empty());
+
+        let mut grid = [[0; 9]; 9];
+        grid[0][0] = 1;
+        grid[8][8] = 9;
+        grid[4][4] = 5;
+        assert_eq!(
+            pattern_symmetries(&grid),
+            [
+                GridSymmetry::Rotate180,
+                GridSymmetry::Diagonal,
+                GridSymmetry::AntiDiagonal
+            ]
+        );
+        // 数字 1 和 9 互换即可对称
+        assert_eq!(grid_symmetries(&grid), pattern_symmetries(&grid));
+        // 中心的 1 只允许 1 对应自身
+        grid[4][4] = 1;
+        assert_eq!(grid_symmetries(&grid), [GridSymmetry::Diagonal]);
+        assert_eq!(GridSymmetry::Rotate90.map((0, 0)), (0, 8));
+    }
+
+    #[test]
+    fn test_unavoidable_sets() {
+        let solution = parse_grid(
+            "534678912672195348198342567859761423426853791713924856961537284287419635345286179",
+        )
+        .unwrap();
+        let sets = unavoidable_sets(&solution, 6);
+        assert!(!sets.is_empty());
+        // 每个集合中的数字可以互换得到另一个解
+        for set in &sets {
+            assert!(set.len() >= 4 && set.len() <= 6);
+            let mut puzzle = solution;
+            for &(r, c) in set {
+                puzzle[r][c] = 0;
+            }
+            let board = SudokuBoard::new_with(&puzzle, Arc::new(Variant::standard()));
+            assert_eq!(count_solutions(&board, 2), 2);
+        }
+        assert!(sets.windows(2).all(|w| w[0].len() <= w[1].len()));
+    }
+}
@@ -0,0 +1,112 @@
+//! 压缩的题库
+//!
+//! 按开头的魔数识别 gzip 和 zstd 压缩的输入，边读边解压，大的题库不必先解压到磁盘。
+//! 解压使用 flate2 和 zstd，需要 `compression` 功能（默认开启）；未开启时压缩的输入报错。
+
+use std::io::{self, BufRead};
+
+/// gzip 的魔数
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// zstd 帧的魔数
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// 以 head 开头的数据是否为 gzip 或 zstd 压缩
+pub fn is_compressed(head: &[u8]) -> bool {
+    head.starts_with(&GZIP_MAGIC) || head.starts_with(&ZSTD_MAGIC)
+}
+
+/// 输入为 gzip 或 zstd 压缩时返回解压后的输入，否则原样返回。gzip 的多个成员首尾相接时依次解压
+#[cfg(feature = "compression")]
+pub fn decompressed<'a>(mut input: Box<dyn BufRead + 'a>) -> io::Result<Box<dyn BufRead + 'a>> {
+    use std::io::BufReader;
+
+    let head = input.fill_buf()?;
+    if head.starts_with(&GZIP_MAGIC) {
+        let decoder = flate2::bufread::MultiGzDecoder::new(input);
+        Ok(Box::new(BufReader::new(decoder)))
+    } else if head.starts_with(&ZSTD_MAGIC) {
+        let decoder = zstd::stream::read::Decoder::with_buffer(input)?;
+        Ok(Box::new(BufReader::new(decoder)))
+    } else {
+        Ok(input)
+    }
+}
+
+/// 输入为 gzip 或 zstd 压缩时报错，否则原样返回
+#[cfg(not(feature = "compression"))]
+pub fn decompressed<'a>(mut input: Box<dyn BufRead + 'a>) -> io::Result<Box<dyn BufRead + 'a>> {
+    if is_compressed(input.fill_buf()?) {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "compressed input needs the `compression` feature",
+        ));
+    }
+    Ok(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::decompressed;
+
+    fn read(data: &[u8]) -> Result<String, String> {
+        let mut text = String::new();
+        decompressed(Box::new(data))
+            .and_then(|mut input| input.read_to_string(&mut text))
+            .map_err(|e| e.to_string())?;
+        Ok(text)
+    }
+
+    #[test]
+    fn test_plain() {
+        assert_eq!(read(b"123\n").unwrap(), "123\n");
+        assert_eq!(read(b"").unwrap(), "");
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_decompressed() {
+        use std::io::{BufRead, Write};
+
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+
+        let member = |data: &[u8]| {
+            let mut encoder = GzEncoder::new(vec![], Compression::default());
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        };
+        let mut gzip = member(b"first\n");
+        gzip.extend(member(b"second\n"));
+        assert_eq!(read(&gzip).unwrap(), "first\nsecond\n");
+        let lines: Vec<String> = decompressed(Box::new(&gzip[..]))
+            .unwrap()
+            .lines()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(lines, ["first", "second"]);
+
+        // 校验和不符
+        let mut corrupt = member(b"first\n");
+        let crc = corrupt.len() - 8;
+        corrupt[crc] ^= 1;
+        assert!(read(&corrupt).is_err());
+        assert!(read(&gzip[..20]).is_err());
+        // printf 'hello\n' | zstd
+        let zstd = [
+            0x28, 0xb5, 0x2f, 0xfd, 0x04, 0x58, 0x31, 0x00, 0x00, 0x68, 0x65, 0x6c, 0x6c, 0x6f,
+            0x0a, 0x53, 0x88, 0xbd, 0x91,
+        ];
+        assert_eq!(read(&zstd).unwrap(), "hello\n");
+    }
+
+    #[cfg(not(feature = "compression"))]
+    #[test]
+    fn test_unsupported() {
+        assert!(read(&[0x1f, 0x8b, 8, 0])
+            .unwrap_err()
+            .contains("compression"));
+    }
+}
@@ -0,0 +1,195 @@
+//! HTTP 服务的令牌认证
+//!
+//! 设置了令牌时，`/v1/` 下的接口要求请求头 `Authorization: Bearer <token>`。每个令牌可以限制
+//! 每分钟的请求数，按令牌桶计算：桶满时可以连续发出这么多请求，之后按平均速率恢复。
+//! 令牌来自配置文件和环境变量 [`TOKENS_ENV`]，格式见 [`ApiToken::parse_list`]。
+
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::json::Json;
+use crate::server::Response;
+
+/// 给出令牌的环境变量
+pub const TOKENS_ENV: &str = "SUPERDO_TOKENS";
+
+/// 一个令牌
+#[derive(Clone, PartialEq, Eq)]
+pub struct ApiToken {
+    pub token: String,
+    /// 每分钟的请求数上限，None 为不限
+    pub per_minute: Option<u32>,
+}
+
+// 不在日志中显示令牌本身
+impl std::fmt::Debug for ApiToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiToken")
+            .field("token", &"***")
+            .field("per_minute", &self.per_minute)
+            .finish()
+    }
+}
+
+impl ApiToken {
+    /// 解析 `token` 或 `token:N`，N 为每分钟的请求数上限
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (token, per_minute) = match text.rsplit_once(':') {
+            Some((token, n)) => match n.parse::<u32>() {
+                Ok(n) if n >= 1 => (token, Some(n)),
+                _ => return Err(format!("invalid request limit `{}`", n)),
+            },
+            None => (text, None),
+        };
+        Self::new(token, per_minute)
+    }
+
+    /// 解析逗号分隔的多个令牌，如 `abc,def:60`
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, String> {
+        text.split(',')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(Self::parse)
+            .collect()
+    }
+
+    /// 从 JSON 形式读取：字符串，或 `{"token": "...", "per_minute": N}`
+    pub fn from_json(json: &Json) -> Result<Self, String> {
+        if let Some(token) = json.as_str() {
+            return Self::new(token, None);
+        }
+        let token = json
+            .get("token")
+            .and_then(Json::as_str)
+            .ok_or("a token must be a string or have a string `token`")?;
+        let per_minute = match json.get("per_minute") {
+            None => None,
+            Some(n) => match n.as_u32() {
+                Some(n) if n >= 1 => Some(n),
+                _ => return Err("per_minute must be a positive integer".to_string()),
+            },
+        };
+        Self::new(token, per_minute)
+    }
+
+    fn new(token: &str, per_minute: Option<u32>) -> Result<Self, String> {
+        if token.is_empty() || token.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err("a token must be non-empty without spaces".to_string());
+        }
+        Ok(Self {
+            token: token.to_string(),
+            per_minute,
+        })
+    }
+}
+
+/// 检查请求的令牌和速率
+#[derive(Debug)]
+pub(crate) struct Auth {
+    tokens: Vec<ApiToken>,
+    /// 每个令牌桶中剩余的请求数和上次更新的时间
+    buckets: Mutex<Vec<(f64, Instant)>>,
+}
+
+impl Auth {
+    pub(crate) fn new(tokens: Vec<ApiToken>) -> Self {
+        let now = Instant::now();
+        let buckets = tokens
+            .iter()
+            .map(|t| (t.per_minute.unwrap_or(0) as f64, now))
+            .collect();
+        Self {
+            tokens,
+            buckets: Mutex::new(buckets),
+        }
+    }
+
+    /// 检查 `Authorization` 请求头。没有设置令牌时都允许；令牌缺失或不对时返回 401，
+    /// 超出速率时返回 429
+    pub(crate) fn check(&self, authorization: Option<&str>) -> Result<(), Response> {
+        if self.tokens.is_empty() {
+            return Ok(());
+        }
+        let given = authorization
+            .and_then(|value| value.split_once(' '))
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+            .map(|(_, token)| token.trim());
+        let found = given.and_then(|given| {
+            self.tokens
+                .iter()
+                .position(|t| same(t.token.as_bytes(), given.as_bytes()))
+        });
+        let Some(i) = found else {
+            return Err(Response::error(
+                401,
+                "unauthorized",
+                "a valid bearer token is required",
+            ));
+        };
+        let Some(per_minute) = self.tokens[i].per_minute else {
+            return Ok(());
+        };
+        let mut buckets = self.buckets.lock().unwrap();
+        let (left, last) = &mut buckets[i];
+        let now = Instant::now();
+        let capacity = per_minute as f64;
+        *left = (*left + now.duration_since(*last).as_secs_f64() * capacity / 60.0).min(capacity);
+        *last = now;
+        if *left < 1.0 {
+            let message = format!("more than {} requests per minute", per_minute);
+            return Err(Response::error(429, "rate_limited", &message));
+        }
+        *left -= 1.0;
+        Ok(())
+    }
+}
+
+/// 比较令牌，用时与相同的前缀长度无关
+fn same(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApiToken, Auth};
+    use crate::json::Json;
+
+    #[test]
+    fn test_parse() {
+        let tokens = ApiToken::parse_list("abc, def:2,").unwrap();
+        assert_eq!(
+            tokens,
+            [
+                ApiToken::parse("abc").unwrap(),
+                ApiToken {
+                    token: "def".to_string(),
+                    per_minute: Some(2)
+                }
+            ]
+        );
+        assert!(ApiToken::parse("def:0").is_err());
+        assert!(ApiToken::parse(":5").is_err());
+        let json = Json::parse(r#"{"token": "def", "per_minute": 2}"#).unwrap();
+        assert_eq!(ApiToken::from_json(&json).unwrap(), tokens[1]);
+        assert!(ApiToken::from_json(&Json::parse(r#"{"per_minute": 2}"#).unwrap()).is_err());
+        assert!(!format!("{:?}", tokens[0]).contains("abc"));
+    }
+
+    #[test]
+    fn test_check() {
+        assert!(Auth::new(vec![]).check(None).is_ok());
+        let auth = Auth::new(ApiToken::parse_list("abc,def:2").unwrap());
+        let status = |header: Option<&str>| auth.check(header).err().map(|r| r.status);
+        assert_eq!(status(None), Some(401));
+        assert_eq!(status(Some("Bearer abd")), Some(401));
+        assert_eq!(status(Some("Basic abc")), Some(401));
+        for _ in 0..5 {
+            assert_eq!(status(Some("Bearer abc")), None);
+        }
+        assert_eq!(status(Some("bearer def")), None);
+        assert_eq!(status(Some("Bearer def")), None);
+        assert_eq!(status(Some("Bearer def")), Some(429));
+        // 其他令牌不受影响
+        assert_eq!(status(Some("Bearer abc")), None);
+    }
+}
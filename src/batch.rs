@@ -0,0 +1,274 @@
+//! 批量求解
+//!
+//! [`solve_all`] 把多道题目交给同一个 rayon 线程池求解，结果经通道逐个返回，
+//! 嵌入本库的服务不必自己安排线程。
+
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rayon::iter::{ParallelBridge, ParallelIterator};
+use rayon::ThreadPool;
+
+use crate::board::{Grid, SudokuBoard};
+use crate::cache::{CacheKey, SolveCache};
+use crate::solver::{SolveStats, SolverKind};
+use crate::variant::Variant;
+
+/// 结果的返回顺序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Order {
+    /// 与输入顺序相同，先完成的结果暂存到前面的题目完成为止
+    #[default]
+    Input,
+    /// 按完成的先后
+    Completed,
+}
+
+/// 批量求解的选项
+#[derive(Clone)]
+pub struct BatchOptions {
+    /// 所有题目共用的规则
+    pub variant: Arc<Variant>,
+    /// 求解算法
+    pub solver: SolverKind,
+    /// 每道题目最多找多少个解，默认 2 以判断是否唯一
+    pub limit: usize,
+    /// 结果的返回顺序
+    pub order: Order,
+    /// 使用的线程池，未给出时使用全局线程池
+    pub pool: Option<Arc<ThreadPool>>,
+    /// 求解结果的缓存，重复或等价的题目不再求解。只用于标准数独，可以在多次批量求解间共用
+    pub cache: Option<Arc<SolveCache>>,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            variant: Arc::new(Variant::standard()),
+            solver: SolverKind::default(),
+            limit: 2,
+            order: Order::default(),
+            pool: None,
+            cache: None,
+        }
+    }
+}
+
+/// 一道题目的求解结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchResult {
+    /// 题目在输入中的序号，从 0 开始
+    pub index: usize,
+    pub givens: Grid,
+    /// 找到的解，至多 limit 个，按找到的先后
+    pub solutions: Vec<Grid>,
+    /// 求解的统计，结果来自缓存时为空
+    pub stats: SolveStats,
+    /// 结果来自缓存
+    pub cached: bool,
+    /// 求解用时
+    pub elapsed: Duration,
+}
+
+impl BatchResult {
+    /// 恰好有一个解（limit 至少为 2 时才有意义）
+    pub fn is_unique(&self) -> bool {
+        self.solutions.len() == 1
+    }
+}
+
+/// 求解结果的迭代器。提前丢弃时，尚未开始的题目不再求解
+pub struct BatchResults {
+    rx: Receiver<BatchResult>,
+    order: Order,
+    /// 按输入顺序返回时，先完成的结果
+    pending: BTreeMap<usize, BatchResult>,
+    next: usize,
+}
+
+impl Iterator for BatchResults {
+    type Item = BatchResult;
+
+    fn next(&mut self) -> Option<BatchResult> {
+        if self.order == Order::Completed {
+            return self.rx.recv().ok();
+        }
+        loop {
+            if let Some(result) = self.pending.remove(&self.next) {
+                self.next += 1;
+                return Some(result);
+            }
+            let result = self.rx.recv().ok()?;
+            self.pending.insert(result.index, result);
+        }
+    }
+}
+
+/// 在后台求解 puzzles 中的所有题目，立即返回结果的迭代器。题目按需从 puzzles 中取出
+pub fn solve_all<I>(puzzles: I, options: BatchOptions) -> BatchResults
+where
+    I: IntoIterator<Item = Grid>,
+    I::IntoIter: Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    let order = options.order;
+    let puzzles = puzzles.into_iter();
+    thread::spawn(move || {
+        let run = || {
+            // 接收方已丢弃时发送失败，以错误结束遍历
+            let _ = puzzles
+                .enumerate()
+                .par_bridge()
+                .try_for_each_with(tx, |tx, (index, givens)| {
+                    tx.send(solve_one(index, givens, &options)).map_err(|_| ())
+                });
+        };
+        match &options.pool {
+            Some(pool) => pool.install(run),
+            None => run(),
+        }
+    });
+    BatchResults {
+        rx,
+        order,
+        pending: BTreeMap::new(),
+        next: 0,
+    }
+}
+
+fn solve_one(index: usize, givens: Grid, options: &BatchOptions) -> BatchResult {
+    let start = Instant::now();
+    let solutions = Mutex::new(vec![]);
+    let limit = options.limit;
+    let cache = options
+        .cache
+        .as_ref()
+        .filter(|_| limit > 0 && options.variant.is_standard())
+        .and_then(|cache| Some((cache, CacheKey::new(&givens)?)));
+    if let Some(found) = cache
+        .as_ref()
+        .and_then(|(cache, key)| cache.get(key, limit))
+    {
+        return BatchResult {
+            index,
+            givens,
+            solutions: found,
+            stats: SolveStats::default(),
+            cached: true,
+            elapsed: start.elapsed(),
+        };
+    }
+    let stats = if limit == 0 {
+        SolveStats::default()
+    } else {
+        let board = SudokuBoard::new_with(&givens, options.variant.clone());
+        options.solver.solver().solve(board, &|board| {
+            let mut solutions = solutions.lock().unwrap();
+            if solutions.len() < limit {
+                solutions.push(board.grid());
+            }
+            solutions.len() < limit
+        })
+    };
+    let solutions = solutions.into_inner().unwrap();
+    if let Some((cache, key)) = cache {
+        cache.insert(&key, &solutions, solutions.len() < limit);
+    }
+    BatchResult {
+        index,
+        givens,
+        solutions,
+        stats,
+        cached: false,
+        elapsed: start.elapsed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{solve_all, BatchOptions, Order};
+    use crate::board::{grid_line, parse_grid};
+    use crate::cache::SolveCache;
+    use crate::solver::SolverKind;
+
+    const PUZZLES: [&str; 3] = [
+        "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        // 去掉 r1c1，两个解
+        "000070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        // 第一行有两个 5，无解
+        "550070000600195000098000060800060003400803001700020006060000280000419005000080079",
+    ];
+
+    #[test]
+    fn test_solve_all() {
+        let puzzles: Vec<_> = PUZZLES.iter().map(|p| parse_grid(p).unwrap()).collect();
+        let results: Vec<_> = solve_all(puzzles.clone(), BatchOptions::default()).collect();
+        assert_eq!(
+            results.iter().map(|r| r.index).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+        assert!(results[0].is_unique());
+        assert_eq!(
+            grid_line(&results[0].solutions[0]),
+            "534678912672195348198342567859761423426853791713924856961537284287419635345286179"
+        );
+        assert_eq!(results[0].givens, puzzles[0]);
+        assert!(results[2].solutions.is_empty());
+
+        // 自带线程池，按完成先后返回，每道题目找全部至多 10 个解
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        let options = BatchOptions {
+            solver: SolverKind::Backtrack,
+            limit: 10,
+            order: Order::Completed,
+            pool: Some(Arc::new(pool)),
+            ..Default::default()
+        };
+        let repeated = puzzles.clone().into_iter().cycle().take(12);
+        let mut results: Vec<_> = solve_all(repeated, options).collect();
+        results.sort_by_key(|r| r.index);
+        assert_eq!(results.len(), 12);
+        for (i, result) in results.iter().enumerate() {
+            assert_eq!(result.index, i);
+            assert_eq!(result.solutions.len(), [1, 2, 0][i % 3], "{}", i);
+        }
+
+        // 共用缓存，重复的题目不再求解
+        let cache = Arc::new(SolveCache::new(8));
+        let options = BatchOptions {
+            order: Order::Completed,
+            pool: Some(Arc::new(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(1)
+                    .build()
+                    .unwrap(),
+            )),
+            cache: Some(cache.clone()),
+            ..Default::default()
+        };
+        let mut results: Vec<_> = solve_all(puzzles.clone(), options.clone()).collect();
+        results.sort_by_key(|r| r.index);
+        assert!(results.iter().all(|r| !r.cached));
+        let again: Vec<_> = solve_all(puzzles, options).collect();
+        assert!(again.iter().all(|r| r.cached));
+        assert_eq!(cache.len(), 3);
+        for result in &again {
+            assert_eq!(result.solutions, results[result.index].solutions);
+        }
+
+        // 提前丢弃迭代器
+        let mut results = solve_all(
+            vec![parse_grid(PUZZLES[0]).unwrap(); 100],
+            Default::default(),
+        );
+        assert!(results.next().unwrap().is_unique());
+    }
+}
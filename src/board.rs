@@ -0,0 +1,918 @@
+//! 数独棋盘
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::constraint::Propagation;
+use crate::json::Json;
+use crate::variant::{Parity, Variant};
+use crate::EOL;
+
+/// 已填数字的棋盘，0 表示空位
+pub type Grid = [[u32; 9]; 9];
+
+/// 81 个数字按行连成一行，0 表示空位
+pub fn grid_line(grid: &Grid) -> String {
+    grid.iter().flatten().map(|d| d.to_string()).collect()
+}
+
+/// 题目集的文件格式
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One puzzle per line, 81 digits with 0 for an empty cell
+    Sdm,
+    /// 9 lines of 9 characters with `.` for an empty cell, puzzles separated by a blank line
+    Sdk,
+    /// One SudokuPad link per line, carrying the variant constraints as f-puzzles data, to share
+    /// a puzzle with the online solving community (f-puzzles reads the same data)
+    CtcLink,
+}
+
+/// 按格式输出盘面，sdk 格式不含末尾的空行；链接只含标准规则，带变体规则的链接用
+/// [`crate::fpuzzles::share_link`]
+pub fn export_grid(grid: &Grid, format: ExportFormat) -> String {
+    match format {
+        ExportFormat::CtcLink => crate::fpuzzles::share_link(grid, &Variant::standard()),
+        ExportFormat::Sdm => grid_line(grid),
+        ExportFormat::Sdk => grid
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|d| char::from_digit(*d, 10).filter(|_| *d != 0).unwrap_or('.'))
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join(EOL),
+    }
+}
+
+/// FNV-1a 的初始值
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// FNV-1a 的乘数
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// 盘面的 64 位哈希（按位置依次对数字做 FNV-1a），与平台和运行次数无关，可用于去重或作为缓存的键
+pub fn grid_hash(grid: &Grid) -> u64 {
+    grid.iter()
+        .flatten()
+        .fold(FNV_OFFSET, |h, d| (h ^ *d as u64).wrapping_mul(FNV_PRIME))
+}
+
+/// Unicode 中各组十进制数字 0 的码位：ASCII、阿拉伯-印度、天城文等文字的数字和全角数字
+const DIGIT_ZEROS: [u32; 18] = [
+    0x30, 0x660, 0x6f0, 0x7c0, 0x966, 0x9e6, 0xa66, 0xae6, 0xb66, 0xbe6, 0xc66, 0xce6, 0xd66,
+    0xe50, 0xed0, 0xf20, 0x1040, 0xff10,
+];
+
+/// 数学字母数字符号中的粗体、双线等五组数字
+const MATH_DIGITS: std::ops::RangeInclusive<u32> = 0x1d7ce..=0x1d7ff;
+
+/// 输入中的数字：除 ASCII 数字外，也接受从中日文网页粘贴来的全角数字（`０`-`９`）和其他文字的十进制数字
+pub fn digit_value(c: char) -> Option<u32> {
+    let code = c as u32;
+    if MATH_DIGITS.contains(&code) {
+        return Some((code - MATH_DIGITS.start()) % 10);
+    }
+    DIGIT_ZEROS
+        .iter()
+        .find(|&&zero| (zero..zero + 10).contains(&code))
+        .map(|zero| code - zero)
+}
+
+/// 输入中表示空位的 `.`，也接受全角的 `．`
+pub fn is_blank(c: char) -> bool {
+    c == '.' || c == '．'
+}
+
+/// 字母数独（wordoku）中代替数字 1-9 的九个字母，如 `WORDSPLAY` 中 `W` 为 1、`Y` 为 9
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alphabet([char; 9]);
+
+impl Alphabet {
+    /// 九个互不相同的字符，不能是数字、空白、`.` 或 `#`；不区分大小写
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let letters: Vec<char> = s.chars().collect();
+        let Ok(letters) = <[char; 9]>::try_from(letters) else {
+            return Err(format!(
+                "an alphabet has 9 letters, `{}` has {}",
+                s,
+                s.chars().count()
+            ));
+        };
+        if let Some(c) = letters
+            .iter()
+            .find(|&&c| c.is_numeric() || c.is_whitespace() || is_blank(c) || c == '#')
+        {
+            return Err(format!("`{}` cannot stand for a digit", c));
+        }
+        for (i, a) in letters.iter().enumerate() {
+            if letters[..i]
+                .iter()
+                .any(|b| a.to_lowercase().eq(b.to_lowercase()))
+            {
+                return Err(format!("`{}` appears twice in the alphabet", a));
+            }
+        }
+        Ok(Self(letters))
+    }
+
+    /// 字母代表的数字，不是字母表中的字母时为 None
+    pub fn digit(&self, c: char) -> Option<u32> {
+        self.0
+            .iter()
+            .position(|l| l.to_lowercase().eq(c.to_lowercase()))
+            .map(|i| i as u32 + 1)
+    }
+
+    /// 把文本中的数字 1-9 换成对应的字母
+    pub fn spell(&self, text: &str) -> String {
+        text.chars()
+            .map(|c| match c.to_digit(10) {
+                Some(d @ 1..=9) => self.0[d as usize - 1],
+                _ => c,
+            })
+            .collect()
+    }
+}
+
+/// 解析 81 个数字组成的棋盘，`0` 或 `.` 表示空位，忽略空白字符
+pub fn parse_grid(s: &str) -> Result<Grid, String> {
+    let mut digits = vec![];
+    for c in s.chars().filter(|c| !c.is_whitespace()) {
+        match c {
+            c if is_blank(c) => digits.push(0),
+            c => digits
+                .push(digit_value(c).ok_or_else(|| format!("invalid character `{}` in grid", c))?),
+        }
+    }
+    if digits.len() != 81 {
+        return Err(format!("grid has {} cells instead of 81", digits.len()));
+    }
+    Ok(std::array::from_fn(|r| {
+        std::array::from_fn(|c| digits[r * 9 + c])
+    }))
+}
+
+/// 数独位置
+#[derive(Debug, Default, Clone)]
+pub struct SudokuPos {
+    /// 当前值，非 0 表示已有确定数字
+    pub val: u32,
+    /// 候选数字
+    pub digits: HashSet<u32>,
+}
+
+impl PartialEq<u32> for SudokuPos {
+    fn eq(&self, r: &u32) -> bool {
+        self.val == *r
+    }
+}
+
+impl SudokuPos {
+    /// 创建一个新的位置，数值非 0 时为已有确定数字，空位的候选数字受奇偶标记限制
+    pub fn new_with(val: u32, parity: Option<Parity>) -> Self {
+        let digits = if val == 0 {
+            (1..10)
+                .filter(|d| parity.is_none_or(|p| p.allows(*d)))
+                .collect()
+        } else {
+            HashSet::default()
+        };
+        Self { val, digits }
+    }
+}
+
+/// 推理停下时的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Deduction {
+    /// 填满且满足所有约束
+    Solved,
+    /// 推理不再有进展，需要猜测
+    Stuck,
+    /// 出现矛盾：某个空位没有候选、约束无法满足或填满后违反约束，此盘面无解
+    Contradiction,
+}
+
+/// 两个棋盘之间数值不同的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellChange {
+    /// 行，从 0 开始
+    pub row: usize,
+    /// 列，从 0 开始
+    pub col: usize,
+    /// 原棋盘的数值，0 表示空位
+    pub from: u32,
+    /// 另一棋盘的数值，0 表示空位
+    pub to: u32,
+}
+
+impl fmt::Display for CellChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "r{}c{}: {} -> {}",
+            self.row + 1,
+            self.col + 1,
+            self.from,
+            self.to
+        )
+    }
+}
+
+/// 按行列顺序列出两个已填数字的棋盘中数值不同的位置
+pub fn diff_grids(from: &Grid, to: &Grid) -> Vec<CellChange> {
+    let mut changes = vec![];
+    for row in 0..9 {
+        for col in 0..9 {
+            let (a, b) = (from[row][col], to[row][col]);
+            if a != b {
+                changes.push(CellChange {
+                    row,
+                    col,
+                    from: a,
+                    to: b,
+                });
+            }
+        }
+    }
+    changes
+}
+
+/// 数独棋盘， 9*9
+#[derive(Debug, Clone)]
+pub struct SudokuBoard {
+    board: Vec<Vec<SudokuPos>>,
+    /// 变体规则
+    variant: Arc<Variant>,
+    /// 通过 [`SudokuBoard::remove_candidate`] 排除的数字的位掩码，清除数字重新推导候选时保留
+    excluded: [[u16; 9]; 9],
+}
+
+impl SudokuBoard {
+    /// 创建一个空白的数独棋盘，约束及奇偶标记由 variant 决定
+    pub fn empty(variant: Arc<Variant>) -> Self {
+        let mut board = vec![];
+        for row in 0..9 {
+            let mut line = vec![];
+            for col in 0..9 {
+                line.push(SudokuPos::new_with(0, variant.parity[row][col]));
+            }
+            board.push(line);
+        }
+
+        Self {
+            board,
+            variant,
+            excluded: [[0; 9]; 9],
+        }
+    }
+
+    /// 创建一个已初始化的数独棋盘
+    pub fn new_with(board: &Grid, variant: Arc<Variant>) -> Self {
+        let mut b = Self::empty(variant);
+        for row in 0..9 {
+            for col in 0..9 {
+                b.set(board[row][col], row, col);
+            }
+        }
+        b
+    }
+
+    /// 变体规则
+    pub fn variant(&self) -> &Arc<Variant> {
+        &self.variant
+    }
+
+    /// 设置某个位置的数值
+    pub fn set(&mut self, val: u32, row: usize, col: usize) {
+        self.get_mut(row, col).val = val;
+        if val != 0 {
+            self.get_mut(row, col).digits.clear();
+
+            // 清理所有同组位置（行、列、3x3 小格及附加约束）
+            let variant = self.variant.clone();
+            for &(r, c) in variant.peers().of(row, col) {
+                self.get_mut(r, c).digits.remove(&val);
+            }
+        }
+    }
+
+    /// 获取某个位置
+    pub fn get(&self, row: usize, col: usize) -> &SudokuPos {
+        &self.board[row][col]
+    }
+
+    /// 获取某个位置，可变形式
+    pub fn get_mut(&mut self, row: usize, col: usize) -> &mut SudokuPos {
+        &mut self.board[row][col]
+    }
+
+    /// 已填数字
+    pub fn grid(&self) -> Grid {
+        let mut grid = [[0; 9]; 9];
+        for row in 0..9 {
+            for col in 0..9 {
+                grid[row][col] = self.board[row][col].val;
+            }
+        }
+        grid
+    }
+
+    /// 与另一棋盘相比数值不同的位置，只比较已填数字，不比较候选
+    pub fn diff(&self, other: &SudokuBoard) -> Vec<CellChange> {
+        diff_grids(&self.grid(), &other.grid())
+    }
+
+    /// 某个位置可能的数字，已确定位置只有当前值
+    pub fn candidates(&self, row: usize, col: usize) -> HashSet<u32> {
+        let pos = self.get(row, col);
+        if pos.val != 0 {
+            HashSet::from([pos.val])
+        } else {
+            pos.digits.clone()
+        }
+    }
+
+    /// 将空位的候选数字限制在 allowed 之内，返回是否有变化
+    pub fn restrict(&mut self, row: usize, col: usize, allowed: &HashSet<u32>) -> bool {
+        let pos = self.get_mut(row, col);
+        if pos.val != 0 {
+            return false;
+        }
+        let len = pos.digits.len();
+        pos.digits.retain(|d| allowed.contains(d));
+        pos.digits.len() != len
+    }
+
+    /// 填入数字的位置不能与已填的同组位置相同
+    fn conflict(&self, val: u32, row: usize, col: usize) -> Option<(usize, usize)> {
+        let parity = self.variant.parity[row][col];
+        if !(1..=9).contains(&val) || parity.is_some_and(|p| !p.allows(val)) {
+            return Some((row, col));
+        }
+        self.variant
+            .peers()
+            .of(row, col)
+            .iter()
+            .find(|&&(r, c)| self.get(r, c).val == val)
+            .copied()
+    }
+
+    /// 在空位填入数字并从同组位置的候选中排除，数字与规则或已填的同组位置冲突时报错
+    pub fn assign(&mut self, val: u32, row: usize, col: usize) -> Result<(), String> {
+        if self.get(row, col).val != 0 {
+            return Err(format!("r{}c{} is already filled", row + 1, col + 1));
+        }
+        if let Some((r, c)) = self.conflict(val, row, col) {
+            return Err(format!(
+                "{} at r{}c{} conflicts with r{}c{}",
+                val,
+                row + 1,
+                col + 1,
+                r + 1,
+                c + 1
+            ));
+        }
+        self.set(val, row, col);
+        Ok(())
+    }
+
+    /// 清除已填的数字，重新推导该位置及同组位置的候选数字，返回清除的数字，空位返回 None。
+    ///
+    /// 候选数字只由已填数字和 [`SudokuBoard::remove_candidate`] 的排除决定，
+    /// 变体约束的推理不会自动重做
+    pub fn unassign(&mut self, row: usize, col: usize) -> Option<u32> {
+        let val = self.get(row, col).val;
+        if val == 0 {
+            return None;
+        }
+        self.get_mut(row, col).val = 0;
+        let digits = (1..10).filter(|&d| self.allowed(d, row, col)).collect();
+        self.get_mut(row, col).digits = digits;
+        let variant = self.variant.clone();
+        for &(r, c) in variant.peers().of(row, col) {
+            if self.get(r, c).val == 0 && self.allowed(val, r, c) {
+                self.get_mut(r, c).digits.insert(val);
+            }
+        }
+        Some(val)
+    }
+
+    /// 空位是否可以有该候选数字：未被排除且不与已填的同组位置冲突
+    fn allowed(&self, val: u32, row: usize, col: usize) -> bool {
+        self.excluded[row][col] & 1 << val == 0 && self.conflict(val, row, col).is_none()
+    }
+
+    /// 为空位加回候选数字，返回是否有变化，数字与规则或已填的同组位置冲突时报错
+    pub fn add_candidate(&mut self, val: u32, row: usize, col: usize) -> Result<bool, String> {
+        if self.get(row, col).val != 0 {
+            return Err(format!("r{}c{} is already filled", row + 1, col + 1));
+        }
+        if let Some((r, c)) = self.conflict(val, row, col) {
+            return Err(format!(
+                "candidate {} at r{}c{} conflicts with r{}c{}",
+                val,
+                row + 1,
+                col + 1,
+                r + 1,
+                c + 1
+            ));
+        }
+        self.excluded[row][col] &= !(1 << val);
+        Ok(self.get_mut(row, col).digits.insert(val))
+    }
+
+    /// 排除空位的候选数字，返回是否有变化。排除会被记住，清除同组位置的数字时不会加回
+    pub fn remove_candidate(&mut self, val: u32, row: usize, col: usize) -> bool {
+        if self.get(row, col).val != 0 || !(1..=9).contains(&val) {
+            return false;
+        }
+        self.excluded[row][col] |= 1 << val;
+        self.get_mut(row, col).digits.remove(&val)
+    }
+
+    /// 是否有自由位置耗尽，此时无解
+    pub fn exhausted(&self) -> bool {
+        for row in &self.board {
+            for col in row {
+                if col.val == 0 && col.digits.is_empty() {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// 包含候选数字的盘面状态的 64 位哈希。空位按候选数字的位掩码计入，填满时与 [`grid_hash`] 相同
+    pub fn state_hash(&self) -> u64 {
+        self.board.iter().flatten().fold(FNV_OFFSET, |h, pos| {
+            let v = if pos.val != 0 {
+                pos.val as u64
+            } else {
+                // 最高位区分空位与已填数字
+                pos.digits.iter().fold(1 << 63, |m, d| m | 1 << d)
+            };
+            (h ^ v).wrapping_mul(FNV_PRIME)
+        })
+    }
+
+    /// 当前的候选数字状态，已填位置给出 value，空位给出排序后的 candidates
+    pub fn snapshot(&self) -> Json {
+        let mut cells = vec![];
+        for row in 0..9 {
+            for col in 0..9 {
+                let pos = self.get(row, col);
+                let mut items = vec![(
+                    "cell".to_string(),
+                    Json::from(format!("r{}c{}", row + 1, col + 1)),
+                )];
+                if pos.val != 0 {
+                    items.push(("value".to_string(), pos.val.into()));
+                } else {
+                    let mut digits: Vec<u32> = pos.digits.iter().cloned().collect();
+                    digits.sort();
+                    items.push(("candidates".to_string(), digits.into()));
+                }
+                cells.push(Json::Object(items));
+            }
+        }
+        Json::Array(cells)
+    }
+
+    /// 进行数独求解
+    pub fn solve(&mut self) -> bool {
+        self.deduce() == Deduction::Solved
+    }
+
+    /// 反复推理直到停下，返回停下的原因
+    pub fn deduce(&mut self) -> Deduction {
+        loop {
+            if let Some(result) = self.advance() {
+                return result;
+            }
+        }
+    }
+
+    /// 进行一轮推理：填入只剩一个候选的数字，再由各约束推理。
+    ///
+    /// 返回 `Some(是否解出)` 表示推理结束，`None` 表示本轮有变化，可继续下一轮
+    pub fn step(&mut self) -> Option<bool> {
+        self.advance().map(|result| result == Deduction::Solved)
+    }
+
+    /// 进行一轮推理，与 [`SudokuBoard::step`] 相同，但区分停滞与矛盾。
+    ///
+    /// 返回 `Some(停下的原因)` 表示推理结束，`None` 表示本轮有变化，可继续下一轮
+    pub fn advance(&mut self) -> Option<Deduction> {
+        let variant = self.variant.clone();
+        let mut has_empty = false; // 是否还有空白的位置
+        let mut has_changes = false; // 本次求解是否产生变化
+        for row in 0..9 {
+            for col in 0..9 {
+                let pos = self.get(row, col);
+                if pos.val == 0 {
+                    has_empty = true;
+                    // 失败
+                    if pos.digits.is_empty() {
+                        return Some(Deduction::Contradiction);
+                    }
+                    // 已经只剩下一个数字
+                    if pos.digits.len() == 1 {
+                        let val = *pos.digits.iter().next().unwrap();
+                        self.set(val, row, col);
+                        has_changes = true;
+                        continue;
+                    }
+                    // 记录下日志，当前位置剩下的可用数字
+                    log::debug!("({},{}) digits: {:?}", row, col, pos.digits);
+                }
+            }
+        }
+        // 各约束进行推理，行、列、3x3 小格中只有一个位置可用的数字在此填入
+        for constraint in variant.constraints() {
+            match constraint.propagate(self) {
+                Propagation::Unchanged => {}
+                Propagation::Changed => has_changes = true,
+                Propagation::Contradiction => return Some(Deduction::Contradiction),
+            }
+        }
+        // 已填满
+        if !has_empty {
+            return Some(match variant.check(&self.grid()) {
+                true => Deduction::Solved,
+                false => Deduction::Contradiction,
+            });
+        }
+        // 未填满，但是本次运行未有找到合适的方案
+        if !has_changes {
+            return Some(Deduction::Stuck);
+        }
+        None
+    }
+}
+
+impl fmt::Display for SudokuBoard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = self
+            .board
+            .iter()
+            .zip(self.variant.parity.iter())
+            .map(|(row, parity)| {
+                row.iter()
+                    .zip(parity.iter())
+                    .map(|(p, parity)| match parity {
+                        // 空位保留奇偶标记
+                        Some(parity) if p.val == 0 => parity.to_char().to_string(),
+                        _ => format!("{}", p.val),
+                    })
+                    .collect::<Vec<String>>()
+                    .join("")
+            })
+            .collect::<Vec<String>>()
+            .join(EOL);
+        write!(f, "{}", s)
+    }
+}
+
+impl PartialEq<Grid> for SudokuBoard {
+    fn eq(&self, a: &Grid) -> bool {
+        for row in 0..9 {
+            for col in 0..9 {
+                if self.get(row, col) != &a[row][col] {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    use super::{
+        diff_grids, digit_value, export_grid, grid_hash, grid_line, parse_grid, Alphabet,
+        CellChange, ExportFormat, SudokuBoard,
+    };
+    use crate::constraint::Cage;
+    use crate::json::Json;
+    use crate::variant::{ConstraintKind, Parity, Variant};
+
+    #[ctor::ctor]
+    fn init() {
+        env_logger::init();
+    }
+
+    #[test]
+    fn test_sudoku_1() {
+        let board = [
+            [0, 4, 0, 6, 1, 0, 9, 2, 5],
+            [0, 5, 1, 0, 0, 0, 7, 4, 6],
+            [9, 2, 6, 0, 0, 0, 8, 1, 3],
+            [0, 8, 0, 0, 5, 0, 0, 7, 1],
+            [0, 9, 0, 1, 0, 0, 0, 3, 2],
+            [0, 1, 3, 4, 7, 0, 5, 9, 8],
+            [0, 0, 0, 0, 0, 0, 1, 8, 9],
+            [1, 6, 2, 8, 0, 0, 3, 5, 7],
+            [8, 0, 9, 0, 0, 1, 2, 6, 4],
+        ];
+        let mut board = SudokuBoard::new_with(&board, Arc::default());
+        println!("{}", board);
+
+        let solved = board.solve();
+        println!("\n{}", board);
+        assert!(solved);
+
+        let board2 = [
+            [7_u32, 4, 8, 6, 1, 3, 9, 2, 5],
+            [3, 5, 1, 9, 2, 8, 7, 4, 6],
+            [9, 2, 6, 7, 4, 5, 8, 1, 3],
+            [2, 8, 4, 3, 5, 9, 6, 7, 1],
+            [5, 9, 7, 1, 8, 6, 4, 3, 2],
+            [6, 1, 3, 4, 7, 2, 5, 9, 8],
+            [4, 3, 5, 2, 6, 7, 1, 8, 9],
+            [1, 6, 2, 8, 9, 4, 3, 5, 7],
+            [8, 7, 9, 5, 3, 1, 2, 6, 4],
+        ];
+        assert!(board == board2);
+    }
+
+    #[test]
+    fn test_sudoku_2() {
+        let board = [
+            [0, 4, 6, 9, 0, 3, 0, 0, 0],
+            [0, 0, 3, 0, 5, 0, 0, 6, 0],
+            [9, 0, 0, 0, 0, 2, 0, 0, 3],
+            [0, 0, 5, 0, 0, 6, 0, 0, 0],
+            [8, 0, 0, 0, 0, 0, 0, 1, 0],
+            [0, 1, 0, 7, 8, 0, 2, 0, 0],
+            [0, 0, 0, 0, 0, 0, 0, 5, 0],
+            [0, 8, 1, 3, 0, 0, 0, 0, 7],
+            [0, 0, 0, 8, 0, 0, 1, 0, 4],
+        ];
+        let mut board = SudokuBoard::new_with(&board, Arc::default());
+        println!("{}", board);
+
+        let mut solved = board.solve();
+        assert!(!solved);
+
+        // 尝试选择
+        let mut board2 = board.clone();
+        'outer: for row in 0..9 {
+            for col in 0..9 {
+                let pos = board.get(row, col);
+                if pos.val == 0 {
+                    for n in &pos.digits {
+                        board2 = board.clone();
+                        board2.set(*n, row, col);
+                        solved = board2.solve();
+                        if solved {
+                            break 'outer;
+                        }
+                    }
+                }
+            }
+        }
+
+        assert!(solved);
+        let result = [
+            [1, 4, 6, 9, 7, 3, 5, 8, 2],
+            [7, 2, 3, 4, 5, 8, 9, 6, 1],
+            [9, 5, 8, 6, 1, 2, 4, 7, 3],
+            [3, 7, 5, 1, 2, 6, 8, 4, 9],
+            [8, 9, 2, 5, 3, 4, 7, 1, 6],
+            [6, 1, 4, 7, 8, 9, 2, 3, 5],
+            [4, 6, 7, 2, 9, 1, 3, 5, 8],
+            [2, 8, 1, 3, 4, 5, 6, 9, 7],
+            [5, 3, 9, 8, 6, 7, 1, 2, 4],
+        ];
+        assert!(board2 == result);
+        println!("{}", board2);
+    }
+
+    #[test]
+    fn test_sudoku_odd_even() {
+        let mut variant = Variant::with_constraints(&[ConstraintKind::OddEven]);
+        variant.parity[0][0] = Some(Parity::Odd);
+        variant.parity[0][1] = Some(Parity::Even);
+
+        // 候选数字按奇偶标记初始化，空位输出奇偶标记
+        let sudoku = SudokuBoard::new_with(&[[0; 9]; 9], Arc::new(variant));
+        assert_eq!(sudoku.get(0, 0).digits.len(), 5);
+        assert!(sudoku.get(0, 1).digits.iter().all(|d| d % 2 == 0));
+        assert!(sudoku.to_string().starts_with("oe0"));
+    }
+
+    #[test]
+    fn test_sudoku_killer() {
+        // 在已知解上挖去一格，由笼子补全；错误的笼子导致无解
+        let mut grid = [
+            [7_u32, 4, 8, 6, 1, 3, 9, 2, 5],
+            [3, 5, 1, 9, 2, 8, 7, 4, 6],
+            [9, 2, 6, 7, 4, 5, 8, 1, 3],
+            [2, 8, 4, 3, 5, 9, 6, 7, 1],
+            [5, 9, 7, 1, 8, 6, 4, 3, 2],
+            [6, 1, 3, 4, 7, 2, 5, 9, 8],
+            [4, 3, 5, 2, 6, 7, 1, 8, 9],
+            [1, 6, 2, 8, 9, 4, 3, 5, 7],
+            [8, 7, 9, 5, 3, 1, 2, 6, 4],
+        ];
+        grid[0][0] = 0;
+        let mut variant = Variant::standard();
+        variant.add("11=r1c1-r1c2".parse::<Cage>().unwrap());
+        let mut board = SudokuBoard::new_with(&grid, Arc::new(variant));
+        assert!(board.solve());
+        assert_eq!(board.get(0, 0).val, 7);
+
+        let mut variant = Variant::standard();
+        variant.add("12=r1c1-r1c2".parse::<Cage>().unwrap());
+        let mut board = SudokuBoard::new_with(&grid, Arc::new(variant));
+        assert!(!board.solve());
+    }
+
+    #[test]
+    fn test_parse_grid() {
+        let line =
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079";
+        let grid = parse_grid(line).unwrap();
+        assert_eq!(grid[0][0], 5);
+        assert_eq!(grid_line(&grid), line);
+        assert_eq!(parse_grid(&line.replace('0', ".")).unwrap(), grid);
+        assert!(parse_grid("123").is_err());
+        assert!(parse_grid(&line.replace('5', "x")).is_err());
+
+        // 全角数字和其他文字的数字
+        let wide: String = line
+            .chars()
+            .map(|c| match c {
+                '0' => '．',
+                c => char::from_u32(c as u32 - '0' as u32 + '０' as u32).unwrap(),
+            })
+            .collect();
+        assert_eq!(parse_grid(&wide).unwrap(), grid);
+        assert_eq!(digit_value('٧'), Some(7));
+        assert_eq!(digit_value('९'), Some(9));
+        assert_eq!(digit_value('𝟙'), Some(1));
+        assert_eq!(digit_value('a'), None);
+        assert_eq!(digit_value('①'), None);
+    }
+
+    #[test]
+    fn test_alphabet() {
+        let alphabet = Alphabet::parse("SUDOKUWRX").unwrap_err();
+        assert_eq!(alphabet, "`U` appears twice in the alphabet");
+        let alphabet = Alphabet::parse("SUDOKWRXY").unwrap();
+        assert_eq!(alphabet.digit('S'), Some(1));
+        assert_eq!(alphabet.digit('y'), Some(9));
+        assert_eq!(alphabet.digit('A'), None);
+        assert_eq!(alphabet.spell("1.9\n0"), "S.Y\n0");
+        assert!(Alphabet::parse("ABCDEFGH").is_err());
+        assert!(Alphabet::parse("ABCDEFGH1").is_err());
+        assert!(Alphabet::parse("ABCDEFGHa").is_err());
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut grid = [[0; 9]; 9];
+        grid[0][0] = 5;
+        let board = SudokuBoard::new_with(&grid, Arc::new(Variant::standard()));
+        let Json::Array(cells) = board.snapshot() else {
+            panic!("snapshot is not an array");
+        };
+        assert_eq!(cells.len(), 81);
+        assert_eq!(cells[0].to_string(), r#"{"cell":"r1c1","value":5}"#);
+        assert_eq!(
+            cells[1].to_string(),
+            r#"{"cell":"r1c2","candidates":[1,2,3,4,6,7,8,9]}"#
+        );
+    }
+
+    #[test]
+    fn test_candidates() {
+        let mut board = SudokuBoard::empty(Arc::new(Variant::standard()));
+        board.assign(5, 0, 0).unwrap();
+        assert!(!board.get(0, 8).digits.contains(&5));
+        assert!(!board.get(2, 2).digits.contains(&5));
+        assert!(board.assign(5, 0, 8).is_err());
+        assert!(board.assign(3, 0, 0).is_err());
+        assert!(board.assign(10, 4, 4).is_err());
+        assert!(board.add_candidate(5, 1, 1).is_err());
+
+        // 手动排除的数字在清除数字后不会加回
+        assert!(board.remove_candidate(7, 0, 8));
+        assert!(!board.remove_candidate(7, 0, 8));
+        board.assign(7, 8, 0).unwrap();
+        assert_eq!(board.unassign(0, 0), Some(5));
+        assert_eq!(board.unassign(0, 0), None);
+        assert_eq!(
+            board.get(0, 0).digits,
+            (1..10).filter(|&d| d != 7).collect()
+        );
+        assert!(board.get(0, 8).digits.contains(&5));
+        assert_eq!(board.unassign(8, 0), Some(7));
+        assert!(!board.get(0, 8).digits.contains(&7));
+        assert_eq!(board.add_candidate(7, 0, 8), Ok(true));
+        assert_eq!(board.add_candidate(7, 0, 8), Ok(false));
+
+        // 同组还有相同数字时不加回
+        board.assign(4, 0, 0).unwrap();
+        board.assign(4, 4, 8).unwrap();
+        board.unassign(0, 0);
+        assert!(!board.get(0, 8).digits.contains(&4));
+        assert!(board.get(0, 1).digits.contains(&4));
+        assert_eq!(
+            board.get(0, 8).digits,
+            HashSet::from([1, 2, 3, 5, 6, 7, 8, 9])
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let puzzle = parse_grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        )
+        .unwrap();
+        let variant = Arc::new(Variant::standard());
+        let board = SudokuBoard::new_with(&puzzle, variant.clone());
+        let mut other = board.clone();
+        assert!(board.diff(&other).is_empty());
+
+        // 候选不同不算差异
+        other.remove_candidate(4, 0, 2);
+        assert!(board.diff(&other).is_empty());
+        other.assign(4, 0, 2).unwrap();
+        other.unassign(0, 0);
+        let changes = board.diff(&other);
+        assert_eq!(
+            changes,
+            vec![
+                CellChange {
+                    row: 0,
+                    col: 0,
+                    from: 5,
+                    to: 0
+                },
+                CellChange {
+                    row: 0,
+                    col: 2,
+                    from: 0,
+                    to: 4
+                },
+            ]
+        );
+        assert_eq!(changes[1].to_string(), "r1c3: 0 -> 4");
+        assert_eq!(diff_grids(&other.grid(), &puzzle)[0].from, 0);
+    }
+
+    #[test]
+    fn test_hash() {
+        let puzzle = parse_grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        )
+        .unwrap();
+        let variant = Arc::new(Variant::standard());
+        // 固定的值，不随平台或运行变化
+        assert_eq!(grid_hash(&[[0; 9]; 9]), 0x0edb_e9ed_be9a_769f);
+        assert_ne!(grid_hash(&puzzle), grid_hash(&[[0; 9]; 9]));
+
+        let mut board = SudokuBoard::new_with(&puzzle, variant.clone());
+        let start = board.state_hash();
+        assert_eq!(start, board.clone().state_hash());
+        assert_ne!(start, grid_hash(&puzzle));
+        // 同样的数字、不同的候选
+        board.step();
+        assert_ne!(board.state_hash(), start);
+        let mut other = board.clone();
+        let (row, col) = (0..81)
+            .map(|i| (i / 9, i % 9))
+            .find(|&(r, c)| board.get(r, c).val == 0)
+            .unwrap();
+        let digit = *other.get(row, col).digits.iter().next().unwrap();
+        other.get_mut(row, col).digits.remove(&digit);
+        assert_eq!(other.grid(), board.grid());
+        assert_ne!(board.state_hash(), other.state_hash());
+
+        assert!(board.solve());
+        assert_eq!(board.state_hash(), grid_hash(&board.grid()));
+    }
+
+    #[test]
+    fn test_export() {
+        let puzzle = parse_grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        )
+        .unwrap();
+        let sdk = export_grid(&puzzle, ExportFormat::Sdk);
+        assert!(sdk.starts_with("53..7...."));
+        assert_eq!(sdk.lines().count(), 9);
+        assert_eq!(parse_grid(&sdk), Ok(puzzle));
+        let sdm = export_grid(&puzzle, ExportFormat::Sdm);
+        assert_eq!(parse_grid(&sdm), Ok(puzzle));
+        let link = export_grid(&puzzle, ExportFormat::CtcLink);
+        assert_eq!(crate::fpuzzles::parse(&link).unwrap().givens, puzzle);
+    }
+}
@@ -0,0 +1,181 @@
+//! 题目册
+//!
+//! 每页排列若干道题目，答案集中在最后几页，输出为 A4 大小的 PDF。
+
+use crate::board::Grid;
+use crate::constraint::Unit;
+use crate::generator::Generated;
+use crate::pdf::{Document, Page, A4_HEIGHT, A4_WIDTH};
+use crate::variant::Variant;
+
+/// 页边距
+const MARGIN: f64 = 40.0;
+/// 页眉高度
+const HEADER: f64 = 30.0;
+/// 题目上方标签的高度
+const LABEL: f64 = 18.0;
+/// 每页答案的个数
+const ANSWERS_PER_PAGE: usize = 6;
+
+/// 每页 n 个时的列数和行数
+fn layout(n: usize) -> (usize, usize) {
+    let cols = if n <= 1 { 1 } else { 2 };
+    (cols, n.div_ceil(cols))
+}
+
+/// 在 (x, y) 处绘制边长为 size 的盘面，answer 为 None 时只绘制题目
+fn draw_grid(
+    page: &mut Page,
+    (x, y): (f64, f64),
+    size: f64,
+    givens: &Grid,
+    answer: Option<&Grid>,
+    variant: &Variant,
+) {
+    let cell = size / 9.0;
+    let at =
+        |row: usize, col: usize| (x + cell * (col as f64 + 0.5), y + cell * (row as f64 + 0.5));
+
+    // 窗口涂灰，对角线画细线
+    for unit in variant.find::<Unit>() {
+        if (0..4).any(|n| *unit == Unit::window(n)) {
+            for &(r, c) in &unit.cells {
+                page.fill_rect(
+                    (x + cell * c as f64, y + cell * r as f64),
+                    (cell, cell),
+                    0.85,
+                );
+            }
+        } else if *unit == Unit::diagonal() {
+            page.line((x, y), (x + size, y + size), 0.5);
+        } else if *unit == Unit::anti_diagonal() {
+            page.line((x + size, y), (x, y + size), 0.5);
+        }
+    }
+
+    // 格线，3x3 小格边界加粗
+    for i in 0..10 {
+        let width = if i % 3 == 0 { 2.0 } else { 0.5 };
+        let p = cell * i as f64;
+        page.line((x + p, y), (x + p, y + size), width);
+        page.line((x, y + p), (x + size, y + p), width);
+    }
+
+    // 给定数字为黑色，答案中填入的数字为灰色
+    let size = cell * 0.6;
+    for row in 0..9 {
+        for col in 0..9 {
+            if givens[row][col] != 0 {
+                page.digit(at(row, col), size, givens[row][col], 0.0);
+            } else if let Some(answer) = answer {
+                page.digit(at(row, col), size, answer[row][col], 0.5);
+            }
+        }
+    }
+}
+
+/// 按每页 per_page 个排列一组盘面，draw 绘制第 i 个盘面
+fn add_pages(
+    doc: &mut Document,
+    count: usize,
+    per_page: usize,
+    heading: &str,
+    mut draw: impl FnMut(&mut Page, usize, (f64, f64), f64),
+) {
+    let (cols, rows) = layout(per_page);
+    let cell_w = (A4_WIDTH - MARGIN * 2.0) / cols as f64;
+    let cell_h = (A4_HEIGHT - MARGIN * 2.0 - HEADER) / rows as f64;
+    let size = (cell_w * 0.9).min(cell_h - LABEL * 2.0);
+    for start in (0..count).step_by(per_page) {
+        let page = doc.add_page();
+        page.text((MARGIN, MARGIN + 14.0), 16.0, heading);
+        for i in start..count.min(start + per_page) {
+            let (col, row) = ((i - start) % cols, (i - start) / cols);
+            let x = MARGIN + cell_w * col as f64 + (cell_w - size) / 2.0;
+            let y = MARGIN + HEADER + cell_h * row as f64 + LABEL;
+            draw(page, i, (x, y), size);
+        }
+    }
+}
+
+/// 输出题目册，heading 为每页的标题，per_page 为每页题目的个数
+pub fn render(puzzles: &[Generated], variant: &Variant, heading: &str, per_page: usize) -> Vec<u8> {
+    let per_page = per_page.max(1);
+    let mut doc = Document::new(A4_WIDTH, A4_HEIGHT);
+    add_pages(
+        &mut doc,
+        puzzles.len(),
+        per_page,
+        heading,
+        |page, i, (x, y), size| {
+            let puzzle = &puzzles[i];
+            let label = format!(
+                "#{}  {}  ({} clues)",
+                i + 1,
+                puzzle.rating.difficulty.name(),
+                puzzle.clues()
+            );
+            page.text((x, y - 6.0), 11.0, &label);
+            draw_grid(page, (x, y), size, &puzzle.givens, None, variant);
+        },
+    );
+    add_pages(
+        &mut doc,
+        puzzles.len(),
+        ANSWERS_PER_PAGE,
+        "Answers",
+        |page, i, (x, y), size| {
+            let puzzle = &puzzles[i];
+            page.text((x, y - 6.0), 11.0, &format!("#{}", i + 1));
+            draw_grid(
+                page,
+                (x, y),
+                size,
+                &puzzle.givens,
+                Some(&puzzle.solution),
+                variant,
+            );
+        },
+    );
+    doc.to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{layout, render};
+    use crate::generator::{generate, GenerateOptions};
+    use crate::rng::Rng;
+    use crate::variant::Variant;
+
+    #[test]
+    fn test_layout() {
+        assert_eq!(layout(1), (1, 1));
+        assert_eq!(layout(4), (2, 2));
+        assert_eq!(layout(6), (2, 3));
+        assert_eq!(layout(3), (2, 2));
+    }
+
+    #[test]
+    fn test_render_book() {
+        let variant = Variant::standard();
+        let mut rng = Rng::new(1);
+        let puzzles: Vec<_> = (0..5)
+            .map(|_| {
+                generate(
+                    Arc::new(variant.clone()),
+                    &GenerateOptions::default(),
+                    &mut rng,
+                )
+                .unwrap()
+            })
+            .collect();
+        let bytes = render(&puzzles, &variant, "Sudoku", 4);
+        let text = String::from_utf8(bytes).unwrap();
+        // 两页题目，一页答案
+        assert!(text.contains("/Count 3"));
+        assert!(text.contains("(#5  "));
+        assert!(text.contains("(Answers) Tj"));
+    }
+}
@@ -0,0 +1,337 @@
+//! 聊天机器人桥接
+//!
+//! 聊天平台把频道中的消息以 JSON POST 过来（Discord 风格的 `content` 或 Slack、Mattermost
+//! 风格的 `text`），以 `!solve` 或 `!hint` 开头且带有题目的消息会得到回复。回复放在响应体中，
+//! 字段名与收到的消息相同；配置了 webhook 时另外 POST `{"content": ...}` 到该地址。
+//! 只支持 http://，https 的 webhook 需要在前面放一个终止 TLS 的代理。
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::Arc;
+
+use crate::board::{export_grid, parse_grid, ExportFormat, Grid};
+use crate::i18n::Lang;
+use crate::json::Json;
+use crate::rating::rank_cells;
+use crate::server::{read_request, write_response, Response, READ_TIMEOUT};
+use crate::solver::solutions;
+use crate::variant::Variant;
+use crate::SudokuBoard;
+
+/// 用 http:// 地址给出的 webhook
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Webhook {
+    /// 主机和端口
+    pub host: String,
+    /// 请求路径，含查询参数
+    pub path: String,
+}
+
+impl Webhook {
+    /// 解析 `http://host[:port]/path`，未给出端口时为 80
+    pub fn parse(url: &str) -> Result<Self, String> {
+        if url.starts_with("https://") {
+            return Err(format!(
+                "{}: https is not supported, use an http:// address of a TLS-terminating proxy",
+                url
+            ));
+        }
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("{}: expected an http:// URL", url))?;
+        let (host, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        if host.is_empty() {
+            return Err(format!("{}: missing host", url));
+        }
+        let host = match host.contains(':') {
+            true => host.to_string(),
+            false => format!("{}:80", host),
+        };
+        Ok(Self {
+            host,
+            path: path.to_string(),
+        })
+    }
+
+    /// POST `{"content": ...}`，返回响应的状态码
+    pub fn post(&self, content: &str) -> io::Result<u16> {
+        let body = Json::Object(vec![("content".to_string(), content.into())]).to_string();
+        let mut stream = TcpStream::connect(&self.host)?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.path,
+            self.host,
+            body.len(),
+            body
+        )?;
+        stream.flush()?;
+        let mut status = String::new();
+        BufReader::new(&stream).read_line(&mut status)?;
+        status
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed status line"))
+    }
+}
+
+/// 聊天机器人
+#[derive(Debug, Clone)]
+pub struct Bot {
+    /// 命令前缀
+    pub prefix: String,
+    /// 回复另外发送到的 webhook
+    pub webhook: Option<Webhook>,
+    /// 回复的语言
+    pub lang: Lang,
+}
+
+impl Bot {
+    /// 对一条消息的回复，不是给机器人的命令时为 None
+    pub fn reply(&self, message: &str) -> Option<String> {
+        let command = message.trim().strip_prefix(&self.prefix)?;
+        let (name, rest) = command
+            .split_once(char::is_whitespace)
+            .unwrap_or((command, ""));
+        // 题目可能放在代码块中
+        let puzzle = rest.trim().trim_matches('`');
+        match name {
+            "solve" => Some(
+                self.parse(puzzle)
+                    .map_or_else(|e| e, |grid| self.solve(&grid)),
+            ),
+            "hint" => Some(
+                self.parse(puzzle)
+                    .map_or_else(|e| e, |grid| self.hint(&grid)),
+            ),
+            "help" => Some(self.help()),
+            _ => None,
+        }
+    }
+
+    fn help(&self) -> String {
+        self.lang.format("bot_help", &[&self.prefix, &self.prefix])
+    }
+
+    /// 解析题目并检查给定数字是否冲突
+    fn parse(&self, puzzle: &str) -> Result<Grid, String> {
+        let givens = parse_grid(puzzle)?;
+        match Variant::standard().conflicts(&givens).first() {
+            Some(conflict) => Err(self.lang.conflict(conflict)),
+            None => Ok(givens),
+        }
+    }
+
+    /// 解放在代码块中，多解时说明只给出其中一个
+    fn solve(&self, givens: &Grid) -> String {
+        let found = solutions(
+            &SudokuBoard::new_with(givens, Arc::new(Variant::standard())),
+            2,
+        );
+        let Some(solution) = found.first() else {
+            return self.lang.message("unsolvable").to_string();
+        };
+        let note = match found.len() {
+            1 => String::new(),
+            _ => format!("{}\n", self.lang.message("one_of_many")),
+        };
+        let rows = export_grid(solution, ExportFormat::Sdk);
+        format!(
+            "{}```\n{}\n```",
+            note,
+            rows.lines().collect::<Vec<_>>().join("\n")
+        )
+    }
+
+    /// 最容易填出的空位
+    fn hint(&self, grid: &Grid) -> String {
+        match rank_cells(grid, &Variant::standard()).first() {
+            Some(hint) => self.lang.hint(hint),
+            None => self.lang.message("board_full").to_string(),
+        }
+    }
+
+    /// 处理收到的一个请求体，返回 HTTP 响应和要发到 webhook 的回复
+    pub fn handle(&self, body: &str) -> (Response, Option<String>) {
+        let request = match Json::parse(body) {
+            Ok(request @ Json::Object(_)) => request,
+            _ => {
+                let message = "request body must be a JSON object";
+                return (Response::error(400, "invalid_request", message), None);
+            }
+        };
+        // 不回复其他机器人（包括自己）的消息，以免来回应答
+        let from_bot = request
+            .get("author")
+            .and_then(|author| author.get("bot"))
+            .and_then(Json::as_bool)
+            .unwrap_or(false)
+            || request.get("bot_id").is_some();
+        let (key, message) = match (request.get("content"), request.get("text")) {
+            (Some(content), _) => ("content", content.as_str()),
+            (None, Some(text)) => ("text", text.as_str()),
+            (None, None) => ("content", None),
+        };
+        match message.filter(|_| !from_bot).and_then(|m| self.reply(m)) {
+            Some(reply) => {
+                let body = Json::Object(vec![(key.to_string(), reply.as_str().into())]);
+                (Response::ok(body), Some(reply))
+            }
+            None => (Response::ok(Json::Object(vec![])), None),
+        }
+    }
+
+    /// 逐个处理连接
+    pub fn serve(self: Arc<Self>, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            if let Err(e) = self.connection(&mut stream?) {
+                log::debug!("connection: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    fn connection(&self, stream: &mut TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let (response, reply) = match read_request(stream)? {
+            Ok(request) if request.method == "POST" => self.handle(&request.body),
+            Ok(_) => (Response::error(405, "method_not_allowed", "use POST"), None),
+            Err(response) => (response, None),
+        };
+        write_response(stream, &response)?;
+        // 先结束这次请求，再发送到 webhook
+        stream.shutdown(Shutdown::Both)?;
+        if let (Some(webhook), Some(reply)) = (&self.webhook, reply) {
+            match webhook.post(&reply) {
+                Ok(status) if status / 100 == 2 => {}
+                Ok(status) => log::warn!("webhook answered {}", status),
+                Err(e) => log::warn!("webhook {}: {}", webhook.host, e),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::i18n::Lang;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::{Bot, Webhook};
+    use crate::json::Json;
+
+    const PUZZLE: &str =
+        "530070000600195000098000060800060003400803001700020006060000280000419005000080079";
+
+    fn bot() -> Bot {
+        Bot {
+            prefix: "!".to_string(),
+            webhook: None,
+            lang: Lang::En,
+        }
+    }
+
+    #[test]
+    fn test_reply() {
+        let bot = bot();
+        let solved = bot.reply(&format!("!solve `{}`", PUZZLE)).unwrap();
+        assert!(
+            solved.starts_with("```\n534678912\n672195348\n"),
+            "{}",
+            solved
+        );
+        assert!(solved.ends_with("345286179\n```"));
+        let multiple = bot.reply(&format!("!solve {}", "0".repeat(81))).unwrap();
+        assert!(multiple.starts_with("the puzzle has more than one solution"));
+        let hint = bot.reply(&format!("  !hint {}", PUZZLE)).unwrap();
+        assert!(hint.contains(" is "), "{}", hint);
+        assert!(bot.reply("!solve 123").unwrap().contains("81"));
+        let conflict = format!("55{}", "0".repeat(79));
+        assert!(bot.reply(&format!("!solve {}", conflict)).is_some());
+        assert!(bot.reply("!help").unwrap().contains("!solve PUZZLE"));
+        assert_eq!(bot.reply(PUZZLE), None);
+        assert_eq!(bot.reply("!dance"), None);
+    }
+
+    #[test]
+    fn test_handle() {
+        let bot = bot();
+        let request = format!(r#"{{"text": "!solve {}"}}"#, PUZZLE);
+        let (response, reply) = bot.handle(&request);
+        assert_eq!(response.status, 200);
+        assert_eq!(
+            response.body.get("text").and_then(Json::as_str),
+            reply.as_deref()
+        );
+        let request = format!(
+            r#"{{"content": "!solve {}", "author": {{"bot": true}}}}"#,
+            PUZZLE
+        );
+        let (response, reply) = bot.handle(&request);
+        assert_eq!((response.body.to_string(), reply), ("{}".to_string(), None));
+        assert_eq!(bot.handle("[]").0.status, 400);
+    }
+
+    #[test]
+    fn test_webhook() {
+        assert_eq!(
+            Webhook::parse("http://localhost/hooks/1?wait=true"),
+            Ok(Webhook {
+                host: "localhost:80".to_string(),
+                path: "/hooks/1?wait=true".to_string(),
+            })
+        );
+        assert_eq!(Webhook::parse("http://[::1]:8000").unwrap().path, "/");
+        assert!(Webhook::parse("https://discord.com/api/webhooks/1")
+            .unwrap_err()
+            .contains("https"));
+        assert!(Webhook::parse("ftp://host").is_err());
+
+        // 收到消息后，回复既在响应体中，也发送到 webhook
+        let hook = TcpListener::bind("127.0.0.1:0").unwrap();
+        let webhook = Webhook::parse(&format!("http://{}/hook", hook.local_addr().unwrap()));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let bot = Arc::new(Bot {
+            webhook: Some(webhook.unwrap()),
+            ..bot()
+        });
+        thread::spawn(move || bot.serve(listener));
+        let body = format!(r#"{{"content": "!hint {}"}}"#, PUZZLE);
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+
+        let (mut posted, _) = hook.accept().unwrap();
+        let mut request = [0; 1024];
+        let n = posted.read(&mut request).unwrap();
+        let request = String::from_utf8_lossy(&request[..n]).to_string();
+        posted
+            .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+            .unwrap();
+        assert!(
+            request.starts_with("POST /hook HTTP/1.1\r\n"),
+            "{}",
+            request
+        );
+        let reply = response.split("\r\n\r\n").nth(1).unwrap();
+        assert!(request.ends_with(reply), "{}\n{}", request, reply);
+    }
+}
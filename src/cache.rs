@@ -0,0 +1,209 @@
+//! 求解结果的缓存
+//!
+//! 以题目的规范形式（见 [`crate::canon`]）为键，等价的题目共用一个条目，解经逆变换对应回
+//! 各自的题目。条目数有上限，满了之后淘汰最久未用的条目。只适用于标准数独。
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::board::Grid;
+use crate::canon::{canonical, Transform};
+
+/// 给定数字少于此数时不缓存：规范形式的搜索很慢，这样的题目也不会有唯一解
+const MIN_GIVENS: usize = 17;
+
+/// 每个条目最多保存的解的个数
+const MAX_SOLUTIONS: usize = 100;
+
+/// 题目在缓存中的键
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey {
+    canonical: Grid,
+    /// 从题目得到规范形式的变换
+    transform: Transform,
+}
+
+impl CacheKey {
+    /// 计算题目的规范形式，给定数字太少时返回 None
+    pub fn new(givens: &Grid) -> Option<Self> {
+        let clues = givens.iter().flatten().filter(|d| **d != 0).count();
+        if clues < MIN_GIVENS {
+            return None;
+        }
+        let (canonical, transform) = canonical(givens);
+        Some(Self {
+            canonical,
+            transform,
+        })
+    }
+}
+
+/// 一个条目：规范形式的解
+#[derive(Debug)]
+struct Entry {
+    solutions: Vec<Grid>,
+    /// solutions 是否为全部的解
+    complete: bool,
+    /// 最近一次使用的序号
+    used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Lru {
+    entries: HashMap<Grid, Entry>,
+    /// 按最近使用的序号排列的键
+    order: BTreeMap<u64, Grid>,
+    tick: u64,
+}
+
+impl Lru {
+    /// 标记条目刚被使用
+    fn touch(&mut self, key: &Grid) {
+        let Some(entry) = self.entries.get_mut(key) else {
+            return;
+        };
+        self.order.remove(&entry.used);
+        self.tick += 1;
+        entry.used = self.tick;
+        self.order.insert(self.tick, *key);
+    }
+}
+
+/// 有上限的 LRU 缓存，可以在线程间共享
+#[derive(Debug)]
+pub struct SolveCache {
+    capacity: usize,
+    lru: Mutex<Lru>,
+}
+
+impl SolveCache {
+    /// 至多保存 capacity 道题目的结果，为 0 时不缓存
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            lru: Mutex::default(),
+        }
+    }
+
+    /// 取得至多 limit 个解，换算到 key 对应的题目。缓存的解不少于 limit 个，
+    /// 或已是全部的解时命中
+    pub fn get(&self, key: &CacheKey, limit: usize) -> Option<Vec<Grid>> {
+        let mut lru = self.lru.lock().unwrap();
+        let entry = lru.entries.get(&key.canonical)?;
+        if !entry.complete && entry.solutions.len() < limit {
+            return None;
+        }
+        let inverse = key.transform.inverse();
+        let found = entry
+            .solutions
+            .iter()
+            .take(limit)
+            .map(|solution| inverse.apply(solution))
+            .collect();
+        lru.touch(&key.canonical);
+        Some(found)
+    }
+
+    /// 记录 key 对应的题目的解，complete 表示这些是全部的解。已有的条目更完整时保留已有的
+    pub fn insert(&self, key: &CacheKey, solutions: &[Grid], complete: bool) {
+        if self.capacity == 0 {
+            return;
+        }
+        let complete = complete && solutions.len() <= MAX_SOLUTIONS;
+        let solutions: Vec<Grid> = solutions
+            .iter()
+            .take(MAX_SOLUTIONS)
+            .map(|solution| key.transform.apply(solution))
+            .collect();
+        let mut lru = self.lru.lock().unwrap();
+        let lru = &mut *lru;
+        if let Some(entry) = lru.entries.get_mut(&key.canonical) {
+            if !entry.complete && (complete || solutions.len() > entry.solutions.len()) {
+                entry.solutions = solutions;
+                entry.complete = complete;
+            }
+            lru.touch(&key.canonical);
+            return;
+        }
+        if lru.entries.len() >= self.capacity {
+            if let Some((_, oldest)) = lru.order.pop_first() {
+                lru.entries.remove(&oldest);
+            }
+        }
+        lru.tick += 1;
+        lru.order.insert(lru.tick, key.canonical);
+        lru.entries.insert(
+            key.canonical,
+            Entry {
+                solutions,
+                complete,
+                used: lru.tick,
+            },
+        );
+    }
+
+    /// 缓存的题目数
+    pub fn len(&self) -> usize {
+        self.lru.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CacheKey, SolveCache};
+    use crate::board::parse_grid;
+    use crate::canon::Transform;
+
+    const PUZZLE: &str =
+        "530070000600195000098000060800060003400803001700020006060000280000419005000080079";
+    const SOLUTION: &str =
+        "534678912672195348198342567859761423426853791713924856961537284287419635345286179";
+
+    #[test]
+    fn test_cache() {
+        let (puzzle, solution) = (parse_grid(PUZZLE).unwrap(), parse_grid(SOLUTION).unwrap());
+        let cache = SolveCache::new(2);
+        let key = CacheKey::new(&puzzle).unwrap();
+        assert_eq!(cache.get(&key, 1), None);
+        cache.insert(&key, &[solution], true);
+        assert_eq!(cache.get(&key, 2), Some(vec![solution]));
+
+        // 等价的题目命中同一个条目，解换算到该题目
+        let transform = Transform {
+            transpose: true,
+            rows: [5, 3, 4, 0, 2, 1, 8, 6, 7],
+            cols: [2, 1, 0, 6, 8, 7, 3, 4, 5],
+            digits: [0, 9, 8, 7, 6, 5, 4, 3, 2, 1],
+        };
+        let other = CacheKey::new(&transform.apply(&puzzle)).unwrap();
+        assert_eq!(cache.get(&other, 1), Some(vec![transform.apply(&solution)]));
+
+        // 不完整的结果只满足不超过其个数的请求
+        let mut two = puzzle;
+        two[0][0] = 0;
+        two[0][1] = 0;
+        let partial = CacheKey::new(&two).unwrap();
+        cache.insert(&partial, &[solution], false);
+        assert_eq!(cache.get(&partial, 1), Some(vec![solution]));
+        assert_eq!(cache.get(&partial, 2), None);
+        assert_eq!(cache.len(), 2);
+
+        // 满了之后淘汰最久未用的
+        cache.get(&key, 1);
+        let mut third = puzzle;
+        third[8][8] = 0;
+        cache.insert(&CacheKey::new(&third).unwrap(), &[solution], true);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&partial, 1), None);
+        assert!(cache.get(&key, 1).is_some());
+
+        assert!(CacheKey::new(&[[0; 9]; 9]).is_none());
+        let disabled = SolveCache::new(0);
+        disabled.insert(&key, &[solution], true);
+        assert!(disabled.is_empty());
+    }
+}
@@ -0,0 +1,409 @@
+//! 题目的等价变换与规范形式
+//!
+//! 数独的对称群包括：转置、行带（三行一组）的排列、行带内各行的排列、列带及列带内各列的排列，
+//! 以及数字的重新编号。两道题目可以通过这些变换互相得到时视为等价。
+//!
+//! 搜索时先枚举转置和列的排列（2 × 6⁴ 种），再逐行回溯选择行，每放一行就检查，大部分分支在
+//! 前几行就被排除。
+
+use std::fmt;
+
+use crate::board::Grid;
+
+/// 一个等价变换：先按需转置，再重排行列，最后重新编号数字
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transform {
+    /// 是否先转置
+    pub transpose: bool,
+    /// 结果的第 r 行取自（转置后的）第 rows[r] 行
+    pub rows: [usize; 9],
+    /// 结果的第 c 列取自（转置后的）第 cols[c] 列
+    pub cols: [usize; 9],
+    /// 数字 d 变为 digits[d]，digits[0] 恒为 0
+    pub digits: [u32; 10],
+}
+
+impl Transform {
+    /// 对盘面进行变换
+    pub fn apply(&self, grid: &Grid) -> Grid {
+        let source = if self.transpose {
+            transposed(grid)
+        } else {
+            *grid
+        };
+        std::array::from_fn(|r| {
+            std::array::from_fn(|c| self.digits[source[self.rows[r]][self.cols[c]] as usize])
+        })
+    }
+
+    /// 逆变换：把变换的结果变回原来的盘面
+    pub fn inverse(&self) -> Transform {
+        let invert = |order: &[usize; 9]| {
+            let mut inverse = [0; 9];
+            for (i, &j) in order.iter().enumerate() {
+                inverse[j] = i;
+            }
+            inverse
+        };
+        let mut digits = [0; 10];
+        for d in 1..10 {
+            digits[self.digits[d] as usize] = d as u32;
+        }
+        // 先重排再转置，等同于先转置再交换行列的排列
+        let (rows, cols) = match self.transpose {
+            true => (invert(&self.cols), invert(&self.rows)),
+            false => (invert(&self.rows), invert(&self.cols)),
+        };
+        Transform {
+            transpose: self.transpose,
+            rows,
+            cols,
+            digits,
+        }
+    }
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |items: &[usize]| {
+            items
+                .iter()
+                .map(|i| (i + 1).to_string())
+                .collect::<String>()
+        };
+        writeln!(
+            f,
+            "transpose: {}",
+            if self.transpose { "yes" } else { "no" }
+        )?;
+        writeln!(f, "rows: {}", join(&self.rows))?;
+        writeln!(f, "columns: {}", join(&self.cols))?;
+        let digits: Vec<String> = (1..10)
+            .map(|d| format!("{}->{}", d, self.digits[d]))
+            .collect();
+        write!(f, "digits: {}", digits.join(" "))
+    }
+}
+
+/// 转置
+fn transposed(grid: &Grid) -> Grid {
+    std::array::from_fn(|r| std::array::from_fn(|c| grid[c][r]))
+}
+
+/// 3 个元素的所有排列
+const PERMS: [[usize; 3]; 6] = [
+    [0, 1, 2],
+    [0, 2, 1],
+    [1, 0, 2],
+    [1, 2, 0],
+    [2, 0, 1],
+    [2, 1, 0],
+];
+
+/// 所有列的排列：列带的排列及每个列带内的排列
+fn column_orders() -> Vec<[usize; 9]> {
+    let mut orders = vec![];
+    for stacks in PERMS {
+        for a in PERMS {
+            for b in PERMS {
+                for c in PERMS {
+                    let within = [a, b, c];
+                    orders.push(std::array::from_fn(|i| {
+                        stacks[i / 3] * 3 + within[i / 3][i % 3]
+                    }));
+                }
+            }
+        }
+    }
+    orders
+}
+
+/// 未补全的数字编号中补上未出现的数字，保持一一对应
+fn complete(mut digits: [u32; 10]) -> [u32; 10] {
+    let unused: Vec<u32> = (1..10).filter(|d| !digits.contains(d)).collect();
+    let mut unused = unused.into_iter();
+    for d in 1..10 {
+        if digits[d] == 0 {
+            digits[d] = unused.next().unwrap_or(0);
+        }
+    }
+    digits
+}
+
+/// 逐行回溯的搜索状态
+struct Search<'a> {
+    /// 转置后的源盘面
+    source: Grid,
+    /// 列的排列
+    cols: [usize; 9],
+    /// 已选的行
+    rows: [usize; 9],
+    /// 匹配目标时为目标盘面，求规范形式时为 None
+    target: Option<&'a Grid>,
+    /// 规范形式：当前最小的结果及其变换
+    best: Option<(Grid, Transform)>,
+    /// 是否转置
+    transpose: bool,
+}
+
+impl Search<'_> {
+    /// 第 r 行可选的源行
+    fn candidates(&self, r: usize) -> Vec<usize> {
+        let used = &self.rows[..r];
+        if r.is_multiple_of(3) {
+            // 行带的第一行：选择一个未使用的行带
+            (0..9)
+                .filter(|&s| !used.iter().any(|u| u / 3 == s / 3))
+                .collect()
+        } else {
+            let band = self.rows[r - 1] / 3;
+            (band * 3..band * 3 + 3)
+                .filter(|s| !used.contains(s))
+                .collect()
+        }
+    }
+
+    /// 第 r 行取源行 s 时与目标是否一致，一致时返回扩充后的数字对应关系
+    fn place(&self, r: usize, s: usize, digits: [u32; 10]) -> Option<[u32; 10]> {
+        let target = self.target?;
+        let mut digits = digits;
+        for c in 0..9 {
+            let (x, y) = (self.source[s][self.cols[c]] as usize, target[r][c]);
+            if (x == 0) != (y == 0) {
+                return None;
+            }
+            if x == 0 {
+                continue;
+            }
+            if digits[x] == 0 {
+                if digits.contains(&y) {
+                    return None;
+                }
+                digits[x] = y;
+            } else if digits[x] != y {
+                return None;
+            }
+        }
+        Some(digits)
+    }
+
+    /// 匹配目标：放置第 r 行，digits 为已确定的数字对应关系
+    fn matches(&mut self, r: usize, digits: [u32; 10]) -> Option<Transform> {
+        if r == 9 {
+            return Some(Transform {
+                transpose: self.transpose,
+                rows: self.rows,
+                cols: self.cols,
+                digits: complete(digits),
+            });
+        }
+        for s in self.candidates(r) {
+            let Some(digits) = self.place(r, s, digits) else {
+                continue;
+            };
+            self.rows[r] = s;
+            if let Some(transform) = self.matches(r + 1, digits) {
+                return Some(transform);
+            }
+        }
+        None
+    }
+
+    /// 统计匹配目标的变换个数，未出现的数字不计入不同的变换
+    fn count(&mut self, r: usize, digits: [u32; 10]) -> usize {
+        if r == 9 {
+            return 1;
+        }
+        let mut total = 0;
+        for s in self.candidates(r) {
+            if let Some(digits) = self.place(r, s, digits) {
+                self.rows[r] = s;
+                total += self.count(r + 1, digits);
+            }
+        }
+        total
+    }
+
+    /// 规范形式：放置第 r 行，result 为已放置的结果，next 为下一个编号
+    fn minimize(&mut self, r: usize, result: &mut Grid, digits: [u32; 10], next: u32) {
+        if r == 9 {
+            if self.best.as_ref().is_none_or(|(best, _)| *result < *best) {
+                let transform = Transform {
+                    transpose: self.transpose,
+                    rows: self.rows,
+                    cols: self.cols,
+                    digits: complete(digits),
+                };
+                self.best = Some((*result, transform));
+            }
+            return;
+        }
+        for s in self.candidates(r) {
+            let (mut digits, mut next) = (digits, next);
+            for c in 0..9 {
+                let x = self.source[s][self.cols[c]] as usize;
+                if x != 0 && digits[x] == 0 {
+                    digits[x] = next;
+                    next += 1;
+                }
+                result[r][c] = digits[x];
+            }
+            // 已放置的部分大于当前最小结果时剪枝
+            if let Some((best, _)) = &self.best {
+                if result[..=r] > best[..=r] {
+                    continue;
+                }
+            }
+            self.rows[r] = s;
+            self.minimize(r + 1, result, digits, next);
+        }
+    }
+}
+
+/// 只按首次出现的顺序重新编号数字，不改变位置
+pub fn relabeled(grid: &Grid) -> Grid {
+    let mut digits = [0; 10];
+    let mut next = 1;
+    for &d in grid.iter().flatten() {
+        if d != 0 && digits[d as usize] == 0 {
+            digits[d as usize] = next;
+            next += 1;
+        }
+    }
+    grid.map(|row| row.map(|d| digits[d as usize]))
+}
+
+/// 找出把 a 变为 b 的变换，两者不等价时返回 None
+pub fn equivalence(a: &Grid, b: &Grid) -> Option<Transform> {
+    let count = |g: &Grid| g.iter().flatten().filter(|d| **d != 0).count();
+    if count(a) != count(b) {
+        return None;
+    }
+    for transpose in [false, true] {
+        let source = if transpose { transposed(a) } else { *a };
+        for cols in column_orders() {
+            let mut search = Search {
+                source,
+                cols,
+                rows: [0; 9],
+                target: Some(b),
+                best: None,
+                transpose,
+            };
+            if let Some(transform) = search.matches(0, [0; 10]) {
+                return Some(transform);
+            }
+        }
+    }
+    None
+}
+
+/// 自同构的个数：把盘面变为自身（允许重新编号数字）的变换个数，包含恒等变换。
+/// 大于 1 时题目是自同构的
+pub fn automorphisms(grid: &Grid) -> usize {
+    let mut total = 0;
+    for transpose in [false, true] {
+        let source = if transpose { transposed(grid) } else { *grid };
+        for cols in column_orders() {
+            let mut search = Search {
+                source,
+                cols,
+                rows: [0; 9],
+                target: Some(grid),
+                best: None,
+                transpose,
+            };
+            total += search.count(0, [0; 10]);
+        }
+    }
+    total
+}
+
+/// 规范形式：所有等价题目中按行优先比较最小的一个，数字按首次出现的顺序编号，
+/// 同时返回从原题目得到规范形式的变换
+pub fn canonical(grid: &Grid) -> (Grid, Transform) {
+    let mut best: Option<(Grid, Transform)> = None;
+    for transpose in [false, true] {
+        let source = if transpose { transposed(grid) } else { *grid };
+        for cols in column_orders() {
+            let mut search = Search {
+                source,
+                cols,
+                rows: [0; 9],
+                target: None,
+                best: best.take(),
+                transpose,
+            };
+            search.minimize(0, &mut [[0; 9]; 9], [0; 10], 1);
+            best = search.best;
+        }
+    }
+    best.unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{automorphisms, canonical, equivalence, relabeled, Transform};
+    use crate::board::parse_grid;
+
+    const PUZZLE: &str =
+        "530070000600195000098000060800060003400803001700020006060000280000419005000080079";
+
+    #[test]
+    fn test_equivalence() {
+        let a = parse_grid(PUZZLE).unwrap();
+        let transform = Transform {
+            transpose: true,
+            rows: [5, 3, 4, 0, 2, 1, 8, 6, 7],
+            cols: [2, 1, 0, 6, 8, 7, 3, 4, 5],
+            digits: [0, 9, 8, 7, 6, 5, 4, 3, 2, 1],
+        };
+        let b = transform.apply(&a);
+        assert_ne!(a, b);
+
+        let found = equivalence(&a, &b).unwrap();
+        assert_eq!(found.apply(&a), b);
+        assert_eq!(transform.inverse().apply(&b), a);
+        let (canon, to_canon) = canonical(&b);
+        assert_eq!(to_canon.inverse().apply(&canon), b);
+        assert_eq!(canonical(&a).0, canonical(&b).0);
+
+        // 改动一个数字后不再等价
+        let mut c = b;
+        c[0][0] = if c[0][0] == 0 { 1 } else { 0 };
+        assert!(equivalence(&a, &c).is_none());
+    }
+
+    #[test]
+    fn test_canonical() {
+        let a = parse_grid(PUZZLE).unwrap();
+        let (canon, transform) = canonical(&a);
+        assert_eq!(transform.apply(&a), canon);
+        // 规范形式中数字按首次出现的顺序编号
+        let first: Vec<u32> = canon
+            .iter()
+            .flatten()
+            .filter(|d| **d != 0)
+            .cloned()
+            .collect();
+        assert_eq!(first[0], 1);
+        assert_eq!(canonical(&canon).0, canon);
+        assert_eq!(relabeled(&canon), canon);
+        assert_eq!(relabeled(&a)[0][..2], [1, 2]);
+    }
+
+    #[test]
+    fn test_automorphisms() {
+        let a = parse_grid(PUZZLE).unwrap();
+        assert_eq!(automorphisms(&a), 1);
+
+        // 关于中心对称且数字按 d -> 10 - d 对应的题目
+        let mut b = [[0; 9]; 9];
+        b[0][0] = 1;
+        b[8][8] = 9;
+        b[1][4] = 2;
+        b[7][4] = 8;
+        b[4][4] = 5;
+        assert!(automorphisms(&b) > 1);
+    }
+}
@@ -0,0 +1,97 @@
+//! 长时间批量求解的断点
+//!
+//! 逐道求解很大的题目文件时定期记下已读完的行数和到此为止的结果，中断后用 `--resume`
+//! 跳过已经求解的题目继续。断点只在两道题目之间记录，跳过的行恰好是完整的题目。
+
+use std::fs;
+use std::path::Path;
+
+use crate::json::Json;
+
+/// 批量求解的进度
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// 已读完的输入行数
+    pub lines: usize,
+    /// 已处理的题目数，含给定数字有冲突的
+    pub puzzles: usize,
+    /// 已找到的解的总数
+    pub solutions: usize,
+    /// 是否有题目有误而被跳过，决定退出码
+    pub invalid: bool,
+    /// 是否有题目超出猜测深度的上限，决定退出码
+    pub too_hard: bool,
+}
+
+impl Checkpoint {
+    /// JSON 形式
+    pub fn to_json(&self) -> Json {
+        Json::Object(vec![
+            ("lines".to_string(), self.lines.into()),
+            ("puzzles".to_string(), self.puzzles.into()),
+            ("solutions".to_string(), self.solutions.into()),
+            ("invalid".to_string(), self.invalid.into()),
+            ("too_hard".to_string(), self.too_hard.into()),
+        ])
+    }
+
+    /// 从 JSON 形式读取
+    pub fn from_json(json: &Json) -> Result<Self, String> {
+        let count = |key: &str| {
+            json.get(key)
+                .and_then(Json::as_u32)
+                .map(|n| n as usize)
+                .ok_or_else(|| format!("checkpoint has no `{}` count", key))
+        };
+        let flag = |key: &str| json.get(key).and_then(Json::as_bool).unwrap_or(false);
+        Ok(Self {
+            lines: count("lines")?,
+            puzzles: count("puzzles")?,
+            solutions: count("solutions")?,
+            invalid: flag("invalid"),
+            too_hard: flag("too_hard"),
+        })
+    }
+
+    /// 读取断点文件
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let json = Json::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::from_json(&json).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// 写入断点文件，先写临时文件再改名，中途被打断也不会留下不完整的断点
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, format!("{}\n", self.to_json()))
+            .and_then(|_| fs::rename(&tmp, path))
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::process;
+
+    use super::Checkpoint;
+
+    #[test]
+    fn test_checkpoint() {
+        let checkpoint = Checkpoint {
+            lines: 1200,
+            puzzles: 400,
+            solutions: 401,
+            invalid: true,
+            too_hard: false,
+        };
+        let path = env::temp_dir().join(format!("superdo-checkpoint-{}.json", process::id()));
+        checkpoint.save(&path).unwrap();
+        assert_eq!(Checkpoint::load(&path).unwrap(), checkpoint);
+        std::fs::write(&path, r#"{"lines": 3}"#).unwrap();
+        assert!(Checkpoint::load(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+        assert!(Checkpoint::load(&path).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! 用户配置
+//!
+//! 配置文件 [`Config`] 和本地记录（如最佳用时）保存在用户的配置目录中：Windows 上为
+//! `%APPDATA%\superdo`，其他平台为 `$XDG_CONFIG_HOME/superdo`，未设置时为 `~/.config/superdo`。
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::auth::ApiToken;
+use crate::json::Json;
+use crate::keys::Keymap;
+use crate::theme::Theme;
+
+/// 配置目录，无法确定时为 None
+pub fn config_dir() -> Option<PathBuf> {
+    let non_empty = |name: &str| {
+        env::var_os(name)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    };
+    let base = if cfg!(windows) {
+        non_empty("APPDATA")
+    } else {
+        non_empty("XDG_CONFIG_HOME").or_else(|| non_empty("HOME").map(|home| home.join(".config")))
+    };
+    base.map(|base| base.join("superdo"))
+}
+
+/// 配置文件 `config.json` 的内容，各项都可省略
+///
+/// ```json
+/// {"theme": "light", "keys": {"preset": "vim", "bindings": {"hint": ["?"]}},
+///  "server": {"tokens": ["s3cret", {"token": "guest", "per_minute": 30}]}}
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    /// 终端输出的配色，`--theme` 优先
+    pub theme: Theme,
+    /// 交互式前端的按键绑定
+    pub keys: Keymap,
+    /// `superdo serve` 接受的令牌
+    pub tokens: Vec<ApiToken>,
+}
+
+impl Config {
+    /// 默认的配置文件，配置目录中的 `config.json`
+    pub fn default_path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("config.json"))
+    }
+
+    /// 从 JSON 形式读取
+    pub fn from_json(json: &Json) -> Result<Self, String> {
+        let keys = match json.get("keys") {
+            Some(keys) => Keymap::from_json(keys).map_err(|e| format!("keys: {}", e))?,
+            None => Keymap::default(),
+        };
+        let theme = match json.get("theme").map(|t| t.as_str()) {
+            None => Theme::default(),
+            Some(Some(name)) => clap::ValueEnum::from_str(name, true)
+                .map_err(|_| format!("unknown theme `{}`", name))?,
+            Some(None) => return Err("theme must be a string".to_string()),
+        };
+        let tokens = match json.get("server").and_then(|server| server.get("tokens")) {
+            None => vec![],
+            Some(tokens) => tokens
+                .as_array()
+                .ok_or("server.tokens must be an array")?
+                .iter()
+                .map(ApiToken::from_json)
+                .collect::<Result<_, _>>()
+                .map_err(|e| format!("server.tokens: {}", e))?,
+        };
+        Ok(Self {
+            theme,
+            keys,
+            tokens,
+        })
+    }
+
+    /// 读取配置文件，文件不存在时为默认配置
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+        let json = Json::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::from_json(&json).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::process;
+
+    use super::Config;
+    use crate::keys::{Action, Key};
+    use crate::theme::Theme;
+
+    #[test]
+    fn test_config() {
+        let path = env::temp_dir().join(format!("superdo-config-{}.json", process::id()));
+        assert_eq!(Config::load(&path), Ok(Config::default()));
+        std::fs::write(&path, r#"{"keys": {"preset": "vim"}}"#).unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.keys.action(Key::Char('u')), Some(Action::Undo));
+        assert_eq!(config.theme, Theme::Dark);
+        std::fs::write(&path, r#"{"theme": "high-contrast"}"#).unwrap();
+        assert_eq!(Config::load(&path).unwrap().theme, Theme::HighContrast);
+        std::fs::write(&path, r#"{"server": {"tokens": ["a", {"token": "b"}]}}"#).unwrap();
+        assert_eq!(Config::load(&path).unwrap().tokens.len(), 2);
+        std::fs::write(&path, r#"{"server": {"tokens": "a"}}"#).unwrap();
+        assert!(Config::load(&path).is_err());
+        std::fs::write(&path, r#"{"theme": "neon"}"#).unwrap();
+        assert!(Config::load(&path).is_err());
+        std::fs::write(&path, r#"{"keys": {"preset": 1}}"#).unwrap();
+        assert!(Config::load(&path).unwrap_err().contains("keys: "));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
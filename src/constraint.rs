@@ -0,0 +1,1086 @@
+//! 约束
+//!
+//! 标准规则（行、列、3x3 小格）和各种变体规则都实现 [`Constraint`]，求解器只通过该 trait
+//! 使用约束，新增变体（包括下游 crate 自定义的约束）无需修改求解器。
+
+use std::any::Any;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::board::{Grid, SudokuBoard};
+use crate::json::Json;
+
+/// 位置，行列从 0 开始
+pub type Cell = (usize, usize);
+
+/// 一次传播的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Propagation {
+    /// 没有变化
+    Unchanged,
+    /// 排除了候选数字或填入了数字
+    Changed,
+    /// 出现矛盾，当前棋盘无解
+    Contradiction,
+}
+
+impl Propagation {
+    /// 由是否有变化得到传播结果
+    pub fn changed(changed: bool) -> Self {
+        if changed {
+            Propagation::Changed
+        } else {
+            Propagation::Unchanged
+        }
+    }
+}
+
+/// 约束。Display 给出面向用户的描述，位置用 `r1c1` 表示
+pub trait Constraint: Any + fmt::Debug + fmt::Display + Send + Sync {
+    /// 必须填入不同数字的位置对，会并入同组关系表，由求解器在填数时直接排除
+    fn peers(&self) -> Vec<(Cell, Cell)> {
+        vec![]
+    }
+
+    /// 除同组关系之外还需检查的位置，回溯求解在这些位置填数后会调用 [`Constraint::check`]
+    fn cells(&self) -> Vec<Cell> {
+        vec![]
+    }
+
+    /// 在候选数字上进行推理
+    fn propagate(&self, board: &mut SudokuBoard) -> Propagation;
+
+    /// 已填数字是否满足约束，0 表示尚未填入的空位
+    fn check(&self, grid: &Grid) -> bool;
+}
+
+/// 解析 `r1c1` 形式的位置（行列从 1 开始），返回从 0 开始的行列
+pub fn parse_cell(s: &str) -> Option<Cell> {
+    let s = s.trim().to_ascii_lowercase();
+    let (row, col) = s.strip_prefix('r')?.split_once('c')?;
+    let row = row.parse::<usize>().ok()?;
+    let col = col.parse::<usize>().ok()?;
+    if (1..=9).contains(&row) && (1..=9).contains(&col) {
+        Some((row - 1, col - 1))
+    } else {
+        None
+    }
+}
+
+/// 解析 `r1c1-r1c2-...` 形式的位置列表
+fn parse_cells(s: &str) -> Result<Vec<Cell>, String> {
+    s.split('-')
+        .map(|c| parse_cell(c).ok_or_else(|| format!("invalid cell `{}` in `{}`", c, s)))
+        .collect()
+}
+
+/// `r1c1` 形式的位置
+fn cell_name((row, col): Cell) -> String {
+    format!("r{}c{}", row + 1, col + 1)
+}
+
+/// `r1c1-r1c2-...` 形式的位置列表
+fn cells_name(cells: &[Cell]) -> String {
+    cells
+        .iter()
+        .map(|c| cell_name(*c))
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// 两两互不相同的位置对
+fn all_pairs(cells: &[Cell]) -> Vec<(Cell, Cell)> {
+    let mut pairs = vec![];
+    for (i, a) in cells.iter().enumerate() {
+        for b in &cells[i + 1..] {
+            pairs.push((*a, *b));
+        }
+    }
+    pairs
+}
+
+/// 区域：9 个位置恰好填入 1~9 各一次，如行、列、3x3 小格、对角线
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unit {
+    /// 区域内的位置
+    pub cells: Vec<Cell>,
+}
+
+impl Unit {
+    /// 第 row 行
+    pub fn row(row: usize) -> Self {
+        Self {
+            cells: (0..9).map(|col| (row, col)).collect(),
+        }
+    }
+
+    /// 第 col 列
+    pub fn col(col: usize) -> Self {
+        Self {
+            cells: (0..9).map(|row| (row, col)).collect(),
+        }
+    }
+
+    /// 第 n 个 3x3 小格，从左到右、从上到下编号
+    pub fn block(n: usize) -> Self {
+        let row_s = (n / 3) * 3;
+        let col_s = (n % 3) * 3;
+        Self {
+            cells: (0..9).map(|i| (row_s + i / 3, col_s + i % 3)).collect(),
+        }
+    }
+
+    /// 主对角线（左上到右下）
+    pub fn diagonal() -> Self {
+        Self {
+            cells: (0..9).map(|i| (i, i)).collect(),
+        }
+    }
+
+    /// 副对角线（右上到左下）
+    pub fn anti_diagonal() -> Self {
+        Self {
+            cells: (0..9).map(|i| (i, 8 - i)).collect(),
+        }
+    }
+
+    /// 第 n 个窗口（hyper / windoku），左上角位于 r2c2、r2c6、r6c2、r6c6
+    pub fn window(n: usize) -> Self {
+        let row_s = 1 + (n / 2) * 4;
+        let col_s = 1 + (n % 2) * 4;
+        Self {
+            cells: (0..9).map(|i| (row_s + i / 3, col_s + i % 3)).collect(),
+        }
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for i in 0..9 {
+            if *self == Unit::row(i) {
+                return write!(f, "row {}", i + 1);
+            }
+            if *self == Unit::col(i) {
+                return write!(f, "column {}", i + 1);
+            }
+            if *self == Unit::block(i) {
+                return write!(f, "box {}", i + 1);
+            }
+        }
+        if *self == Unit::diagonal() {
+            return write!(f, "main diagonal");
+        }
+        if *self == Unit::anti_diagonal() {
+            return write!(f, "anti-diagonal");
+        }
+        for n in 0..4 {
+            if *self == Unit::window(n) {
+                return write!(f, "window {}", n + 1);
+            }
+        }
+        write!(f, "region {}", cells_name(&self.cells))
+    }
+}
+
+impl Constraint for Unit {
+    fn peers(&self) -> Vec<(Cell, Cell)> {
+        all_pairs(&self.cells)
+    }
+
+    /// 某个数字在区域内只剩一个位置可填时填入，无处可填时矛盾
+    fn propagate(&self, board: &mut SudokuBoard) -> Propagation {
+        let mut changed = false;
+        for digit in 1..10 {
+            let placed = self
+                .cells
+                .iter()
+                .filter(|&&(r, c)| board.get(r, c).val == digit)
+                .count();
+            match placed {
+                0 => {}
+                1 => continue,
+                _ => return Propagation::Contradiction,
+            }
+            let spots: Vec<Cell> = self
+                .cells
+                .iter()
+                .filter(|&&(r, c)| {
+                    let pos = board.get(r, c);
+                    pos.val == 0 && pos.digits.contains(&digit)
+                })
+                .cloned()
+                .collect();
+            match spots[..] {
+                [] => return Propagation::Contradiction,
+                [(row, col)] => {
+                    log::debug!("({},{}) solved: {}", row, col, digit);
+                    board.set(digit, row, col);
+                    changed = true;
+                }
+                _ => {}
+            }
+        }
+        Propagation::changed(changed)
+    }
+
+    fn check(&self, grid: &Grid) -> bool {
+        let mut seen = HashSet::new();
+        self.cells
+            .iter()
+            .map(|&(r, c)| grid[r][c])
+            .filter(|d| *d != 0)
+            .all(|d| seen.insert(d))
+    }
+}
+
+/// 马步偏移
+const KNIGHT_MOVES: [(isize, isize); 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
+];
+
+/// 王步偏移，上下左右已属于同行同列，只需对角
+const KING_MOVES: [(isize, isize); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+
+/// 棋步约束：相隔指定棋步的两个位置数字不同
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AntiMove {
+    /// 相对偏移
+    pub offsets: Vec<(isize, isize)>,
+}
+
+impl AntiMove {
+    /// 反马步
+    pub fn knight() -> Self {
+        Self {
+            offsets: KNIGHT_MOVES.to_vec(),
+        }
+    }
+
+    /// 反王步（对角相邻）
+    pub fn king() -> Self {
+        Self {
+            offsets: KING_MOVES.to_vec(),
+        }
+    }
+
+    /// 所有相隔指定棋步的位置对
+    fn pairs(&self) -> Vec<(Cell, Cell)> {
+        let mut pairs = vec![];
+        for row in 0..9 {
+            for col in 0..9 {
+                for (dr, dc) in &self.offsets {
+                    let r = row as isize + dr;
+                    let c = col as isize + dc;
+                    if (0..9).contains(&r) && (0..9).contains(&c) {
+                        pairs.push(((row, col), (r as usize, c as usize)));
+                    }
+                }
+            }
+        }
+        pairs
+    }
+}
+
+impl fmt::Display for AntiMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if *self == AntiMove::knight() {
+            write!(f, "anti-knight")
+        } else if *self == AntiMove::king() {
+            write!(f, "anti-king")
+        } else {
+            let moves: Vec<String> = self
+                .offsets
+                .iter()
+                .map(|(dr, dc)| format!("({:+},{:+})", dr, dc))
+                .collect();
+            write!(f, "anti-move {}", moves.join(" "))
+        }
+    }
+}
+
+impl Constraint for AntiMove {
+    fn peers(&self) -> Vec<(Cell, Cell)> {
+        self.pairs()
+    }
+
+    /// 全部由同组关系表完成
+    fn propagate(&self, _board: &mut SudokuBoard) -> Propagation {
+        Propagation::Unchanged
+    }
+
+    fn check(&self, grid: &Grid) -> bool {
+        self.pairs()
+            .iter()
+            .all(|&((r1, c1), (r2, c2))| grid[r1][c1] == 0 || grid[r1][c1] != grid[r2][c2])
+    }
+}
+
+/// 求和约束传播：cells 为各位置的候选数字，各位置数字之和必须是 targets 之一。
+///
+/// 返回收窄后的各位置候选数字及 targets，无解时返回 None。箭头以圆圈候选数字作为
+/// targets，杀手笼子以单个和作为 targets。
+pub fn propagate_sum(
+    cells: &[HashSet<u32>],
+    targets: &HashSet<u32>,
+) -> Option<(Vec<HashSet<u32>>, HashSet<u32>)> {
+    let mut mins = vec![];
+    let mut maxs = vec![];
+    for digits in cells {
+        mins.push(*digits.iter().min()?);
+        maxs.push(*digits.iter().max()?);
+    }
+    let total_min: u32 = mins.iter().sum();
+    let total_max: u32 = maxs.iter().sum();
+
+    // 和只能落在各位置最小、最大值之和之间
+    let targets: HashSet<u32> = targets
+        .iter()
+        .filter(|t| (total_min..=total_max).contains(*t))
+        .cloned()
+        .collect();
+    let target_min = *targets.iter().min()?;
+    let target_max = *targets.iter().max()?;
+
+    // 每个位置的数字加上其余位置的取值范围，要能够达到 targets
+    let mut narrowed = vec![];
+    for (i, digits) in cells.iter().enumerate() {
+        let rest_min = total_min - mins[i];
+        let rest_max = total_max - maxs[i];
+        let digits: HashSet<u32> = digits
+            .iter()
+            .filter(|d| **d + rest_min <= target_max && **d + rest_max >= target_min)
+            .cloned()
+            .collect();
+        if digits.is_empty() {
+            return None;
+        }
+        narrowed.push(digits);
+    }
+    Some((narrowed, targets))
+}
+
+/// 在棋盘上对一组位置进行求和传播
+fn propagate_cells_sum(
+    board: &mut SudokuBoard,
+    cells: &[Cell],
+    targets: &HashSet<u32>,
+) -> Option<(bool, HashSet<u32>)> {
+    let digits: Vec<_> = cells.iter().map(|&(r, c)| board.candidates(r, c)).collect();
+    let (digits, targets) = propagate_sum(&digits, targets)?;
+    let mut changed = false;
+    for (&(r, c), digits) in cells.iter().zip(digits.iter()) {
+        changed |= board.restrict(r, c, digits);
+    }
+    Some((changed, targets))
+}
+
+/// 已填数字之和及空位个数
+fn partial_sum(grid: &Grid, cells: &[Cell]) -> (u32, u32) {
+    cells
+        .iter()
+        .fold((0, 0), |(sum, free), &(r, c)| match grid[r][c] {
+            0 => (sum, free + 1),
+            d => (sum + d, free),
+        })
+}
+
+/// 箭头：圆圈位置的数字等于箭身各位置数字之和
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Arrow {
+    /// 圆圈位置
+    pub circle: Cell,
+    /// 箭身位置，从圆圈出发依次排列
+    pub shaft: Vec<Cell>,
+}
+
+impl FromStr for Arrow {
+    type Err = String;
+
+    /// 解析 `r5c5-r4c4-r3c3` 形式的箭头，第一个位置为圆圈
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let cells = parse_cells(s)?;
+        if cells.len() < 2 {
+            return Err(format!(
+                "arrow `{}` needs a circle and at least one cell",
+                s
+            ));
+        }
+        Ok(Self {
+            circle: cells[0],
+            shaft: cells[1..].to_vec(),
+        })
+    }
+}
+
+impl fmt::Display for Arrow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "arrow {}-{}",
+            cell_name(self.circle),
+            cells_name(&self.shaft)
+        )
+    }
+}
+
+impl Constraint for Arrow {
+    fn cells(&self) -> Vec<Cell> {
+        let mut cells = vec![self.circle];
+        cells.extend(&self.shaft);
+        cells
+    }
+
+    fn propagate(&self, board: &mut SudokuBoard) -> Propagation {
+        let (row, col) = self.circle;
+        let targets = board.candidates(row, col);
+        match propagate_cells_sum(board, &self.shaft, &targets) {
+            Some((changed, targets)) => {
+                Propagation::changed(board.restrict(row, col, &targets) || changed)
+            }
+            None => Propagation::Contradiction,
+        }
+    }
+
+    fn check(&self, grid: &Grid) -> bool {
+        let (sum, free) = partial_sum(grid, &self.shaft);
+        match grid[self.circle.0][self.circle.1] {
+            // 圆圈未填，箭身至多为 9
+            0 => sum + free <= 9,
+            // 箭身已填满时必须相等，否则剩余位置至少各填 1
+            circle if free == 0 => sum == circle,
+            circle => sum + free <= circle,
+        }
+    }
+}
+
+/// 杀手笼子：笼内数字互不相同，给出和时笼内数字之和等于该值
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cage {
+    /// 笼内数字之和
+    pub sum: Option<u32>,
+    /// 笼内位置
+    pub cells: Vec<Cell>,
+}
+
+impl FromStr for Cage {
+    type Err = String;
+
+    /// 解析 `15=r1c1-r1c2-r2c1` 形式的笼子，省略 `15=` 时不限制和
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (sum, cells) = match s.split_once('=') {
+            Some((sum, cells)) => {
+                let sum = sum
+                    .trim()
+                    .parse::<u32>()
+                    .map_err(|_| format!("invalid sum in cage `{}`", s))?;
+                (Some(sum), cells)
+            }
+            None => (None, s),
+        };
+        let cells = parse_cells(cells)?;
+        if cells.is_empty() || cells.len() > 9 {
+            return Err(format!("cage `{}` must contain 1 to 9 cells", s));
+        }
+        Ok(Self { sum, cells })
+    }
+}
+
+impl fmt::Display for Cage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.sum {
+            Some(sum) => write!(f, "cage {}={}", sum, cells_name(&self.cells)),
+            None => write!(f, "cage {}", cells_name(&self.cells)),
+        }
+    }
+}
+
+impl Constraint for Cage {
+    fn peers(&self) -> Vec<(Cell, Cell)> {
+        all_pairs(&self.cells)
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+        self.cells.clone()
+    }
+
+    fn propagate(&self, board: &mut SudokuBoard) -> Propagation {
+        let Some(sum) = self.sum else {
+            return Propagation::Unchanged;
+        };
+        match propagate_cells_sum(board, &self.cells, &HashSet::from([sum])) {
+            Some((changed, _)) => Propagation::changed(changed),
+            None => Propagation::Contradiction,
+        }
+    }
+
+    fn check(&self, grid: &Grid) -> bool {
+        let mut seen = HashSet::new();
+        let distinct = self
+            .cells
+            .iter()
+            .map(|&(r, c)| grid[r][c])
+            .filter(|d| *d != 0)
+            .all(|d| seen.insert(d));
+        let Some(target) = self.sum else {
+            return distinct;
+        };
+        let (sum, free) = partial_sum(grid, &self.cells);
+        distinct && sum + free <= target && sum + free * 9 >= target
+    }
+}
+
+/// 温度计：从球部出发数字严格递增
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thermo {
+    /// 位置，第一个为球部
+    pub cells: Vec<Cell>,
+}
+
+impl FromStr for Thermo {
+    type Err = String;
+
+    /// 解析 `r1c1-r1c2-r1c3` 形式的温度计，第一个位置为球部
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let cells = parse_cells(s)?;
+        if cells.len() < 2 || cells.len() > 9 {
+            return Err(format!("thermometer `{}` must contain 2 to 9 cells", s));
+        }
+        Ok(Self { cells })
+    }
+}
+
+impl fmt::Display for Thermo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "thermometer {}", cells_name(&self.cells))
+    }
+}
+
+impl Constraint for Thermo {
+    /// 严格递增，各位置数字互不相同
+    fn peers(&self) -> Vec<(Cell, Cell)> {
+        all_pairs(&self.cells)
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+        self.cells.clone()
+    }
+
+    /// 每个位置必须大于前一位置的最小值，小于后一位置的最大值
+    fn propagate(&self, board: &mut SudokuBoard) -> Propagation {
+        let mut digits: Vec<HashSet<u32>> = self
+            .cells
+            .iter()
+            .map(|&(r, c)| board.candidates(r, c))
+            .collect();
+        let mut low = 0;
+        for d in digits.iter_mut() {
+            d.retain(|x| *x > low);
+            match d.iter().min() {
+                Some(min) => low = *min,
+                None => return Propagation::Contradiction,
+            }
+        }
+        let mut high = 10;
+        for d in digits.iter_mut().rev() {
+            d.retain(|x| *x < high);
+            match d.iter().max() {
+                Some(max) => high = *max,
+                None => return Propagation::Contradiction,
+            }
+        }
+        let mut changed = false;
+        for (&(r, c), d) in self.cells.iter().zip(digits.iter()) {
+            changed |= board.restrict(r, c, d);
+        }
+        Propagation::changed(changed)
+    }
+
+    fn check(&self, grid: &Grid) -> bool {
+        let n = self.cells.len() as u32;
+        let mut prev: Option<(u32, u32)> = None;
+        for (i, &(r, c)) in self.cells.iter().enumerate() {
+            let (i, d) = (i as u32, grid[r][c]);
+            if d == 0 {
+                continue;
+            }
+            // 前后剩余位置要能放下递增的数字
+            if d < i + 1 || d + (n - 1 - i) > 9 {
+                return false;
+            }
+            if let Some((j, p)) = prev {
+                if d < p + (i - j) {
+                    return false;
+                }
+            }
+            prev = Some((i, d));
+        }
+        true
+    }
+}
+
+/// 相邻两格之间的二元约束
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairKind {
+    /// X：两数之和为 10
+    X,
+    /// V：两数之和为 5
+    V,
+    /// 负约束：未标记的相邻两格之和既不是 10 也不是 5
+    NotXv,
+    /// 白点：两数相差 1
+    White,
+    /// 黑点：一个数是另一个的 2 倍
+    Black,
+}
+
+impl PairKind {
+    /// 两个位置分别填入 a、b 是否满足约束
+    pub fn allows(self, a: u32, b: u32) -> bool {
+        match self {
+            PairKind::X => a + b == 10,
+            PairKind::V => a + b == 5,
+            PairKind::NotXv => a + b != 10 && a + b != 5,
+            PairKind::White => a.abs_diff(b) == 1,
+            PairKind::Black => a == b * 2 || b == a * 2,
+        }
+    }
+
+    /// XV 标记字符，其余类型没有字符
+    pub fn to_char(self) -> Option<char> {
+        match self {
+            PairKind::X => Some('X'),
+            PairKind::V => Some('V'),
+            _ => None,
+        }
+    }
+}
+
+/// 二元约束
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pair {
+    /// 约束类型
+    pub kind: PairKind,
+    /// 第一个位置
+    pub a: Cell,
+    /// 第二个位置
+    pub b: Cell,
+}
+
+impl FromStr for Pair {
+    type Err = String;
+
+    /// 解析 `x:r1c1-r1c2`、`v:r1c1-r2c1` 形式的 XV 标记或 `w:`、`b:` 开头的白点、黑点，
+    /// 两个位置必须相邻
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, cells) = s
+            .split_once(':')
+            .ok_or_else(|| format!("invalid marker `{}`", s))?;
+        let kind = match kind.trim() {
+            "x" | "X" => PairKind::X,
+            "v" | "V" => PairKind::V,
+            "w" | "W" => PairKind::White,
+            "b" | "B" => PairKind::Black,
+            _ => return Err(format!("unknown marker kind `{}`", kind)),
+        };
+        let (a, b) = match parse_cells(cells)?[..] {
+            [a, b] => (a, b),
+            _ => return Err(format!("marker `{}` needs exactly two cells", s)),
+        };
+        if a.0.abs_diff(b.0) + a.1.abs_diff(b.1) != 1 {
+            return Err(format!("cells of marker `{}` are not adjacent", s));
+        }
+        Ok(Self { kind, a, b })
+    }
+}
+
+impl fmt::Display for Pair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            PairKind::X => "X",
+            PairKind::V => "V",
+            PairKind::NotXv => "no X or V",
+            PairKind::White => "white dot",
+            PairKind::Black => "black dot",
+        };
+        write!(
+            f,
+            "{} between {} and {}",
+            kind,
+            cell_name(self.a),
+            cell_name(self.b)
+        )
+    }
+}
+
+impl Constraint for Pair {
+    fn cells(&self) -> Vec<Cell> {
+        vec![self.a, self.b]
+    }
+
+    /// 每个候选数字在另一个位置都要有能够配对的数字
+    fn propagate(&self, board: &mut SudokuBoard) -> Propagation {
+        let a = board.candidates(self.a.0, self.a.1);
+        let b = board.candidates(self.b.0, self.b.1);
+        let a2: HashSet<u32> = a
+            .iter()
+            .filter(|x| b.iter().any(|y| self.kind.allows(**x, *y)))
+            .cloned()
+            .collect();
+        let b2: HashSet<u32> = b
+            .iter()
+            .filter(|y| a.iter().any(|x| self.kind.allows(*x, **y)))
+            .cloned()
+            .collect();
+        if a2.is_empty() || b2.is_empty() {
+            return Propagation::Contradiction;
+        }
+        let changed = board.restrict(self.a.0, self.a.1, &a2);
+        Propagation::changed(board.restrict(self.b.0, self.b.1, &b2) || changed)
+    }
+
+    fn check(&self, grid: &Grid) -> bool {
+        match (grid[self.a.0][self.a.1], grid[self.b.0][self.b.1]) {
+            (0, _) | (_, 0) => true,
+            (a, b) => self.kind.allows(a, b),
+        }
+    }
+}
+
+/// 模板：位置只能填入给定的几个数字
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Allowed {
+    /// 位置
+    pub cell: Cell,
+    /// 允许的数字
+    pub digits: HashSet<u32>,
+}
+
+impl Allowed {
+    /// 解析 `{"r1c1": [2, 5, 7], ...}` 形式的模板，每个键是一个位置，值为允许的数字
+    pub fn parse_template(json: &Json) -> Result<Vec<Self>, String> {
+        let items = match json {
+            Json::Object(items) => items,
+            _ => return Err("template must be a JSON object of cells".to_string()),
+        };
+        items
+            .iter()
+            .map(|(key, value)| {
+                let cell = parse_cell(key).ok_or_else(|| format!("invalid cell `{}`", key))?;
+                let digits = value
+                    .as_array()
+                    .ok_or_else(|| format!("digits of `{}` must be an array", key))?
+                    .iter()
+                    .map(|d| match d.as_u32() {
+                        Some(d @ 1..=9) => Ok(d),
+                        _ => Err(format!("invalid digit {} for `{}`", d, key)),
+                    })
+                    .collect::<Result<HashSet<u32>, String>>()?;
+                if digits.is_empty() {
+                    return Err(format!("no digit allowed for `{}`", key));
+                }
+                Ok(Self { cell, digits })
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Allowed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut digits: Vec<u32> = self.digits.iter().cloned().collect();
+        digits.sort();
+        let digits: Vec<String> = digits.iter().map(u32::to_string).collect();
+        write!(
+            f,
+            "{} limited to {}",
+            cell_name(self.cell),
+            digits.join(",")
+        )
+    }
+}
+
+impl Constraint for Allowed {
+    fn cells(&self) -> Vec<Cell> {
+        vec![self.cell]
+    }
+
+    fn propagate(&self, board: &mut SudokuBoard) -> Propagation {
+        let (row, col) = self.cell;
+        if board.candidates(row, col).is_disjoint(&self.digits) {
+            return Propagation::Contradiction;
+        }
+        Propagation::changed(board.restrict(row, col, &self.digits))
+    }
+
+    fn check(&self, grid: &Grid) -> bool {
+        let d = grid[self.cell.0][self.cell.1];
+        d == 0 || self.digits.contains(&d)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    use super::{
+        parse_cell, propagate_sum, Allowed, AntiMove, Arrow, Cage, Constraint, Pair, PairKind,
+        Propagation, Thermo, Unit,
+    };
+    use crate::board::SudokuBoard;
+    use crate::json::Json;
+    use crate::variant::Variant;
+
+    #[test]
+    fn test_parse_cells() {
+        assert_eq!(parse_cell("r1c9"), Some((0, 8)));
+        assert_eq!(parse_cell("R5C5"), Some((4, 4)));
+        assert_eq!(parse_cell("r0c1"), None);
+        assert_eq!(parse_cell("c1r1"), None);
+
+        let arrow: Arrow = "r5c5-r4c4-r3c3".parse().unwrap();
+        assert_eq!(arrow.circle, (4, 4));
+        assert_eq!(arrow.shaft, vec![(3, 3), (2, 2)]);
+        assert!("r5c5".parse::<Arrow>().is_err());
+        assert!("r5c5-x".parse::<Arrow>().is_err());
+
+        let cage: Cage = "15=r1c1-r1c2-r2c1".parse().unwrap();
+        assert_eq!(cage.sum, Some(15));
+        assert_eq!(cage.cells.len(), 3);
+        assert_eq!("r1c1-r1c2".parse::<Cage>().unwrap().sum, None);
+        assert!("x=r1c1".parse::<Cage>().is_err());
+
+        let pair: Pair = "x:r1c1-r1c2".parse().unwrap();
+        assert_eq!(pair.kind, PairKind::X);
+        assert_eq!((pair.a, pair.b), ((0, 0), (0, 1)));
+        assert!("v:r1c1-r3c1".parse::<Pair>().is_err());
+        assert!("z:r1c1-r1c2".parse::<Pair>().is_err());
+    }
+
+    #[test]
+    fn test_unit() {
+        assert_eq!(Unit::block(4).cells[0], (3, 3));
+        assert_eq!(Unit::anti_diagonal().cells[0], (0, 8));
+        assert_eq!(Unit::window(3).cells[0], (5, 5));
+        assert_eq!(Unit::window(1).cells[8], (3, 7));
+        assert_eq!(Unit::row(0).peers().len(), 36);
+
+        // 第一行只剩 (0,8) 能填 9
+        let mut grid = [[0; 9]; 9];
+        grid[0] = [1, 2, 3, 4, 5, 6, 7, 8, 0];
+        let mut board = SudokuBoard::new_with(&grid, Arc::default());
+        assert_eq!(Unit::row(0).propagate(&mut board), Propagation::Changed);
+        assert_eq!(board.get(0, 8).val, 9);
+
+        grid[0][8] = 1;
+        assert!(!Unit::row(0).check(&grid));
+        assert!(Unit::col(8).check(&grid));
+    }
+
+    #[test]
+    fn test_anti_move() {
+        let mut grid = [[0; 9]; 9];
+        grid[0][0] = 1;
+        grid[1][2] = 1;
+        assert!(!AntiMove::knight().check(&grid));
+        assert!(AntiMove::king().check(&grid));
+        grid[1][2] = 0;
+        grid[1][1] = 1;
+        assert!(!AntiMove::king().check(&grid));
+    }
+
+    #[test]
+    fn test_propagate_sum() {
+        let all: HashSet<u32> = (1..10).collect();
+        let (cells, targets) = propagate_sum(&[all.clone(), all.clone()], &all).unwrap();
+        assert_eq!(targets, (2..10).collect());
+        assert_eq!(cells[0], (1..9).collect());
+
+        // 固定和 17 的两个位置只能是 8、9
+        let (cells, _) = propagate_sum(&[all.clone(), all.clone()], &HashSet::from([17])).unwrap();
+        assert_eq!(cells[1], HashSet::from([8, 9]));
+
+        // 无法达到的和
+        assert!(propagate_sum(
+            &[HashSet::from([5]), HashSet::from([6])],
+            &HashSet::from([3])
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_arrow() {
+        let arrow: Arrow = "r1c1-r1c2-r1c3".parse().unwrap();
+
+        // 传播：圆圈至少为 2，箭身至多为 8
+        let mut board = SudokuBoard::new_with(&[[0; 9]; 9], Arc::default());
+        assert_eq!(arrow.propagate(&mut board), Propagation::Changed);
+        assert_eq!(board.get(0, 0).digits, (2..10).collect());
+        assert_eq!(board.get(0, 1).digits, (1..9).collect());
+
+        // 圆圈已定时箭身和必须相等
+        let mut grid = [[0; 9]; 9];
+        grid[0][0] = 3;
+        let mut board = SudokuBoard::new_with(&grid, Arc::default());
+        assert_eq!(arrow.propagate(&mut board), Propagation::Changed);
+        assert_eq!(board.get(0, 1).digits, HashSet::from([1, 2]));
+
+        assert!(arrow.check(&grid));
+        grid[0][1] = 3;
+        assert!(!arrow.check(&grid));
+        grid[0][1] = 2;
+        grid[0][2] = 1;
+        assert!(arrow.check(&grid));
+        grid[0][2] = 2;
+        assert!(!arrow.check(&grid));
+    }
+
+    #[test]
+    fn test_cage() {
+        let cage: Cage = "17=r1c1-r2c1".parse().unwrap();
+        let mut board = SudokuBoard::new_with(&[[0; 9]; 9], Arc::default());
+        assert_eq!(cage.propagate(&mut board), Propagation::Changed);
+        assert_eq!(board.get(1, 0).digits, HashSet::from([8, 9]));
+
+        let mut grid = [[0; 9]; 9];
+        grid[0][0] = 7;
+        assert!(!cage.check(&grid));
+        grid[0][0] = 8;
+        assert!(cage.check(&grid));
+    }
+
+    #[test]
+    fn test_pair() {
+        let mut variant = Variant::default();
+        variant.add("v:r1c1-r1c2".parse::<Pair>().unwrap());
+        variant.add_negative_xv();
+
+        // V 两侧只能是 1~4
+        let mut board = SudokuBoard::new_with(&[[0; 9]; 9], Arc::new(variant));
+        let pair: Pair = "v:r1c1-r1c2".parse().unwrap();
+        assert_eq!(pair.propagate(&mut board), Propagation::Changed);
+        assert_eq!(board.get(0, 0).digits, (1..5).collect());
+
+        let mut grid = [[0; 9]; 9];
+        grid[0][1] = 4;
+        assert!(pair.check(&grid));
+        grid[0][0] = 2;
+        assert!(!pair.check(&grid));
+    }
+
+    #[test]
+    fn test_thermo() {
+        let thermo: Thermo = "r1c1-r1c2-r1c3".parse().unwrap();
+        assert!("r1c1".parse::<Thermo>().is_err());
+
+        // 三格温度计：球部 1~7，末端 3~9
+        let mut board = SudokuBoard::new_with(&[[0; 9]; 9], Arc::default());
+        assert_eq!(thermo.propagate(&mut board), Propagation::Changed);
+        assert_eq!(board.get(0, 0).digits, (1..8).collect());
+        assert_eq!(board.get(0, 2).digits, (3..10).collect());
+
+        let mut grid = [[0; 9]; 9];
+        grid[0][0] = 4;
+        grid[0][2] = 5;
+        assert!(!thermo.check(&grid));
+        grid[0][2] = 6;
+        assert!(thermo.check(&grid));
+        grid[0][0] = 8;
+        assert!(!thermo.check(&grid));
+    }
+
+    #[test]
+    fn test_kropki() {
+        let white: Pair = "w:r1c1-r1c2".parse().unwrap();
+        let black: Pair = "b:r2c1-r2c2".parse().unwrap();
+        assert_eq!(white.kind, PairKind::White);
+        assert_eq!(white.kind.to_char(), None);
+
+        // 黑点两侧不可能是 5、7、9
+        let mut board = SudokuBoard::new_with(&[[0; 9]; 9], Arc::default());
+        assert_eq!(black.propagate(&mut board), Propagation::Changed);
+        assert_eq!(board.get(1, 0).digits, HashSet::from([1, 2, 3, 4, 6, 8]));
+
+        assert!(PairKind::White.allows(3, 4));
+        assert!(!PairKind::White.allows(3, 5));
+        assert!(PairKind::Black.allows(8, 4));
+        assert!(!PairKind::Black.allows(3, 5));
+    }
+
+    #[test]
+    fn test_allowed() {
+        let json = Json::parse(r#"{"r1c1": [2, 5, 7], "R9C9": [1]}"#).unwrap();
+        let template = Allowed::parse_template(&json).unwrap();
+        assert_eq!(template.len(), 2);
+        assert_eq!(template[1].cell, (8, 8));
+        for bad in [
+            r#"[1]"#,
+            r#"{"r0c1": [1]}"#,
+            r#"{"r1c1": []}"#,
+            r#"{"r1c1": [10]}"#,
+        ] {
+            assert!(Allowed::parse_template(&Json::parse(bad).unwrap()).is_err());
+        }
+
+        let mut board = SudokuBoard::new_with(&[[0; 9]; 9], Arc::default());
+        assert_eq!(template[0].propagate(&mut board), Propagation::Changed);
+        assert_eq!(board.get(0, 0).digits, HashSet::from([2, 5, 7]));
+        assert_eq!(template[0].propagate(&mut board), Propagation::Unchanged);
+        board.restrict(0, 0, &HashSet::from([1, 3]));
+        assert_eq!(
+            template[0].propagate(&mut board),
+            Propagation::Contradiction
+        );
+
+        let mut grid = [[0; 9]; 9];
+        assert!(template[0].check(&grid));
+        grid[0][0] = 5;
+        assert!(template[0].check(&grid));
+        grid[0][0] = 4;
+        assert!(!template[0].check(&grid));
+    }
+
+    #[test]
+    fn test_display() {
+        let names = [
+            Unit::row(0).to_string(),
+            Unit::col(8).to_string(),
+            Unit::block(2).to_string(),
+            Unit::anti_diagonal().to_string(),
+            Unit::window(3).to_string(),
+            AntiMove::knight().to_string(),
+            "r5c5-r4c4-r3c3".parse::<Arrow>().unwrap().to_string(),
+            "15=r1c1-r1c2".parse::<Cage>().unwrap().to_string(),
+            "r1c1-r1c2-r1c3".parse::<Thermo>().unwrap().to_string(),
+            "w:r1c1-r2c1".parse::<Pair>().unwrap().to_string(),
+            Allowed {
+                cell: (8, 8),
+                digits: HashSet::from([7, 2]),
+            }
+            .to_string(),
+        ];
+        assert_eq!(
+            names,
+            [
+                "row 1",
+                "column 9",
+                "box 3",
+                "anti-diagonal",
+                "window 4",
+                "anti-knight",
+                "arrow r5c5-r4c4-r3c3",
+                "cage 15=r1c1-r1c2",
+                "thermometer r1c1-r1c2-r1c3",
+                "white dot between r1c1 and r2c1",
+                "r9c9 limited to 2,7",
+            ]
+        );
+    }
+}
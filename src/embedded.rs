@@ -0,0 +1,331 @@
+//! 只用栈上定长数组的棋盘和求解，供固件和没有分配器的 WASM 使用
+//!
+//! 本模块只依赖 `core`，不分配内存。几何尺寸由常量泛型给出：边长 `N`、宫的行数和列数，
+//! 以及格子总数 `CELLS`（须等于 `N * N`，稳定版 Rust 不能在类型中写出 `N * N`）。
+//! 每格的数字存于 `[u8; CELLS]`，允许的数字以位掩码存于 `[u16; CELLS]`，边长最大为 16。
+//! 求解为迭代的回溯搜索，每次选择候选最少的空格，显式的栈也是定长数组。
+
+use crate::board::Grid;
+
+/// 标准 9x9 数独
+pub type Board81 = Board<9, 3, 3, 81>;
+
+/// 求解结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolveOutcome {
+    /// 只有一个解，已填入棋盘
+    Unique,
+    /// 不止一个解，已填入找到的第一个
+    Multiple,
+    /// 无解或给定数字冲突，棋盘不变
+    NoSolution,
+}
+
+/// 定长棋盘，数字 0 为空格
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Board<const N: usize, const BOX_ROWS: usize, const BOX_COLS: usize, const CELLS: usize> {
+    /// 按行排列的数字
+    cells: [u8; CELLS],
+    /// 每格允许的数字，第 d-1 位对应数字 d，可用于奇偶格等限制
+    allowed: [u16; CELLS],
+}
+
+/// 搜索中下一步要填的格子
+enum Pick {
+    /// 所有格子都已填满
+    Full,
+    /// 某个空格已没有候选
+    Dead,
+    /// 候选最少的空格及其候选
+    Cell(usize, u16),
+}
+
+impl<const N: usize, const BOX_ROWS: usize, const BOX_COLS: usize, const CELLS: usize> Default
+    for Board<N, BOX_ROWS, BOX_COLS, CELLS>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, const BOX_ROWS: usize, const BOX_COLS: usize, const CELLS: usize>
+    Board<N, BOX_ROWS, BOX_COLS, CELLS>
+{
+    /// 编译时检查几何尺寸
+    const GEOMETRY: () = assert!(
+        N >= 1 && N <= 16 && N == BOX_ROWS * BOX_COLS && CELLS == N * N,
+        "invalid board geometry"
+    );
+
+    /// 所有数字
+    const ALL: u16 = ((1u32 << N) - 1) as u16;
+
+    /// 空棋盘
+    pub const fn new() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let _ = Self::GEOMETRY;
+        Self {
+            cells: [0; CELLS],
+            allowed: [Self::ALL; CELLS],
+        }
+    }
+
+    /// 由按行排列的数字构建，有大于 N 的数字时返回 None
+    pub fn from_cells(cells: [u8; CELLS]) -> Option<Self> {
+        if cells.iter().any(|&d| d as usize > N) {
+            return None;
+        }
+        Some(Self {
+            cells,
+            ..Self::new()
+        })
+    }
+
+    /// 解析 `CELLS` 个数字，`0` 或 `.` 为空格，忽略空白；只适用于边长不超过 9 的棋盘
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut board = Self::new();
+        let mut n = 0;
+        for c in s.chars().filter(|c| !c.is_whitespace()) {
+            let d = match c {
+                '.' => 0,
+                c => c.to_digit(10).filter(|&d| d as usize <= N)?,
+            };
+            *board.cells.get_mut(n)? = d as u8;
+            n += 1;
+        }
+        (n == CELLS).then_some(board)
+    }
+
+    /// 按行排列的数字
+    pub fn cells(&self) -> &[u8; CELLS] {
+        &self.cells
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> u8 {
+        self.cells[row * N + col]
+    }
+
+    /// 填入数字，0 为清空
+    pub fn set(&mut self, row: usize, col: usize, digit: u8) {
+        assert!(digit as usize <= N, "digit out of range");
+        self.cells[row * N + col] = digit;
+    }
+
+    /// 某格允许的数字
+    pub fn allowed(&self, row: usize, col: usize) -> u16 {
+        self.allowed[row * N + col]
+    }
+
+    /// 限制某格允许的数字，第 d-1 位对应数字 d
+    pub fn restrict(&mut self, row: usize, col: usize, mask: u16) {
+        self.allowed[row * N + col] &= mask;
+    }
+
+    /// 所有格子都已填满
+    pub fn is_full(&self) -> bool {
+        self.cells.iter().all(|&d| d != 0)
+    }
+
+    /// 格子所在的行、列、宫
+    fn units(index: usize) -> (usize, usize, usize) {
+        let (row, col) = (index / N, index % N);
+        (row, col, row / BOX_ROWS * BOX_ROWS + col / BOX_COLS)
+    }
+
+    /// 求解并把解填入棋盘，最多找两个解以判断是否唯一
+    pub fn solve_into(&mut self) -> SolveOutcome {
+        // 每行、列、宫已用的数字
+        let (mut rows, mut cols, mut boxes) = ([0u16; N], [0u16; N], [0u16; N]);
+        for (i, &d) in self.cells.iter().enumerate() {
+            if d == 0 {
+                continue;
+            }
+            let bit = 1 << (d - 1);
+            let (r, c, b) = Self::units(i);
+            if (rows[r] | cols[c] | boxes[b]) & bit != 0 || self.allowed[i] & bit == 0 {
+                return SolveOutcome::NoSolution;
+            }
+            (rows[r], cols[c], boxes[b]) = (rows[r] | bit, cols[c] | bit, boxes[b] | bit);
+        }
+        let mut cells = self.cells;
+        // 每层为填入的格子和还没试过的候选
+        let mut stack = [(0usize, 0u16); CELLS];
+        let mut depth = 0;
+        let mut first = None;
+        loop {
+            let pick = {
+                let mut best = Pick::Full;
+                let mut fewest = u32::MAX;
+                for (i, &d) in cells.iter().enumerate() {
+                    if d != 0 {
+                        continue;
+                    }
+                    let (r, c, b) = Self::units(i);
+                    let candidates = self.allowed[i] & !(rows[r] | cols[c] | boxes[b]);
+                    if candidates == 0 {
+                        best = Pick::Dead;
+                        break;
+                    }
+                    if candidates.count_ones() < fewest {
+                        fewest = candidates.count_ones();
+                        best = Pick::Cell(i, candidates);
+                    }
+                }
+                best
+            };
+            match pick {
+                Pick::Full if first.is_some() => {
+                    self.cells = first.unwrap();
+                    return SolveOutcome::Multiple;
+                }
+                Pick::Full => first = Some(cells),
+                Pick::Dead => {}
+                Pick::Cell(i, candidates) => {
+                    stack[depth] = (i, candidates);
+                    depth += 1;
+                }
+            }
+            // 撤销栈顶格子的数字，换下一个候选；候选用完则退回上一层
+            loop {
+                if depth == 0 {
+                    return match first {
+                        Some(solution) => {
+                            self.cells = solution;
+                            SolveOutcome::Unique
+                        }
+                        None => SolveOutcome::NoSolution,
+                    };
+                }
+                let (i, candidates) = stack[depth - 1];
+                let (r, c, b) = Self::units(i);
+                if cells[i] != 0 {
+                    let bit = !(1 << (cells[i] - 1));
+                    (rows[r], cols[c], boxes[b]) = (rows[r] & bit, cols[c] & bit, boxes[b] & bit);
+                    cells[i] = 0;
+                }
+                if candidates == 0 {
+                    depth -= 1;
+                    continue;
+                }
+                let bit = candidates & candidates.wrapping_neg();
+                stack[depth - 1].1 = candidates & !bit;
+                cells[i] = bit.trailing_zeros() as u8 + 1;
+                (rows[r], cols[c], boxes[b]) = (rows[r] | bit, cols[c] | bit, boxes[b] | bit);
+                break;
+            }
+        }
+    }
+}
+
+impl From<&Grid> for Board81 {
+    fn from(grid: &Grid) -> Self {
+        let mut board = Self::new();
+        for (i, &d) in grid.iter().flatten().enumerate() {
+            board.cells[i] = d as u8;
+        }
+        board
+    }
+}
+
+impl From<&Board81> for Grid {
+    fn from(board: &Board81) -> Self {
+        let mut grid = [[0; 9]; 9];
+        for (i, &d) in board.cells.iter().enumerate() {
+            grid[i / 9][i % 9] = d as u32;
+        }
+        grid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Board, Board81, SolveOutcome};
+    use crate::board::{grid_line, parse_grid, Grid};
+    use crate::solver::solutions;
+    use crate::{SudokuBoard, Variant};
+
+    const PUZZLE: &str =
+        "530070000600195000098000060800060003400803001700020006060000280000419005000080079";
+
+    /// 每行、列、宫都恰好含 1 到 N
+    fn valid<const N: usize, const R: usize, const C: usize, const CELLS: usize>(
+        board: &Board<N, R, C, CELLS>,
+    ) -> bool {
+        (0..N).all(|i| {
+            let unit = |f: &dyn Fn(usize) -> (usize, usize)| {
+                (0..N).fold(0u32, |m, k| {
+                    let (r, c) = f(k);
+                    m | 1 << board.get(r, c)
+                }) == ((1 << (N + 1)) - 2)
+            };
+            unit(&|k| (i, k))
+                && unit(&|k| (k, i))
+                && unit(&|k| (i / R * R + k / C, i % R * C + k % C))
+        })
+    }
+
+    #[test]
+    fn test_solve_into() {
+        let mut board = Board81::parse(PUZZLE).unwrap();
+        assert_eq!(board.solve_into(), SolveOutcome::Unique);
+        let givens = parse_grid(PUZZLE).unwrap();
+        let expected = solutions(&SudokuBoard::new_with(&givens, Default::default()), 1);
+        assert_eq!(Grid::from(&board), expected[0]);
+
+        let mut empty = Board81::new();
+        assert_eq!(empty.solve_into(), SolveOutcome::Multiple);
+        assert!(empty.is_full() && valid(&empty));
+
+        let mut conflict = Board81::parse(&format!("55{}", "0".repeat(79))).unwrap();
+        assert_eq!(conflict.solve_into(), SolveOutcome::NoSolution);
+        assert_eq!(conflict.get(0, 1), 5);
+
+        // r1c3 只允许偶数时无解（唯一解中为 4，改为只允许奇数）
+        let mut restricted = Board81::from(&givens);
+        restricted.restrict(0, 2, 0b1_0101_0101);
+        assert_eq!(restricted.solve_into(), SolveOutcome::NoSolution);
+
+        assert!(Board81::parse("123").is_none());
+        assert!(Board81::from_cells([10; 81]).is_none());
+    }
+
+    #[test]
+    fn test_geometry() {
+        let mut mini = Board::<4, 2, 2, 16>::parse("1000 0000 0000 0001").unwrap();
+        assert_eq!(mini.solve_into(), SolveOutcome::Multiple);
+        assert!(valid(&mini));
+        let mut six = Board::<6, 2, 3, 36>::new();
+        six.set(0, 0, 6);
+        assert_eq!(six.solve_into(), SolveOutcome::Multiple);
+        assert!(valid(&six) && six.get(0, 0) == 6);
+        let mut sixteen = Board::<16, 4, 4, 256>::new();
+        assert_eq!(sixteen.solve_into(), SolveOutcome::Multiple);
+        assert!(valid(&sixteen));
+    }
+
+    #[test]
+    fn test_agrees_with_solver() {
+        // 与通用求解器比较解的个数
+        let puzzles = [
+            "000000010400000000020000000000050407008000300001090000300400200050100000000806000",
+            "100007090030020008009600500005300900010080002600004000300000010040000007007000300",
+            "000000000000003085001020000000507000004000100090000000500000073002010000000040009",
+        ];
+        for puzzle in puzzles {
+            let givens = parse_grid(puzzle).unwrap();
+            let found = solutions(
+                &SudokuBoard::new_with(&givens, Variant::standard().into()),
+                2,
+            );
+            let mut board = Board81::from(&givens);
+            let outcome = board.solve_into();
+            match found.len() {
+                1 => {
+                    assert_eq!(outcome, SolveOutcome::Unique, "{}", puzzle);
+                    assert_eq!(grid_line(&Grid::from(&board)), grid_line(&found[0]));
+                }
+                _ => assert_eq!(outcome, SolveOutcome::Multiple, "{}", puzzle),
+            }
+        }
+    }
+}
@@ -0,0 +1,295 @@
+//! 按编号从公开的题库取得题目
+//!
+//! 每个题库实现 [`Source`]。题库可以从 http:// 地址或本地目录读取，本程序不支持 TLS，
+//! https 上的题库需要先下载到本地，或经由终止 TLS 的代理访问。
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::board::{parse_grid, Grid};
+use crate::bot::Webhook;
+use crate::server::READ_TIMEOUT;
+
+/// Sudoku Exchange 题库的默认地址
+pub const SUDOKU_EXCHANGE: &str =
+    "https://raw.githubusercontent.com/grantm/sudoku-exchange-puzzle-bank/master";
+
+/// Sudoku Exchange 题库按难度分成的文件
+const SUDOKU_EXCHANGE_FILES: [&str; 4] = ["easy.txt", "medium.txt", "hard.txt", "diabolical.txt"];
+
+/// 题库
+pub trait Source {
+    /// 按编号取得题目
+    fn fetch(&self, id: &str) -> Result<Grid, String>;
+}
+
+/// 已知的题库
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceKind {
+    /// The Sudoku Exchange puzzle bank (github.com/grantm/sudoku-exchange-puzzle-bank), whose IDs
+    /// are 12 hexadecimal digits
+    SudokuExchange,
+}
+
+impl SourceKind {
+    /// 题库的实现，base 为 http:// 地址或本地目录，未给出时使用默认地址
+    pub fn source(self, base: Option<&str>) -> Box<dyn Source> {
+        match self {
+            SourceKind::SudokuExchange => Box::new(SudokuExchange {
+                base: base.unwrap_or(SUDOKU_EXCHANGE).to_string(),
+            }),
+        }
+    }
+}
+
+/// Sudoku Exchange 题库，每行为编号、题目和难度分数，以空白分隔
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SudokuExchange {
+    /// 题库文件所在的地址或目录
+    pub base: String,
+}
+
+impl Source for SudokuExchange {
+    fn fetch(&self, id: &str) -> Result<Grid, String> {
+        let id = id.to_ascii_lowercase();
+        if id.len() != 12 || !id.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(format!(
+                "{}: a Sudoku Exchange ID is 12 hexadecimal digits",
+                id
+            ));
+        }
+        for file in SUDOKU_EXCHANGE_FILES {
+            let reader = open(&self.base, file)?;
+            for line in reader.lines() {
+                let line = line.map_err(|e| format!("{}: {}", file, e))?;
+                let mut fields = line.split_whitespace();
+                if fields.next() == Some(id.as_str()) {
+                    let puzzle = fields.next().unwrap_or_default();
+                    return parse_grid(puzzle).map_err(|e| format!("{}: {}", id, e));
+                }
+            }
+        }
+        Err(format!(
+            "{}: not found in the Sudoku Exchange puzzle bank",
+            id
+        ))
+    }
+}
+
+/// 打开题库中的一个文件
+fn open(base: &str, file: &str) -> Result<Box<dyn BufRead>, String> {
+    if base.starts_with("https://") {
+        return Err(format!(
+            "{}: https is not supported, download the files into a directory or use an \
+             http:// mirror, and pass it with --base",
+            base
+        ));
+    }
+    if base.starts_with("http://") {
+        return http_get(&format!("{}/{}", base.trim_end_matches('/'), file));
+    }
+    let path = Path::new(base).join(file);
+    match File::open(&path) {
+        Ok(f) => Ok(Box::new(BufReader::new(f))),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+/// GET 一个 http:// 地址（格式与 webhook 相同），返回响应体
+pub fn http_get(url: &str) -> Result<Box<dyn BufRead>, String> {
+    let target = Webhook::parse(url)?;
+    let response = request(&target, "GET", &[], None, Some(READ_TIMEOUT));
+    match response.map_err(|e| format!("{}: {}", url, e))? {
+        (status, body) if status / 100 == 2 => Ok(body),
+        (status, _) => Err(format!("{}: server answered {}", url, status)),
+    }
+}
+
+/// POST JSON 请求体，返回状态码和响应体，不论状态码。headers 为额外的请求头，
+/// timeout 为等待响应的时间，None 为一直等待
+pub fn http_post(
+    url: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+    timeout: Option<Duration>,
+) -> Result<(u16, String), String> {
+    let target = Webhook::parse(url)?;
+    let mut text = String::new();
+    request(&target, "POST", headers, Some(body), timeout)
+        .and_then(|(status, mut body)| body.read_to_string(&mut text).map(|_| status))
+        .map(|status| (status, text))
+        .map_err(|e| format!("{}: {}", url, e))
+}
+
+/// 发送请求，返回状态码和响应体
+fn request(
+    target: &Webhook,
+    method: &str,
+    headers: &[(&str, &str)],
+    body: Option<&str>,
+    timeout: Option<Duration>,
+) -> io::Result<(u16, Box<dyn BufRead>)> {
+    let mut stream = TcpStream::connect(&target.host)?;
+    stream.set_read_timeout(timeout)?;
+    let mut request = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n",
+        method, target.path, target.host
+    );
+    for (name, value) in headers {
+        request += &format!("{}: {}\r\n", name, value);
+    }
+    if let Some(body) = body {
+        request += &format!(
+            "Content-Type: application/json\r\nContent-Length: {}\r\n",
+            body.len()
+        );
+    }
+    request += "\r\n";
+    request += body.unwrap_or_default();
+    stream.write_all(request.as_bytes())?;
+    let malformed = |what: &str| io::Error::new(io::ErrorKind::InvalidData, what.to_string());
+    let mut reader = BufReader::new(stream);
+    let mut status = String::new();
+    reader.read_line(&mut status)?;
+    let status: u16 = status
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| malformed("malformed status line"))?;
+    let mut chunked = false;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            chunked |= name.trim().eq_ignore_ascii_case("transfer-encoding")
+                && value.to_ascii_lowercase().contains("chunked");
+        }
+    }
+    if !chunked {
+        // 连接关闭即响应体结束
+        return Ok((status, Box::new(reader)));
+    }
+    let mut body = vec![];
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let size = line.trim().split(';').next().unwrap_or_default();
+        let size =
+            usize::from_str_radix(size, 16).map_err(|_| malformed("malformed chunk size"))?;
+        if size == 0 {
+            break;
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..])?;
+        reader.read_line(&mut line)?;
+    }
+    Ok((status, Box::new(Cursor::new(body))))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::{fs, process, thread};
+
+    use super::{SourceKind, SUDOKU_EXCHANGE};
+    use crate::board::{grid_line, parse_grid};
+
+    const PUZZLE: &str =
+        "530070000600195000098000060800060003400803001700020006060000280000419005000080079";
+
+    /// 题库文件的内容，题目在 hard.txt 中
+    fn bank(file: &str) -> String {
+        match file {
+            "hard.txt" => format!(
+                "0000183b305c  {}  4.5\n00a1b2c3d4e5  {}  4.9\n",
+                "0".repeat(81),
+                PUZZLE
+            ),
+            _ => format!("ffffffffffff  {}  1.2\n", "0".repeat(81)),
+        }
+    }
+
+    #[test]
+    fn test_local() {
+        let dir = std::env::temp_dir().join(format!("superdo-fetch-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for file in ["easy.txt", "medium.txt", "hard.txt", "diabolical.txt"] {
+            fs::write(dir.join(file), bank(file)).unwrap();
+        }
+        let source = SourceKind::SudokuExchange.source(dir.to_str());
+        let grid = source.fetch("00A1B2C3D4E5").unwrap();
+        assert_eq!(grid, parse_grid(PUZZLE).unwrap());
+        assert!(source
+            .fetch("123456789abc")
+            .unwrap_err()
+            .contains("not found"));
+        assert!(source.fetch("xyz").unwrap_err().contains("12 hexadecimal"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let source = SourceKind::SudokuExchange.source(None);
+        let error = source.fetch("00a1b2c3d4e5").unwrap_err();
+        assert!(error.starts_with(SUDOKU_EXCHANGE), "{}", error);
+        assert!(error.contains("--base"));
+    }
+
+    #[test]
+    fn test_http() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // hard.txt 分块发送，其余一次发送，missing.txt 不存在
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                // 读完请求头再回复，以免关闭时连接被重置
+                let lines: Vec<String> = BufReader::new(&stream)
+                    .lines()
+                    .map_while(Result::ok)
+                    .take_while(|line| !line.is_empty())
+                    .collect();
+                let path = lines[0].split_whitespace().nth(1).unwrap().to_string();
+                let file = path.rsplit('/').next().unwrap();
+                let body = bank(file);
+                let response = match file {
+                    "missing.txt" => "HTTP/1.1 404 Not Found\r\n\r\n".to_string(),
+                    "hard.txt" => {
+                        let (a, b) = body.split_at(20);
+                        format!(
+                            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+                             {:x}\r\n{}\r\n{:x};ext=1\r\n{}\r\n0\r\n\r\n",
+                            a.len(),
+                            a,
+                            b.len(),
+                            b
+                        )
+                    }
+                    _ => format!("HTTP/1.1 200 OK\r\n\r\n{}", body),
+                };
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        let base = format!("http://{}/bank/", addr);
+        let source = SourceKind::SudokuExchange.source(Some(&base));
+        let grid = source.fetch("00a1b2c3d4e5").unwrap();
+        assert_eq!(grid_line(&grid), PUZZLE);
+
+        let mut body = String::new();
+        let mut response = super::http_get(&format!("{}hard.txt", base)).unwrap();
+        response.read_to_string(&mut body).unwrap();
+        assert_eq!(body, bank("hard.txt"));
+        let error = super::http_get(&format!("{}missing.txt", base))
+            .err()
+            .unwrap();
+        assert!(
+            error.contains("missing.txt") && error.contains("404"),
+            "{}",
+            error
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! C 语言接口
+//!
+//! 声明见 `include/superdo.h`。题目为 81 个数字（`0` 或 `.` 为空位）组成的 NUL 结尾字符串，
+//! 结果写入调用方提供的缓冲区。作为 C 库构建：
+//! `cargo rustc --release --lib --features ffi --crate-type cdylib`（或 `staticlib`）。
+
+use std::ffi::{c_char, c_int, c_longlong, CStr};
+use std::sync::Arc;
+
+use clap::ValueEnum;
+
+use crate::board::{grid_line, parse_grid, Grid, SudokuBoard};
+use crate::generator::{generate, GenerateOptions};
+use crate::rating::Difficulty;
+use crate::rng::Rng;
+use crate::solver::{count_solutions, solutions};
+use crate::variant::Variant;
+
+/// 成功
+pub const SUPERDO_OK: c_int = 0;
+/// 题目或参数无效：空指针、格式错误或给定数字冲突
+pub const SUPERDO_INVALID: c_int = -1;
+/// 题目无解或无法生成
+pub const SUPERDO_NO_SOLUTION: c_int = -2;
+/// 缓冲区不足 82 字节
+pub const SUPERDO_BUFFER_TOO_SMALL: c_int = -3;
+
+/// 读取题目，给定数字冲突时视为无效
+unsafe fn read_puzzle(puzzle: *const c_char, variant: &Variant) -> Option<Grid> {
+    if puzzle.is_null() {
+        return None;
+    }
+    let text = CStr::from_ptr(puzzle).to_str().ok()?;
+    let givens = parse_grid(text).ok()?;
+    variant.conflicts(&givens).is_empty().then_some(givens)
+}
+
+/// 把 81 个数字和结尾的 NUL 写入 out
+unsafe fn write_grid(grid: &Grid, out: *mut c_char, len: usize) -> c_int {
+    let line = grid_line(grid);
+    if out.is_null() || len <= line.len() {
+        return SUPERDO_BUFFER_TOO_SMALL;
+    }
+    std::ptr::copy_nonoverlapping(line.as_ptr() as *const c_char, out, line.len());
+    *out.add(line.len()) = 0;
+    SUPERDO_OK
+}
+
+/// 求解标准数独，把第一个解写入 out
+///
+/// # Safety
+///
+/// puzzle 为空指针或 NUL 结尾的字符串，out 为空指针或至少可写 len 字节
+#[no_mangle]
+pub unsafe extern "C" fn superdo_solve(
+    puzzle: *const c_char,
+    out: *mut c_char,
+    len: usize,
+) -> c_int {
+    let variant = Arc::new(Variant::standard());
+    let Some(givens) = read_puzzle(puzzle, &variant) else {
+        return SUPERDO_INVALID;
+    };
+    match solutions(&SudokuBoard::new_with(&givens, variant), 1).first() {
+        Some(solution) => write_grid(solution, out, len),
+        None => SUPERDO_NO_SOLUTION,
+    }
+}
+
+/// 统计标准数独解的个数，数到 cap 个为止。题目无效时返回 -1
+///
+/// # Safety
+///
+/// puzzle 为空指针或 NUL 结尾的字符串
+#[no_mangle]
+pub unsafe extern "C" fn superdo_count(puzzle: *const c_char, cap: usize) -> c_longlong {
+    let variant = Arc::new(Variant::standard());
+    match read_puzzle(puzzle, &variant) {
+        Some(givens) => {
+            count_solutions(&SudokuBoard::new_with(&givens, variant), cap) as c_longlong
+        }
+        None => SUPERDO_INVALID as c_longlong,
+    }
+}
+
+/// 生成一道解唯一的标准数独写入 out，difficulty 为 0（easy）到 4（evil），相同的种子得到相同的题目
+///
+/// # Safety
+///
+/// out 为空指针或至少可写 len 字节
+#[no_mangle]
+pub unsafe extern "C" fn superdo_generate(
+    difficulty: c_int,
+    seed: u64,
+    out: *mut c_char,
+    len: usize,
+) -> c_int {
+    let Some(difficulty) = usize::try_from(difficulty)
+        .ok()
+        .and_then(|i| Difficulty::value_variants().get(i))
+    else {
+        return SUPERDO_INVALID;
+    };
+    let options = GenerateOptions {
+        difficulty: Some(*difficulty),
+        ..Default::default()
+    };
+    let variant = Arc::new(Variant::standard());
+    match generate(variant, &options, &mut Rng::new(seed)) {
+        Some(generated) => write_grid(&generated.givens, out, len),
+        None => SUPERDO_NO_SOLUTION,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{c_char, CStr, CString};
+    use std::ptr;
+
+    use super::{
+        superdo_count, superdo_generate, superdo_solve, SUPERDO_BUFFER_TOO_SMALL, SUPERDO_INVALID,
+        SUPERDO_NO_SOLUTION, SUPERDO_OK,
+    };
+
+    #[test]
+    fn test_ffi() {
+        let puzzle = CString::new(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        )
+        .unwrap();
+        let mut out = [0 as c_char; 82];
+        unsafe {
+            assert_eq!(
+                superdo_solve(puzzle.as_ptr(), out.as_mut_ptr(), out.len()),
+                SUPERDO_OK
+            );
+            assert_eq!(
+                CStr::from_ptr(out.as_ptr()).to_str().unwrap(),
+                "534678912672195348198342567859761423426853791713924856961537284287419635345286179"
+            );
+            assert_eq!(
+                superdo_solve(puzzle.as_ptr(), out.as_mut_ptr(), 81),
+                SUPERDO_BUFFER_TOO_SMALL
+            );
+            assert_eq!(
+                superdo_solve(ptr::null(), out.as_mut_ptr(), out.len()),
+                SUPERDO_INVALID
+            );
+            assert_eq!(superdo_count(puzzle.as_ptr(), 10), 1);
+
+            let empty = CString::new("0".repeat(81)).unwrap();
+            assert_eq!(superdo_count(empty.as_ptr(), 5), 5);
+            let broken =
+                CString::new(format!("123456780{}9{}", "0".repeat(35), "0".repeat(36))).unwrap();
+            assert_eq!(
+                superdo_solve(broken.as_ptr(), out.as_mut_ptr(), out.len()),
+                SUPERDO_NO_SOLUTION
+            );
+            let conflict = CString::new(format!("11{}", "0".repeat(79))).unwrap();
+            assert_eq!(superdo_count(conflict.as_ptr(), 5), -1);
+
+            assert_eq!(
+                superdo_generate(0, 7, out.as_mut_ptr(), out.len()),
+                SUPERDO_OK
+            );
+            let generated = CStr::from_ptr(out.as_ptr()).to_owned();
+            assert_eq!(superdo_count(generated.as_ptr(), 2), 1);
+            assert_eq!(
+                superdo_generate(5, 7, out.as_mut_ptr(), out.len()),
+                SUPERDO_INVALID
+            );
+        }
+    }
+}
@@ -0,0 +1,557 @@
+//! 导入和导出 f-puzzles 格式的题目
+//!
+//! 支持 f-puzzles 导出的 JSON，以及 f-puzzles、SudokuPad（Cracking The Cryptic）链接中用
+//! lz-string 压缩后 base64 编码的同一份 JSON。
+
+use crate::board::Grid;
+use crate::constraint::{parse_cell, AntiMove, Arrow, Cage, Cell, Pair, PairKind, Thermo, Unit};
+use crate::json::Json;
+use crate::lzstring;
+use crate::variant::{ConstraintKind, Parity, Variant};
+
+/// 导入的题目
+#[derive(Debug, Clone)]
+pub struct Puzzle {
+    /// 给定的数字
+    pub givens: Grid,
+    /// 变体规则
+    pub variant: Variant,
+}
+
+/// SudokuPad 打开 f-puzzles 数据的链接前缀
+const SUDOKUPAD: &str = "https://sudokupad.app/fpuzzles";
+
+/// 只影响显示、不影响求解的字段
+const COSMETIC: [&str; 12] = [
+    "size",
+    "grid",
+    "title",
+    "author",
+    "ruleset",
+    "solution",
+    "text",
+    "line",
+    "rectangle",
+    "circle",
+    "cage",
+    "negative",
+];
+
+/// 解析 JSON 文本、f-puzzles / SudokuPad 链接或链接中的压缩数据
+pub fn parse(input: &str) -> Result<Puzzle, String> {
+    let input = input.trim();
+    let text = if input.starts_with('{') {
+        input.to_string()
+    } else {
+        let text = lzstring::decompress_from_base64(&percent_decode(payload(input)?))?;
+        if !text.starts_with('{') {
+            return Err("link does not contain an f-puzzles puzzle".to_string());
+        }
+        text
+    };
+    from_json(&Json::parse(&text)?)
+}
+
+/// 从链接中取出压缩数据
+fn payload(link: &str) -> Result<&str, String> {
+    // f-puzzles: https://www.f-puzzles.com/?load=...
+    if let Some((_, rest)) = link.split_once("load=") {
+        return Ok(rest.split(['&', '#']).next().unwrap_or(rest));
+    }
+    // SudokuPad: https://sudokupad.app/fpuzzles... 或 ?puzzleid=fpuzzles...，
+    // 数据中可能有未转义的 `/`
+    for prefix in ["/fpuzzles", "puzzleid=fpuzzles"] {
+        if let Some((_, rest)) = link.split_once(prefix) {
+            return Ok(rest.split(['?', '&', '#']).next().unwrap_or(rest));
+        }
+    }
+    let last = link.rsplit('/').next().unwrap_or(link);
+    let last = last.split(['?', '#']).next().unwrap_or(last);
+    if let Some(data) = last.strip_prefix("fpuzzles") {
+        return Ok(data);
+    }
+    // SudokuPad 自己的 scl 格式不是 f-puzzles 数据
+    if link.contains("://") && (last.starts_with("scl") || link.contains("puzzleid=scl")) {
+        return Err(format!(
+            "SudokuPad scl links are not supported (`{}`), share the puzzle as an f-puzzles link \
+             instead",
+            link
+        ));
+    }
+    if link.contains("://") {
+        return Err(format!(
+            "unsupported link `{}`, share the puzzle as an f-puzzles link instead",
+            link
+        ));
+    }
+    Ok(last)
+}
+
+/// 还原 URL 中的 `%XX` 转义
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(b) = s
+                .get(i + 1..i + 3)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+            {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// 解析 `R1C1` 形式的位置
+fn cell(value: &Json) -> Result<Cell, String> {
+    let s = value.as_str().ok_or("cell must be a string")?;
+    parse_cell(s).ok_or_else(|| format!("invalid cell `{}`", s))
+}
+
+/// 解析位置列表
+fn cells(value: Option<&Json>) -> Result<Vec<Cell>, String> {
+    value
+        .and_then(Json::as_array)
+        .ok_or("missing cells")?
+        .iter()
+        .map(cell)
+        .collect()
+}
+
+/// 某种约束的所有条目
+fn entries<'a>(json: &'a Json, key: &str) -> &'a [Json] {
+    json.get(key).and_then(Json::as_array).unwrap_or(&[])
+}
+
+/// 布尔开关
+fn flag(json: &Json, key: &str) -> bool {
+    json.get(key).and_then(Json::as_bool).unwrap_or(false)
+}
+
+/// 相邻两格的标记
+fn pair(kind: PairKind, entry: &Json) -> Result<Pair, String> {
+    match cells(entry.get("cells"))?[..] {
+        [a, b] if a.0.abs_diff(b.0) + a.1.abs_diff(b.1) == 1 => Ok(Pair { kind, a, b }),
+        _ => Err("marker cells must be two adjacent cells".to_string()),
+    }
+}
+
+/// 从 f-puzzles JSON 构建题目
+pub fn from_json(json: &Json) -> Result<Puzzle, String> {
+    if let Some(size) = json.get("size").and_then(Json::as_u32) {
+        if size != 9 {
+            return Err(format!("unsupported grid size {}", size));
+        }
+    }
+
+    // 给定数字
+    let mut givens = [[0; 9]; 9];
+    let rows = json
+        .get("grid")
+        .and_then(Json::as_array)
+        .ok_or("missing grid")?;
+    for (row, cols) in rows.iter().take(9).enumerate() {
+        for (col, pos) in cols.as_array().unwrap_or(&[]).iter().take(9).enumerate() {
+            if pos.get("given").and_then(Json::as_bool) == Some(true) {
+                let val = pos.get("value").and_then(Json::as_u32).unwrap_or(0);
+                if val > 9 {
+                    return Err(format!("invalid digit {} at r{}c{}", val, row + 1, col + 1));
+                }
+                givens[row][col] = val;
+            }
+        }
+    }
+
+    let mut variant = Variant::standard();
+    if flag(json, "diagonal-") {
+        variant.add(Unit::diagonal());
+    }
+    if flag(json, "diagonal+") {
+        variant.add(Unit::anti_diagonal());
+    }
+    for entry in entries(json, "extraregion") {
+        let cells = cells(entry.get("cells"))?;
+        if cells.len() != 9 {
+            return Err("extra region must contain 9 cells".to_string());
+        }
+        variant.add(Unit { cells });
+    }
+    if flag(json, "antiknight") {
+        variant.add_kind(ConstraintKind::AntiKnight);
+    }
+    if flag(json, "antiking") {
+        variant.add_kind(ConstraintKind::AntiKing);
+    }
+
+    // 奇偶标记
+    for (key, parity) in [("odd", Parity::Odd), ("even", Parity::Even)] {
+        for entry in entries(json, key) {
+            let (row, col) = cell(entry.get("cell").ok_or("missing cell")?)?;
+            variant.parity[row][col] = Some(parity);
+        }
+    }
+
+    for entry in entries(json, "killercage") {
+        let sum = match entry.get("value") {
+            None | Some(Json::Null) => None,
+            Some(Json::String(s)) if s.is_empty() => None,
+            Some(value) => Some(value.as_u32().ok_or("invalid cage sum")?),
+        };
+        let cells = cells(entry.get("cells"))?;
+        if cells.is_empty() || cells.len() > 9 {
+            return Err("cage must contain 1 to 9 cells".to_string());
+        }
+        variant.add(Cage { sum, cells });
+    }
+
+    for entry in entries(json, "thermometer") {
+        for line in entries(entry, "lines") {
+            let cells = cells(Some(line))?;
+            if cells.len() < 2 || cells.len() > 9 {
+                return Err("thermometer must contain 2 to 9 cells".to_string());
+            }
+            variant.add(Thermo { cells });
+        }
+    }
+
+    // 箭头的每条线都从圆圈出发
+    for entry in entries(json, "arrow") {
+        let circle = match cells(entry.get("cells"))?[..] {
+            [circle] => circle,
+            _ => return Err("only single-cell arrow circles are supported".to_string()),
+        };
+        for line in entries(entry, "lines") {
+            let mut shaft = cells(Some(line))?;
+            if shaft.first() == Some(&circle) {
+                shaft.remove(0);
+            }
+            if shaft.is_empty() {
+                return Err("arrow needs at least one shaft cell".to_string());
+            }
+            variant.add(Arrow { circle, shaft });
+        }
+    }
+
+    // 白点默认相差 1，黑点默认 2 倍
+    for (key, kind, default) in [
+        ("difference", PairKind::White, 1),
+        ("ratio", PairKind::Black, 2),
+    ] {
+        for entry in entries(json, key) {
+            let value = entry.get("value").and_then(Json::as_u32).unwrap_or(default);
+            if value != default {
+                return Err(format!("unsupported {} dot value {}", key, value));
+            }
+            variant.add(pair(kind, entry)?);
+        }
+    }
+
+    for entry in entries(json, "xv") {
+        let kind = match entry.get("value").and_then(Json::as_str) {
+            Some("X") | Some("x") => PairKind::X,
+            Some("V") | Some("v") => PairKind::V,
+            _ => return Err("invalid XV marker value".to_string()),
+        };
+        variant.add(pair(kind, entry)?);
+    }
+
+    for negative in entries(json, "negative") {
+        match negative.as_str() {
+            Some("xv") => variant.add_negative_xv(),
+            other => log::warn!("unsupported negative constraint {:?} ignored", other),
+        }
+    }
+
+    // 未支持的规则只给出提示
+    const SUPPORTED: [&str; 13] = [
+        "diagonal-",
+        "diagonal+",
+        "extraregion",
+        "antiknight",
+        "antiking",
+        "odd",
+        "even",
+        "killercage",
+        "thermometer",
+        "arrow",
+        "difference",
+        "ratio",
+        "xv",
+    ];
+    if let Json::Object(items) = json {
+        for (key, value) in items {
+            let used = match value {
+                Json::Bool(b) => *b,
+                Json::Array(a) => !a.is_empty(),
+                _ => false,
+            };
+            if used && !SUPPORTED.contains(&key.as_str()) && !COSMETIC.contains(&key.as_str()) {
+                log::warn!("unsupported constraint `{}` ignored", key);
+            }
+        }
+    }
+
+    Ok(Puzzle { givens, variant })
+}
+
+/// 位置的 `R1C1` 写法
+fn cell_name((row, col): Cell) -> Json {
+    format!("R{}C{}", row + 1, col + 1).into()
+}
+
+fn cell_list(cells: &[Cell]) -> Json {
+    Json::Array(cells.iter().map(|&c| cell_name(c)).collect())
+}
+
+/// 导出为 f-puzzles JSON，与 [`from_json`] 互逆
+pub fn to_json(givens: &Grid, variant: &Variant) -> Json {
+    let grid = givens
+        .iter()
+        .map(|row| {
+            Json::Array(
+                row.iter()
+                    .map(|&d| match d {
+                        0 => Json::Object(vec![]),
+                        d => Json::Object(vec![
+                            ("value".to_string(), d.into()),
+                            ("given".to_string(), true.into()),
+                        ]),
+                    })
+                    .collect(),
+            )
+        })
+        .collect();
+    let mut items = vec![
+        ("size".to_string(), 9u32.into()),
+        ("grid".to_string(), Json::Array(grid)),
+    ];
+    let mut lists: Vec<(&str, Vec<Json>)> = [
+        "extraregion",
+        "odd",
+        "even",
+        "killercage",
+        "thermometer",
+        "arrow",
+        "difference",
+        "ratio",
+        "xv",
+        "negative",
+    ]
+    .into_iter()
+    .map(|key| (key, vec![]))
+    .collect();
+    let mut push = |key: &str, entry: Json| {
+        let list = lists.iter_mut().find(|(k, _)| *k == key).unwrap();
+        list.1.push(entry);
+    };
+    let object = |items: Vec<(&str, Json)>| {
+        Json::Object(items.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    };
+
+    // 行、列、小格是默认规则，对角线有专门的开关，其余区域为 extraregion
+    let standard: Vec<Unit> = (0..9)
+        .flat_map(|i| [Unit::row(i), Unit::col(i), Unit::block(i)])
+        .collect();
+    for unit in variant.find::<Unit>() {
+        if *unit == Unit::diagonal() {
+            items.push(("diagonal-".to_string(), true.into()));
+        } else if *unit == Unit::anti_diagonal() {
+            items.push(("diagonal+".to_string(), true.into()));
+        } else if !standard.contains(unit) {
+            push(
+                "extraregion",
+                object(vec![("cells", cell_list(&unit.cells))]),
+            );
+        }
+    }
+    for anti in variant.find::<AntiMove>() {
+        if *anti == AntiMove::knight() {
+            items.push(("antiknight".to_string(), true.into()));
+        } else if *anti == AntiMove::king() {
+            items.push(("antiking".to_string(), true.into()));
+        }
+    }
+    for row in 0..9 {
+        for col in 0..9 {
+            match variant.parity[row][col] {
+                Some(Parity::Odd) => push("odd", object(vec![("cell", cell_name((row, col)))])),
+                Some(Parity::Even) => push("even", object(vec![("cell", cell_name((row, col)))])),
+                None => {}
+            }
+        }
+    }
+    for cage in variant.find::<Cage>() {
+        let mut entry = vec![("cells", cell_list(&cage.cells))];
+        if let Some(sum) = cage.sum {
+            entry.push(("value", sum.to_string().into()));
+        }
+        push("killercage", object(entry));
+    }
+    for thermo in variant.find::<Thermo>() {
+        let lines = Json::Array(vec![cell_list(&thermo.cells)]);
+        push("thermometer", object(vec![("lines", lines)]));
+    }
+    for arrow in variant.find::<Arrow>() {
+        let mut line = vec![arrow.circle];
+        line.extend(&arrow.shaft);
+        push(
+            "arrow",
+            object(vec![
+                ("lines", Json::Array(vec![cell_list(&line)])),
+                ("cells", cell_list(&[arrow.circle])),
+            ]),
+        );
+    }
+    let mut negative_xv = false;
+    for pair in variant.find::<Pair>() {
+        let cells = ("cells", cell_list(&[pair.a, pair.b]));
+        match pair.kind {
+            PairKind::White => push("difference", object(vec![cells])),
+            PairKind::Black => push("ratio", object(vec![cells])),
+            PairKind::X => push("xv", object(vec![cells, ("value", "X".into())])),
+            PairKind::V => push("xv", object(vec![cells, ("value", "V".into())])),
+            PairKind::NotXv => negative_xv = true,
+        }
+    }
+    if negative_xv {
+        push("negative", "xv".into());
+    }
+    items.extend(
+        lists
+            .into_iter()
+            .filter(|(_, list)| !list.is_empty())
+            .map(|(key, list)| (key.to_string(), Json::Array(list))),
+    );
+    Json::Object(items)
+}
+
+/// 在 SudokuPad 中打开题目的链接，f-puzzles 也能读取其中的数据
+pub fn share_link(givens: &Grid, variant: &Variant) -> String {
+    let data = lzstring::compress_to_base64(&to_json(givens, variant).to_string());
+    // base64 中的 `+`、`/`、`=` 在 URL 中有特殊含义
+    let data = data
+        .replace('+', "%2B")
+        .replace('/', "%2F")
+        .replace('=', "%3D");
+    format!("{}{}", SUDOKUPAD, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_json;
+    use super::{parse, share_link};
+    use crate::constraint::{Arrow, Cage, Pair, PairKind, Thermo};
+    use crate::json::Json;
+    use crate::variant::{ConstraintKind, Parity, Variant};
+
+    const PUZZLE: &str = r#"{"size":9,"title":"Test",
+        "grid":[[{"value":5,"given":true},{},{},{},{},{},{},{},{}],
+                [{},{},{},{},{},{},{},{},{}],[{},{},{},{},{},{},{},{},{}],
+                [{},{},{},{},{},{},{},{},{}],[{},{},{},{},{},{},{},{},{}],
+                [{},{},{},{},{},{},{},{},{}],[{},{},{},{},{},{},{},{},{}],
+                [{},{},{},{},{},{},{},{},{}],[{},{},{},{},{"value":3},{},{},{},{}]],
+        "diagonal+":true,
+        "killercage":[{"cells":["R9C1","R9C2"],"value":"17"}],
+        "thermometer":[{"lines":[["R2C1","R2C2","R2C3"]]}],
+        "arrow":[{"lines":[["R5C5","R4C4","R3C3"]],"cells":["R5C5"]}],
+        "odd":[{"cell":"R1C9"}],
+        "difference":[{"cells":["R7C1","R7C2"]}],
+        "ratio":[{"cells":["R8C1","R8C2"]}],
+        "xv":[{"cells":["R6C1","R6C2"],"value":"X"}]}"#;
+
+    #[test]
+    fn test_parse_json() {
+        let puzzle = parse(PUZZLE).unwrap();
+        assert_eq!(puzzle.givens[0][0], 5);
+        // 未标记为 given 的数字不是题目的一部分
+        assert_eq!(puzzle.givens[8][4], 0);
+
+        let variant = &puzzle.variant;
+        assert!(variant.peers().of(0, 8).contains(&(8, 0)));
+        assert!(!variant.peers().of(0, 0).contains(&(8, 8)));
+        assert_eq!(variant.parity[0][8], Some(Parity::Odd));
+        assert_eq!(variant.find::<Cage>().next().unwrap().sum, Some(17));
+        assert_eq!(variant.find::<Thermo>().next().unwrap().cells.len(), 3);
+        let arrow = variant.find::<Arrow>().next().unwrap();
+        assert_eq!(arrow.circle, (4, 4));
+        assert_eq!(arrow.shaft, vec![(3, 3), (2, 2)]);
+        let kinds: Vec<PairKind> = variant.find::<Pair>().map(|p| p.kind).collect();
+        assert_eq!(kinds, vec![PairKind::White, PairKind::Black, PairKind::X]);
+    }
+
+    #[test]
+    fn test_share_link() {
+        let puzzle = parse(PUZZLE).unwrap();
+        let mut variant = puzzle.variant.clone();
+        variant.add_kind(ConstraintKind::Hyper);
+        variant.add_kind(ConstraintKind::AntiKing);
+        variant.parity[3][3] = Some(Parity::Even);
+        variant.add_negative_xv();
+        let link = share_link(&puzzle.givens, &variant);
+        assert!(
+            link.starts_with("https://sudokupad.app/fpuzzles"),
+            "{}",
+            link
+        );
+        let data = &link["https://sudokupad.app/fpuzzles".len()..];
+        assert!(!data.contains(['/', '+', '=']), "{}", link);
+        // 未转义的链接也能读取
+        let raw = link
+            .replace("%2B", "+")
+            .replace("%2F", "/")
+            .replace("%3D", "=");
+        assert_eq!(parse(&raw).unwrap().givens, puzzle.givens);
+
+        let shared = parse(&link).unwrap();
+        assert_eq!(shared.givens, puzzle.givens);
+        let debug = |v: &Variant| {
+            let mut constraints: Vec<String> =
+                v.constraints().iter().map(|c| format!("{:?}", c)).collect();
+            constraints.sort();
+            constraints
+        };
+        assert_eq!(debug(&shared.variant), debug(&variant));
+        assert_eq!(shared.variant.parity, variant.parity);
+        // 标准数独只有给定数字
+        let plain = to_json(&puzzle.givens, &Variant::standard());
+        let Json::Object(items) = plain else {
+            panic!("not an object")
+        };
+        let keys: Vec<&str> = items.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, ["size", "grid"]);
+    }
+
+    #[test]
+    fn test_parse_link() {
+        // LZString.compressToBase64('{"size":9,"grid":[],"antiknight":true}')
+        let data = "N4IgzglgXgpiBcBOANCA5gJwgEwQbQF1UBDAOwBcIBrUiNAC3IXIwFcYBfIA";
+        let expected = parse(r#"{"size":9,"grid":[],"antiknight":true}"#).unwrap();
+        for link in [
+            format!("https://www.f-puzzles.com/?load={}", data),
+            format!("https://sudokupad.app/fpuzzles{}", data),
+            format!("https://sudokupad.app/fpuzzles{}?setting-nogrid=1", data),
+            format!(
+                "https://sudokupad.app/?puzzleid=fpuzzles{}&setting-nogrid=1",
+                data
+            ),
+            format!("https://app.crackingthecryptic.com/sudoku/fpuzzles{}", data),
+            format!("fpuzzles{}", data),
+            data.to_string(),
+        ] {
+            let puzzle = parse(&link).unwrap();
+            assert_eq!(puzzle.givens, expected.givens);
+            assert!(puzzle.variant.peers().of(0, 0).contains(&(1, 2)));
+        }
+        assert!(parse("https://sudokupad.app/abc123").is_err());
+        let scl = parse(&format!("https://sudokupad.app/scl{}", data));
+        assert!(scl.unwrap_err().contains("scl"));
+        assert!(parse(r#"{"size":6,"grid":[]}"#).is_err());
+    }
+}
@@ -0,0 +1,801 @@
+//! 生成题目
+//!
+//! 先用随机回溯得到一个完整的终盘，再按随机顺序逐个挖去数字，每挖一个都用最多数到 2 个解的
+//! 计数求解确认解仍然唯一，否则放回。指定难度时，挖去后超出该难度的数字同样放回，挖完仍未
+//! 达到该难度则重新生成终盘。
+//!
+//! 极小模式在挖完之后不再考虑对称，继续逐个尝试，直到去掉任何一个数字都会使解不唯一；指定目标
+//! 个数时，还会尝试去掉两个数字再补回一个，进一步减少给定数字。
+//!
+//! 终盘、唯一性计数和难度评定都使用传入的变体规则，因此同样可以生成 X 数独、窗口数独、无马步
+//! 等变体题目。
+//!
+//! 指定位置模板时，先去掉模板外的数字，剩下的数字确定唯一解才继续，否则重新生成终盘。
+
+use std::collections::{BTreeMap, HashSet};
+use std::ops::RangeInclusive;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Instant;
+
+use rayon::prelude::*;
+
+use crate::board::{grid_line, Grid, SudokuBoard};
+use crate::canon;
+use crate::json::Json;
+use crate::rating::{
+    available_at_start, first_placement, rate_variant, Difficulty, Rating, Technique,
+};
+use crate::rng::Rng;
+use crate::solver::{most_constrained, solutions, solutions_until};
+use crate::variant::Variant;
+
+/// 给定数字的对称方式
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Symmetry {
+    /// 180 degree rotation around the center
+    Rotational,
+    /// Left-right mirror
+    Mirror,
+    /// Reflection across the main diagonal
+    Diagonal,
+    /// No symmetry
+    #[default]
+    None,
+}
+
+impl Symmetry {
+    /// 与 (row, col) 对称的所有位置，包含自身
+    pub fn orbit(self, (row, col): (usize, usize)) -> Vec<(usize, usize)> {
+        let other = match self {
+            Symmetry::Rotational => (8 - row, 8 - col),
+            Symmetry::Mirror => (row, 8 - col),
+            Symmetry::Diagonal => (col, row),
+            Symmetry::None => (row, col),
+        };
+        if other == (row, col) {
+            vec![(row, col)]
+        } else {
+            vec![(row, col), other]
+        }
+    }
+}
+
+/// 给定数字的位置模板，true 表示可以有给定数字
+pub type Pattern = [[bool; 9]; 9];
+
+/// 解析位置模板：`x`、`X`、`#`、`*`、`1` 表示可以有给定数字，`.`、`-`、`0` 表示空位，忽略空白
+pub fn parse_pattern(s: &str) -> Result<Pattern, String> {
+    let mut cells = vec![];
+    for c in s.chars().filter(|c| !c.is_whitespace()) {
+        match c {
+            'x' | 'X' | '#' | '*' | '1' => cells.push(true),
+            '.' | '-' | '0' => cells.push(false),
+            _ => return Err(format!("invalid pattern character `{}`", c)),
+        }
+    }
+    if cells.len() != 81 {
+        return Err(format!("pattern has {} cells, expected 81", cells.len()));
+    }
+    Ok(std::array::from_fn(|r| {
+        std::array::from_fn(|c| cells[r * 9 + c])
+    }))
+}
+
+/// 生成选项
+#[derive(Debug, Clone, Default)]
+pub struct GenerateOptions {
+    /// 目标难度，None 表示不限
+    pub difficulty: Option<Difficulty>,
+    /// 给定数字的对称方式
+    pub symmetry: Symmetry,
+    /// 挖到去掉任何一个数字都会使解不唯一为止
+    pub minimal: bool,
+    /// 目标给定数字个数，隐含极小模式
+    pub target_clues: Option<usize>,
+    /// 给定数字个数的范围
+    pub clues: Option<RangeInclusive<usize>>,
+    /// 解题过程中必须用到的技巧
+    pub requires: Option<Technique>,
+    /// 从指定的终盘挖数字，None 时随机生成终盘
+    pub solution: Option<Grid>,
+    /// 给定数字只能出现在模板标记的位置，未指定个数范围时给定数字恰好是这些位置
+    pub pattern: Option<Pattern>,
+    /// 开始解题时不能使用的技巧
+    pub forbid_start: Vec<Technique>,
+    /// 按技巧阶梯推理时，第一个填入的数字必须位于这个 3x3 小格（从 0 开始编号）
+    pub first_move_box: Option<usize>,
+    /// 到这个时刻仍未生成时放弃，返回 None
+    pub deadline: Option<Instant>,
+}
+
+impl GenerateOptions {
+    /// 给定数字个数的下限和上限
+    fn clue_bounds(&self) -> (usize, usize) {
+        let (min, max) = match (&self.clues, &self.pattern) {
+            (Some(range), _) => (*range.start(), *range.end()),
+            (None, Some(pattern)) => {
+                let n = pattern.iter().flatten().filter(|p| **p).count();
+                (n, n)
+            }
+            (None, None) => (0, 81),
+        };
+        (min, self.target_clues.map_or(max, |target| target.min(max)))
+    }
+
+    /// 是否已过了放弃的时刻
+    pub fn expired(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// 挖数字时允许的最高难度，要求某种技巧时不超过该技巧的难度
+    fn max_difficulty(&self) -> Option<Difficulty> {
+        self.difficulty
+            .or_else(|| self.requires.map(Technique::difficulty))
+    }
+}
+
+/// 生成的题目
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Generated {
+    /// 题目，0 表示空位
+    pub givens: Grid,
+    /// 唯一解
+    pub solution: Grid,
+    /// 难度评定
+    pub rating: Rating,
+}
+
+impl Generated {
+    /// 给定数字的个数
+    pub fn clues(&self) -> usize {
+        clues(&self.givens)
+    }
+
+    /// 输出为 JSON，包含题目、解、给定数字个数和难度
+    pub fn to_json(&self) -> Json {
+        Json::Object(vec![
+            ("puzzle".to_string(), grid_line(&self.givens).into()),
+            ("solution".to_string(), grid_line(&self.solution).into()),
+            ("clues".to_string(), self.clues().into()),
+            (
+                "difficulty".to_string(),
+                self.rating.difficulty.name().into(),
+            ),
+        ])
+    }
+}
+
+/// 给定数字的个数
+pub fn clues(givens: &Grid) -> usize {
+    givens.iter().flatten().filter(|d| **d != 0).count()
+}
+
+/// 解唯一且难度不超过要求
+fn acceptable(givens: &Grid, variant: &Arc<Variant>, options: &GenerateOptions) -> bool {
+    let board = SudokuBoard::new_with(givens, variant.clone());
+    solutions_until(&board, 2, options.deadline).is_some_and(|found| found.len() == 1)
+        && options
+            .max_difficulty()
+            .is_none_or(|max| rate_variant(givens, variant).difficulty <= max)
+}
+
+/// 是否符合开始解题时的要求
+fn styled(givens: &Grid, variant: &Variant, options: &GenerateOptions) -> bool {
+    let forbidden = options
+        .forbid_start
+        .iter()
+        .any(|t| available_at_start(givens, variant, *t));
+    let first_move = options.first_move_box.is_none_or(|n| {
+        first_placement(givens, variant).is_some_and(|(r, c)| (r / 3) * 3 + c / 3 == n)
+    });
+    !forbidden && first_move
+}
+
+/// 随机回溯得到一个满足规则的完整终盘，规则无解时返回 None
+pub fn random_solution(variant: Arc<Variant>, rng: &mut Rng) -> Option<Grid> {
+    fn search(board: SudokuBoard, rng: &mut Rng) -> Option<Grid> {
+        let mut board = board;
+        if board.solve() {
+            return Some(board.grid());
+        }
+        let (row, col) = most_constrained(&board)?;
+        let mut digits: Vec<u32> = board.get(row, col).digits.iter().cloned().collect();
+        digits.sort();
+        rng.shuffle(&mut digits);
+        for digit in digits {
+            let mut guess = board.clone();
+            guess.set(digit, row, col);
+            if let Some(grid) = search(guess, rng) {
+                return Some(grid);
+            }
+        }
+        None
+    }
+    search(SudokuBoard::empty(variant), rng)
+}
+
+/// 从终盘中随机挖去数字，对称的位置成组挖去，保持解唯一且难度不超过要求
+pub fn dig(
+    solution: &Grid,
+    variant: Arc<Variant>,
+    options: &GenerateOptions,
+    rng: &mut Rng,
+) -> Grid {
+    let mut givens = *solution;
+    if let Some(pattern) = &options.pattern {
+        for (row, col) in (0..81).map(|i| (i / 9, i % 9)) {
+            if !pattern[row][col] {
+                givens[row][col] = 0;
+            }
+        }
+    }
+    let mut cells: Vec<(usize, usize)> = (0..81).map(|i| (i / 9, i % 9)).collect();
+    rng.shuffle(&mut cells);
+    for cell in cells {
+        let orbit = options.symmetry.orbit(cell);
+        // 已随对称位置挖去，或已达到个数下限
+        if givens[cell.0][cell.1] == 0 || clues(&givens) < options.clue_bounds().0 + orbit.len() {
+            continue;
+        }
+        let digits: Vec<u32> = orbit.iter().map(|&(r, c)| givens[r][c]).collect();
+        for &(r, c) in &orbit {
+            givens[r][c] = 0;
+        }
+        let keep = !acceptable(&givens, &variant, options);
+        if keep {
+            for (&(r, c), digit) in orbit.iter().zip(digits) {
+                givens[r][c] = digit;
+            }
+        }
+    }
+    givens
+}
+
+/// 不考虑对称逐个去掉数字，直到去掉任何一个都不再满足要求
+pub fn minimize(
+    givens: &mut Grid,
+    variant: &Arc<Variant>,
+    options: &GenerateOptions,
+    rng: &mut Rng,
+) {
+    let mut cells: Vec<(usize, usize)> = (0..81)
+        .map(|i| (i / 9, i % 9))
+        .filter(|&(r, c)| givens[r][c] != 0)
+        .collect();
+    rng.shuffle(&mut cells);
+    for (row, col) in cells {
+        if clues(givens) <= options.clue_bounds().0 {
+            break;
+        }
+        let digit = givens[row][col];
+        givens[row][col] = 0;
+        if !acceptable(givens, variant, options) {
+            givens[row][col] = digit;
+        }
+    }
+}
+
+/// 去掉两个数字再从终盘补回另一个，使给定数字减少一个，找不到时返回 false
+fn reduce(
+    givens: &mut Grid,
+    solution: &Grid,
+    variant: &Arc<Variant>,
+    options: &GenerateOptions,
+    rng: &mut Rng,
+) -> bool {
+    let mut cells: Vec<(usize, usize)> = (0..81)
+        .map(|i| (i / 9, i % 9))
+        .filter(|&(r, c)| givens[r][c] != 0)
+        .collect();
+    rng.shuffle(&mut cells);
+    for (i, &a) in cells.iter().enumerate() {
+        for &b in &cells[i + 1..] {
+            if options.expired() {
+                return false;
+            }
+            let mut trial = *givens;
+            trial[a.0][a.1] = 0;
+            trial[b.0][b.1] = 0;
+            let board = SudokuBoard::new_with(&trial, variant.clone());
+            let Some(other) = solutions(&board, 2).into_iter().find(|g| g != solution) else {
+                continue;
+            };
+            // 补回的位置必须能排除另一个解
+            for row in 0..9 {
+                for col in 0..9 {
+                    if other[row][col] == solution[row][col] || (row, col) == a || (row, col) == b {
+                        continue;
+                    }
+                    if options.pattern.is_some_and(|p| !p[row][col]) {
+                        continue;
+                    }
+                    trial[row][col] = solution[row][col];
+                    if acceptable(&trial, variant, options) {
+                        *givens = trial;
+                        return true;
+                    }
+                    trial[row][col] = 0;
+                }
+            }
+        }
+    }
+    false
+}
+
+/// 指定终盘时最多尝试的次数
+const FIXED_ATTEMPTS: usize = 1000;
+/// 指定位置模板时最多尝试的次数，稀疏的模板需要很多个终盘才能找到解唯一的
+const PATTERN_ATTEMPTS: usize = 10_000;
+
+/// 生成一道解唯一的题目，指定难度或技巧时重复生成直到符合，要求互相矛盾、指定的终盘不满足
+/// 规则、多次尝试仍无法满足或到了 deadline 时返回 None
+pub fn generate(
+    variant: Arc<Variant>,
+    options: &GenerateOptions,
+    rng: &mut Rng,
+) -> Option<Generated> {
+    if let (Some(difficulty), Some(technique)) = (options.difficulty, options.requires) {
+        if technique.difficulty() > difficulty {
+            return None;
+        }
+    }
+    if let Some(solution) = &options.solution {
+        if solution.iter().flatten().any(|d| *d == 0) || !variant.check(solution) {
+            return None;
+        }
+    }
+    for attempt in 1.. {
+        let limit = match (&options.solution, &options.pattern) {
+            (_, Some(_)) => PATTERN_ATTEMPTS,
+            (Some(_), None) => FIXED_ATTEMPTS,
+            (None, None) => usize::MAX,
+        };
+        if attempt > limit || options.expired() {
+            break;
+        }
+        let solution = match options.solution {
+            Some(solution) => solution,
+            None => random_solution(variant.clone(), rng)?,
+        };
+        let mut givens = dig(&solution, variant.clone(), options, rng);
+        // 模板位置上的数字不足以确定唯一解
+        if options.pattern.is_some() && !acceptable(&givens, &variant, options) {
+            continue;
+        }
+        let (_, max) = options.clue_bounds();
+        if options.minimal || max < 81 {
+            minimize(&mut givens, &variant, options, rng);
+        }
+        while clues(&givens) > max && reduce(&mut givens, &solution, &variant, options, rng) {
+            minimize(&mut givens, &variant, options, rng);
+        }
+        if clues(&givens) > max {
+            log::debug!("{} clues left, regenerating", clues(&givens));
+            continue;
+        }
+        // 超时后挖数字的检查都不通过，得到的题目不可用
+        if options.expired() {
+            break;
+        }
+        let rating = rate_variant(&givens, &variant);
+        let required = options
+            .requires
+            .is_none_or(|t| rating.solved && rating.steps.contains(&t));
+        if required
+            && options.difficulty.is_none_or(|d| d == rating.difficulty)
+            && styled(&givens, &variant, options)
+        {
+            log::debug!("generated after {} attempt(s)", attempt);
+            return Some(Generated {
+                givens,
+                solution,
+                rating,
+            });
+        }
+    }
+    None
+}
+
+/// 在 rayon 线程池中并行生成，每道题目使用由 rng 派生的独立种子，因此结果与线程数无关。
+/// 第 i 道题目按 options[i] 生成，完成后按顺序交给 emit
+pub fn generate_parallel(
+    variant: Arc<Variant>,
+    options: &[GenerateOptions],
+    rng: &mut Rng,
+    mut emit: impl FnMut(usize, Option<Generated>),
+) {
+    let seeds: Vec<u64> = options.iter().map(|_| rng.next_u64()).collect();
+    let (tx, rx) = mpsc::channel();
+    thread::scope(|s| {
+        s.spawn(move || {
+            seeds
+                .into_par_iter()
+                .enumerate()
+                .for_each_with(tx, |tx, (i, seed)| {
+                    let generated = generate(variant.clone(), &options[i], &mut Rng::new(seed));
+                    let _ = tx.send((i, generated));
+                });
+        });
+        // 先完成的题目暂存，直到前面的题目都已输出
+        let mut pending = BTreeMap::new();
+        let mut next = 0;
+        for (i, generated) in rx {
+            pending.insert(i, generated);
+            while let Some(generated) = pending.remove(&next) {
+                emit(next, generated);
+                next += 1;
+            }
+        }
+    });
+}
+
+/// 连续多少轮没有得到新题目时放弃
+const DISTINCT_ROUNDS: usize = 10;
+
+/// 与 [`generate_parallel`] 相同，但跳过与已生成题目等价的题目，并重新生成补足个数。
+/// 标准规则下按规范形式判断等价，变体规则下只考虑数字重新编号。多轮仍补不足时，剩余的题目
+/// 以 None 交给 emit
+pub fn generate_distinct(
+    variant: Arc<Variant>,
+    options: &[GenerateOptions],
+    rng: &mut Rng,
+    mut emit: impl FnMut(usize, Option<Generated>),
+) {
+    let key = |grid: &Grid| {
+        if variant.is_standard() {
+            canon::canonical(grid).0
+        } else {
+            canon::relabeled(grid)
+        }
+    };
+    let mut seen = HashSet::new();
+    let mut pending = options.to_vec();
+    let mut emitted = 0;
+    let mut idle = 0;
+    while !pending.is_empty() && idle < DISTINCT_ROUNDS {
+        let mut retry = vec![];
+        generate_parallel(
+            variant.clone(),
+            &pending,
+            rng,
+            |i, generated| match generated {
+                Some(generated) if !seen.insert(key(&generated.givens)) => {
+                    log::debug!("skipped a duplicate puzzle");
+                    retry.push(pending[i].clone());
+                }
+                generated => {
+                    emit(emitted, generated);
+                    emitted += 1;
+                }
+            },
+        );
+        idle = if retry.len() < pending.len() {
+            0
+        } else {
+            idle + 1
+        };
+        pending = retry;
+    }
+    for _ in pending {
+        emit(emitted, None);
+        emitted += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    use super::{
+        clues, generate, generate_distinct, generate_parallel, parse_pattern, GenerateOptions,
+        Symmetry,
+    };
+    use crate::rating::{available_at_start, first_placement, Difficulty, Technique};
+    use crate::rng::Rng;
+    use crate::solver::count_solutions;
+    use crate::variant::{ConstraintKind, Variant};
+    use crate::SudokuBoard;
+
+    #[test]
+    fn test_generate() {
+        let variant = Arc::new(Variant::default());
+        let mut rng = Rng::new(1);
+        let options = GenerateOptions::default();
+        let generated = generate(variant.clone(), &options, &mut rng).unwrap();
+        assert!(variant.check(&generated.solution));
+        assert!(generated.solution.iter().flatten().all(|d| *d != 0));
+        assert!(generated.clues() < 40);
+
+        let board = SudokuBoard::new_with(&generated.givens, variant.clone());
+        assert_eq!(count_solutions(&board, 2), 1);
+
+        let json = generated.to_json();
+        assert_eq!(
+            json.get("clues").unwrap().as_u32(),
+            Some(generated.clues() as u32)
+        );
+        assert_eq!(json.get("puzzle").unwrap().as_str().unwrap().len(), 81);
+
+        // 相同种子得到相同题目
+        let again = generate(variant.clone(), &options, &mut Rng::new(1)).unwrap();
+        assert_eq!(again, generated);
+
+        // 已过截止时刻时放弃
+        let expired = GenerateOptions {
+            deadline: Some(Instant::now()),
+            ..Default::default()
+        };
+        assert_eq!(generate(variant, &expired, &mut Rng::new(1)), None);
+    }
+
+    #[test]
+    fn test_generate_parallel() {
+        let variant = Arc::new(Variant::default());
+        let options = vec![GenerateOptions::default(); 4];
+        let run = || {
+            let mut puzzles = vec![];
+            generate_parallel(
+                variant.clone(),
+                &options,
+                &mut Rng::new(1),
+                |i, generated| {
+                    assert_eq!(i, puzzles.len());
+                    puzzles.push(generated.unwrap());
+                },
+            );
+            puzzles
+        };
+        let puzzles = run();
+        assert_eq!(puzzles.len(), 4);
+        assert_eq!(puzzles, run());
+        assert_ne!(puzzles[0], puzzles[1]);
+    }
+
+    #[test]
+    fn test_generate_distinct() {
+        // 指定终盘且不挖数字，每次都得到同一道题目
+        let variant = Arc::new(Variant::default());
+        let solution = generate(
+            variant.clone(),
+            &GenerateOptions::default(),
+            &mut Rng::new(1),
+        )
+        .unwrap()
+        .solution;
+        let options = GenerateOptions {
+            solution: Some(solution),
+            clues: Some(81..=81),
+            ..Default::default()
+        };
+        let mut puzzles = vec![];
+        generate_distinct(
+            variant.clone(),
+            &vec![options; 3],
+            &mut Rng::new(1),
+            |i, generated| {
+                assert_eq!(i, puzzles.len());
+                puzzles.push(generated);
+            },
+        );
+        assert_eq!(puzzles.len(), 3);
+        assert_eq!(puzzles[0].as_ref().unwrap().givens, solution);
+        assert!(puzzles[1..].iter().all(Option::is_none));
+
+        // 不同的题目都会输出
+        let mut count = 0;
+        let options = vec![GenerateOptions::default(); 3];
+        generate_distinct(variant, &options, &mut Rng::new(1), |_, generated| {
+            assert!(generated.is_some());
+            count += 1;
+        });
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn test_generate_pattern() {
+        let pattern = parse_pattern(
+            ".xx...xx. xxxx.xxxx x..xxx..x x.......x xx.....xx .xx...xx. ..xx.xx.. ...xxx... ....x....",
+        )
+        .unwrap();
+        assert!(parse_pattern("x").is_err());
+        assert!(parse_pattern(&"?".repeat(81)).is_err());
+
+        let variant = Arc::new(Variant::default());
+        let mut options = GenerateOptions {
+            pattern: Some(pattern),
+            ..Default::default()
+        };
+        let generated = generate(variant.clone(), &options, &mut Rng::new(1)).unwrap();
+        for row in 0..9 {
+            for col in 0..9 {
+                assert_eq!(generated.givens[row][col] != 0, pattern[row][col]);
+            }
+        }
+
+        // 指定个数范围时只需落在模板内
+        options.clues = Some(25..=30);
+        let generated = generate(variant.clone(), &options, &mut Rng::new(2)).unwrap();
+        assert!((25..=30).contains(&generated.clues()));
+        for row in 0..9 {
+            for col in 0..9 {
+                assert!(generated.givens[row][col] == 0 || pattern[row][col]);
+            }
+        }
+        let board = SudokuBoard::new_with(&generated.givens, variant);
+        assert_eq!(count_solutions(&board, 2), 1);
+    }
+
+    #[test]
+    fn test_generate_style() {
+        let variant = Arc::new(Variant::default());
+        let options = GenerateOptions {
+            forbid_start: vec![Technique::NakedSingle],
+            first_move_box: Some(4),
+            ..Default::default()
+        };
+        let generated = generate(variant.clone(), &options, &mut Rng::new(1)).unwrap();
+        assert!(!available_at_start(
+            &generated.givens,
+            &variant,
+            Technique::NakedSingle
+        ));
+        let (row, col) = first_placement(&generated.givens, &variant).unwrap();
+        assert_eq!((row / 3, col / 3), (1, 1));
+    }
+
+    #[test]
+    fn test_generate_variant() {
+        let variant = Arc::new(Variant::with_constraints(&[
+            ConstraintKind::Diagonal,
+            ConstraintKind::Hyper,
+        ]));
+        let generated = generate(
+            variant.clone(),
+            &GenerateOptions::default(),
+            &mut Rng::new(1),
+        )
+        .unwrap();
+        assert!(variant.check(&generated.solution));
+        let board = SudokuBoard::new_with(&generated.givens, variant.clone());
+        assert_eq!(count_solutions(&board, 2), 1);
+        // 只按标准规则解不唯一
+        let board = SudokuBoard::new_with(&generated.givens, Arc::default());
+        assert_eq!(count_solutions(&board, 2), 2);
+    }
+
+    #[test]
+    fn test_generate_difficulty() {
+        let variant = Arc::new(Variant::default());
+        for difficulty in [Difficulty::Easy, Difficulty::Medium] {
+            let options = GenerateOptions {
+                difficulty: Some(difficulty),
+                ..Default::default()
+            };
+            let generated = generate(variant.clone(), &options, &mut Rng::new(3)).unwrap();
+            assert_eq!(generated.rating.difficulty, difficulty);
+        }
+    }
+
+    #[test]
+    fn test_generate_symmetry() {
+        let variant = Arc::new(Variant::default());
+        for symmetry in [Symmetry::Rotational, Symmetry::Mirror, Symmetry::Diagonal] {
+            let options = GenerateOptions {
+                symmetry,
+                ..Default::default()
+            };
+            let generated = generate(variant.clone(), &options, &mut Rng::new(2)).unwrap();
+            for row in 0..9 {
+                for col in 0..9 {
+                    let given = generated.givens[row][col] != 0;
+                    for (r, c) in symmetry.orbit((row, col)) {
+                        assert_eq!(generated.givens[r][c] != 0, given);
+                    }
+                }
+            }
+        }
+        assert_eq!(Symmetry::Rotational.orbit((4, 4)), vec![(4, 4)]);
+        assert_eq!(Symmetry::Diagonal.orbit((0, 1)), vec![(0, 1), (1, 0)]);
+    }
+
+    #[test]
+    fn test_generate_minimal() {
+        let variant = Arc::new(Variant::default());
+        let options = GenerateOptions {
+            symmetry: Symmetry::Rotational,
+            minimal: true,
+            ..Default::default()
+        };
+        let generated = generate(variant.clone(), &options, &mut Rng::new(4)).unwrap();
+        // 去掉任何一个数字都会有多个解
+        for row in 0..9 {
+            for col in 0..9 {
+                if generated.givens[row][col] != 0 {
+                    let mut givens = generated.givens;
+                    givens[row][col] = 0;
+                    let board = SudokuBoard::new_with(&givens, variant.clone());
+                    assert_eq!(count_solutions(&board, 2), 2);
+                }
+            }
+        }
+
+        // 比同一种子得到的极小题目再少一个数字
+        let options = GenerateOptions {
+            minimal: true,
+            ..Default::default()
+        };
+        let minimal = generate(variant.clone(), &options, &mut Rng::new(3)).unwrap();
+        let target = minimal.clues() - 1;
+        let options = GenerateOptions {
+            target_clues: Some(target),
+            ..Default::default()
+        };
+        let generated = generate(variant.clone(), &options, &mut Rng::new(3)).unwrap();
+        assert!(clues(&generated.givens) <= target);
+        let board = SudokuBoard::new_with(&generated.givens, variant);
+        assert_eq!(count_solutions(&board, 2), 1);
+    }
+
+    #[test]
+    fn test_generate_requires() {
+        let variant = Arc::new(Variant::default());
+        let options = GenerateOptions {
+            requires: Some(Technique::NakedPair),
+            ..Default::default()
+        };
+        let generated = generate(variant.clone(), &options, &mut Rng::new(5)).unwrap();
+        assert!(generated.rating.steps.contains(&Technique::NakedPair));
+        assert_eq!(generated.rating.difficulty, Difficulty::Medium);
+
+        // 简单题目不可能用到 X-wing
+        let options = GenerateOptions {
+            difficulty: Some(Difficulty::Easy),
+            requires: Some(Technique::XWing),
+            ..Default::default()
+        };
+        assert!(generate(variant, &options, &mut Rng::new(5)).is_none());
+    }
+
+    #[test]
+    fn test_generate_from_solution() {
+        let variant = Arc::new(Variant::default());
+        let solution = generate(
+            variant.clone(),
+            &GenerateOptions::default(),
+            &mut Rng::new(6),
+        )
+        .unwrap()
+        .solution;
+        let options = GenerateOptions {
+            solution: Some(solution),
+            ..Default::default()
+        };
+        let generated = generate(variant.clone(), &options, &mut Rng::new(7)).unwrap();
+        assert_eq!(generated.solution, solution);
+
+        // 不满足规则的终盘
+        let mut broken = solution;
+        broken[0].swap(0, 1);
+        let options = GenerateOptions {
+            solution: Some(broken),
+            ..Default::default()
+        };
+        assert!(generate(variant, &options, &mut Rng::new(7)).is_none());
+    }
+
+    #[test]
+    fn test_generate_clue_range() {
+        let variant = Arc::new(Variant::default());
+        let options = GenerateOptions {
+            clues: Some(30..=32),
+            ..Default::default()
+        };
+        for seed in 0..3 {
+            let generated = generate(variant.clone(), &options, &mut Rng::new(seed)).unwrap();
+            assert!((30..=32).contains(&generated.clues()));
+        }
+    }
+}
@@ -0,0 +1,350 @@
+//! 桌面图形界面
+//!
+//! 用 eframe（egui）绘制可点击的棋盘：点选位置后按数字键填数，勾选笔记模式时改为增删候选数字；
+//! 「Step」执行一轮推理，「Solve」给出一个解。界面状态与绘制分开，状态的操作不依赖窗口。
+
+use std::sync::Arc;
+
+use eframe::egui;
+use egui::{Align2, Color32, FontId, Key, Pos2, Rect, Sense, Stroke, Vec2};
+
+use crate::board::{parse_grid, Grid, SudokuBoard};
+use crate::constraint::Cell;
+use crate::solver::{count_solutions, solutions};
+use crate::variant::Variant;
+
+/// 界面状态
+#[derive(Debug, Clone)]
+pub struct GuiState {
+    /// 题目给定的数字
+    givens: Grid,
+    /// 当前盘面，空位的候选数字即显示的笔记
+    board: SudokuBoard,
+    /// 选中的位置
+    selected: Option<Cell>,
+    /// 数字键是否增删候选数字
+    pub pencil: bool,
+    /// 最近一次操作的结果
+    status: String,
+}
+
+impl GuiState {
+    /// 打开一道题目
+    pub fn new(givens: Grid) -> Self {
+        let variant = Arc::new(Variant::standard());
+        let (board, status) = match variant.conflicts(&givens).first() {
+            Some(conflict) => (SudokuBoard::empty(variant), conflict.to_string()),
+            None => (SudokuBoard::new_with(&givens, variant), String::new()),
+        };
+        Self {
+            givens: board.grid(),
+            board,
+            selected: None,
+            pencil: false,
+            status,
+        }
+    }
+
+    /// 当前盘面
+    pub fn board(&self) -> &SudokuBoard {
+        &self.board
+    }
+
+    /// 最近一次操作的结果
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    /// 选中某个位置
+    pub fn select(&mut self, cell: Cell) {
+        self.selected = Some(cell);
+    }
+
+    /// 选中的位置移动一格，超出棋盘时不动
+    pub fn move_by(&mut self, dr: isize, dc: isize) {
+        let (row, col) = self.selected.unwrap_or((0, 0));
+        let (r, c) = (row as isize + dr, col as isize + dc);
+        if (0..9).contains(&r) && (0..9).contains(&c) {
+            self.selected = Some((r as usize, c as usize));
+        }
+    }
+
+    /// 在选中的位置按下数字：填入，或在笔记模式下切换该候选数字；给定数字不能修改
+    pub fn press(&mut self, digit: u32) {
+        let Some((row, col)) = self.selected else {
+            return;
+        };
+        if self.givens[row][col] != 0 {
+            return;
+        }
+        let result = if self.pencil {
+            if self.board.get(row, col).digits.contains(&digit) {
+                Ok(self.board.remove_candidate(digit, row, col))
+            } else {
+                self.board.add_candidate(digit, row, col)
+            }
+        } else {
+            self.board.unassign(row, col);
+            self.board.assign(digit, row, col).map(|_| true)
+        };
+        self.status = result.err().unwrap_or_default();
+    }
+
+    /// 清除选中位置填入的数字
+    pub fn clear(&mut self) {
+        if let Some((row, col)) = self.selected {
+            if self.givens[row][col] == 0 {
+                self.board.unassign(row, col);
+                self.status.clear();
+            }
+        }
+    }
+
+    /// 清除所有填入的数字和笔记，回到题目
+    pub fn reset(&mut self) {
+        *self = Self {
+            pencil: self.pencil,
+            selected: self.selected,
+            ..Self::new(self.givens)
+        };
+    }
+
+    /// 在当前盘面上执行一轮推理
+    pub fn step(&mut self) {
+        self.status = match self.board.step() {
+            None => "progress",
+            Some(true) => "solved",
+            // 推理停下时，无解的盘面视为矛盾
+            Some(false) if self.board.exhausted() || count_solutions(&self.board, 1) == 0 => {
+                "contradiction"
+            }
+            Some(false) => "stuck",
+        }
+        .to_string();
+    }
+
+    /// 填入从题目求出的一个解
+    pub fn solve(&mut self) {
+        let board = SudokuBoard::new_with(&self.givens, self.board.variant().clone());
+        match solutions(&board, 1).first() {
+            Some(solution) => {
+                self.board = SudokuBoard::new_with(solution, board.variant().clone());
+                self.status = "solved".to_string();
+            }
+            None => self.status = "no solution".to_string(),
+        }
+    }
+}
+
+/// 窗口
+struct App {
+    state: GuiState,
+    /// 输入框中的题目
+    input: String,
+}
+
+impl App {
+    /// 处理键盘：数字填数或切换候选，0、退格和删除清除，方向键移动
+    fn keys(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let digits = [
+            Key::Num1,
+            Key::Num2,
+            Key::Num3,
+            Key::Num4,
+            Key::Num5,
+            Key::Num6,
+            Key::Num7,
+            Key::Num8,
+            Key::Num9,
+        ];
+        ctx.input(|input| {
+            for (digit, key) in (1..).zip(digits) {
+                if input.key_pressed(key) {
+                    self.state.press(digit);
+                }
+            }
+            if [Key::Num0, Key::Backspace, Key::Delete]
+                .iter()
+                .any(|key| input.key_pressed(*key))
+            {
+                self.state.clear();
+            }
+            for (key, dr, dc) in [
+                (Key::ArrowUp, -1, 0),
+                (Key::ArrowDown, 1, 0),
+                (Key::ArrowLeft, 0, -1),
+                (Key::ArrowRight, 0, 1),
+            ] {
+                if input.key_pressed(key) {
+                    self.state.move_by(dr, dc);
+                }
+            }
+        });
+    }
+
+    /// 绘制棋盘，点击时选中位置
+    fn grid(&mut self, ui: &mut egui::Ui) {
+        let size = ui.available_width().min(ui.available_height()).max(180.0);
+        let (response, painter) = ui.allocate_painter(Vec2::splat(size), Sense::click());
+        let rect = response.rect;
+        let cell = size / 9.0;
+        let at = |row: usize, col: usize| {
+            Rect::from_min_size(
+                rect.min + Vec2::new(col as f32 * cell, row as f32 * cell),
+                Vec2::splat(cell),
+            )
+        };
+        if let Some(pos) = response
+            .interact_pointer_pos()
+            .filter(|_| response.clicked())
+        {
+            let offset = pos - rect.min;
+            let (row, col) = ((offset.y / cell) as usize, (offset.x / cell) as usize);
+            self.state.select((row.min(8), col.min(8)));
+        }
+
+        let text = ui.visuals().text_color();
+        let weak = ui.visuals().weak_text_color();
+        let entered = Color32::from_rgb(40, 110, 200);
+        if let Some((row, col)) = self.state.selected {
+            painter.rect_filled(at(row, col), 0.0, ui.visuals().selection.bg_fill);
+        }
+        for row in 0..9 {
+            for col in 0..9 {
+                let pos = self.state.board.get(row, col);
+                let area = at(row, col);
+                if pos.val != 0 {
+                    let color = if self.state.givens[row][col] != 0 {
+                        text
+                    } else {
+                        entered
+                    };
+                    let font = FontId::proportional(cell * 0.6);
+                    painter.text(area.center(), Align2::CENTER_CENTER, pos.val, font, color);
+                    continue;
+                }
+                // 候选数字按 3x3 排在位置内
+                for &digit in &pos.digits {
+                    let (r, c) = ((digit - 1) / 3, (digit - 1) % 3);
+                    let center = area.min
+                        + Vec2::new((c as f32 + 0.5) * cell / 3.0, (r as f32 + 0.5) * cell / 3.0);
+                    let font = FontId::proportional(cell * 0.22);
+                    painter.text(center, Align2::CENTER_CENTER, digit, font, weak);
+                }
+            }
+        }
+        for i in 0..=9 {
+            let width = if i % 3 == 0 { 2.0 } else { 0.5 };
+            let d = i as f32 * cell;
+            let stroke = Stroke::new(width, text);
+            painter.line_segment(
+                [rect.min + Vec2::new(d, 0.0), rect.min + Vec2::new(d, size)],
+                stroke,
+            );
+            painter.line_segment(
+                [
+                    Pos2::new(rect.min.x, rect.min.y + d),
+                    Pos2::new(rect.min.x + size, rect.min.y + d),
+                ],
+                stroke,
+            );
+        }
+    }
+}
+
+impl eframe::App for App {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.keys(ctx);
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.input)
+                        .hint_text("81 digits, 0 or . for an empty cell")
+                        .desired_width(ui.available_width() - 60.0),
+                );
+                if ui.button("Load").clicked() {
+                    match parse_grid(&self.input) {
+                        Ok(givens) => self.state = GuiState::new(givens),
+                        Err(e) => self.state.status = e,
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.state.pencil, "Pencil marks");
+                if ui.button("Step").clicked() {
+                    self.state.step();
+                }
+                if ui.button("Solve").clicked() {
+                    self.state.solve();
+                }
+                if ui.button("Reset").clicked() {
+                    self.state.reset();
+                }
+                ui.label(self.state.status());
+            });
+            self.grid(ui);
+        });
+    }
+}
+
+/// 打开窗口，直到窗口关闭
+pub fn run(puzzle: Option<Grid>) -> Result<(), String> {
+    let state = GuiState::new(puzzle.unwrap_or([[0; 9]; 9]));
+    let app = App {
+        input: crate::board::grid_line(&state.givens),
+        state,
+    };
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_title("superdo")
+            .with_inner_size([480.0, 580.0]),
+        ..Default::default()
+    };
+    eframe::run_native("superdo", options, Box::new(|_| Ok(Box::new(app))))
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GuiState;
+    use crate::board::parse_grid;
+
+    const PUZZLE: &str =
+        "530070000600195000098000060800060003400803001700020006060000280000419005000080079";
+
+    #[test]
+    fn test_state() {
+        let mut state = GuiState::new(parse_grid(PUZZLE).unwrap());
+        // 给定数字不能修改
+        state.select((0, 0));
+        state.press(4);
+        assert_eq!(state.board().get(0, 0).val, 5);
+
+        state.move_by(0, 2);
+        state.press(5);
+        assert!(state.status().contains("conflicts"));
+        state.press(4);
+        assert_eq!(state.board().get(0, 2).val, 4);
+        state.clear();
+        assert_eq!(state.board().get(0, 2).val, 0);
+
+        state.pencil = true;
+        assert!(state.board().get(0, 2).digits.contains(&4));
+        state.press(4);
+        assert!(!state.board().get(0, 2).digits.contains(&4));
+        state.press(4);
+        assert!(state.board().get(0, 2).digits.contains(&4));
+
+        state.step();
+        assert_eq!(state.status(), "progress");
+        state.solve();
+        assert_eq!(state.status(), "solved");
+        assert_eq!(state.board().get(0, 2).val, 4);
+        state.reset();
+        assert_eq!(state.board().get(0, 2).val, 0);
+        assert!(state.pencil);
+    }
+}
@@ -0,0 +1,180 @@
+//! 编辑历史
+//!
+//! 交互式前端对棋盘的每次修改（填数、清除、增删候选数字）都作为一条 [`Edit`] 交给
+//! [`History`] 执行，可以无限次撤销和重做。撤销直接恢复执行前的棋盘，
+//! 候选数字与执行前完全相同，不依赖重新推导。
+
+use std::fmt;
+
+use crate::board::SudokuBoard;
+
+/// 对棋盘的一次修改，行列从 0 开始
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    /// 在空位填入数字
+    Assign { row: usize, col: usize, val: u32 },
+    /// 清除已填的数字
+    Unassign { row: usize, col: usize },
+    /// 加回候选数字
+    AddCandidate { row: usize, col: usize, val: u32 },
+    /// 排除候选数字
+    RemoveCandidate { row: usize, col: usize, val: u32 },
+}
+
+impl Edit {
+    /// 修改的位置
+    pub fn cell(&self) -> (usize, usize) {
+        match *self {
+            Edit::Assign { row, col, .. }
+            | Edit::Unassign { row, col }
+            | Edit::AddCandidate { row, col, .. }
+            | Edit::RemoveCandidate { row, col, .. } => (row, col),
+        }
+    }
+
+    /// 在棋盘上执行，返回是否有变化，数字冲突时报错
+    pub fn apply(&self, board: &mut SudokuBoard) -> Result<bool, String> {
+        match *self {
+            Edit::Assign { row, col, val } => board.assign(val, row, col).map(|_| true),
+            Edit::Unassign { row, col } => Ok(board.unassign(row, col).is_some()),
+            Edit::AddCandidate { row, col, val } => board.add_candidate(val, row, col),
+            Edit::RemoveCandidate { row, col, val } => Ok(board.remove_candidate(val, row, col)),
+        }
+    }
+}
+
+impl fmt::Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Edit::Assign { row, col, val } => write!(f, "r{}c{}={}", row + 1, col + 1, val),
+            Edit::Unassign { row, col } => write!(f, "r{}c{}=0", row + 1, col + 1),
+            Edit::AddCandidate { row, col, val } => write!(f, "r{}c{}+{}", row + 1, col + 1, val),
+            Edit::RemoveCandidate { row, col, val } => {
+                write!(f, "r{}c{}-{}", row + 1, col + 1, val)
+            }
+        }
+    }
+}
+
+/// 可撤销、重做的编辑历史
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    /// 已执行的修改及执行前的棋盘
+    done: Vec<(Edit, SudokuBoard)>,
+    /// 已撤销、可重做的修改，最近撤销的在最后
+    undone: Vec<Edit>,
+}
+
+impl History {
+    /// 空的历史
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 执行一次修改并记入历史，清空可重做的修改；没有变化或报错时不记录
+    pub fn apply(&mut self, board: &mut SudokuBoard, edit: Edit) -> Result<bool, String> {
+        let before = board.clone();
+        if !edit.apply(board)? {
+            return Ok(false);
+        }
+        self.done.push((edit, before));
+        self.undone.clear();
+        Ok(true)
+    }
+
+    /// 撤销最近一次修改，返回撤销的修改，没有可撤销的时为 None
+    pub fn undo(&mut self, board: &mut SudokuBoard) -> Option<Edit> {
+        let (edit, before) = self.done.pop()?;
+        *board = before;
+        self.undone.push(edit);
+        Some(edit)
+    }
+
+    /// 重做最近撤销的修改，返回重做的修改，没有可重做的时为 None。
+    /// 撤销之后棋盘在历史之外被改动而无法重做时报错，该修改仍可重做
+    pub fn redo(&mut self, board: &mut SudokuBoard) -> Result<Option<Edit>, String> {
+        let Some(edit) = self.undone.pop() else {
+            return Ok(None);
+        };
+        let before = board.clone();
+        if let Err(e) = edit.apply(board) {
+            self.undone.push(edit);
+            return Err(e);
+        }
+        self.done.push((edit, before));
+        Ok(Some(edit))
+    }
+
+    /// 是否有可撤销的修改
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    /// 是否有可重做的修改
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// 已执行的修改，按执行顺序
+    pub fn edits(&self) -> impl Iterator<Item = &Edit> {
+        self.done.iter().map(|(edit, _)| edit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{Edit, History};
+    use crate::board::SudokuBoard;
+    use crate::variant::Variant;
+
+    #[test]
+    fn test_history() {
+        let mut board = SudokuBoard::empty(Arc::new(Variant::standard()));
+        let start = board.state_hash();
+        let mut history = History::new();
+        assert!(!history.can_undo());
+        assert_eq!(history.undo(&mut board), None);
+
+        let assign = Edit::Assign {
+            row: 0,
+            col: 0,
+            val: 5,
+        };
+        let remove = Edit::RemoveCandidate {
+            row: 0,
+            col: 1,
+            val: 3,
+        };
+        assert_eq!(history.apply(&mut board, assign), Ok(true));
+        assert_eq!(history.apply(&mut board, remove), Ok(true));
+        // 没有变化或冲突的修改不记录
+        assert_eq!(history.apply(&mut board, remove), Ok(false));
+        let conflict = Edit::Assign {
+            row: 0,
+            col: 8,
+            val: 5,
+        };
+        assert!(history.apply(&mut board, conflict).is_err());
+        assert_eq!(history.edits().count(), 2);
+        let edited = board.state_hash();
+
+        assert_eq!(history.undo(&mut board), Some(remove));
+        assert_eq!(history.undo(&mut board), Some(assign));
+        assert_eq!(board.state_hash(), start);
+        assert!(history.can_redo());
+        assert_eq!(history.redo(&mut board), Ok(Some(assign)));
+        assert_eq!(history.redo(&mut board), Ok(Some(remove)));
+        assert_eq!(history.redo(&mut board), Ok(None));
+        assert_eq!(board.state_hash(), edited);
+
+        // 新的修改清空可重做的修改
+        history.undo(&mut board);
+        let unassign = Edit::Unassign { row: 0, col: 0 };
+        assert_eq!(history.apply(&mut board, unassign), Ok(true));
+        assert!(!history.can_redo());
+        assert_eq!(unassign.to_string(), "r1c1=0");
+        assert_eq!(remove.to_string(), "r1c2-3");
+    }
+}
@@ -0,0 +1,380 @@
+//! 命令行消息的本地化
+//!
+//! 消息目录按键查找，每条消息有英文（默认）和中文两种写法，其中的 `{}` 依次替换为参数。
+//! 语言由 `--lang` 给出，未给出时按 `LC_ALL`、`LC_MESSAGES`、`LANG` 环境变量判断。
+//! 只翻译给人读的文字；JSON 中的错误类型和消息、技巧名称等机器可读的输出不随语言变化。
+
+use std::fmt;
+
+use clap::ValueEnum;
+
+use crate::constraint::Cell;
+use crate::rating::{CellHint, Difficulty, Technique};
+use crate::solver::Contradiction;
+use crate::variant::Conflict;
+
+/// 消息的语言
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lang {
+    /// English
+    #[default]
+    En,
+    /// Chinese (简体中文)
+    Zh,
+}
+
+/// 消息目录：键、英文、中文
+const CATALOG: &[(&str, &str, &str)] = &[
+    ("no_solution", "no solution: {}", "无解：{}"),
+    (
+        "every_guess_fails",
+        "every guess leads to a contradiction",
+        "每个猜测都导致矛盾",
+    ),
+    (
+        "too_few_clues",
+        "only {} givens: the puzzle cannot have a unique solution",
+        "只有 {} 个给定数字：题目不可能有唯一解",
+    ),
+    (
+        "infeasible",
+        "enumerating all solutions is infeasible; add --limit N or --force",
+        "无法列举所有解；请加上 --limit N 或 --force",
+    ),
+    (
+        "too_hard",
+        "too hard under current limits: {} branches needed more than {} guesses",
+        "在当前限制下太难：{} 个分支需要超过 {} 次猜测",
+    ),
+    (
+        "cut_off",
+        "{} branches needed more than {} guesses and were abandoned, solutions may be missing",
+        "{} 个分支需要超过 {} 次猜测，已放弃，可能漏掉了解",
+    ),
+    (
+        "unexpected_character",
+        "line {}, column {}: unexpected character `{}`; skipping the puzzle",
+        "第 {} 行第 {} 列：意外的字符 `{}`；跳过这道题目",
+    ),
+    (
+        "malformed_row",
+        "line {}: row {} of the puzzle has {} cells, expected 9; skipping the puzzle",
+        "第 {} 行：题目的第 {} 行有 {} 个格子，应为 9 个；跳过这道题目",
+    ),
+    (
+        "incomplete_puzzle",
+        "input ended with an incomplete puzzle: {} of 81 digits read",
+        "输入在题目读完之前结束：只读到 81 个数字中的 {} 个",
+    ),
+    (
+        "truncated_puzzle",
+        "line {}: blank line after {} of 81 digits; skipping the puzzle",
+        "第 {1} 行：空行之前只读到 81 个数字中的 {2} 个；跳过这道题目",
+    ),
+    (
+        "duplicate_row",
+        "duplicate {} in row {} at c{} and c{}",
+        "第 {2} 行的 c{3} 和 c{4} 重复了 {1}",
+    ),
+    (
+        "duplicate_col",
+        "duplicate {} in column {} at r{} and r{}",
+        "第 {2} 列的 r{3} 和 r{4} 重复了 {1}",
+    ),
+    (
+        "duplicate_box",
+        "duplicate {} in box {} at {} and {}",
+        "第 {2} 宫的 {3} 和 {4} 重复了 {1}",
+    ),
+    (
+        "duplicate",
+        "duplicate {} at {} and {}",
+        "{2} 和 {3} 重复了 {1}",
+    ),
+    (
+        "violated_givens",
+        "givens violate constraint: {}",
+        "给定数字违反约束：{}",
+    ),
+    (
+        "no_candidates",
+        "no candidates left for {}",
+        "{} 已没有候选数字",
+    ),
+    ("givens", "givens {}", "给定 {}"),
+    ("deduced", "deduced {}", "推出 {}"),
+    (
+        "no_place",
+        "no place for {} in {}",
+        "{2} 中已没有位置可以填 {1}",
+    ),
+    (
+        "unsatisfiable",
+        "constraint cannot be satisfied: {}",
+        "约束无法满足：{}",
+    ),
+    ("hint", "{} is {} ({})", "{} 是 {}（{}）"),
+    (
+        "hint_stuck",
+        "{} has {} candidates and no single technique fills it",
+        "{} 有 {} 个候选数字，没有哪个技巧能单独填出",
+    ),
+    ("board_full", "the board is already full", "盘面已经填满"),
+    ("unsolvable", "the puzzle has no solution", "题目无解"),
+    (
+        "one_of_many",
+        "the puzzle has more than one solution, here is one:",
+        "题目不止一个解，这是其中一个：",
+    ),
+    (
+        "bot_help",
+        "`{}solve PUZZLE` solves a puzzle, `{}hint PUZZLE` gives the easiest next cell. \
+         A puzzle is 81 digits, 0 or . for an empty cell.",
+        "`{}solve 题目` 求解，`{}hint 题目` 给出最容易填的下一格。\
+         题目为 81 个数字，空格写作 0 或 .。",
+    ),
+];
+
+impl Lang {
+    /// 按环境变量判断语言，依次查看 `LC_ALL`、`LC_MESSAGES`、`LANG` 中第一个非空的
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .map_or(Lang::En, |locale| Self::from_locale(&locale))
+    }
+
+    /// 由 locale 名称（如 `zh_CN.UTF-8`）得到语言，不认识的为英文
+    pub fn from_locale(locale: &str) -> Self {
+        match locale.to_ascii_lowercase() {
+            l if l.starts_with("zh") => Lang::Zh,
+            _ => Lang::En,
+        }
+    }
+
+    /// 目录中的消息，键不存在时 panic
+    pub fn message(self, key: &str) -> &'static str {
+        let &(_, en, zh) = CATALOG
+            .iter()
+            .find(|(k, ..)| *k == key)
+            .unwrap_or_else(|| panic!("no message `{}`", key));
+        match self {
+            Lang::En => en,
+            Lang::Zh => zh,
+        }
+    }
+
+    /// 填入参数后的消息。`{}` 依次取参数，`{n}` 取第 n 个参数（从 1 开始），供语序不同的翻译使用
+    pub fn format(self, key: &str, args: &[&dyn fmt::Display]) -> String {
+        let template = self.message(key);
+        let mut out = String::new();
+        let mut next = 0;
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            out.push_str(&rest[..start]);
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            let index = match rest[start + 1..start + len].parse::<usize>() {
+                Ok(n) => n - 1,
+                Err(_) => {
+                    next += 1;
+                    next - 1
+                }
+            };
+            if let Some(arg) = args.get(index) {
+                out.push_str(&arg.to_string());
+            }
+            rest = &rest[start + len + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// 技巧的名称
+    pub fn technique(self, technique: Technique) -> &'static str {
+        if self == Lang::En {
+            return technique.name();
+        }
+        match technique {
+            Technique::NakedSingle => "唯余法",
+            Technique::HiddenSingle => "摒除法",
+            Technique::LockedCandidates => "区块摒除",
+            Technique::NakedPair => "显性数对",
+            Technique::HiddenPair => "隐性数对",
+            Technique::NakedTriple => "显性三数组",
+            Technique::HiddenTriple => "隐性三数组",
+            Technique::NakedQuad => "显性四数组",
+            Technique::HiddenQuad => "隐性四数组",
+            Technique::XWing => "X-Wing",
+            Technique::Swordfish => "剑鱼",
+            Technique::Jellyfish => "水母",
+        }
+    }
+
+    /// 难度的名称
+    pub fn difficulty(self, difficulty: Difficulty) -> &'static str {
+        if self == Lang::En {
+            return difficulty.name();
+        }
+        match difficulty {
+            Difficulty::Easy => "简单",
+            Difficulty::Medium => "中等",
+            Difficulty::Hard => "困难",
+            Difficulty::Expert => "专家",
+            Difficulty::Evil => "地狱",
+        }
+    }
+
+    /// 给定数字冲突的说明
+    pub fn conflict(self, conflict: &Conflict) -> String {
+        match *conflict {
+            Conflict::Duplicate { digit, a, b } if a.0 == b.0 => self.format(
+                "duplicate_row",
+                &[&digit, &(a.0 + 1), &(a.1 + 1), &(b.1 + 1)],
+            ),
+            Conflict::Duplicate { digit, a, b } if a.1 == b.1 => self.format(
+                "duplicate_col",
+                &[&digit, &(a.1 + 1), &(a.0 + 1), &(b.0 + 1)],
+            ),
+            Conflict::Duplicate { digit, a, b } if (a.0 / 3, a.1 / 3) == (b.0 / 3, b.1 / 3) => {
+                let unit = a.0 / 3 * 3 + a.1 / 3 + 1;
+                self.format("duplicate_box", &[&digit, &unit, &cell(a), &cell(b)])
+            }
+            Conflict::Duplicate { digit, a, b } => {
+                self.format("duplicate", &[&digit, &cell(a), &cell(b)])
+            }
+            Conflict::Violated(ref constraint) => self.format("violated_givens", &[constraint]),
+        }
+    }
+
+    /// 推理中发现的矛盾的说明
+    pub fn contradiction(self, contradiction: &Contradiction) -> String {
+        let join = |cells: &[(Cell, u32)]| {
+            cells
+                .iter()
+                .map(|&(c, d)| format!("{}={}", cell(c), d))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        match contradiction {
+            Contradiction::Empty {
+                cell: at,
+                givens,
+                deduced,
+            } => {
+                let mut parts = vec![self.format("no_candidates", &[&cell(*at)])];
+                if !givens.is_empty() {
+                    parts.push(self.format("givens", &[&join(givens)]));
+                }
+                if !deduced.is_empty() {
+                    parts.push(self.format("deduced", &[&join(deduced)]));
+                }
+                parts.join(if self == Lang::Zh { "；" } else { "; " })
+            }
+            Contradiction::Violated {
+                constraint,
+                digit: Some(digit),
+                ..
+            } => self.format("no_place", &[digit, constraint]),
+            Contradiction::Violated { constraint, .. } => {
+                self.format("unsatisfiable", &[constraint])
+            }
+        }
+    }
+
+    /// 提示：某格可由哪个技巧填出
+    pub fn hint(self, hint: &CellHint) -> String {
+        let at = cell(hint.cell);
+        match (hint.digit, hint.technique) {
+            (Some(digit), Some(technique)) => {
+                self.format("hint", &[&at, &digit, &self.technique(technique)])
+            }
+            _ => self.format("hint_stuck", &[&at, &hint.candidates]),
+        }
+    }
+}
+
+/// 格子的写法 `r1c1`
+fn cell((row, col): Cell) -> String {
+    format!("r{}c{}", row + 1, col + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Lang, CATALOG};
+    use crate::rating::{Difficulty, Technique};
+    use crate::solver::Contradiction;
+    use crate::variant::Conflict;
+
+    #[test]
+    fn test_from_locale() {
+        assert_eq!(Lang::from_locale("zh_CN.UTF-8"), Lang::Zh);
+        assert_eq!(Lang::from_locale("zh_TW"), Lang::Zh);
+        assert_eq!(Lang::from_locale("en_US.UTF-8"), Lang::En);
+        assert_eq!(Lang::from_locale("C"), Lang::En);
+    }
+
+    #[test]
+    fn test_catalog() {
+        // 两种写法的参数个数相同
+        let count = |s: &str| s.matches('{').count();
+        for (key, en, zh) in CATALOG {
+            assert_eq!(count(en), count(zh), "{}", key);
+        }
+        assert_eq!(
+            Lang::En.format("malformed_row", &[&3, &1, &8]),
+            "line 3: row 1 of the puzzle has 8 cells, expected 9; skipping the puzzle"
+        );
+        assert_eq!(
+            Lang::Zh.format("too_few_clues", &[&16]),
+            "只有 16 个给定数字：题目不可能有唯一解"
+        );
+        assert_eq!(Lang::Zh.technique(Technique::HiddenSingle), "摒除法");
+        assert_eq!(Lang::En.difficulty(Difficulty::Evil), "evil");
+    }
+
+    #[test]
+    fn test_explanations() {
+        // 英文与各类型自己的 Display 一致
+        let conflicts = [
+            Conflict::Duplicate {
+                digit: 5,
+                a: (0, 0),
+                b: (0, 1),
+            },
+            Conflict::Duplicate {
+                digit: 5,
+                a: (0, 0),
+                b: (4, 0),
+            },
+            Conflict::Duplicate {
+                digit: 5,
+                a: (0, 0),
+                b: (1, 1),
+            },
+            Conflict::Violated("thermometer r1c1-r1c2".to_string()),
+        ];
+        for conflict in &conflicts {
+            assert_eq!(Lang::En.conflict(conflict), conflict.to_string());
+        }
+        assert_eq!(
+            Lang::Zh.conflict(&conflicts[0]),
+            "第 1 行的 c1 和 c2 重复了 5"
+        );
+        let contradiction = Contradiction::Empty {
+            cell: (0, 8),
+            givens: vec![((0, 0), 1)],
+            deduced: vec![((1, 8), 9)],
+        };
+        assert_eq!(
+            Lang::En.contradiction(&contradiction),
+            contradiction.to_string()
+        );
+        assert_eq!(
+            Lang::Zh.contradiction(&contradiction),
+            "r1c9 已没有候选数字；给定 r1c1=1；推出 r2c9=9"
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! 读取图片，转为 8 位灰度
+//!
+//! 用 image crate 解码 PNG 和 Netpbm（PBM、PGM、PPM、PAM）。带透明度的像素按白色背景合成。
+//! 另可编码为灰度 PNG。
+
+use std::io::Cursor;
+
+use ::image::codecs::png::PngEncoder;
+use ::image::{ExtendedColorType, ImageEncoder, ImageFormat};
+
+/// 灰度图片，0 为黑，255 为白
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    /// 按行排列的像素
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// 全白的图片
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![255; width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, v: u8) {
+        self.pixels[y * self.width + x] = v;
+    }
+}
+
+/// 按文件头识别格式并解码
+pub fn decode(data: &[u8]) -> Result<Image, String> {
+    let format = ::image::guess_format(data)
+        .ok()
+        .filter(|f| matches!(f, ImageFormat::Png | ImageFormat::Pnm))
+        .ok_or("unsupported image format, expected PNG, PGM or PPM")?;
+    let rgba = ::image::load_from_memory_with_format(data, format)
+        .map_err(|e| e.to_string())?
+        .into_rgba8();
+    let (width, height) = (rgba.width() as usize, rgba.height() as usize);
+    let pixels = rgba
+        .pixels()
+        .map(|p| {
+            let [r, g, b, alpha] = p.0;
+            over_white(luma(r, g, b), alpha)
+        })
+        .collect();
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// 编码为 8 位灰度 PNG
+pub fn encode_png(image: &Image) -> Vec<u8> {
+    let mut out = Cursor::new(vec![]);
+    PngEncoder::new(&mut out)
+        .write_image(
+            &image.pixels,
+            image.width as u32,
+            image.height as u32,
+            ExtendedColorType::L8,
+        )
+        .expect("the pixels match the image size");
+    out.into_inner()
+}
+
+/// RGB 转为灰度
+fn luma(r: u8, g: u8, b: u8) -> u8 {
+    ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as u8
+}
+
+/// 按透明度与白色背景合成
+fn over_white(v: u8, alpha: u8) -> u8 {
+    ((v as u32 * alpha as u32 + 255 * (255 - alpha as u32)) / 255) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode_png, Image};
+
+    #[test]
+    fn test_png() {
+        // RGB，5 行依次使用 5 种过滤方式
+        let rgb = [
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48,
+            0x44, 0x52, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x05, 0x08, 0x02, 0x00, 0x00,
+            0x00, 0x0f, 0x13, 0xc1, 0xf5, 0x00, 0x00, 0x00, 0x28, 0x49, 0x44, 0x41, 0x54, 0x78,
+            0x9c, 0x63, 0x60, 0x90, 0xb3, 0x71, 0x4b, 0x69, 0xea, 0x59, 0x75, 0x82, 0x51, 0xc3,
+            0x2d, 0xc5, 0x0d, 0x0c, 0x98, 0x34, 0x60, 0x80, 0x39, 0x20, 0x3e, 0xcf, 0x1c, 0x04,
+            0xb6, 0xb3, 0x40, 0x45, 0xdc, 0xdc, 0x00, 0x44, 0xe0, 0x0b, 0xf8, 0xf6, 0x60, 0x6a,
+            0xaa, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+        ];
+        let image = decode(&rgb).unwrap();
+        assert_eq!((image.width, image.height), (3, 5));
+        assert_eq!(
+            image.pixels,
+            [24, 94, 164, 64, 134, 204, 104, 174, 215, 144, 214, 28, 184, 74, 68]
+        );
+        // 1 位灰度，每行末尾有填充位
+        let gray = [
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48,
+            0x44, 0x52, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x02, 0x01, 0x00, 0x00, 0x00,
+            0x00, 0x49, 0x1a, 0x70, 0x7d, 0x00, 0x00, 0x00, 0x0e, 0x49, 0x44, 0x41, 0x54, 0x78,
+            0x9c, 0x63, 0xd8, 0xe4, 0xc0, 0xe0, 0xdb, 0x00, 0x00, 0x05, 0x9a, 0x01, 0xc0, 0xdb,
+            0x66, 0xc1, 0xfa, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60,
+            0x82,
+        ];
+        assert_eq!(
+            decode(&gray).unwrap().pixels,
+            [255, 0, 255, 255, 0, 0, 255, 0, 0, 255, 0, 255, 0, 0, 255, 255, 0, 255, 255, 0]
+        );
+        // 2 位调色板：黑、白、红
+        let palette = [
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48,
+            0x44, 0x52, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01, 0x02, 0x03, 0x00, 0x00,
+            0x00, 0x66, 0x8e, 0xfc, 0x27, 0x00, 0x00, 0x00, 0x09, 0x50, 0x4c, 0x54, 0x45, 0x00,
+            0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0xcd, 0x5e, 0xb7, 0x9c, 0x00, 0x00,
+            0x00, 0x0a, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0x90, 0x00, 0x00, 0x00, 0x1a,
+            0x00, 0x19, 0x2d, 0x88, 0xf4, 0x36, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44,
+            0xae, 0x42, 0x60, 0x82,
+        ];
+        assert_eq!(decode(&palette).unwrap().pixels, [0, 255, 76]);
+        assert!(decode(&rgb[..40]).is_err());
+    }
+
+    #[test]
+    fn test_encode_png() {
+        let mut image = Image::new(300, 250);
+        for (i, v) in image.pixels.iter_mut().enumerate() {
+            *v = (i * 7 % 256) as u8;
+        }
+        assert_eq!(decode(&encode_png(&image)).unwrap(), image);
+        assert_eq!(
+            decode(&encode_png(&Image::new(1, 1))).unwrap().pixels,
+            [255]
+        );
+    }
+
+    #[test]
+    fn test_netpbm() {
+        let image = decode(b"P2\n# comment\n3 2\n4\n0 1 2\n3 4 9\n").unwrap();
+        assert_eq!(
+            image,
+            Image {
+                width: 3,
+                height: 2,
+                pixels: vec![0, 64, 128, 191, 255, 255],
+            }
+        );
+        let mut binary = b"P6 2 1 255\n".to_vec();
+        binary.extend([255, 255, 255, 0, 0, 0]);
+        assert_eq!(decode(&binary).unwrap().pixels, [255, 0]);
+        assert!(decode(b"P5 2 2 255\n\x00").is_err());
+        assert!(decode(b"GIF89a").is_err());
+    }
+}
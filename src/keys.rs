@@ -0,0 +1,335 @@
+//! 按键绑定
+//!
+//! 交互式前端把按键经 [`Keymap`] 映射为 [`Action`]，不直接判断按键。内置默认和 vim 风格两套绑定，
+//! 配置文件中可以选择其一并逐项改绑，见 [`crate::config`]。
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::json::Json;
+
+/// 按键
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    /// 字符键，区分大小写
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Backspace,
+    Delete,
+    Tab,
+    Esc,
+    /// Ctrl 加字母，字母为小写
+    Ctrl(char),
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    /// 解析 `a`、`?`、`space`、`up`、`ctrl-z` 等按键名称，名称不区分大小写（单个字符除外）
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Ok(Key::Char(c));
+        }
+        let name = s.to_ascii_lowercase();
+        let key = match name.as_str() {
+            "space" => Key::Char(' '),
+            "up" => Key::Up,
+            "down" => Key::Down,
+            "left" => Key::Left,
+            "right" => Key::Right,
+            "enter" => Key::Enter,
+            "backspace" => Key::Backspace,
+            "delete" => Key::Delete,
+            "tab" => Key::Tab,
+            "esc" => Key::Esc,
+            _ => match name
+                .strip_prefix("ctrl-")
+                .or_else(|| name.strip_prefix("ctrl+"))
+                .map(|rest| rest.chars().collect::<Vec<_>>())
+                .as_deref()
+            {
+                Some(&[c]) if c.is_ascii_alphabetic() => Key::Ctrl(c),
+                _ => return Err(format!("unknown key `{}`", s)),
+            },
+        };
+        Ok(key)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Key::Char(' ') => write!(f, "space"),
+            Key::Char(c) => write!(f, "{}", c),
+            Key::Up => write!(f, "up"),
+            Key::Down => write!(f, "down"),
+            Key::Left => write!(f, "left"),
+            Key::Right => write!(f, "right"),
+            Key::Enter => write!(f, "enter"),
+            Key::Backspace => write!(f, "backspace"),
+            Key::Delete => write!(f, "delete"),
+            Key::Tab => write!(f, "tab"),
+            Key::Esc => write!(f, "esc"),
+            Key::Ctrl(c) => write!(f, "ctrl-{}", c),
+        }
+    }
+}
+
+/// 前端的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// 光标上移
+    Up,
+    /// 光标下移
+    Down,
+    /// 光标左移
+    Left,
+    /// 光标右移
+    Right,
+    /// 填入数字，标注模式下切换该候选数字
+    Digit(u32),
+    /// 清除光标处的数字
+    Clear,
+    /// 切换填数和标注候选数字，相当于按住修饰键
+    Pencil,
+    /// 切换自动和手动维护候选数字标记
+    Marks,
+    /// 提示
+    Hint,
+    /// 撤销
+    Undo,
+    /// 重做
+    Redo,
+    /// 保存进度
+    Save,
+    /// 退出
+    Quit,
+}
+
+impl Action {
+    /// 配置文件中的名称
+    pub fn name(self) -> String {
+        match self {
+            Action::Up => "up".to_string(),
+            Action::Down => "down".to_string(),
+            Action::Left => "left".to_string(),
+            Action::Right => "right".to_string(),
+            Action::Digit(d) => format!("digit-{}", d),
+            Action::Clear => "clear".to_string(),
+            Action::Pencil => "pencil".to_string(),
+            Action::Marks => "marks".to_string(),
+            Action::Hint => "hint".to_string(),
+            Action::Undo => "undo".to_string(),
+            Action::Redo => "redo".to_string(),
+            Action::Save => "save".to_string(),
+            Action::Quit => "quit".to_string(),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let action = match s {
+            "up" => Action::Up,
+            "down" => Action::Down,
+            "left" => Action::Left,
+            "right" => Action::Right,
+            "clear" => Action::Clear,
+            "pencil" => Action::Pencil,
+            "marks" => Action::Marks,
+            "hint" => Action::Hint,
+            "undo" => Action::Undo,
+            "redo" => Action::Redo,
+            "save" => Action::Save,
+            "quit" => Action::Quit,
+            _ => match s.strip_prefix("digit-").and_then(|d| d.parse().ok()) {
+                Some(d @ 1..=9) => Action::Digit(d),
+                _ => return Err(format!("unknown action `{}`", s)),
+            },
+        };
+        Ok(action)
+    }
+}
+
+/// 内置的按键绑定
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Preset {
+    /// Arrow keys, p for pencil marks, ctrl-z / ctrl-y to undo / redo
+    #[default]
+    Default,
+    /// hjkl (and arrows), m for pencil marks, u / ctrl-r to undo / redo
+    Vim,
+}
+
+/// 按键到操作的映射
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: Vec<(Key, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::preset(Preset::Default)
+    }
+}
+
+impl Keymap {
+    /// 内置的绑定，两套都可以用方向键移动、数字键填数
+    pub fn preset(preset: Preset) -> Self {
+        let mut bindings = vec![
+            (Key::Up, Action::Up),
+            (Key::Down, Action::Down),
+            (Key::Left, Action::Left),
+            (Key::Right, Action::Right),
+            (Key::Backspace, Action::Clear),
+            (Key::Delete, Action::Clear),
+            (Key::Char('a'), Action::Marks),
+            (Key::Ctrl('s'), Action::Save),
+            (Key::Char('q'), Action::Quit),
+        ];
+        bindings.extend((1..10).map(|d| {
+            (
+                Key::Char(char::from_digit(d, 10).unwrap()),
+                Action::Digit(d),
+            )
+        }));
+        let extra: &[(Key, Action)] = match preset {
+            Preset::Default => &[
+                (Key::Char('0'), Action::Clear),
+                (Key::Char('p'), Action::Pencil),
+                (Key::Char('h'), Action::Hint),
+                (Key::Ctrl('z'), Action::Undo),
+                (Key::Ctrl('y'), Action::Redo),
+            ],
+            Preset::Vim => &[
+                (Key::Char('k'), Action::Up),
+                (Key::Char('j'), Action::Down),
+                (Key::Char('h'), Action::Left),
+                (Key::Char('l'), Action::Right),
+                (Key::Char('x'), Action::Clear),
+                (Key::Char('m'), Action::Pencil),
+                (Key::Char('?'), Action::Hint),
+                (Key::Char('u'), Action::Undo),
+                (Key::Ctrl('r'), Action::Redo),
+            ],
+        };
+        bindings.extend_from_slice(extra);
+        Self { bindings }
+    }
+
+    /// 按键对应的操作
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, action)| *action)
+    }
+
+    /// 操作绑定的按键，用于显示帮助
+    pub fn keys(&self, action: Action) -> Vec<Key> {
+        self.bindings
+            .iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(k, _)| *k)
+            .collect()
+    }
+
+    /// 把操作改绑到这些按键上，取代该操作原有的按键；这些按键原来的绑定被移除
+    pub fn bind(&mut self, action: Action, keys: &[Key]) {
+        self.bindings
+            .retain(|(k, a)| *a != action && !keys.contains(k));
+        self.bindings.extend(keys.iter().map(|k| (*k, action)));
+    }
+
+    /// 读取配置中的 `{"preset": "vim", "bindings": {"hint": ["?", "ctrl-h"]}}`，两项都可省略
+    pub fn from_json(json: &Json) -> Result<Self, String> {
+        let preset = match json.get("preset").map(|p| p.as_str()) {
+            None => Preset::Default,
+            Some(Some(name)) => clap::ValueEnum::from_str(name, true)
+                .map_err(|_| format!("unknown key preset `{}`", name))?,
+            Some(None) => return Err("key preset must be a string".to_string()),
+        };
+        let mut keymap = Self::preset(preset);
+        let bindings = match json.get("bindings") {
+            None => return Ok(keymap),
+            Some(Json::Object(items)) => items,
+            Some(_) => return Err("key bindings must be an object".to_string()),
+        };
+        for (name, keys) in bindings {
+            let action: Action = name.parse()?;
+            let keys = match keys {
+                Json::String(key) => vec![key.parse()?],
+                Json::Array(keys) => keys
+                    .iter()
+                    .map(|k| k.as_str().ok_or("keys must be strings")?.parse())
+                    .collect::<Result<Vec<Key>, String>>()?,
+                _ => return Err(format!("keys of `{}` must be a string or an array", name)),
+            };
+            keymap.bind(action, &keys);
+        }
+        Ok(keymap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, Key, Keymap, Preset};
+    use crate::json::Json;
+
+    #[test]
+    fn test_key() {
+        for name in ["a", "?", "space", "up", "ctrl-z", "esc"] {
+            assert_eq!(name.parse::<Key>().unwrap().to_string(), name);
+        }
+        assert_eq!("Ctrl+R".parse(), Ok(Key::Ctrl('r')));
+        assert_eq!("H".parse(), Ok(Key::Char('H')));
+        assert!("ctrl-1".parse::<Key>().is_err());
+        assert!("f13".parse::<Key>().is_err());
+        for action in [
+            Action::Digit(7),
+            Action::Pencil,
+            Action::Marks,
+            Action::Redo,
+        ] {
+            assert_eq!(action.name().parse(), Ok(action));
+        }
+        assert!("digit-0".parse::<Action>().is_err());
+    }
+
+    #[test]
+    fn test_keymap() {
+        let default = Keymap::default();
+        assert_eq!(default.action(Key::Char('h')), Some(Action::Hint));
+        assert_eq!(default.action(Key::Char('5')), Some(Action::Digit(5)));
+        assert_eq!(default.action(Key::Char('j')), None);
+        let vim = Keymap::preset(Preset::Vim);
+        assert_eq!(vim.action(Key::Char('h')), Some(Action::Left));
+        assert_eq!(vim.action(Key::Left), Some(Action::Left));
+        assert_eq!(vim.keys(Action::Undo), vec![Key::Char('u')]);
+
+        let json = Json::parse(
+            r#"{"preset": "vim", "bindings": {"hint": ["h", "ctrl-h"], "quit": "esc"}}"#,
+        )
+        .unwrap();
+        let keymap = Keymap::from_json(&json).unwrap();
+        assert_eq!(keymap.action(Key::Char('h')), Some(Action::Hint));
+        assert_eq!(keymap.action(Key::Char('?')), None);
+        assert_eq!(keymap.keys(Action::Left), vec![Key::Left]);
+        assert_eq!(keymap.keys(Action::Quit), vec![Key::Esc]);
+        assert_eq!(Keymap::from_json(&Json::parse("{}").unwrap()), Ok(default));
+        for bad in [
+            r#"{"preset": "emacs"}"#,
+            r#"{"bindings": {"jump": "j"}}"#,
+            r#"{"bindings": {"hint": 1}}"#,
+        ] {
+            assert!(Keymap::from_json(&Json::parse(bad).unwrap()).is_err());
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! 数独求解
+//!
+//! 棋盘 [`SudokuBoard`] 在候选数字上进行推理，规则由 [`Variant`] 中的一组
+//! [`Constraint`] 给出，标准规则和各种变体使用同一套机制。
+
+#![allow(clippy::needless_range_loop)]
+
+pub mod analysis;
+pub mod archive;
+pub mod auth;
+pub mod batch;
+pub mod board;
+pub mod book;
+pub mod bot;
+pub mod cache;
+pub mod canon;
+pub mod checkpoint;
+pub mod config;
+pub mod constraint;
+pub mod embedded;
+pub mod fetch;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fpuzzles;
+pub mod generator;
+#[cfg(feature = "gui")]
+pub mod gui;
+pub mod history;
+pub mod i18n;
+pub mod image;
+pub mod json;
+pub mod keys;
+pub mod lzstring;
+pub mod mapped;
+pub mod marks;
+pub mod ocr;
+pub mod output;
+pub mod pdf;
+pub mod play;
+pub mod qr;
+pub mod rating;
+pub mod record;
+pub mod remote;
+pub mod rng;
+pub mod scores;
+pub mod server;
+pub mod session;
+pub mod solver;
+pub mod svg;
+pub mod testing;
+pub mod theme;
+pub mod variant;
+pub mod visualize;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod websocket;
+
+pub use board::{Grid, SudokuBoard, SudokuPos};
+pub use constraint::{Constraint, Propagation};
+pub use variant::Variant;
+
+#[cfg(windows)]
+pub(crate) const EOL: &str = "\r\n";
+#[cfg(not(windows))]
+pub(crate) const EOL: &str = "\n";
@@ -0,0 +1,278 @@
+//! lz-string 压缩和解压，f-puzzles 和 SudokuPad 链接使用 `compressToBase64` 编码题目
+
+use std::collections::{HashMap, HashSet};
+
+/// base64 字符表
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// base64 字符对应的值，同时接受 URI 安全变体中的 `-`、`$`
+fn base64_value(c: char) -> Option<u32> {
+    match c {
+        'A'..='Z' => Some(c as u32 - 'A' as u32),
+        'a'..='z' => Some(c as u32 - 'a' as u32 + 26),
+        '0'..='9' => Some(c as u32 - '0' as u32 + 52),
+        // URL 解码后 `+` 可能变成空格
+        '+' | '-' | ' ' => Some(62),
+        '/' | '$' => Some(63),
+        _ => None,
+    }
+}
+
+/// 按位读取输入
+struct Bits {
+    values: Vec<u32>,
+    /// 当前值
+    val: u32,
+    /// 当前值中下一位的掩码
+    position: u32,
+    /// 下一个值的下标
+    index: usize,
+}
+
+impl Bits {
+    /// 读取 n 位，低位在前
+    fn read(&mut self, n: u32) -> u32 {
+        let mut bits = 0;
+        for i in 0..n {
+            let bit = self.val & self.position;
+            self.position >>= 1;
+            if self.position == 0 {
+                self.position = 32;
+                self.val = self.values.get(self.index).cloned().unwrap_or(0);
+                self.index += 1;
+            }
+            if bit > 0 {
+                bits |= 1 << i;
+            }
+        }
+        bits
+    }
+}
+
+/// 按位写出，每 6 位为一个 base64 字符，高位在前
+#[derive(Default)]
+struct Output {
+    chars: String,
+    /// 当前字符已写入的位
+    val: u8,
+    /// 当前字符已写入的位数
+    position: u32,
+}
+
+impl Output {
+    /// 写入 value 的低 n 位，低位在前
+    fn write(&mut self, value: u32, n: u32) {
+        for i in 0..n {
+            self.val = self.val << 1 | (value >> i & 1) as u8;
+            self.position += 1;
+            if self.position == 6 {
+                self.chars.push(BASE64[self.val as usize] as char);
+                (self.val, self.position) = (0, 0);
+            }
+        }
+    }
+}
+
+/// 压缩的状态
+struct Compressor {
+    out: Output,
+    /// 字典，0、1、2 为控制码，串以 UTF-16 编码保存
+    dictionary: HashMap<Vec<u16>, u32>,
+    /// 已加入字典但还未写出过的单个字符
+    to_create: HashSet<Vec<u16>>,
+    enlarge_in: u32,
+    num_bits: u32,
+}
+
+impl Compressor {
+    /// 写出一个编码后，编码位数可能需要加一
+    fn grow(&mut self) {
+        self.enlarge_in -= 1;
+        if self.enlarge_in == 0 {
+            self.enlarge_in = 1 << self.num_bits;
+            self.num_bits += 1;
+        }
+    }
+
+    /// 写出 w 的编码，首次出现的单个字符直接写出其值
+    fn emit(&mut self, w: &[u16]) {
+        if self.to_create.remove(w) {
+            let (code, bits) = if w[0] < 256 { (0, 8) } else { (1, 16) };
+            self.out.write(code, self.num_bits);
+            self.out.write(w[0] as u32, bits);
+            self.grow();
+        } else {
+            self.out.write(self.dictionary[w], self.num_bits);
+        }
+        self.grow();
+    }
+
+    /// 加入字典，编码依次递增
+    fn insert(&mut self, s: Vec<u16>) {
+        let code = self.dictionary.len() as u32 + 3;
+        self.dictionary.insert(s, code);
+    }
+}
+
+/// 与 `LZString.compressToBase64` 结果相同的压缩
+pub fn compress_to_base64(input: &str) -> String {
+    let mut state = Compressor {
+        out: Output::default(),
+        dictionary: HashMap::new(),
+        to_create: HashSet::new(),
+        enlarge_in: 2,
+        num_bits: 2,
+    };
+    let mut w = vec![];
+    for c in input.encode_utf16() {
+        if !state.dictionary.contains_key(&[c][..]) {
+            state.insert(vec![c]);
+            state.to_create.insert(vec![c]);
+        }
+        let mut wc = w.clone();
+        wc.push(c);
+        if state.dictionary.contains_key(&wc) {
+            w = wc;
+        } else {
+            state.emit(&w);
+            state.insert(wc);
+            w = vec![c];
+        }
+    }
+    if !w.is_empty() {
+        state.emit(&w);
+    }
+    // 结束符，再补齐最后一个字符和 `=`，恰好写满时与原实现一样多写一个字符
+    let mut out = state.out;
+    out.write(2, state.num_bits);
+    loop {
+        out.write(0, 1);
+        if out.position == 0 {
+            break;
+        }
+    }
+    let mut chars = out.chars;
+    while !chars.len().is_multiple_of(4) {
+        chars.push('=');
+    }
+    chars
+}
+
+/// 解压 `compressToBase64` 的结果，末尾的 `=` 填充会被忽略
+pub fn decompress_from_base64(input: &str) -> Result<String, String> {
+    let values = input
+        .trim_end_matches('=')
+        .chars()
+        .map(|c| base64_value(c).ok_or_else(|| format!("invalid base64 character `{}`", c)))
+        .collect::<Result<Vec<u32>, String>>()?;
+    if values.is_empty() {
+        return Ok(String::new());
+    }
+    let length = values.len();
+    let mut bits = Bits {
+        val: values[0],
+        values,
+        position: 32,
+        index: 1,
+    };
+
+    // 字典中 0、1、2 为控制码，以 UTF-16 编码保存
+    let mut dictionary: Vec<Vec<u16>> = vec![vec![], vec![], vec![]];
+    let mut enlarge_in = 4u32;
+    let mut num_bits = 3;
+    let invalid = || "invalid lz-string data".to_string();
+
+    let first = match bits.read(2) {
+        0 => bits.read(8),
+        1 => bits.read(16),
+        _ => return Ok(String::new()),
+    };
+    let mut w = vec![first as u16];
+    dictionary.push(w.clone());
+    let mut result = w.clone();
+
+    loop {
+        if bits.index > length {
+            return Err(invalid());
+        }
+        let mut c = bits.read(num_bits) as usize;
+        match c {
+            0 | 1 => {
+                let code = bits.read(if c == 0 { 8 } else { 16 });
+                dictionary.push(vec![code as u16]);
+                c = dictionary.len() - 1;
+                enlarge_in -= 1;
+            }
+            2 => return Ok(String::from_utf16_lossy(&result)),
+            _ => {}
+        }
+        if enlarge_in == 0 {
+            enlarge_in = 1 << num_bits;
+            num_bits += 1;
+        }
+
+        let entry = if c < dictionary.len() {
+            dictionary[c].clone()
+        } else if c == dictionary.len() {
+            let mut entry = w.clone();
+            entry.push(w[0]);
+            entry
+        } else {
+            return Err(invalid());
+        };
+        result.extend(&entry);
+
+        let mut next = w;
+        next.push(entry[0]);
+        dictionary.push(next);
+        enlarge_in -= 1;
+        w = entry;
+
+        if enlarge_in == 0 {
+            enlarge_in = 1 << num_bits;
+            num_bits += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compress_to_base64, decompress_from_base64};
+
+    #[test]
+    fn test_decompress() {
+        // LZString.compressToBase64("hello world")
+        assert_eq!(
+            decompress_from_base64("BYUwNmD2AEDukCcwBMg=").unwrap(),
+            "hello world"
+        );
+        // LZString.compressToBase64('{"size":9}')
+        assert_eq!(
+            decompress_from_base64("N4IgzglgXgpiBcBOAvkA").unwrap(),
+            r#"{"size":9}"#
+        );
+        assert!(decompress_from_base64("*").is_err());
+    }
+
+    #[test]
+    fn test_compress() {
+        assert_eq!(compress_to_base64("hello world"), "BYUwNmD2AEDukCcwBMg=");
+        assert_eq!(compress_to_base64(r#"{"size":9}"#), "N4IgzglgXgpiBcBOAvkA");
+        assert_eq!(
+            compress_to_base64(r#"{"size":9,"grid":[],"antiknight":true}"#),
+            "N4IgzglgXgpiBcBOANCA5gJwgEwQbQF1UBDAOwBcIBrUiNAC3IXIwFcYBfIA"
+        );
+        assert_eq!(compress_to_base64(""), "Q===");
+        for text in [
+            "",
+            "aaaaaaaaaaaaaaaaaaaaaaaa",
+            "数独 sudoku ✓ 𝄞",
+            &"abcab".repeat(500),
+        ] {
+            assert_eq!(
+                decompress_from_base64(&compress_to_base64(text)).unwrap(),
+                text
+            );
+        }
+    }
+}
@@ -1,650 +1,2745 @@
-use std::collections::HashMap;
-use std::collections::HashSet;
-use std::fmt;
-use std::io;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+use std::net::TcpListener;
+use std::ops::{Range, RangeInclusive};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::{fs, process, thread};
 
-use clap::Parser;
-use env_logger::Env;
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use env_logger::{Env, Target, WriteStyle};
+use rayon::prelude::*;
 
-#[cfg(windows)]
-const EOL: &'static str = "\r\n";
-#[cfg(not(windows))]
-const EOL: &str = "\n";
+use superdo::analysis::{grid_symmetries, pattern_symmetries, unavoidable_sets, GridSymmetry};
+use superdo::archive::{decompressed, is_compressed};
+use superdo::auth::{ApiToken, TOKENS_ENV};
+use superdo::board::{
+    diff_grids, digit_value, export_grid, grid_line, is_blank, parse_grid, Alphabet, ExportFormat,
+};
+use superdo::bot::{Bot, Webhook};
+use superdo::cache::{CacheKey, SolveCache};
+use superdo::canon::automorphisms;
+use superdo::checkpoint::Checkpoint;
+use superdo::config::{config_dir, Config};
+use superdo::constraint::{Allowed, Arrow, Cage, Pair, PairKind, Thermo};
+use superdo::fetch::SourceKind;
+use superdo::fpuzzles;
+use superdo::generator::{
+    clues, generate, generate_distinct, parse_pattern, GenerateOptions, Pattern, Symmetry,
+};
+use superdo::i18n::Lang;
+use superdo::image::{decode, encode_png};
+use superdo::json::Json;
+use superdo::keys::{Keymap, Preset};
+use superdo::mapped::Mapped;
+use superdo::ocr::recognize;
+use superdo::output::Printer;
+use superdo::play::{self, Game};
+use superdo::rating::{
+    backdoor_cells, backdoor_size, rank_cells, rate_se, rate_variant, CellHint, Difficulty, Scale,
+    Technique,
+};
+use superdo::record::{pretty, EventKind, Recording};
+use superdo::remote::Client;
+use superdo::rng::{Date, Rng};
+use superdo::scores::BestTimes;
+use superdo::server::{openapi, Server, ServerOptions};
+use superdo::session::{CheckMode, MarkMode, Session};
+use superdo::solver::{
+    audit, contradiction, count_solutions, cross_check, logic_cells, print_breakdown, print_count,
+    print_error, print_stats, print_time, print_uniqueness, snapshot_at, solutions, trace,
+    Contradiction, Label, OutputFormat, Propagating, ResolveCtx, SearchProgress, Shard, SolveStats,
+    Solver, SolverKind,
+};
+use superdo::theme::{Role, Theme};
+use superdo::variant::{ConstraintKind, Parity, Variant};
+use superdo::{book, canon, qr, svg, visualize, Grid, SudokuBoard};
 
-/// 数独位置
-#[derive(Debug, Default, Clone)]
-struct SudokuPos {
-    /// 当前值，非 0 表示已有确定数字
-    val: u32,
-    /// 候选数字
-    digits: HashSet<u32>,
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "A sudoku puzzle solver.\n\n\
+            Input the sudoku puzzle digit by digit (left to right, top to down, \
+                0 or . for unknown digit, whitespace and other characters are ignored).\n\n\
+            Output is a list of solutions separated by the chosen separator, then \
+                followed by a blank line. A puzzle may also be given as 9 lines of 9 \
+                digits; such puzzles are separated by blank lines. A `# name` line \
+                before a puzzle, or the first column of an `id,puzzle` CSV line, labels \
+                it: text output gets a `== puzzle name ==` heading and JSON and CSV \
+                results carry the id (JSON also the line the puzzle starts on). \
+                Puzzles whose givens conflict \
+                or whose rows are malformed are \
+                reported and skipped, and the exit code is 2.\n\n\
+            gzip (.gz) and zstd (.zst) compressed input is recognized and \
+                decompressed while reading (with the default `compression` feature).\n\n\
+            Solutions and results go to stdout; logs, warnings, progress and the \
+                --stats and --time reports go to stderr (with --format json the \
+                reports are JSON objects on stdout).",
+    long_about = None
+)]
+struct Args {
+    /// Show debug log
+    #[arg(short, long)]
+    debug: bool,
+
+    /// Find all solutions
+    #[arg(short, long)]
+    all: bool,
+
+    /// With --all, print the solutions of each puzzle sorted by their digits once the search
+    /// ends, instead of as the parallel search finds them, so every run prints the same output
+    /// (the solutions are kept in memory until then)
+    #[arg(long)]
+    ordered: bool,
+
+    /// Print the solutions of the whole run together at the end, sorted by their digits, instead
+    /// of puzzle by puzzle (the blank lines and headings between puzzles are left out); all
+    /// solutions are kept in memory until the input ends
+    #[arg(long, conflicts_with_all = ["checkpoint", "resume"])]
+    sort: bool,
+
+    /// Print each distinct solution only once over the whole run, e.g. when merging the
+    /// enumerations of overlapping puzzles into one dataset
+    #[arg(long, conflicts_with = "resume")]
+    dedupe: bool,
+
+    /// With --all, stop after printing this many solutions
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    limit: Option<u64>,
+
+    /// Count solutions instead of printing them, stopping at N ("at least N solutions")
+    #[arg(long, value_name = "N", conflicts_with_all = ["all", "limit"], value_parser = clap::value_parser!(u64).range(1..))]
+    count_limit: Option<u64>,
+
+    /// With --all, enumerate solutions of puzzles with fewer than 17 givens without a --limit
+    #[arg(long)]
+    force: bool,
+
+    /// Only search shard I of N (e.g. 2/8) of the search tree. The top of the tree is split the
+    /// same way on every run, so N processes can each take one shard with --all (or
+    /// --count-limit) and their outputs together cover every solution exactly once
+    #[arg(long, value_name = "I/N", conflicts_with_all = ["unique", "visualize"])]
+    shard: Option<Shard>,
+
+    /// Solution separator
+    #[arg(long, default_value = "---------")]
+    sep: String,
+
+    /// Max number of threads
+    #[arg(long, default_value_t = num_cpus::get(), global = true)]
+    threads: usize,
+
+    /// Extra constraint applied on top of the classic rules (repeatable)
+    #[arg(long, value_enum, global = true)]
+    constraint: Vec<ConstraintKind>,
+
+    /// Arrow clue, circle first then the shaft, e.g. r5c5-r4c4-r3c3 (repeatable)
+    #[arg(long)]
+    arrow: Vec<Arrow>,
+
+    /// XV marker between adjacent cells, X sums to 10 and V to 5, e.g. x:r1c1-r1c2 (repeatable)
+    #[arg(long, value_parser = parse_xv)]
+    xv: Vec<Pair>,
+
+    /// Negative XV constraint: unmarked adjacent cells sum to neither 10 nor 5
+    #[arg(long)]
+    xv_negative: bool,
+
+    /// Killer cage, optional sum then the cells, e.g. 15=r1c1-r1c2-r2c1 (repeatable)
+    #[arg(long)]
+    cage: Vec<Cage>,
+
+    /// Thermometer, bulb first, digits strictly increase from the bulb, e.g. r1c1-r1c2-r1c3 (repeatable)
+    #[arg(long)]
+    thermo: Vec<Thermo>,
+
+    /// Kropki dot between adjacent cells, w: differ by 1, b: one is double the other, e.g. w:r1c1-r1c2 (repeatable)
+    #[arg(long, value_parser = parse_dot)]
+    dot: Vec<Pair>,
+
+    /// Restrict cells to the given digits, as a JSON object such as {"r1c1": [2,5,7]} or a file
+    /// containing one; the solver enumerates the completions consistent with the template
+    #[arg(long, value_name = "SOURCE")]
+    template: Option<String>,
+
+    /// Reject puzzles containing any character other than digits, `.` (an empty cell), whitespace
+    /// and comments, instead of ignoring such characters
+    #[arg(long)]
+    strict_parse: bool,
+
+    /// Load the puzzle with its variant constraints from f-puzzles JSON, an f-puzzles / SudokuPad
+    /// share link (also accepted as --link URL), or a file containing either, instead of stdin
+    #[arg(long, value_name = "SOURCE", visible_alias = "link")]
+    fpuzzles: Option<String>,
+
+    /// Print corner (Snyder) and center pencil marks after each logical step before searching (text or json format)
+    #[arg(long)]
+    steps: bool,
+
+    /// Solve wordoku: nine distinct letters standing for the digits 1-9 (case-insensitive), e.g.
+    /// WORDSPLAY. Letters are read in the puzzle and solutions are printed back with letters
+    /// (except in svg output)
+    #[arg(long, value_parser = Alphabet::parse)]
+    alphabet: Option<Alphabet>,
+
+    /// Print each puzzle as it was parsed, with 3x3 box borders and the blanks highlighted, before
+    /// its solutions (text, json and sdm format)
+    #[arg(long, conflicts_with = "export")]
+    echo: bool,
+
+    /// Solving algorithm
+    #[arg(long, value_enum, default_value_t = SolverKind::Propagate)]
+    solver: SolverKind,
+
+    /// Before solving, print the full candidate state as JSON after STEP rounds of logical
+    /// deduction (0 is the initial state; repeatable)
+    #[arg(long, value_name = "STEP")]
+    snapshot: Vec<usize>,
+
+    /// Also solve each puzzle with every solver and exit with code 3 if their solution sets differ
+    #[arg(long)]
+    cross_check: bool,
+
+    /// Verify every logical elimination by checking that no completion of the board at that point
+    /// uses the eliminated candidate, and exit with code 4 on an unsound one (slow)
+    #[arg(long)]
+    audit: bool,
+
+    /// Write the placements, eliminations and guesses of each solve to this file (one JSON object
+    /// per puzzle), for `superdo replay`
+    #[arg(long, value_name = "FILE")]
+    record: Option<PathBuf>,
+
+    /// Every 30 seconds and when the input ends, write the progress of a long run over many
+    /// puzzles (lines read, puzzles done and solutions found) to FILE for --resume
+    #[arg(long, value_name = "FILE", conflicts_with = "fpuzzles")]
+    checkpoint: Option<PathBuf>,
+
+    /// Continue an interrupted run from the checkpoint in FILE: feed the same input again and the
+    /// lines read before are skipped; the checkpoint keeps being updated and --record appends
+    #[arg(long, value_name = "FILE", conflicts_with = "fpuzzles")]
+    resume: Option<PathBuf>,
+
+    /// After solving, report how many cells were filled by pure logic and how many needed guessing
+    #[arg(long)]
+    breakdown: bool,
+
+    /// After solving, report how many guesses, dead ends and backtracks the solver went through
+    /// (on stderr, except with --format json)
+    #[arg(long, conflicts_with = "count_limit")]
+    stats: bool,
+
+    /// Redraw a live view of the parallel search on stderr while solving: the board of one
+    /// branch with the candidate counts of its empty cells, open branches, guesses, dead ends
+    /// and solutions found
+    #[arg(long, conflicts_with = "count_limit")]
+    visualize: bool,
+
+    /// After solving, report the wall-clock time the search of each puzzle took (on stderr,
+    /// except with --format json, where it is a `time_ms` object)
+    #[arg(long)]
+    time: bool,
+
+    /// Abandon search branches stacking more than N guesses; a puzzle whose every remaining
+    /// branch is abandoned is reported as too hard under the current limits (exit code 5)
+    /// instead of being searched on, protecting batch runs from pathological grids
+    #[arg(long, value_name = "N", conflicts_with = "count_limit")]
+    max_guess_depth: Option<usize>,
+
+    /// Try the candidates of each guess in a random order, one branch at a time instead of in
+    /// parallel, so the first solution found is a random one: without --all this samples a random
+    /// completion of a grid with many solutions
+    #[arg(long, conflicts_with = "count_limit")]
+    shuffle: bool,
+
+    /// Seed of --shuffle; the same seed gives the same solutions in the same order (defaults to
+    /// the current time)
+    #[arg(long, requires = "shuffle")]
+    seed: Option<u64>,
+
+    /// Remember the solutions of up to N puzzles by canonical form, so a puzzle seen before in
+    /// the run, also relabeled, transposed or with rows and columns permuted, is answered without
+    /// searching again (standard sudoku with at least 17 givens; --stats reports nothing for those)
+    #[arg(long, value_name = "N", conflicts_with_all = ["shard", "shuffle", "max_guess_depth"])]
+    cache: Option<usize>,
+
+    /// Re-check every solution against the givens and all constraints before printing (always on in debug builds)
+    #[arg(long)]
+    verify: bool,
+
+    /// Also report whether the solution is unique ("unique" or "multiple solutions exist")
+    #[arg(long)]
+    unique: bool,
+
+    /// Instead of solving, write each puzzle read from stdin in this format, converting a
+    /// collection between the formats the solver reads
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    export: Option<ExportFormat>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, global = true)]
+    format: OutputFormat,
+
+    /// Language of messages meant for people (errors, hints, technique names); defaults to the
+    /// LC_ALL, LC_MESSAGES or LANG locale. JSON output stays in English
+    #[arg(long, value_enum, global = true)]
+    lang: Option<Lang>,
+
+    /// When to use colors: auto colors output written to a terminal unless the NO_COLOR
+    /// environment variable is set
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto, global = true)]
+    color: ColorChoice,
+
+    /// Color theme of terminal output (the --echo grid, --visualize, `diff` and `play`); defaults to the
+    /// `theme` of config.json in the config directory, then dark
+    #[arg(long, value_enum, global = true)]
+    theme: Option<Theme>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-impl PartialEq<u32> for SudokuPos {
-    fn eq(&self, r: &u32) -> bool {
-        self.val == *r
-    }
+/// 何时使用颜色
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum ColorChoice {
+    /// Only when writing to a terminal and NO_COLOR is not set
+    Auto,
+    Always,
+    Never,
 }
 
-impl SudokuPos {
-    /// 创建一个新的位置，数值非 0 时为已有确定数字
-    pub fn new_with(val: u32) -> Self {
-        let digits = if val == 0 {
-            (1..10).collect()
-        } else {
-            HashSet::default()
+/// 终端输出的配色：--theme，其次是配置文件中的 theme。配置文件有误时警告并用默认配色
+fn configured_theme(theme: Option<Theme>) -> Theme {
+    theme.unwrap_or_else(|| {
+        let Some(path) = Config::default_path() else {
+            return Theme::default();
         };
-        Self { val, digits }
+        Config::load(&path).map_or_else(
+            |e| {
+                log::warn!("{}", e);
+                Theme::default()
+            },
+            |config| config.theme,
+        )
+    })
+}
+
+impl ColorChoice {
+    /// 写到 stream 时是否使用颜色。按 NO_COLOR 的约定，设为非空值时 auto 不用颜色
+    fn enabled(self, stream: &impl IsTerminal) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+                let dumb = std::env::var_os("TERM").is_some_and(|t| t == "dumb");
+                stream.is_terminal() && !no_color && !dumb
+            }
+        }
     }
 }
 
-/// 数独棋盘， 9*9
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Generate a random puzzle with a unique solution
+    Generate(Box<GenerateArgs>),
+    /// Rate puzzles read from stdin by the human solving techniques they need, under the rules
+    /// added by --constraint
+    Rate(RateArgs),
+    /// Generate a printable PDF puzzle book with the answers at the back
+    Book(BookArgs),
+    /// Print the canonical form of puzzles, or decide whether two puzzles are equivalent
+    Canon(CanonArgs),
+    /// Remove duplicate puzzles from a collection, including puzzles equivalent under relabeling,
+    /// transposition and row/column permutations, keeping the first of each; the kept puzzles are
+    /// written as 81-digit lines and every removed one is reported on stderr
+    Dedupe(DedupeArgs),
+    /// List the cells whose values differ between two grids, e.g. to check a grid in progress
+    /// against the solution or to compare the outputs of two solvers; exits with 1 if any differ
+    Diff(DiffArgs),
+    /// Report structural properties of puzzles read from stdin
+    Analyze(AnalyzeArgs),
+    /// Step through a solving trace written by --record
+    Replay(ReplayArgs),
+    /// Serve the solver over HTTP: POST /v1/solve, /v1/count, /v1/rate, /v1/step and /v1/generate
+    /// with JSON bodies, GET /v1/schema for the request schemas, a WebSocket at
+    /// /v1/enumerate?puzzle=...&limit=N streaming solutions and progress as they are found, and
+    /// the web page of `superdo gui` at /. When tokens are given by `server.tokens` of
+    /// config.json or the comma-separated SUPERDO_TOKENS environment variable (TOKEN or
+    /// TOKEN:REQUESTS_PER_MINUTE), /v1/ requests need an `Authorization: Bearer TOKEN` header
+    Serve(ServeArgs),
+    /// Open a window with a clickable grid, candidate toggling, solving and step-through of the
+    /// logical solver. Builds without the `gui` feature, or --web, serve the same interface as a
+    /// web page and print its URL; it uses the tokens of `serve`, given as #token=TOKEN
+    Gui(GuiArgs),
+    /// Play a puzzle in the terminal. Progress is saved after every move and resumed on the next
+    /// `play` without a puzzle; without a saved game a new puzzle is generated. Each hint adds
+    /// 30 seconds to the time that is scored and kept in best-times.json of the config directory.
+    /// Keys are bound by `keys` of config.json, e.g. {"keys": {"preset": "vim", "bindings":
+    /// {"hint": ["?"]}}}, and listed below the grid
+    Play(Box<PlayArgs>),
+    /// Answer chat messages posted by a webhook: `!solve PUZZLE` replies with the solution and
+    /// `!hint PUZZLE` with the easiest next cell
+    Bot(BotArgs),
+    /// Recognize the puzzle in a screenshot (PNG, PGM or PPM) and print it as an 81-digit line,
+    /// warning about digits recognized with low confidence
+    ImportImage(ImportImageArgs),
+    /// Encode a puzzle as a QR code (SVG, or PNG when the output ends in .png), or read a puzzle
+    /// back from an image of one
+    Qr(QrArgs),
+    /// Download a puzzle by ID from a public puzzle bank and print it as an 81-digit line, ready
+    /// to pipe into the solver or `superdo rate`
+    Fetch(FetchArgs),
+    /// Send puzzles to a running `superdo serve` and print its answers as the local solver
+    /// would, offloading heavy enumeration to a stronger machine
+    Remote(RemoteArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct GenerateArgs {
+    /// Random seed, the same seed gives the same puzzle (defaults to the current time)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Puzzle of the day: derive the seed from today's UTC date, so everyone gets the same puzzle
+    #[arg(long, conflicts_with = "seed")]
+    daily: bool,
+
+    /// Namespace mixed into the daily seed, giving a community its own puzzle of the day
+    #[arg(long, value_name = "NAME", requires = "daily", default_value = "")]
+    namespace: String,
+
+    /// Difficulty of the puzzle, rated by the human solving techniques it needs
+    #[arg(long, value_enum)]
+    difficulty: Option<Difficulty>,
+
+    /// Symmetry of the clue pattern, clues are removed in symmetric pairs
+    #[arg(long, value_enum, default_value_t = Symmetry::None)]
+    symmetry: Symmetry,
+
+    /// Keep removing clues (ignoring symmetry) until none can be removed without losing uniqueness
+    #[arg(long)]
+    minimal: bool,
+
+    /// Aim for at most this many clues, trading clues aggressively (implies --minimal)
+    #[arg(long, value_name = "N")]
+    target_clues: Option<usize>,
+
+    /// Only output puzzles whose logical solution needs this technique at least once
+    #[arg(long, value_enum)]
+    requires: Option<Technique>,
+
+    /// Dig clues from this completed grid (81 digits) instead of a random one
+    #[arg(long, value_name = "GRID", value_parser = parse_grid)]
+    from_solution: Option<Grid>,
+
+    /// Range of clue counts to aim for, e.g. 24..28 (inclusive) or a single count
+    #[arg(long, value_name = "MIN..MAX", value_parser = parse_clue_range)]
+    clues: Option<RangeInclusive<usize>>,
+
+    /// Clue pattern: 81 characters (or a file holding them), `x` where a clue may go and `.`
+    /// elsewhere. Without --clues every marked cell gets a clue
+    #[arg(long, value_name = "MASK", value_parser = parse_pattern_arg)]
+    pattern: Option<Pattern>,
+
+    /// Reject puzzles where this technique can already be used on the starting grid (repeatable),
+    /// e.g. `--forbid-start naked-single`
+    #[arg(long, value_enum, value_name = "TECHNIQUE")]
+    forbid_start: Vec<Technique>,
+
+    /// Require the first digit placed by the logical solve to lie in this box (1-9, 5 is the center)
+    #[arg(long, value_name = "BOX", value_parser = clap::value_parser!(u8).range(1..=9))]
+    first_move_box: Option<u8>,
+
+    /// Number of puzzles to generate, equivalent puzzles are generated only once
+    #[arg(long, default_value_t = 1)]
+    count: usize,
+}
+
+#[derive(clap::Args, Debug)]
+struct RateArgs {
+    /// Read puzzles from this file instead of stdin (plain, gzip or zstd compressed)
+    #[arg(short, long, value_name = "FILE")]
+    file: Option<PathBuf>,
+
+    /// Rating scale of the reported score
+    #[arg(long, value_enum, default_value_t = Scale::Ladder)]
+    scale: Scale,
+
+    /// Also report structural metrics: the backdoor size (cells to guess before singles solve the
+    /// puzzle, searched up to 3)
+    #[arg(long)]
+    stats: bool,
+}
+
+/// 给定数字有冲突或按行输入的题目格式有误时的退出码
+const EXIT_INVALID: i32 = 2;
+
+/// 各求解器的结果不一致时的退出码
+const EXIT_MISMATCH: i32 = 3;
+
+/// 推理排除了仍可能出现在解中的候选时的退出码
+const EXIT_UNSOUND: i32 = 4;
+
+/// 有题目超出 `--max-guess-depth` 时的退出码
+const EXIT_TOO_HARD: i32 = 5;
+
+/// `--checkpoint` 写入断点的间隔
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// `--cross-check` 每个求解器最多列出的解的个数
+const CROSS_CHECK_CAP: usize = 1000;
+
+/// 标准数独有唯一解所需的最少给定数字个数
+const MIN_CLUES: usize = 17;
+
+/// `rate --stats` 搜索后门的最大大小
+const MAX_BACKDOOR: usize = 3;
+
+#[derive(clap::Args, Debug)]
+struct CanonArgs {
+    /// Read puzzles from this file instead of stdin (plain, gzip or zstd compressed)
+    #[arg(short, long, value_name = "FILE")]
+    file: Option<PathBuf>,
+
+    /// Decide whether two puzzles (files or 81-digit strings) are equivalent under relabeling,
+    /// transposition and row/column permutations, and print the transformation if so
+    #[arg(long, num_args = 2, value_names = ["A", "B"], conflicts_with = "file")]
+    compare: Option<Vec<String>>,
+}
+
+#[derive(clap::Args, Debug)]
+struct DedupeArgs {
+    /// Read puzzles from this file instead of stdin (plain, gzip or zstd compressed)
+    #[arg(short, long, value_name = "FILE")]
+    file: Option<PathBuf>,
+
+    /// Write the cleaned collection to this file instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Only remove identical puzzles and keep equivalent ones
+    #[arg(long)]
+    identical_only: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct DiffArgs {
+    /// First grid, a file or an 81-digit string (0 or . for an empty cell)
+    a: String,
+
+    /// Second grid, a file or an 81-digit string
+    b: String,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(group(ArgGroup::new("analysis").required(true).multiple(true)))]
+struct AnalyzeArgs {
+    /// Read puzzles from this file instead of stdin (plain, gzip or zstd compressed)
+    #[arg(short, long, value_name = "FILE")]
+    file: Option<PathBuf>,
+
+    /// Report rotational and mirror symmetries of the clue pattern and of the (unique) solution,
+    /// and the number of automorphisms of the puzzle
+    #[arg(long, group = "analysis")]
+    symmetry: bool,
+
+    /// List the cells whose correct value, once given, lets naked and hidden singles solve the rest
+    #[arg(long, group = "analysis")]
+    backdoors: bool,
+
+    /// List the minimal unavoidable sets of the (unique) solution with at most SIZE cells; a puzzle
+    /// needs a clue in every one of them to have a unique solution (SIZE defaults to 8)
+    #[arg(long, group = "analysis", value_name = "SIZE", num_args = 0..=1, default_missing_value = "8")]
+    unavoidable: Option<usize>,
+
+    /// List the N most useful hint cells: those cracked by the simplest technique first, then the
+    /// most constrained (N defaults to 3)
+    #[arg(long, group = "analysis", value_name = "N", num_args = 0..=1, default_missing_value = "3")]
+    hints: Option<usize>,
+}
+
+#[derive(clap::Args, Debug)]
+struct ReplayArgs {
+    /// Trace file written by --record
+    #[arg(value_name = "FILE")]
+    file: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    addr: String,
+
+    /// Most solutions a request may list or count
+    #[arg(long, value_name = "N", default_value_t = 100, value_parser = clap::value_parser!(u32).range(1..))]
+    max_solutions: u32,
+
+    /// Seconds of computation allowed per request
+    #[arg(long, value_name = "SECONDS", default_value_t = 10)]
+    timeout: u64,
+
+    /// Most connections handled at the same time, further ones get 503
+    #[arg(long, value_name = "N", default_value_t = 64)]
+    max_connections: usize,
+
+    /// Most requests computed at the same time (defaults to the number of CPUs)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    max_jobs: Option<u32>,
+
+    /// Most requests waiting for a free job, further ones get 429
+    #[arg(long, value_name = "N", default_value_t = 16)]
+    queue: usize,
+
+    /// Most puzzles whose /v1/solve results are cached, equivalent puzzles sharing an entry; the
+    /// least recently used are dropped first (0 disables the cache)
+    #[arg(long, value_name = "N", default_value_t = 1024)]
+    cache: usize,
+
+    /// Print the OpenAPI 3 document of the HTTP API and exit
+    #[arg(long)]
+    openapi: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct GuiArgs {
+    /// Serve the web page even when a desktop window is available
+    #[arg(long)]
+    web: bool,
+
+    /// Address to listen on for the web page
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    addr: String,
+
+    /// Puzzle to open, 81 digits with 0 or . for an empty cell
+    #[arg(long, value_name = "GRID", value_parser = |s: &str| parse_grid(s).map(|g| grid_line(&g)))]
+    puzzle: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct PlayArgs {
+    /// Puzzle to start, 81 digits with 0 or . for an empty cell; replaces the saved game
+    #[arg(value_name = "GRID", value_parser = parse_grid)]
+    puzzle: Option<Grid>,
+
+    /// Difficulty of a generated puzzle
+    #[arg(long, value_enum, default_value = "easy")]
+    difficulty: Difficulty,
+
+    /// File the game is saved to [default: session.json in the config directory]
+    #[arg(long, value_name = "FILE")]
+    session: Option<PathBuf>,
+
+    /// How to flag mistakes; saved with the game [default: off, or the saved setting]
+    #[arg(long, value_enum)]
+    check: Option<CheckMode>,
+
+    /// Who keeps the candidate marks; saved with the game and toggled with the `marks` key
+    /// [default: auto, or the saved setting]
+    #[arg(long, value_enum)]
+    marks: Option<MarkMode>,
+
+    /// Built-in key bindings to use instead of the `keys` of config.json
+    #[arg(long, value_enum)]
+    keys: Option<Preset>,
+}
+
+#[derive(clap::Args, Debug)]
+struct BotArgs {
+    /// Address to listen on for messages, POSTed as JSON with a Discord-style `content` or a
+    /// Slack-style `text` field; the reply is sent back in the same field of the response
+    #[arg(long, default_value = "127.0.0.1:8090")]
+    addr: String,
+
+    /// Also POST each reply as {"content": ...} to this http:// webhook
+    #[arg(long, value_name = "URL", value_parser = Webhook::parse)]
+    webhook: Option<Webhook>,
+
+    /// Prefix of the bot commands
+    #[arg(long, default_value = "!")]
+    prefix: String,
+}
+
+#[derive(clap::Args, Debug)]
+struct ImportImageArgs {
+    /// Image containing the 9x9 grid
+    file: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(group(ArgGroup::new("direction").required(true)))]
+struct QrArgs {
+    /// Puzzle to encode, 81 digits with 0 or . for an empty cell
+    #[arg(long, value_name = "GRID", group = "direction", value_parser = |s: &str| parse_grid(s).map(|g| grid_line(&g)))]
+    encode: Option<String>,
+
+    /// Image (PNG, PGM or PPM) of an upright QR code to read the puzzle from
+    #[arg(long, value_name = "IMAGE", group = "direction")]
+    decode: Option<PathBuf>,
+
+    /// Write the QR code to this file instead of standard output, as PNG if the name ends in
+    /// .png and as SVG otherwise
+    #[arg(short, long, requires = "encode")]
+    output: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+struct FetchArgs {
+    /// Puzzle bank to fetch from
+    #[arg(long, value_enum, default_value_t = SourceKind::SudokuExchange)]
+    source: SourceKind,
+
+    /// ID of the puzzle in the bank
+    #[arg(long)]
+    id: String,
+
+    /// Where to read the bank from instead of its default address: an http:// URL or a local
+    /// directory with a copy of its files (https is not supported)
+    #[arg(long, value_name = "URL|DIR")]
+    base: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct RemoteArgs {
+    /// Address of the server, e.g. http://solver:8080 (https is not supported)
+    #[arg(long, value_name = "URL", value_parser = Client::new)]
+    host: Client,
+
+    /// What to ask the server for each puzzle
+    #[arg(value_enum)]
+    action: RemoteAction,
+
+    /// Read puzzles from this file instead of stdin (plain, gzip or zstd compressed)
+    #[arg(short, long, value_name = "FILE")]
+    file: Option<PathBuf>,
+
+    /// solve: list up to N solutions (default 1); count: stop counting at N (default the
+    /// server's cap). The server rejects more than its --max-solutions
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    limit: Option<u32>,
+
+    /// Seconds to wait for each answer (defaults to waiting until the server's own timeout)
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+
+    /// Bearer token for a server that requires one (defaults to the SUPERDO_TOKEN environment
+    /// variable)
+    #[arg(long)]
+    token: Option<String>,
+}
+
+/// 远程服务的接口
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum RemoteAction {
+    /// List solutions
+    Solve,
+    /// Count solutions
+    Count,
+    /// Rate by the human solving techniques needed
+    Rate,
+}
+
+#[derive(clap::Args, Debug)]
+struct BookArgs {
+    /// Random seed, the same seed gives the same book (defaults to the current time)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Number of puzzles in the book
+    #[arg(long, default_value_t = 100)]
+    count: usize,
+
+    /// Difficulty of the puzzles, `mixed` cycles from easy to expert
+    #[arg(long, value_name = "DIFFICULTY", default_value = "mixed", value_parser = parse_book_difficulty)]
+    difficulty: BookDifficulty,
+
+    /// Number of puzzles per page
+    #[arg(long, default_value_t = 4)]
+    per_page: usize,
+
+    /// Output PDF file
+    #[arg(short, long, value_name = "FILE")]
+    output: PathBuf,
+}
+
+/// 题目册中各题目的难度，依次循环
 #[derive(Debug, Clone)]
-struct SudokuBoard {
-    board: Vec<Vec<SudokuPos>>,
+struct BookDifficulty(Vec<Difficulty>);
+
+/// 解析题目册难度，`mixed` 为 easy 到 expert
+fn parse_book_difficulty(s: &str) -> Result<BookDifficulty, String> {
+    if s.eq_ignore_ascii_case("mixed") {
+        return Ok(BookDifficulty(vec![
+            Difficulty::Easy,
+            Difficulty::Medium,
+            Difficulty::Hard,
+            Difficulty::Expert,
+        ]));
+    }
+    Difficulty::from_str(s, true)
+        .map(|d| BookDifficulty(vec![d]))
+        .map_err(|_| format!("invalid difficulty `{}`", s))
 }
 
-impl SudokuBoard {
-    /// 创建一个空白的数独棋盘
-    pub fn empty() -> Self {
-        let mut line = vec![];
-        for _ in 0..9 {
-            line.push(SudokuPos::new_with(0));
-        }
-        let mut board = vec![];
-        for _ in 0..9 {
-            board.push(line.clone());
-        }
+/// 解析 XV 标记
+fn parse_xv(s: &str) -> Result<Pair, String> {
+    let pair: Pair = s.parse()?;
+    match pair.kind {
+        PairKind::X | PairKind::V => Ok(pair),
+        _ => Err(format!("`{}` is not an XV marker", s)),
+    }
+}
 
-        Self { board }
+/// 解析黑白点
+fn parse_dot(s: &str) -> Result<Pair, String> {
+    let pair: Pair = s.parse()?;
+    match pair.kind {
+        PairKind::White | PairKind::Black => Ok(pair),
+        _ => Err(format!("`{}` is not a kropki dot", s)),
     }
+}
 
-    /// 创建一个已初始化的数独棋盘
-    pub fn new_with(board: &[[u32; 9]; 9]) -> Self {
-        let mut b = Self::empty();
-        for row in 0..9 {
-            for col in 0..9 {
-                b.set(board[row][col], row, col);
-            }
-        }
-        b
+/// 解析位置模板，参数为文件时读取文件内容
+fn parse_pattern_arg(s: &str) -> Result<Pattern, String> {
+    if Path::new(s).is_file() {
+        let text = fs::read_to_string(s).map_err(|e| format!("{}: {}", s, e))?;
+        parse_pattern(&text)
+    } else {
+        parse_pattern(s)
     }
+}
 
-    /// 设置某个位置的数值
-    pub fn set(&mut self, val: u32, row: usize, col: usize) {
-        self.get_mut(row, col).val = val;
-        if val != 0 {
-            self.get_mut(row, col).digits.clear();
+/// 解析给定数字个数范围，`24..28`、`24..=28` 均包含两端
+fn parse_clue_range(s: &str) -> Result<RangeInclusive<usize>, String> {
+    let parse = |n: &str| {
+        n.trim()
+            .parse::<usize>()
+            .map_err(|_| format!("invalid clue count `{}`", n))
+    };
+    let (min, max) = match s.split_once("..") {
+        Some((min, max)) => (parse(min)?, parse(max.trim_start_matches('='))?),
+        None => (parse(s)?, parse(s)?),
+    };
+    if min > max || max > 81 {
+        return Err(format!("invalid clue range `{}`", s));
+    }
+    Ok(min..=max)
+}
 
-            // 清理行
-            for i in 0..9 {
-                self.get_mut(row, i).digits.remove(&val);
+/// 生成题目并输出
+fn run_generate(args: &GenerateArgs, constraints: &[ConstraintKind], format: OutputFormat) {
+    if constraints.contains(&ConstraintKind::OddEven) {
+        log::error!("generate does not support the odd-even constraint");
+        process::exit(1);
+    }
+    let mut rng = if args.daily {
+        let today = Date::today();
+        log::info!("puzzle of the day for {}", today);
+        Rng::daily(today, &args.namespace)
+    } else {
+        args.seed.map(Rng::new).unwrap_or_else(Rng::from_time)
+    };
+    let variant = Arc::new(Variant::with_constraints(constraints));
+    let options = GenerateOptions {
+        difficulty: args.difficulty,
+        symmetry: args.symmetry,
+        minimal: args.minimal,
+        target_clues: args.target_clues,
+        requires: args.requires,
+        solution: args.from_solution,
+        clues: args.clues.clone(),
+        pattern: args.pattern,
+        forbid_start: args.forbid_start.clone(),
+        first_move_box: args.first_move_box.map(|n| n as usize - 1),
+        deadline: None,
+    };
+    let options = vec![options; args.count];
+    generate_distinct(variant.clone(), &options, &mut rng, |i, generated| {
+        let Some(generated) = generated else {
+            match i {
+                0 => log::error!("no puzzle satisfies the rules and options"),
+                _ => log::error!("no more distinct puzzles satisfy the rules and options"),
+            }
+            process::exit(1);
+        };
+        log::info!(
+            "puzzle {}: {} clues, {}",
+            i + 1,
+            generated.clues(),
+            generated.rating.difficulty.name()
+        );
+        match format {
+            OutputFormat::Text => {
+                // 多道题目之间空一行
+                if i > 0 {
+                    println!();
+                }
+                println!(
+                    "{}",
+                    SudokuBoard::new_with(&generated.givens, variant.clone())
+                );
             }
-            // 清理列
-            for i in 0..9 {
-                self.get_mut(i, col).digits.remove(&val);
+            OutputFormat::Svg => {
+                let board = SudokuBoard::new_with(&generated.givens, variant.clone());
+                println!("{}", svg::render(&board, &generated.givens));
             }
-            // 清理 3x3 小格
-            let row_s = (row / 3) * 3;
-            let col_s = (col / 3) * 3;
-            for i in 0..3 {
-                for j in 0..3 {
-                    self.get_mut(row_s + i, col_s + j).digits.remove(&val);
+            OutputFormat::Json => println!("{}", generated.to_json()),
+            OutputFormat::Sdm => println!(
+                "{} # {}, {} clues",
+                grid_line(&generated.givens),
+                generated.rating.difficulty.name(),
+                generated.clues()
+            ),
+            OutputFormat::Csv => {
+                if i == 0 {
+                    println!("puzzle,solution,clues,difficulty");
                 }
+                println!(
+                    "{},{},{},{}",
+                    grid_line(&generated.givens),
+                    grid_line(&generated.solution),
+                    generated.clues(),
+                    generated.rating.difficulty.name()
+                );
             }
         }
+    });
+}
+
+/// 生成题目册并写入 PDF 文件
+fn run_book(args: &BookArgs, constraints: &[ConstraintKind]) {
+    if constraints.contains(&ConstraintKind::OddEven) {
+        log::error!("book does not support the odd-even constraint");
+        process::exit(1);
     }
+    let mut rng = args.seed.map(Rng::new).unwrap_or_else(Rng::from_time);
+    let variant = Arc::new(Variant::with_constraints(constraints));
+    let levels = &args.difficulty.0;
+    let options: Vec<_> = (0..args.count)
+        .map(|i| GenerateOptions {
+            difficulty: Some(levels[i % levels.len()]),
+            ..Default::default()
+        })
+        .collect();
+    let mut puzzles = vec![];
+    generate_distinct(variant.clone(), &options, &mut rng, |i, generated| {
+        let Some(generated) = generated else {
+            match i {
+                0 => log::error!("no puzzle satisfies the rules and options"),
+                _ => log::error!("no more distinct puzzles satisfy the rules and options"),
+            }
+            process::exit(1);
+        };
+        log::debug!("puzzle {}: {}", i + 1, generated.rating.difficulty.name());
+        puzzles.push(generated);
+    });
+    // 由易到难排列
+    puzzles.sort_by_key(|p| p.rating.difficulty);
 
-    /// 获取某个位置
-    pub fn get(&self, row: usize, col: usize) -> &SudokuPos {
-        &self.board[row][col]
+    let mut heading = String::from("Sudoku");
+    if !constraints.is_empty() {
+        let names: Vec<String> = constraints
+            .iter()
+            .filter_map(|c| c.to_possible_value())
+            .map(|v| v.get_name().to_string())
+            .collect();
+        heading = format!("{} ({})", heading, names.join(", "));
     }
+    let pdf = book::render(&puzzles, &variant, &heading, args.per_page);
+    if let Err(e) = fs::write(&args.output, pdf) {
+        log::error!("{}: {}", args.output.display(), e);
+        process::exit(1);
+    }
+    log::info!(
+        "wrote {} puzzles to {}",
+        puzzles.len(),
+        args.output.display()
+    );
+}
 
-    /// 获取某个位置，可变形式
-    pub fn get_mut(&mut self, row: usize, col: usize) -> &mut SudokuPos {
-        &mut self.board[row][col]
+fn run_serve(args: &ServeArgs) {
+    if args.openapi {
+        println!("{}", openapi());
+        return;
     }
+    let tokens = load_tokens();
+    if tokens.is_empty() {
+        log::info!("no tokens configured, the API is open to anyone who can connect");
+    }
+    start_server(
+        &args.addr,
+        ServerOptions {
+            max_solutions: args.max_solutions as usize,
+            timeout: Duration::from_secs(args.timeout),
+            max_connections: args.max_connections,
+            max_jobs: args.max_jobs.map_or_else(num_cpus::get, |n| n as usize),
+            queue: args.queue,
+            tokens,
+            cache: args.cache,
+        },
+    );
+}
 
-    /// 是否有自由位置耗尽，此时无解
-    pub fn exhausted(&self) -> bool {
-        for row in &self.board {
-            for col in row {
-                if col.val == 0 && col.digits.is_empty() {
-                    return true;
-                }
+/// 配置文件和环境变量中的令牌。令牌有误时退出，不能退回到无需认证的服务
+fn load_tokens() -> Vec<ApiToken> {
+    let mut tokens = match Config::default_path().map(|path| Config::load(&path)) {
+        Some(Ok(config)) => config.tokens,
+        Some(Err(e)) => {
+            log::error!("{}", e);
+            process::exit(1);
+        }
+        None => vec![],
+    };
+    if let Ok(text) = std::env::var(TOKENS_ENV) {
+        match ApiToken::parse_list(&text) {
+            Ok(more) => tokens.extend(more),
+            Err(e) => {
+                log::error!("{}: {}", TOKENS_ENV, e);
+                process::exit(1);
             }
         }
-        false
     }
+    tokens
+}
 
-    /// 进行数独求解
-    pub fn solve(&mut self) -> bool {
-        loop {
-            let mut has_empty = false; // 是否还有空白的位置
-            let mut has_changes = false; // 本次求解是否产生变化
+/// 在 addr 上提供 HTTP 服务，直到出错
+fn start_server(addr: &str, options: ServerOptions) {
+    let listener = TcpListener::bind(addr).unwrap_or_else(|e| {
+        log::error!("{}: {}", addr, e);
+        process::exit(1);
+    });
+    log::info!("listening on {}", addr);
+    if let Err(e) = Arc::new(Server::new(options)).serve(listener) {
+        log::error!("{}", e);
+        process::exit(1);
+    }
+}
 
-            for row in 0..9 {
-                for col in 0..9 {
-                    if self.board[row][col].val == 0 {
-                        has_empty = true;
+/// 图形界面：带 gui 功能构建时打开桌面窗口，否则或给出 --web 时提供网页界面。
+/// 网页界面使用默认限制，令牌与 serve 相同
+fn run_gui(args: &GuiArgs) {
+    #[cfg(feature = "gui")]
+    if !args.web {
+        let puzzle = args.puzzle.as_deref().map(|p| parse_grid(p).unwrap());
+        if let Err(e) = superdo::gui::run(puzzle) {
+            log::error!("{}; --web serves the interface as a web page instead", e);
+            process::exit(1);
+        }
+        return;
+    }
+    let mut url = format!("http://{}/", args.addr);
+    if let Some(puzzle) = &args.puzzle {
+        url = format!("{}?puzzle={}", url, puzzle);
+    }
+    let tokens = load_tokens();
+    // 默认日志级别下也要显示地址
+    if tokens.is_empty() {
+        eprintln!("open {} in a browser", url);
+    } else {
+        eprintln!(
+            "open {}#token=TOKEN in a browser, with one of the configured tokens",
+            url
+        );
+    }
+    start_server(
+        &args.addr,
+        ServerOptions {
+            tokens,
+            ..Default::default()
+        },
+    );
+}
 
-                        // 失败
-                        if self.board[row][col].digits.is_empty() {
-                            return false;
-                        }
+/// 游戏的按键绑定：--keys，其次是配置文件中的 keys。配置文件有误时警告并用默认绑定
+fn configured_keymap(preset: Option<Preset>) -> Keymap {
+    if let Some(preset) = preset {
+        return Keymap::preset(preset);
+    }
+    let Some(path) = Config::default_path() else {
+        return Keymap::default();
+    };
+    Config::load(&path).map_or_else(
+        |e| {
+            log::warn!("{}", e);
+            Keymap::default()
+        },
+        |config| config.keys,
+    )
+}
 
-                        // 已经只剩下一个数字
-                        let pos = self.get_mut(row, col);
-                        if pos.val == 0 && pos.digits.len() == 1 {
-                            let val = *pos.digits.iter().next().unwrap();
-                            self.set(val, row, col);
-                            has_changes = true;
-                            continue;
-                        }
+/// 在终端中游戏：给出题目时开始新的一局，否则继续保存的进度，没有进度时生成一道题目
+fn run_play(args: &PlayArgs, theme: Option<Theme>) {
+    let path = args
+        .session
+        .clone()
+        .or_else(|| config_dir().map(|dir| dir.join("session.json")));
+    let saved = match (&args.puzzle, &path) {
+        (None, Some(path)) if path.exists() => match Session::load(path) {
+            Ok(session) => Some(session),
+            Err(e) => {
+                log::error!("{}; give a puzzle to start a new game", e);
+                process::exit(1);
+            }
+        },
+        _ => None,
+    };
+    let mut session = saved.unwrap_or_else(|| {
+        let puzzle = args.puzzle.unwrap_or_else(|| {
+            let options = GenerateOptions {
+                difficulty: Some(args.difficulty),
+                ..Default::default()
+            };
+            let variant = Arc::new(Variant::standard());
+            generate(variant, &options, &mut Rng::from_time())
+                .unwrap_or_else(|| {
+                    log::error!("no puzzle found");
+                    process::exit(1);
+                })
+                .givens
+        });
+        Session::new(puzzle)
+    });
+    if let Some(check) = args.check {
+        session.set_check_mode(check);
+    }
+    if let Some(marks) = args.marks {
+        session.set_mark_mode(marks);
+    }
+    if let Some(dir) = path.as_deref().and_then(Path::parent) {
+        if let Err(e) = fs::create_dir_all(dir) {
+            log::error!("{}: {}", dir.display(), e);
+            process::exit(1);
+        }
+    }
+    let mut game = Game::new(session, configured_keymap(args.keys), theme, path);
+    // 记录文件有误时照常游戏，只是不记入成绩
+    let best_path = BestTimes::default_path();
+    match best_path.as_deref().map(BestTimes::load) {
+        Some(Ok(best_times)) => game.set_best_times(best_times, best_path),
+        Some(Err(e)) => log::warn!("{}", e),
+        None => {}
+    }
+    if let Err(e) = play::run(&mut game) {
+        log::error!("{}", e);
+        process::exit(1);
+    }
+}
 
-                        // 记录下日志，当前位置剩下的可用数字
-                        log::debug!("({},{}) digits: {:?}", row, col, pos.digits);
+fn run_bot(args: &BotArgs, lang: Lang) {
+    let listener = TcpListener::bind(&args.addr).unwrap_or_else(|e| {
+        log::error!("{}: {}", args.addr, e);
+        process::exit(1);
+    });
+    log::info!("listening on {}", args.addr);
+    let bot = Arc::new(Bot {
+        prefix: args.prefix.clone(),
+        webhook: args.webhook.clone(),
+        lang,
+    });
+    if let Err(e) = bot.serve(listener) {
+        log::error!("{}", e);
+        process::exit(1);
+    }
+}
 
-                        // 检查是否只有当前位置才能使用的数字，进行数字统计
-                        let mut digit_stats = HashMap::new();
-                        for n in self.get(row, col).digits.iter() {
-                            digit_stats.insert(*n, 1);
-                        }
-                        let digit_stats_cloned = digit_stats.clone();
-                        // 辅助函数，如果只有计数为 1 中进行一次更新
-                        let count_and_set =
-                            |board: &mut SudokuBoard, stats: HashMap<u32, u32>| -> bool {
-                                stats.iter().any(|(k, v)| {
-                                    if *v == 1 {
-                                        log::debug!("({},{}) solved: {}", row, col, k);
-                                        board.set(*k, row, col);
-                                        return true;
-                                    }
-                                    false
-                                })
-                            };
-
-                        // 当前行统计，是否有唯一只能被当前使用的数字
-                        let mut row_digit_stats = digit_stats_cloned.clone();
-                        for i in 0..9 {
-                            if i != col {
-                                let pos = self.get(row, i);
-                                for n in pos.digits.iter() {
-                                    if let Some(count) = row_digit_stats.get_mut(n) {
-                                        *count += 1;
-                                    }
-                                }
-                            }
-                        }
-                        if count_and_set(self, row_digit_stats) {
-                            has_changes = true;
-                            continue;
-                        }
+fn run_import_image(args: &ImportImageArgs, format: OutputFormat) {
+    let data = fs::read(&args.file).unwrap_or_else(|e| {
+        log::error!("{}: {}", args.file.display(), e);
+        process::exit(1);
+    });
+    let recognized = decode(&data).and_then(|image| recognize(&image));
+    let recognized = recognized.unwrap_or_else(|e| {
+        log::error!("{}: {}", args.file.display(), e);
+        print_error(format, "unrecognized_image", &e, vec![]);
+        process::exit(EXIT_INVALID);
+    });
+    let uncertain: Vec<String> = recognized
+        .uncertain
+        .iter()
+        .map(|&(row, col)| format!("r{}c{}", row + 1, col + 1))
+        .collect();
+    for (cell, &(row, col)) in uncertain.iter().zip(&recognized.uncertain) {
+        log::warn!(
+            "{} recognized as {} with low confidence, please check it",
+            cell,
+            recognized.grid[row][col]
+        );
+    }
+    match format {
+        OutputFormat::Json => println!(
+            "{}",
+            Json::Object(vec![
+                ("puzzle".to_string(), grid_line(&recognized.grid).into()),
+                ("uncertain".to_string(), uncertain.into()),
+            ])
+        ),
+        _ => println!("{}", grid_line(&recognized.grid)),
+    }
+}
 
-                        // 当前列统计，是否有唯一只能被当前使用的数字
-                        let mut col_digit_stats = digit_stats_cloned.clone();
-                        for i in 0..9 {
-                            if i != row {
-                                let pos = self.get(i, col);
-                                for n in pos.digits.iter() {
-                                    if let Some(count) = col_digit_stats.get_mut(n) {
-                                        *count += 1;
-                                    }
-                                }
-                            }
-                        }
-                        if count_and_set(self, col_digit_stats) {
-                            has_changes = true;
-                            continue;
-                        }
+fn run_fetch(args: &FetchArgs, format: OutputFormat) {
+    let source = args.source.source(args.base.as_deref());
+    let grid = source.fetch(&args.id).unwrap_or_else(|e| {
+        log::error!("{}", e);
+        print_error(format, "fetch_failed", &e, vec![]);
+        process::exit(1);
+    });
+    match format {
+        OutputFormat::Json => {
+            let name = args.source.to_possible_value().unwrap();
+            println!(
+                "{}",
+                Json::Object(vec![
+                    ("source".to_string(), name.get_name().into()),
+                    ("id".to_string(), args.id.to_ascii_lowercase().into()),
+                    ("puzzle".to_string(), grid_line(&grid).into()),
+                ])
+            )
+        }
+        _ => println!("{}", grid_line(&grid)),
+    }
+}
 
-                        // 3x3 小格统计，剩下数字中，是否有唯一只能被当前位置使用的数字
-                        let mut grid_digit_stats = digit_stats_cloned.clone();
-                        let row_s = (row / 3) * 3;
-                        let col_s = (col / 3) * 3;
-                        for a in 0..3 {
-                            for b in 0..3 {
-                                let pos = self.get(row_s + a, col_s + b);
-                                if (row_s + a != row || col_s + b != col) && pos.val == 0 {
-                                    for n in pos.digits.iter() {
-                                        if let Some(count) = grid_digit_stats.get_mut(n) {
-                                            *count += 1;
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        if count_and_set(self, grid_digit_stats) {
-                            has_changes = true;
-                            continue;
-                        }
+/// 每个模块在 PNG 中的像素数
+const QR_SCALE: usize = 8;
+
+fn run_qr(args: &QrArgs, format: OutputFormat) {
+    if let Some(puzzle) = &args.encode {
+        // 81 位数字用数字模式，放得进版本 3
+        let modules = qr::encode(puzzle).expect("a puzzle fits in a QR code");
+        let result = match &args.output {
+            Some(path)
+                if path
+                    .extension()
+                    .is_some_and(|e| e.eq_ignore_ascii_case("png")) =>
+            {
+                fs::write(path, encode_png(&qr::to_image(&modules, QR_SCALE)))
+            }
+            Some(path) => fs::write(path, svg::qr(&modules)),
+            None => {
+                println!("{}", svg::qr(&modules));
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            log::error!("{}: {}", args.output.as_deref().unwrap().display(), e);
+            process::exit(1);
+        }
+        return;
+    }
+    let path = args.decode.as_deref().unwrap();
+    let data = fs::read(path).unwrap_or_else(|e| {
+        log::error!("{}: {}", path.display(), e);
+        process::exit(1);
+    });
+    let puzzle = decode(&data)
+        .and_then(|image| qr::decode(&image))
+        .and_then(|text| {
+            parse_grid(&text).map_err(|e| format!("the QR code is not a puzzle: {}", e))
+        });
+    let puzzle = puzzle.unwrap_or_else(|e| {
+        log::error!("{}: {}", path.display(), e);
+        print_error(format, "unrecognized_image", &e, vec![]);
+        process::exit(EXIT_INVALID);
+    });
+    match format {
+        OutputFormat::Json => println!(
+            "{}",
+            Json::Object(vec![("puzzle".to_string(), grid_line(&puzzle).into())])
+        ),
+        _ => println!("{}", grid_line(&puzzle)),
+    }
+}
+
+/// 把文件或标准输入中的题目发给远程服务，按所选格式输出结果
+fn run_remote(args: &RemoteArgs, format: OutputFormat, sep: &str) {
+    if format == OutputFormat::Svg && args.action != RemoteAction::Solve {
+        log::error!("remote {:?} does not support svg output", args.action);
+        process::exit(1);
+    }
+    if format == OutputFormat::Csv && args.action == RemoteAction::Rate {
+        println!("puzzle,grade,score,hardest_technique,clues");
+    }
+    let mut client = args.host.clone();
+    client.timeout = args.timeout.map(Duration::from_secs);
+    client.token = args.token.clone().or_else(|| {
+        std::env::var("SUPERDO_TOKEN")
+            .ok()
+            .filter(|t| !t.is_empty())
+    });
+    let limit = args.limit.map(|n| n as usize);
+    let printer = Printer::stdout(false, false);
+    let mut invalid = false;
+    read_puzzles(args.file.as_deref(), |givens| {
+        let result = match args.action {
+            RemoteAction::Solve => client.solve(&givens, limit.unwrap_or(1)).map(|found| {
+                let ctx = ResolveCtx {
+                    sep: sep.to_string(),
+                    format,
+                    givens,
+                    all: limit.is_some(),
+                    limit,
+                    verify: true,
+                    total: AtomicUsize::new(0),
+                    seen: Default::default(),
+                    out: printer.clone(),
+                    alphabet: None,
+                    label: Label::default(),
+                };
+                let variant = Arc::new(Variant::standard());
+                for solution in &found {
+                    ctx.emit(&SudokuBoard::new_with(solution, variant.clone()));
+                }
+                printer.flush();
+                if found.is_empty() {
+                    log::error!("no solution: {}", grid_line(&givens));
+                    print_error(
+                        format,
+                        "no_solution",
+                        "the server found no solution",
+                        vec![],
+                    );
+                }
+                println!();
+            }),
+            RemoteAction::Count => client.count(&givens, limit).map(|(count, capped)| {
+                print_count(format, count, capped);
+                println!();
+            }),
+            RemoteAction::Rate => client.rate(&givens).map(|rating| {
+                let line = grid_line(&givens);
+                let hardest = rating.hardest.as_deref().unwrap_or("none");
+                match format {
+                    OutputFormat::Json => {
+                        let items = vec![
+                            ("puzzle".to_string(), line.into()),
+                            ("grade".to_string(), rating.grade.into()),
+                            ("score".to_string(), rating.score.into()),
+                            ("hardest".to_string(), hardest.into()),
+                            ("solved".to_string(), rating.solved.into()),
+                            ("steps".to_string(), rating.steps.into()),
+                        ];
+                        println!("{}", Json::Object(items));
+                    }
+                    OutputFormat::Sdm => {
+                        println!("{} # {}, score {}", line, rating.grade, rating.score)
                     }
+                    OutputFormat::Csv => println!(
+                        "{},{},{},{},{}",
+                        line,
+                        rating.grade,
+                        rating.score,
+                        hardest,
+                        clues(&givens)
+                    ),
+                    _ => println!("{} {} {} {}", line, rating.grade, rating.score, hardest),
                 }
+            }),
+        };
+        // 连接失败时不再发送，服务对某道题目报错时跳过这道题目
+        if let Err(e) = result {
+            log::error!("{}", e.message);
+            print_error(format, &e.kind, &e.message, e.details.clone());
+            if e.is_transport() {
+                process::exit(1);
+            }
+            invalid = true;
+        }
+    });
+    if invalid {
+        process::exit(EXIT_INVALID);
+    }
+}
+
+/// 输出读到的题目，有配色时突出空位
+fn echo(givens: &Grid, format: OutputFormat, theme: Option<Theme>, alphabet: Option<Alphabet>) {
+    let spell = |text: String| match alphabet {
+        Some(alphabet) => alphabet.spell(&text),
+        None => text,
+    };
+    let grid = spell(pretty(givens));
+    match format {
+        OutputFormat::Json => println!(
+            "{}",
+            Json::Object(vec![("input".to_string(), spell(grid_line(givens)).into())])
+        ),
+        OutputFormat::Sdm => {
+            for line in grid.lines() {
+                println!("# {}", line);
+            }
+        }
+        _ => match theme {
+            Some(theme) => println!(
+                "{}",
+                grid.replace(" .", &format!(" {}", theme.paint(Role::Blank, '.')))
+            ),
+            None => println!("{}", grid),
+        },
+    }
+}
+
+/// 去掉 `#` 之后的注释，sdm 文件的注释中可能含有数字
+fn strip_comment(line: &str) -> &str {
+    line.split('#').next().unwrap_or(line)
+}
+
+/// CSV 的一行中题目所在字段的字节范围和第一列给出的名称。题目是第一个恰好有 81 格的字段，
+/// 它不在第一列时第一列为名称；不是 CSV 或没有这样的字段时为 None
+fn csv_puzzle(
+    line: &str,
+    is_cell: impl Fn(char) -> bool,
+) -> Option<(Range<usize>, Option<String>)> {
+    if !line.contains(',') {
+        return None;
+    }
+    // 引号中的逗号不分隔字段
+    let mut fields = vec![];
+    let (mut start, mut quoted) = (0, false);
+    for (at, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                fields.push(start..at);
+                start = at + 1;
             }
+            _ => {}
+        }
+    }
+    fields.push(start..line.len());
+    let i = fields
+        .iter()
+        .position(|range| line[range.clone()].chars().filter(|c| is_cell(*c)).count() == 81)?;
+    let id = line[fields[0].clone()].trim();
+    let id = match id.strip_prefix('"').and_then(|id| id.strip_suffix('"')) {
+        Some(quoted) => quoted.replace("\"\"", "\""),
+        None => id.to_string(),
+    };
+    let id = (i > 0 && !id.is_empty()).then_some(id);
+    Some((fields[i].clone(), id))
+}
+
+/// 打开文件或标准输入，gzip 和 zstd 压缩的输入边读边解压
+fn open_input(file: Option<&Path>) -> Input {
+    let name = file.map_or("stdin".to_string(), |path| path.display().to_string());
+    let fail = |e: io::Error| -> ! {
+        log::error!("{}: {}", name, e);
+        process::exit(1);
+    };
+    let opened = match file {
+        Some(path) => Some(fs::File::open(path).unwrap_or_else(|e| fail(e))),
+        None => stdin_file(),
+    };
+    // 未压缩的普通文件映射到内存
+    if let Some(opened) = &opened {
+        match Mapped::open(opened).unwrap_or_else(|e| fail(e)) {
+            Some(mapped) if !is_compressed(mapped.bytes()) => return Input::Mapped(mapped),
+            _ => {}
+        }
+    }
+    let input: Box<dyn BufRead> = match (file, opened) {
+        (Some(_), Some(opened)) => Box::new(BufReader::new(opened)),
+        _ => Box::new(io::stdin().lock()),
+    };
+    Input::Stream(decompressed(input).unwrap_or_else(|e| fail(e)))
+}
+
+/// 标准输入重定向自文件时可以映射到内存
+#[cfg(unix)]
+fn stdin_file() -> Option<fs::File> {
+    use std::os::fd::AsFd;
+
+    io::stdin()
+        .as_fd()
+        .try_clone_to_owned()
+        .ok()
+        .map(fs::File::from)
+}
 
-            // 已填满
-            if !has_empty {
-                return true;
-            }
-            // 未填满，但是本次运行未有找到合适的方案
-            if !has_changes {
-                return false;
-            }
+#[cfg(not(unix))]
+fn stdin_file() -> Option<fs::File> {
+    None
+}
+
+/// 打开的输入。普通文件（包括重定向到标准输入的）映射到内存后按行切分，每一行不必复制；
+/// 管道、终端和压缩的文件按流读取
+enum Input {
+    Mapped(Mapped),
+    Stream(Box<dyn BufRead>),
+}
+
+impl Input {
+    fn lines(&mut self) -> Box<dyn Iterator<Item = io::Result<Cow<'_, str>>> + '_> {
+        match self {
+            Input::Mapped(mapped) => Box::new(mapped.lines().map(|line| line.map(Cow::Borrowed))),
+            Input::Stream(input) => Box::new(input.lines().map(|line| line.map(Cow::Owned))),
         }
     }
 }
 
-impl fmt::Display for SudokuBoard {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let s = self
-            .board
-            .iter()
-            .map(|row| {
-                row.iter()
-                    .map(|p| format!("{}", p.val))
-                    .collect::<Vec<String>>()
-                    .join("")
-            })
-            .collect::<Vec<String>>()
-            .join(EOL);
-        write!(f, "{}", s)
+/// 读取输入的一行，读取或解压出错时退出
+fn input_line<T>(line: io::Result<T>) -> T {
+    line.unwrap_or_else(|e| {
+        log::error!("reading input: {}", e);
+        process::exit(1);
+    })
+}
+
+/// 题目中的一格，数字和空位统一为 ASCII 的 `0`-`9` 和 `.`，其他字符为 None
+fn cell_char(c: char) -> Option<char> {
+    match digit_value(c) {
+        Some(d) => char::from_digit(d, 10),
+        None => is_blank(c).then_some('.'),
     }
 }
 
-impl PartialEq<[[u32; 9]; 9]> for SudokuBoard {
-    fn eq(&self, a: &[[u32; 9]; 9]) -> bool {
-        for row in 0..9 {
-            for col in 0..9 {
-                if self.get(row, col) != &a[row][col] {
-                    return false;
-                }
-            }
+/// 依次读取文件或标准输入中的题目，每 81 个数字（`.` 表示空位）为一道
+fn read_puzzles(file: Option<&Path>, mut each: impl FnMut(Grid)) {
+    let mut cells = String::new();
+    for line in open_input(file).lines() {
+        let line = input_line(line);
+        cells.extend(strip_comment(&line).chars().filter_map(cell_char));
+        while cells.len() >= 81 {
+            let rest = cells.split_off(81);
+            each(parse_grid(&cells).unwrap());
+            cells = rest;
         }
-        true
     }
 }
 
-/// 求解上下文
-struct ResolveCtx {
-    /// 结果分隔符
-    sep: String,
-    /// 是否求解所有结果
-    all: bool,
-    /// 结果总数
-    total: AtomicUsize,
+/// 读取一道题目，source 为文件路径或题目本身
+fn read_puzzle(source: &str) -> Grid {
+    let mut puzzle = None;
+    if Path::new(source).is_file() {
+        read_puzzles(Some(Path::new(source)), |grid| {
+            puzzle.get_or_insert(grid);
+        });
+    } else {
+        let cells: String = source.chars().filter_map(cell_char).collect();
+        puzzle = parse_grid(&cells).ok();
+    }
+    puzzle.unwrap_or_else(|| {
+        log::error!(
+            "`{}` is neither a puzzle file nor an 81-digit puzzle",
+            source
+        );
+        process::exit(1);
+    })
 }
 
-/// 进行求解
-fn resolve(ctx: Arc<ResolveCtx>, board: SudokuBoard, q: Vec<(usize, usize, u32)>) {
-    if ctx.total.load(Ordering::Relaxed) > 0 && !ctx.all {
-        return;
+/// 评定文件或标准输入中每道题目的难度并输出
+fn run_rate(args: &RateArgs, constraints: &[ConstraintKind], format: OutputFormat) {
+    if constraints.contains(&ConstraintKind::OddEven) {
+        log::error!("rate does not support the odd-even constraint");
+        process::exit(1);
+    }
+    if !constraints.is_empty() && args.scale == Scale::Se {
+        log::error!("--scale se rates classic sudoku only");
+        process::exit(1);
+    }
+    if format == OutputFormat::Csv {
+        let stats = if args.stats { ",backdoor" } else { "" };
+        println!("puzzle,grade,score,hardest_technique,clues{}", stats);
     }
-    let mut board = board;
-    let solved = board.solve();
-    if solved {
-        ctx.total.fetch_add(1, Ordering::Relaxed);
-        log::debug!("q: {:?}", q);
-        println!("{}\n{}", ctx.sep, board);
-    } else if !board.exhausted() {
-        // 固定某个自由参数
-        let (free_row, free_col, _) = q.last().cloned().unwrap_or((0, 0, 0));
-        let free_pos = free_row * 9 + free_col;
-        let mut found_free = false;
-        for row in free_row..9 {
-            for col in 0..9 {
-                let cur_pos = row * 9 + col;
-                if cur_pos < free_pos {
-                    continue;
+    let variant = Variant::with_constraints(constraints);
+    read_puzzles(args.file.as_deref(), |givens| {
+        let rating = rate_variant(&givens, &variant);
+        let line = grid_line(&givens);
+        let hardest = rating.hardest.map_or("none", |t| t.name());
+        // SE 评分超出已实现的技巧时输出 `-`
+        let (score, score_json) = match args.scale {
+            Scale::Ladder => (rating.score.to_string(), Json::from(rating.score)),
+            Scale::Se => match rate_se(&givens) {
+                Some(se) => (
+                    format!("{}.{}", se / 10, se % 10),
+                    Json::from(se as f64 / 10.0),
+                ),
+                None => ("-".to_string(), Json::Null),
+            },
+        };
+        // 超出搜索范围的后门输出 `>3`
+        let backdoor = args
+            .stats
+            .then(|| backdoor_size(&givens, &variant, MAX_BACKDOOR));
+        let backdoor_text = match backdoor {
+            Some(Some(n)) => n.to_string(),
+            Some(None) => format!(">{}", MAX_BACKDOOR),
+            None => String::new(),
+        };
+        match format {
+            OutputFormat::Text => {
+                print!("{} {} {} {}", line, rating.grade(), score, hardest);
+                if args.stats {
+                    print!(" backdoor {}", backdoor_text);
                 }
-                let pos = board.get(row, col);
-                if pos.val == 0 {
-                    // 找到一个自由参数
-                    found_free = true;
-                    log::debug!("free pos: ({},{})={} {:?}", row, col, pos.val, pos.digits);
-                    for digit in pos.digits.clone() {
-                        let mut board2 = board.clone();
-                        board2.set(digit, row, col);
-                        let ctx_cloned = ctx.clone();
-                        let mut q2 = q.clone();
-                        q2.push((row, col, digit));
-                        rayon::spawn(move || {
-                            resolve(ctx_cloned.clone(), board2, q2);
-                        });
-                    }
+                println!();
+            }
+            OutputFormat::Json => {
+                let steps: Vec<&str> = rating.steps.iter().map(|t| t.name()).collect();
+                let mut items = vec![
+                    ("puzzle".to_string(), line.into()),
+                    ("grade".to_string(), rating.grade().into()),
+                    ("score".to_string(), score_json),
+                    ("hardest".to_string(), hardest.into()),
+                    ("solved".to_string(), rating.solved.into()),
+                    ("steps".to_string(), steps.into()),
+                ];
+                if let Some(backdoor) = backdoor {
+                    items.push((
+                        "backdoor".to_string(),
+                        backdoor.map_or(Json::Null, Json::from),
+                    ));
                 }
-                if found_free {
-                    break;
+                println!("{}", Json::Object(items));
+            }
+            OutputFormat::Sdm => {
+                print!("{} # {}, score {}", line, rating.grade(), score);
+                if args.stats {
+                    print!(", backdoor {}", backdoor_text);
                 }
+                println!();
             }
-            if found_free {
-                break;
+            OutputFormat::Csv => {
+                print!(
+                    "{},{},{},{},{}",
+                    line,
+                    rating.grade(),
+                    score,
+                    hardest,
+                    clues(&givens)
+                );
+                if args.stats {
+                    print!(",{}", backdoor_text);
+                }
+                println!();
+            }
+            OutputFormat::Svg => {
+                log::error!("rate does not support svg output");
+                process::exit(1);
             }
         }
-    }
+    });
 }
 
-/// 回溯法找一个解
-fn brute_force(board: &mut [[u32; 9]; 9], empty: &[[bool; 9]; 9], stack: &mut Vec<(usize, usize)>) -> bool {
-    // 无法回溯或缺少初始值
-    if stack.is_empty() {
-        return false;
+/// 输出题目的规范形式，或判断两道题目是否等价
+fn run_canon(args: &CanonArgs, format: OutputFormat) {
+    if let Some([a, b]) = args.compare.as_deref() {
+        let (a, b) = (read_puzzle(a), read_puzzle(b));
+        let transform = canon::equivalence(&a, &b);
+        match format {
+            OutputFormat::Json => {
+                let mut items = vec![("equivalent".to_string(), transform.is_some().into())];
+                if let Some(t) = &transform {
+                    let order =
+                        |items: &[usize]| items.iter().map(|i| *i as u32 + 1).collect::<Vec<_>>();
+                    items.push((
+                        "transform".to_string(),
+                        Json::Object(vec![
+                            ("transpose".to_string(), t.transpose.into()),
+                            ("rows".to_string(), order(&t.rows).into()),
+                            ("columns".to_string(), order(&t.cols).into()),
+                            ("digits".to_string(), t.digits[1..].to_vec().into()),
+                        ]),
+                    ));
+                }
+                println!("{}", Json::Object(items));
+            }
+            _ => match &transform {
+                Some(t) => println!("equivalent\n{}", t),
+                None => println!("not equivalent"),
+            },
+        }
+        // 与 cmp 一样，不等价时以 1 退出
+        if transform.is_none() {
+            process::exit(1);
+        }
+        return;
     }
-    
-    let (mut row,mut col) = stack.pop().unwrap(); // 当前行列
 
-    // 为 (row,col) 查找下一个能用的值
-    let next_digit = |board: &[[u32; 9]; 9], row: usize, col: usize| -> Option<u32> {
-        // 注意，使用 +1 来越过旧值
-        'next_digit: for digit in (board[row][col] + 1)..10 {
-            // 同一行唯一
-            for i in 0..9 {
-                if board[row][i] == digit {
-                    continue 'next_digit;
-                }
+    if format == OutputFormat::Csv {
+        println!("puzzle,canonical");
+    }
+    read_puzzles(args.file.as_deref(), |grid| {
+        let (canonical, _) = canon::canonical(&grid);
+        let (line, canonical) = (grid_line(&grid), grid_line(&canonical));
+        match format {
+            OutputFormat::Json => println!(
+                "{}",
+                Json::Object(vec![
+                    ("puzzle".to_string(), line.into()),
+                    ("canonical".to_string(), canonical.into()),
+                ])
+            ),
+            OutputFormat::Csv => println!("{},{}", line, canonical),
+            OutputFormat::Text | OutputFormat::Sdm => println!("{}", canonical),
+            OutputFormat::Svg => {
+                log::error!("canon does not support svg output");
+                process::exit(1);
             }
-            // 同一列唯一
-            for i in 0..9 {
-                if board[i][col] == digit {
-                    continue 'next_digit;
-                }
+        }
+    });
+}
+
+/// 去掉题目集中重复和等价的题目，保留每组中的第一道
+fn run_dedupe(args: &DedupeArgs) {
+    let mut puzzles = vec![];
+    read_puzzles(args.file.as_deref(), |grid| puzzles.push(grid));
+    let keys: Vec<Grid> = if args.identical_only {
+        puzzles.clone()
+    } else {
+        puzzles
+            .par_iter()
+            .map(|grid| canon::canonical(grid).0)
+            .collect()
+    };
+
+    // 每个规范形式第一次出现的题目序号
+    let mut first: HashMap<Grid, usize> = HashMap::new();
+    let mut kept = String::new();
+    let (mut identical, mut equivalent) = (0, 0);
+    for (i, (grid, key)) in puzzles.iter().zip(&keys).enumerate() {
+        match first.get(key) {
+            Some(&j) if puzzles[j] == *grid => {
+                log::info!("puzzle {} is identical to puzzle {}", i + 1, j + 1);
+                identical += 1;
             }
-            // 3x3 小格唯一
-            let row_grid = (row / 3) * 3;
-            let col_grid = (col / 3) * 3;
-            for row_x in 0..3 {
-                for col_x in 0..3 {
-                    if board[row_grid + row_x][col_grid + col_x] == digit {
-                        continue 'next_digit;
-                    }
-                }
+            Some(&j) => {
+                log::info!("puzzle {} is equivalent to puzzle {}", i + 1, j + 1);
+                equivalent += 1;
+            }
+            None => {
+                first.insert(*key, i);
+                kept.push_str(&grid_line(grid));
+                kept.push('\n');
             }
-            return Some(digit);
         }
-        None
+    }
+    log::info!(
+        "kept {} of {} puzzles, removed {} identical and {} equivalent",
+        first.len(),
+        puzzles.len(),
+        identical,
+        equivalent
+    );
+
+    let written = match &args.output {
+        Some(path) => fs::write(path, kept).map_err(|e| format!("{}: {}", path.display(), e)),
+        None => io::stdout()
+            .write_all(kept.as_bytes())
+            .map_err(|e| e.to_string()),
     };
+    if let Err(e) = written {
+        log::error!("{}", e);
+        process::exit(1);
+    }
+}
 
-    'back_trace: while row < 9 {
-        while col < 9 {
-            // 空位处理
-            if board[row][col] == 0 || empty[row][col] {
-                match next_digit(board, row, col) {
-                    Some(digit) => {
-                        // 找到可能值，压栈
-                        board[row][col] = digit;
-                        stack.push((row, col));
-                    }
-                    None => {
-                        // 当前复位，向前回溯
-                        board[row][col] = 0;
-                        match stack.pop() {
-                            Some((prev_row, prev_col)) => {
-                                row = prev_row;
-                                col = prev_col;
-                                continue 'back_trace;
-                            }
-                            None => {
-                                // 已不能回溯，失败
-                                return false;
-                            }
-                        }
-                    }
-                }
+/// 列出两个棋盘数值不同的位置，有配色时以不同样式显示原有和新的数值
+fn run_diff(args: &DiffArgs, format: OutputFormat, theme: Option<Theme>) {
+    let changes = diff_grids(&read_puzzle(&args.a), &read_puzzle(&args.b));
+    match format {
+        OutputFormat::Json => {
+            let changes: Vec<Json> = changes
+                .iter()
+                .map(|change| {
+                    Json::Object(vec![
+                        (
+                            "cell".to_string(),
+                            format!("r{}c{}", change.row + 1, change.col + 1).into(),
+                        ),
+                        ("from".to_string(), change.from.into()),
+                        ("to".to_string(), change.to.into()),
+                    ])
+                })
+                .collect();
+            println!("{}", Json::Array(changes));
+        }
+        OutputFormat::Csv => {
+            println!("cell,from,to");
+            for change in &changes {
+                println!(
+                    "r{}c{},{},{}",
+                    change.row + 1,
+                    change.col + 1,
+                    change.from,
+                    change.to
+                );
             }
-            // 成功找到结果，打印出来，然后向前回溯
-            if row == 8 && col == 8 && board[row][col] != 0 {
-                return true;
+        }
+        OutputFormat::Text | OutputFormat::Sdm => {
+            for change in &changes {
+                match theme {
+                    Some(theme) => println!(
+                        "r{}c{}: {} -> {}",
+                        change.row + 1,
+                        change.col + 1,
+                        theme.paint(Role::Removed, change.from),
+                        theme.paint(Role::Added, change.to)
+                    ),
+                    None => println!("{}", change),
+                }
             }
-            col += 1;
         }
-        row += 1;
-        col = 0;
+        OutputFormat::Svg => {
+            log::error!("diff does not support svg output");
+            process::exit(1);
+        }
+    }
+    // 与 diff 一样，有差异时以 1 退出
+    if !changes.is_empty() {
+        process::exit(1);
     }
-    false
 }
 
-fn resolve_2(board: &mut [[u32; 9]; 9]) {
-    // 空位
-    let mut empty = [[false; 9]; 9];
-    for i in 0..9 {
-        for j in 0..9 {
-            empty[i][j] = board[i][j] == 0;
+/// 回放求解记录，在终端中每按一次回车前进一步
+fn run_replay(args: &ReplayArgs) {
+    let text = fs::read_to_string(&args.file).unwrap_or_else(|e| {
+        log::error!("{}: {}", args.file.display(), e);
+        process::exit(1);
+    });
+    let interactive = io::stdin().is_terminal() && io::stdout().is_terminal();
+    let mut input = String::new();
+    for (n, line) in text.lines().filter(|l| !l.trim().is_empty()).enumerate() {
+        let recording = Json::parse(line).and_then(|json| Recording::from_json(&json));
+        let recording = recording.unwrap_or_else(|e| {
+            log::error!("{}: puzzle {}: {}", args.file.display(), n + 1, e);
+            process::exit(1);
+        });
+        println!("puzzle {}: {}", n + 1, grid_line(&recording.givens));
+        let mut grid = recording.givens;
+        println!("{}", pretty(&grid));
+        for event in &recording.events {
+            if interactive {
+                input.clear();
+                let _ = io::stdin().read_line(&mut input);
+            }
+            println!("step {}: {}", event.step, event);
+            if event.kind != EventKind::Eliminate {
+                grid[event.cell.0][event.cell.1] = event.digit;
+                println!("{}", pretty(&grid));
+            }
         }
+        println!(
+            "{}",
+            if recording.solved {
+                "solved"
+            } else {
+                "no solution"
+            }
+        );
+        println!();
     }
-    // 回溯栈
-    let mut stack = Vec::with_capacity(81);
-    stack.push((0, 0));
-
-    // 打印
-    let dump_board = |board: &[[u32; 9]; 9]| -> String {
-        board
-            .map(|row| row.map(|d| d.to_string()).join(""))
-            .join(EOL)
-    };
+}
 
-    loop {
-        let resolve = brute_force(board, &empty, &mut stack);
-        if resolve {
-            println!("---------\n{}", dump_board(board));
+/// 分析文件或标准输入中每道题目的结构并输出
+fn run_analyze(args: &AnalyzeArgs, format: OutputFormat) {
+    // 各项结果：名称、文本形式、JSON 形式
+    type Field = (&'static str, String, Json);
+    let names = |symmetries: &[GridSymmetry]| -> (String, Json) {
+        let list: Vec<&str> = symmetries.iter().map(|s| s.name()).collect();
+        let text = if list.is_empty() {
+            "none".to_string()
         } else {
-            break;
+            list.join(",")
+        };
+        (text, list.into())
+    };
+    if format == OutputFormat::Csv {
+        let mut header = vec!["puzzle"];
+        if args.symmetry {
+            header.extend(["clue_symmetry", "solution_symmetry", "automorphisms"]);
         }
+        if args.backdoors {
+            header.push("backdoors");
+        }
+        if args.unavoidable.is_some() {
+            header.push("unavoidable_sets");
+        }
+        if args.hints.is_some() {
+            header.push("hints");
+        }
+        println!("{}", header.join(","));
     }
-    println!();
-}
-
-#[derive(Parser, Debug)]
-#[command(
-    version,
-    about = "A sudoku puzzle solver.\n\n\
-            Input the sudoku puzzle digit by digit (left to right, top to down, \
-                0 for unknown digit, whitespace and other characters are ignored).\n\n\
-            Output is a list of solutions separated by the chosen separator, then \
-                followed by a blank line.",
-    long_about = None
-)]
-struct Args {
-    /// Show debug log
-    #[arg(short, long)]
-    debug: bool,
-
-    /// Find all solutions
-    #[arg(short, long)]
-    all: bool,
-
-    /// Solution separator
-    #[arg(long, default_value = "---------")]
-    sep: String,
-
-    /// Max number of threads
-    #[arg(long, default_value_t = num_cpus::get())]
-    threads: usize,
+    let variant = Arc::new(Variant::standard());
+    read_puzzles(args.file.as_deref(), |givens| {
+        let line = grid_line(&givens);
+        let mut fields: Vec<Field> = vec![];
+        if args.symmetry {
+            let (text, json) = names(&pattern_symmetries(&givens));
+            fields.push(("clues", text, json));
+            // 解不唯一时不分析解的对称
+            let found = solutions(&SudokuBoard::new_with(&givens, variant.clone()), 2);
+            let (text, json) = match found.as_slice() {
+                [solution] => names(&grid_symmetries(solution)),
+                _ => ("-".to_string(), Json::Null),
+            };
+            fields.push(("solution", text, json));
+            let n = automorphisms(&givens);
+            fields.push(("automorphisms", n.to_string(), n.into()));
+        }
+        if args.backdoors {
+            // 无解时为 `-`，只用唯余法和摒除法已能解出时为 `not-needed`
+            let (text, json) = match backdoor_cells(&givens, &variant) {
+                None => ("-".to_string(), Json::Null),
+                Some(cells)
+                    if cells.is_empty() && backdoor_size(&givens, &variant, 0).is_some() =>
+                {
+                    ("not-needed".to_string(), Json::Array(vec![]))
+                }
+                Some(cells) => {
+                    let list: Vec<String> = cells
+                        .iter()
+                        .map(|((r, c), d)| format!("r{}c{}={}", r + 1, c + 1, d))
+                        .collect();
+                    let text = if list.is_empty() {
+                        "none".to_string()
+                    } else {
+                        list.join(",")
+                    };
+                    (text, list.into())
+                }
+            };
+            fields.push(("backdoors", text, json));
+        }
+        if let Some(max_size) = args.unavoidable {
+            // 解不唯一时不分析
+            let found = solutions(&SudokuBoard::new_with(&givens, variant.clone()), 2);
+            let (text, json) = match found.as_slice() {
+                [solution] => {
+                    let sets: Vec<String> = unavoidable_sets(solution, max_size)
+                        .iter()
+                        .map(|set| {
+                            set.iter()
+                                .map(|(r, c)| format!("r{}c{}", r + 1, c + 1))
+                                .collect::<Vec<_>>()
+                                .join("+")
+                        })
+                        .collect();
+                    let text = if sets.is_empty() {
+                        "none".to_string()
+                    } else {
+                        sets.join(",")
+                    };
+                    (text, sets.into())
+                }
+                _ => ("-".to_string(), Json::Null),
+            };
+            fields.push(("unavoidable", text, json));
+        }
+        if let Some(n) = args.hints {
+            // 现有技巧都不能填出的位置写作 `r1c1?`
+            let hints: Vec<_> = rank_cells(&givens, &variant).into_iter().take(n).collect();
+            let text: Vec<String> = hints
+                .iter()
+                .map(|h| {
+                    let cell = format!("r{}c{}", h.cell.0 + 1, h.cell.1 + 1);
+                    match (h.digit, h.technique) {
+                        (Some(d), Some(t)) => format!("{}={}:{}", cell, d, t.name()),
+                        _ => format!("{}?", cell),
+                    }
+                })
+                .collect();
+            let json: Vec<Json> = hints.iter().map(CellHint::to_json).collect();
+            let text = if text.is_empty() {
+                "none".to_string()
+            } else {
+                text.join(",")
+            };
+            fields.push(("hints", text, Json::Array(json)));
+        }
+        match format {
+            OutputFormat::Text => {
+                let items: Vec<String> = fields
+                    .iter()
+                    .map(|(name, text, _)| format!("{} {}", name, text))
+                    .collect();
+                println!("{} {}", line, items.join(" "));
+            }
+            OutputFormat::Json => {
+                let mut items = vec![("puzzle".to_string(), line.into())];
+                items.extend(
+                    fields
+                        .into_iter()
+                        .map(|(name, _, json)| (name.to_string(), json)),
+                );
+                println!("{}", Json::Object(items));
+            }
+            OutputFormat::Sdm => {
+                let items: Vec<String> = fields
+                    .iter()
+                    .map(|(name, text, _)| format!("{} {}", name, text))
+                    .collect();
+                println!("{} # {}", line, items.join(", "));
+            }
+            OutputFormat::Csv => {
+                let items: Vec<String> = fields
+                    .iter()
+                    .map(|(_, text, _)| {
+                        // 多个对称以逗号分隔，需加引号
+                        if text.contains(',') {
+                            format!("\"{}\"", text)
+                        } else {
+                            text.clone()
+                        }
+                    })
+                    .collect();
+                println!("{},{}", line, items.join(","));
+            }
+            OutputFormat::Svg => {
+                log::error!("analyze does not support svg output");
+                process::exit(1);
+            }
+        }
+    });
 }
 
 fn main() {
     let args = Args::parse();
 
     // 日志初始化
+    // 日志只写到标准错误，不混入结果
     let log_level = if args.debug { "debug" } else { "info" };
-    env_logger::init_from_env(Env::default().default_filter_or(log_level));
+    let write_style = match args.color.enabled(&io::stderr()) {
+        true => WriteStyle::Always,
+        false => WriteStyle::Never,
+    };
+    env_logger::Builder::from_env(Env::default().default_filter_or(log_level))
+        .target(Target::Stderr)
+        .write_style(write_style)
+        .init();
 
-    // 线程池
+    let lang = args.lang.unwrap_or_else(Lang::from_env);
+
+    // 线程池，求解和生成题目共用
     let num_threads = if args.threads > 0 {
         args.threads
     } else {
         num_cpus::get()
     };
-    let thread_pool = rayon::ThreadPoolBuilder::new()
+    rayon::ThreadPoolBuilder::new()
         .num_threads(num_threads)
-        .build()
+        .build_global()
         .unwrap();
 
+    match &args.command {
+        Some(Command::Generate(generate_args)) => {
+            run_generate(generate_args, &args.constraint, args.format);
+            return;
+        }
+        Some(Command::Book(book_args)) => {
+            run_book(book_args, &args.constraint);
+            return;
+        }
+        Some(Command::Canon(canon_args)) => {
+            run_canon(canon_args, args.format);
+            return;
+        }
+        Some(Command::Dedupe(dedupe_args)) => {
+            run_dedupe(dedupe_args);
+            return;
+        }
+        Some(Command::Diff(diff_args)) => {
+            let theme = args
+                .color
+                .enabled(&io::stdout())
+                .then(|| configured_theme(args.theme));
+            run_diff(diff_args, args.format, theme);
+            return;
+        }
+        Some(Command::Rate(rate_args)) => {
+            run_rate(rate_args, &args.constraint, args.format);
+            return;
+        }
+        Some(Command::Analyze(analyze_args)) => {
+            if !args.constraint.is_empty() {
+                log::error!("analyze supports classic sudoku only, without --constraint");
+                process::exit(1);
+            }
+            run_analyze(analyze_args, args.format);
+            return;
+        }
+        Some(Command::Replay(replay_args)) => {
+            run_replay(replay_args);
+            return;
+        }
+        Some(Command::Serve(serve_args)) => {
+            run_serve(serve_args);
+            return;
+        }
+        Some(Command::Gui(gui_args)) => {
+            run_gui(gui_args);
+            return;
+        }
+        Some(Command::Play(play_args)) => {
+            let theme = args
+                .color
+                .enabled(&io::stdout())
+                .then(|| configured_theme(args.theme));
+            run_play(play_args, theme);
+            return;
+        }
+        Some(Command::Bot(bot_args)) => {
+            run_bot(bot_args, lang);
+            return;
+        }
+        Some(Command::ImportImage(import_args)) => {
+            run_import_image(import_args, args.format);
+            return;
+        }
+        Some(Command::Qr(qr_args)) => {
+            run_qr(qr_args, args.format);
+            return;
+        }
+        Some(Command::Fetch(fetch_args)) => {
+            run_fetch(fetch_args, args.format);
+            return;
+        }
+        Some(Command::Remote(remote_args)) => {
+            run_remote(remote_args, args.format, &args.sep);
+            return;
+        }
+        None => {}
+    }
+
     // 结果格式
     let all = args.all;
-    let sep = args.sep;
+    let sep = &args.sep;
+
+    if args.steps && matches!(args.format, OutputFormat::Svg | OutputFormat::Csv) {
+        log::error!("--steps supports text and json output only");
+        process::exit(1);
+    }
+    if args.echo && matches!(args.format, OutputFormat::Svg | OutputFormat::Csv) {
+        log::error!("--echo supports text, json and sdm output only");
+        process::exit(1);
+    }
+    let theme = args
+        .color
+        .enabled(&io::stdout())
+        .then(|| configured_theme(args.theme));
+    if args.max_guess_depth.is_some() && args.solver != SolverKind::Propagate {
+        log::error!("--max-guess-depth limits the propagate solver only");
+        process::exit(1);
+    }
+    if args.shuffle && args.solver != SolverKind::Propagate {
+        log::error!("--shuffle randomizes the propagate solver only");
+        process::exit(1);
+    }
+    if args.visualize && args.solver != SolverKind::Propagate {
+        log::error!("--visualize shows the parallel search of the propagate solver only");
+        process::exit(1);
+    }
+
+    // 模板限定的候选数字
+    let template = args.template.as_ref().map_or_else(Vec::new, |source| {
+        let text = if Path::new(source).is_file() {
+            fs::read_to_string(source).unwrap_or_else(|e| {
+                log::error!("{}: {}", source, e);
+                process::exit(1);
+            })
+        } else {
+            source.clone()
+        };
+        Json::parse(&text)
+            .and_then(|json| Allowed::parse_template(&json))
+            .unwrap_or_else(|e| {
+                log::error!("--template: {}", e);
+                process::exit(1);
+            })
+    });
+
+    // 变体规则
+    let add_args = |variant: &mut Variant| {
+        for kind in &args.constraint {
+            variant.add_kind(*kind);
+        }
+        for arrow in &args.arrow {
+            variant.add(arrow.clone());
+        }
+        for pair in args.xv.iter().chain(&args.dot) {
+            variant.add(pair.clone());
+        }
+        for cage in &args.cage {
+            variant.add(cage.clone());
+        }
+        for thermo in &args.thermo {
+            variant.add(thermo.clone());
+        }
+        for allowed in &template {
+            variant.add(allowed.clone());
+        }
+        if args.xv_negative {
+            variant.add_negative_xv();
+        }
+    };
+
+    // 求解记录
+    // 从断点继续时沿用之前的结果，求解记录接在之前的后面
+    let resumed = match &args.resume {
+        Some(path) => Checkpoint::load(path).unwrap_or_else(|e| {
+            log::error!("{}", e);
+            process::exit(1);
+        }),
+        None => Checkpoint::default(),
+    };
+    let record = args.record.as_ref().map(|path| {
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(args.resume.is_some())
+            .truncate(args.resume.is_none())
+            .open(path);
+        RefCell::new(file.unwrap_or_else(|e| {
+            log::error!("{}: {}", path.display(), e);
+            process::exit(1);
+        }))
+    });
+
+    // 并行搜索找到的解都交给同一个写线程输出
+    let printer = Printer::stdout(args.ordered || args.sort, args.dedupe);
+    let propagating = Propagating {
+        max_depth: args.max_guess_depth,
+        shuffle: args
+            .shuffle
+            .then(|| args.seed.unwrap_or_else(|| Rng::from_time().next_u64())),
+    };
+    let solver: Box<dyn Solver> = match args.solver {
+        SolverKind::Propagate => Box::new(propagating),
+        kind => kind.solver(),
+    };
+    // 有题目超出猜测深度的上限
+    let too_hard = Cell::new(resumed.too_hard);
+    // 所有题目的解的总数，记入断点
+    let solutions = Cell::new(resumed.solutions);
+    let cache = args.cache.map(SolveCache::new);
+
+    // 进行求解，给定数字有冲突时报告并返回 false
+    let solve = |givens: Grid, variant: Variant, label: Label| {
+        if let Some(export) = args.export {
+            match export {
+                ExportFormat::CtcLink => println!("{}", fpuzzles::share_link(&givens, &variant)),
+                _ => println!("{}", export_grid(&givens, export)),
+            }
+            if export == ExportFormat::Sdk {
+                println!();
+            }
+            return true;
+        }
+        // 题目的名称作为结果的标题；sdm 的注释与输入相同，输出可以再读入
+        match (&label.id, args.format) {
+            _ if args.sort => {}
+            (Some(id), OutputFormat::Text) => println!("== puzzle {} ==", id),
+            (Some(id), OutputFormat::Sdm) => println!("# {}", id),
+            _ => {}
+        }
+        // JSON 的错误对象也带上题目的标识
+        let labelled = |mut details: Vec<(String, Json)>| {
+            details.extend(label.fields());
+            details
+        };
+        // 在检查冲突之前输出，读错的题目也能看到
+        if args.echo {
+            echo(&givens, args.format, theme, args.alphabet);
+        }
+        let conflicts = variant.conflicts(&givens);
+        if !conflicts.is_empty() {
+            for conflict in &conflicts {
+                log::error!("{}", lang.conflict(conflict));
+                print_error(
+                    args.format,
+                    "conflict",
+                    &conflict.to_string(),
+                    labelled(conflict.details()),
+                );
+            }
+            return false;
+        }
+        // 标准数独至少需要 17 个给定数字才可能有唯一解
+        let clues = givens.iter().flatten().filter(|d| **d != 0).count();
+        if clues < MIN_CLUES && variant.is_standard() {
+            log::warn!("{}", lang.format("too_few_clues", &[&clues]));
+            if all && args.limit.is_none() && !args.force {
+                // 先写出已暂存的结果，退出时它们不会丢失
+                printer.flush();
+                log::error!("{}", lang.message("infeasible"));
+                print_error(
+                    args.format,
+                    "too_few_clues",
+                    Lang::En.message("infeasible"),
+                    labelled(vec![("clues".to_string(), clues.into())]),
+                );
+                process::exit(1);
+            }
+        }
+        let ctx = ResolveCtx {
+            sep: sep.clone(),
+            format: args.format,
+            givens,
+            all,
+            limit: args.limit.map(|n| n as usize),
+            verify: args.verify || cfg!(debug_assertions),
+            total: AtomicUsize::new(0),
+            seen: Default::default(),
+            out: printer.clone(),
+            alphabet: args.alphabet,
+            label: label.clone(),
+        };
+        let variant = Arc::new(variant);
+        if let Some(file) = &record {
+            let recording = Recording::record(&givens, variant.clone());
+            if let Err(e) = writeln!(file.borrow_mut(), "{}", recording.to_json()) {
+                printer.flush();
+                log::error!("{}: {}", args.record.as_ref().unwrap().display(), e);
+                process::exit(1);
+            }
+        }
+        let mut board = SudokuBoard::new_with(&givens, variant.clone());
+        for &step in &args.snapshot {
+            let json = Json::Object(vec![
+                ("step".to_string(), step.into()),
+                ("cells".to_string(), snapshot_at(&board, step).snapshot()),
+            ]);
+            println!("{}", json);
+        }
+        if args.steps {
+            trace(&mut board, args.format);
+        }
+        if args.cross_check {
+            let board = SudokuBoard::new_with(&givens, variant.clone());
+            if let Err(e) = cross_check(&board, CROSS_CHECK_CAP) {
+                printer.flush();
+                log::error!("solvers disagree on {}: {}", grid_line(&givens), e);
+                print_error(
+                    args.format,
+                    "solver_mismatch",
+                    &e,
+                    labelled(vec![("puzzle".to_string(), grid_line(&givens).into())]),
+                );
+                process::exit(EXIT_MISMATCH);
+            }
+        }
+        if args.audit {
+            match audit(&givens, variant.clone()) {
+                Ok(checked) => log::info!("audit: {} eliminations verified", checked),
+                Err(e) => {
+                    printer.flush();
+                    log::error!("unsound deduction in {}: {}", grid_line(&givens), e);
+                    print_error(
+                        args.format,
+                        "unsound_deduction",
+                        &e,
+                        labelled(vec![("puzzle".to_string(), grid_line(&givens).into())]),
+                    );
+                    process::exit(EXIT_UNSOUND);
+                }
+            }
+        }
+        // 分片时只搜索其中的子树
+        let subtrees = |board: SudokuBoard| match &args.shard {
+            Some(shard) => shard.subtrees(&board),
+            None => vec![board],
+        };
+        let start = Instant::now();
+        let (total, cut_off) = match args.count_limit {
+            Some(limit) => {
+                let limit = limit as usize;
+                let mut total = 0;
+                for board in subtrees(board) {
+                    total += count_solutions(&board, limit - total);
+                    if total == limit {
+                        break;
+                    }
+                }
+                print_count(args.format, total, total == limit);
+                (total, 0)
+            }
+            None => {
+                let emit = |board: &SudokuBoard| ctx.emit(board);
+                let stats = if args.visualize {
+                    let (progress, done) = (SearchProgress::default(), AtomicBool::new(false));
+                    let theme = args
+                        .color
+                        .enabled(&io::stderr())
+                        .then(|| configured_theme(args.theme));
+                    thread::scope(|s| {
+                        s.spawn(|| visualize::run(&progress, &done, &mut io::stderr(), theme));
+                        let stats = propagating.solve_with(board, &emit, &progress);
+                        done.store(true, Ordering::Relaxed);
+                        stats
+                    })
+                } else if let Some((cache, key)) = cache
+                    .as_ref()
+                    .filter(|_| variant.is_standard())
+                    .and_then(|cache| Some((cache, CacheKey::new(&givens)?)))
+                {
+                    match cache.get(&key, ctx.wanted()) {
+                        Some(found) => {
+                            for solution in found {
+                                if !emit(&SudokuBoard::new_with(&solution, variant.clone())) {
+                                    break;
+                                }
+                            }
+                            SolveStats::default()
+                        }
+                        None => {
+                            let found = Mutex::new(vec![]);
+                            let stats = solver.solve(board, &|board: &SudokuBoard| {
+                                found.lock().unwrap().push(board.grid());
+                                emit(board)
+                            });
+                            // 并行的分支可能解出同一个解
+                            let mut found = found.into_inner().unwrap();
+                            let mut seen = HashSet::new();
+                            found.retain(|solution| seen.insert(*solution));
+                            found.truncate(ctx.wanted());
+                            cache.insert(&key, &found, found.len() < ctx.wanted());
+                            stats
+                        }
+                    }
+                } else {
+                    let mut stats = SolveStats::default();
+                    for board in subtrees(board) {
+                        stats += solver.solve(board, &emit);
+                        if ctx.total.load(Ordering::Relaxed) >= ctx.wanted() {
+                            break;
+                        }
+                    }
+                    stats
+                };
+                // 解都写出后再输出其余的结果，全部排序时到输入结束才写出
+                if !args.sort {
+                    printer.flush();
+                }
+                if args.stats {
+                    print_stats(args.format, &stats);
+                }
+                (ctx.total.load(Ordering::Relaxed), stats.cut_off)
+            }
+        };
+        if args.time {
+            print_time(args.format, start.elapsed());
+        }
+        if args.unique && total > 0 {
+            // 未求所有解时再数到第二个解为止
+            let unique = if all && args.limit.is_none() {
+                total == 1
+            } else {
+                count_solutions(&SudokuBoard::new_with(&givens, variant.clone()), 2) == 1
+            };
+            print_uniqueness(args.format, unique);
+        }
+        if args.breakdown && total > 0 {
+            print_breakdown(args.format, clues, logic_cells(&givens, variant.clone()));
+        }
+        let depth = args.max_guess_depth.unwrap_or_default();
+        if cut_off > 0 && total > 0 && total < ctx.wanted() {
+            log::warn!("{}", lang.format("cut_off", &[&cut_off, &depth]));
+        }
+        // 无解时说明原因，分片中没有解是正常的
+        if total == 0 && cut_off > 0 {
+            log::error!("{}", lang.format("too_hard", &[&cut_off, &depth]));
+            print_error(
+                args.format,
+                "too_hard",
+                &Lang::En.format("too_hard", &[&cut_off, &depth]),
+                labelled(vec![
+                    ("max_guess_depth".to_string(), depth.into()),
+                    ("cut_off".to_string(), cut_off.into()),
+                ]),
+            );
+            too_hard.set(true);
+        } else if total == 0 && args.shard.is_some() {
+            log::info!("no solutions in this shard");
+        } else if total == 0 {
+            // JSON 中的消息保持英文
+            let explain = |lang: Lang, found: &Option<Contradiction>| match found {
+                Some(c) => lang.contradiction(c),
+                None => lang.message("every_guess_fails").to_string(),
+            };
+            let found = contradiction(&givens, variant);
+            log::error!("{}", lang.format("no_solution", &[&explain(lang, &found)]));
+            let details = found.as_ref().map_or(vec![], Contradiction::details);
+            print_error(
+                args.format,
+                "no_solution",
+                &explain(Lang::En, &found),
+                labelled(details),
+            );
+        }
+        solutions.set(solutions.get() + total);
+        if !args.sort {
+            println!();
+        }
+        true
+    };
+
+    // 从 f-puzzles 导入
+    if let Some(source) = &args.fpuzzles {
+        let text = if Path::new(source).is_file() {
+            fs::read_to_string(source).unwrap_or_else(|e| {
+                log::error!("{}: {}", source, e);
+                process::exit(1);
+            })
+        } else {
+            source.clone()
+        };
+        match fpuzzles::parse(&text) {
+            Ok(puzzle) => {
+                let mut variant = puzzle.variant;
+                add_args(&mut variant);
+                let solved = solve(puzzle.givens, variant, Label::default());
+                printer.flush();
+                if !solved {
+                    process::exit(EXIT_INVALID);
+                }
+                if too_hard.get() {
+                    process::exit(EXIT_TOO_HARD);
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e);
+                print_error(args.format, "parse", &e, vec![]);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let mut variant = Variant::standard();
+    add_args(&mut variant);
+    let parse_parity = args.constraint.contains(&ConstraintKind::OddEven);
 
     // 数独板
     let mut board = [[0; 9]; 9];
+    let mut parity = [[None; 9]; 9];
     let mut count = 0;
-    for line in io::stdin().lines() {
-        for c in line.unwrap().chars().filter(|c| c.is_ascii_digit()) {
-            // 读取
-            board[count / 9][count % 9] = c.to_digit(10).unwrap();
+    let mut invalid = resumed.invalid;
+    // 题目从行首开始且第一行不足 81 个数字时，按每行 9 个数字、共 9 行的格式读取
+    let mut grid_rows = false;
+    // 按行读取时格式有误的题目还需跳过的行数
+    let mut skip_rows = 0;
+    // 题目之前 `# name` 注释行或 CSV 第一列给出的名称，以及题目开始的行号
+    let mut name = None;
+    let mut start = 0;
+    let is_cell = |c: char| {
+        args.alphabet
+            .and_then(|alphabet| alphabet.digit(c))
+            .is_some()
+            || digit_value(c).is_some()
+            || is_blank(c)
+            || parse_parity && Parity::from_char(c).is_some()
+    };
+    // 定期写入断点，只在两道题目之间写入
+    let checkpoint = args.checkpoint.as_ref().or(args.resume.as_ref());
+    let mut puzzles = resumed.puzzles;
+    let mut lines = 0;
+    let mut saved = Instant::now();
+    let save = |lines: usize, puzzles: usize, invalid: bool| {
+        let Some(path) = checkpoint else {
+            return;
+        };
+        let progress = Checkpoint {
+            lines,
+            puzzles,
+            solutions: solutions.get(),
+            invalid,
+            too_hard: too_hard.get(),
+        };
+        if let Err(e) = progress.save(path) {
+            log::error!("{}", e);
+            process::exit(1);
+        }
+    };
+    if args.resume.is_some() {
+        log::info!(
+            "resuming after line {}: {} puzzles and {} solutions so far",
+            resumed.lines,
+            resumed.puzzles,
+            resumed.solutions
+        );
+    }
+    for (n, line) in open_input(None).lines().enumerate() {
+        let line = input_line(line);
+        lines = n + 1;
+        if n < resumed.lines {
+            continue;
+        }
+        if let Some(comment) = line.trim_start().strip_prefix('#') {
+            let comment = comment.trim();
+            if count == 0 && skip_rows == 0 && !comment.is_empty() {
+                name = Some(comment.to_string());
+            }
+            continue;
+        }
+        // 按行读取时空行结束一道题目
+        if line.trim().is_empty() {
+            if grid_rows && count > 0 {
+                log::error!("{}", lang.format("truncated_puzzle", &[&(n + 1), &count]));
+                print_error(
+                    args.format,
+                    "incomplete_puzzle",
+                    "blank line inside a puzzle",
+                    vec![
+                        ("line".to_string(), (n + 1).into()),
+                        ("digits".to_string(), count.into()),
+                    ],
+                );
+                invalid = true;
+                count = 0;
+                name = None;
+            }
+            skip_rows = 0;
+            continue;
+        }
+        // 读取，`.` 为空位，开启奇偶约束时空位可用 o/e 标记；严格模式下只允许数字、`.` 和空白
+        let mut cells: Vec<(u32, Option<Parity>)> = vec![];
+        let mut stray = None;
+        // `id,puzzle` 形式的 CSV 只读取题目所在的字段
+        let text = strip_comment(&line);
+        let mut field = 0..text.len();
+        if count == 0 {
+            if let Some((range, id)) = csv_puzzle(text, is_cell) {
+                field = range;
+                name = id.or(name);
+            }
+        }
+        let chars = text.char_indices().enumerate();
+        for (i, c) in chars.filter_map(|(i, (at, c))| field.contains(&at).then_some((i, c))) {
+            let letter = args.alphabet.and_then(|alphabet| alphabet.digit(c));
+            match letter.or_else(|| digit_value(c)) {
+                Some(val) => cells.push((val, None)),
+                None => match Parity::from_char(c) {
+                    Some(mark) if parse_parity => cells.push((0, Some(mark))),
+                    _ if is_blank(c) => cells.push((0, None)),
+                    _ if !args.strict_parse || c.is_whitespace() => {}
+                    _ => {
+                        stray.get_or_insert((i, c));
+                    }
+                },
+            }
+        }
+        if let Some((i, c)) = stray {
+            if skip_rows > 0 {
+                skip_rows -= usize::from(!cells.is_empty());
+                continue;
+            }
+            log::error!(
+                "{}",
+                lang.format("unexpected_character", &[&(n + 1), &(i + 1), &c])
+            );
+            print_error(
+                args.format,
+                "unexpected_character",
+                &format!("unexpected character `{}`", c),
+                vec![
+                    ("line".to_string(), (n + 1).into()),
+                    ("column".to_string(), (i + 1).into()),
+                    ("character".to_string(), c.to_string().into()),
+                ],
+            );
+            invalid = true;
+            // 按行读取时跳过这道题目余下的行
+            let first_row = count == 0 && !cells.is_empty() && cells.len() < 81;
+            if grid_rows && count > 0 || first_row {
+                skip_rows = 8 - count / 9;
+            }
+            count = 0;
+            name = None;
+            continue;
+        }
+        // 空行和分隔线
+        if cells.is_empty() {
+            continue;
+        }
+        if skip_rows > 0 {
+            skip_rows -= 1;
+            continue;
+        }
+        if count == 0 {
+            grid_rows = cells.len() < 81;
+            start = n + 1;
+        }
+        if grid_rows && cells.len() != 9 {
+            log::error!(
+                "{}",
+                lang.format("malformed_row", &[&(n + 1), &(count / 9 + 1), &cells.len()])
+            );
+            print_error(
+                args.format,
+                "malformed_row",
+                &format!("row has {} cells, expected 9", cells.len()),
+                vec![
+                    ("line".to_string(), (n + 1).into()),
+                    ("row".to_string(), (count / 9 + 1).into()),
+                    ("cells".to_string(), cells.len().into()),
+                ],
+            );
+            invalid = true;
+            skip_rows = 8 - count / 9;
+            count = 0;
+            name = None;
+            continue;
+        }
+        for (val, mark) in cells {
+            board[count / 9][count % 9] = val;
+            parity[count / 9][count % 9] = mark;
             count += 1;
             // 进行求解
             if count == 81 {
-                let ctx = Arc::new(ResolveCtx {
-                    sep: sep.clone(),
-                    all,
-                    total: AtomicUsize::new(0),
-                });
-                let board = SudokuBoard::new_with(&board);
-                let _ = thread_pool.install(|| resolve(ctx, board, vec![]));
+                let mut variant = variant.clone();
+                variant.parity = parity;
+                let label = Label {
+                    id: name.take(),
+                    line: Some(start),
+                };
+                invalid |= !solve(board, variant, label);
                 count = 0;
-                println!();
+                puzzles += 1;
+                if saved.elapsed() >= CHECKPOINT_INTERVAL {
+                    save(lines, puzzles, invalid);
+                    saved = Instant::now();
+                }
                 break;
             }
         }
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use crate::brute_force;
-
-    use super::SudokuBoard;
-
-    #[ctor::ctor]
-    fn init() {
-        env_logger::init();
-    }
-
-    #[test]
-    fn test_sudoku_1() {
-        let board = [
-            [0, 4, 0, 6, 1, 0, 9, 2, 5],
-            [0, 5, 1, 0, 0, 0, 7, 4, 6],
-            [9, 2, 6, 0, 0, 0, 8, 1, 3],
-            [0, 8, 0, 0, 5, 0, 0, 7, 1],
-            [0, 9, 0, 1, 0, 0, 0, 3, 2],
-            [0, 1, 3, 4, 7, 0, 5, 9, 8],
-            [0, 0, 0, 0, 0, 0, 1, 8, 9],
-            [1, 6, 2, 8, 0, 0, 3, 5, 7],
-            [8, 0, 9, 0, 0, 1, 2, 6, 4],
-        ];
-        let mut board = SudokuBoard::new_with(&board);
-        println!("{}", board);
-
-        let solved = board.solve();
-        println!("\n{}", board);
-        assert_eq!(solved, true);
-
-        let board2 = [
-            [7_u32, 4, 8, 6, 1, 3, 9, 2, 5],
-            [3, 5, 1, 9, 2, 8, 7, 4, 6],
-            [9, 2, 6, 7, 4, 5, 8, 1, 3],
-            [2, 8, 4, 3, 5, 9, 6, 7, 1],
-            [5, 9, 7, 1, 8, 6, 4, 3, 2],
-            [6, 1, 3, 4, 7, 2, 5, 9, 8],
-            [4, 3, 5, 2, 6, 7, 1, 8, 9],
-            [1, 6, 2, 8, 9, 4, 3, 5, 7],
-            [8, 7, 9, 5, 3, 1, 2, 6, 4],
-        ];
-        assert!(board == board2);
-    }
-
-    #[test]
-    fn test_sudoku_2() {
-        let board = [
-            [0, 4, 6, 9, 0, 3, 0, 0, 0],
-            [0, 0, 3, 0, 5, 0, 0, 6, 0],
-            [9, 0, 0, 0, 0, 2, 0, 0, 3],
-            [0, 0, 5, 0, 0, 6, 0, 0, 0],
-            [8, 0, 0, 0, 0, 0, 0, 1, 0],
-            [0, 1, 0, 7, 8, 0, 2, 0, 0],
-            [0, 0, 0, 0, 0, 0, 0, 5, 0],
-            [0, 8, 1, 3, 0, 0, 0, 0, 7],
-            [0, 0, 0, 8, 0, 0, 1, 0, 4],
-        ];
-        let mut board = SudokuBoard::new_with(&board);
-        println!("{}", board);
-
-        let mut solved = board.solve();
-        assert_eq!(solved, false);
-
-        // 尝试选择
-        let mut board2 = board.clone();
-        'outer: for row in 0..9 {
-            for col in 0..9 {
-                let pos = board.get(row, col);
-                if pos.val == 0 {
-                    for n in &pos.digits {
-                        board2 = board.clone();
-                        board2.set(*n, row, col);
-                        solved = board2.solve();
-                        if solved {
-                            break 'outer;
-                        }
-                    }
+    // 输入结束时还有未读完的题目
+    if count > 0 {
+        log::warn!("{}", lang.format("incomplete_puzzle", &[&count]));
+        print_error(
+            args.format,
+            "incomplete_puzzle",
+            "input ended with an incomplete puzzle",
+            vec![("digits".to_string(), count.into())],
+        );
+        let partial: String = (0..81)
+            .map(|i| {
+                let (row, col) = (i / 9, i % 9);
+                match parity[row][col] {
+                    _ if i >= count => '.',
+                    Some(mark) => mark.to_char(),
+                    None => char::from_digit(board[row][col], 10).unwrap(),
                 }
-            }
-        }
-
-        assert_eq!(solved, true);
-        let result = [
-            [1, 4, 6, 9, 7, 3, 5, 8, 2],
-            [7, 2, 3, 4, 5, 8, 9, 6, 1],
-            [9, 5, 8, 6, 1, 2, 4, 7, 3],
-            [3, 7, 5, 1, 2, 6, 8, 4, 9],
-            [8, 9, 2, 5, 3, 4, 7, 1, 6],
-            [6, 1, 4, 7, 8, 9, 2, 3, 5],
-            [4, 6, 7, 2, 9, 1, 3, 5, 8],
-            [2, 8, 1, 3, 4, 5, 6, 9, 7],
-            [5, 3, 9, 8, 6, 7, 1, 2, 4],
-        ];
-        assert!(board2 == result);
-        println!("{}", board2);
-    }
-
-    #[test]
-    fn test_sudoku_3() {
-        let mut board = [
-            [0, 4, 0, 6, 1, 0, 9, 2, 5],
-            [0, 5, 1, 0, 0, 0, 7, 4, 6],
-            [9, 2, 6, 0, 0, 0, 8, 1, 3],
-            [0, 8, 0, 0, 5, 0, 0, 7, 1],
-            [0, 9, 0, 1, 0, 0, 0, 3, 2],
-            [0, 1, 3, 4, 7, 0, 5, 9, 8],
-            [0, 0, 0, 0, 0, 0, 1, 8, 9],
-            [1, 6, 2, 8, 0, 0, 3, 5, 7],
-            [8, 0, 9, 0, 0, 1, 2, 6, 4],
-        ];
-        let mut empty = [[false; 9]; 9];
-        for i in 0..9 {
-            for j in 0..9 {
-                empty[i][j] = board[i][j] == 0;
-            }
-        }
-        let mut stack = Vec::with_capacity(81);
-        stack.push((0, 0));
-        let resolved = brute_force(&mut board, &empty, &mut stack);
-        assert!(resolved);
-
-        let board2 = [
-            [7_u32, 4, 8, 6, 1, 3, 9, 2, 5],
-            [3, 5, 1, 9, 2, 8, 7, 4, 6],
-            [9, 2, 6, 7, 4, 5, 8, 1, 3],
-            [2, 8, 4, 3, 5, 9, 6, 7, 1],
-            [5, 9, 7, 1, 8, 6, 4, 3, 2],
-            [6, 1, 3, 4, 7, 2, 5, 9, 8],
-            [4, 3, 5, 2, 6, 7, 1, 8, 9],
-            [1, 6, 2, 8, 9, 4, 3, 5, 7],
-            [8, 7, 9, 5, 3, 1, 2, 6, 4],
-        ];
-        assert_eq!(board, board2);
-
-        let resolved = brute_force(&mut board, &empty, &mut stack);
-        assert!(!resolved);
+            })
+            .collect();
+        log::debug!("partial puzzle (unread cells as '.'): {}", partial);
+    }
+    if lines < resumed.lines {
+        log::warn!(
+            "the input has fewer lines than the {} read before the checkpoint",
+            resumed.lines
+        );
+    }
+    save(lines, puzzles, invalid);
+    printer.flush();
+    if invalid {
+        process::exit(EXIT_INVALID);
+    }
+    if too_hard.get() {
+        process::exit(EXIT_TOO_HARD);
     }
 }
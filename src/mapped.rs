@@ -0,0 +1,154 @@
+//! 大文件的内存映射读取
+//!
+//! 几百 MB 的 sdm 文件按行缓冲读取时，每一行都要复制到新的 `String` 中。把文件映射到内存后
+//! 直接按换行切分，每一行都是映射上的切片。映射期间文件被其他进程截短时读取会收到 SIGBUS，
+//! 只用于读取题目文件。不支持映射的平台上退回到一次读入内存。
+
+use std::fs::File;
+use std::io::{self, Seek};
+use std::str;
+
+/// 映射到内存的文件，从打开时的读取位置到文件末尾
+pub struct Mapped {
+    data: Data,
+    /// 开始的位置，标准输入重定向自文件时可能已被读过一部分
+    start: usize,
+}
+
+enum Data {
+    #[cfg(unix)]
+    Map {
+        ptr: *mut libc::c_void,
+        len: usize,
+    },
+    Owned(Vec<u8>),
+}
+
+impl Mapped {
+    /// 映射文件；不是普通文件（管道、终端）时为 None，应按流读取
+    pub fn open(file: &File) -> io::Result<Option<Self>> {
+        let metadata = file.metadata()?;
+        if !metadata.is_file() {
+            return Ok(None);
+        }
+        let start = (&mut &*file).stream_position()? as usize;
+        let len = metadata.len() as usize;
+        let data = match len {
+            0 => Data::Owned(vec![]),
+            _ => map(file, len)?,
+        };
+        Ok(Some(Self {
+            data,
+            start: start.min(len),
+        }))
+    }
+
+    /// 文件的内容
+    pub fn bytes(&self) -> &[u8] {
+        let all = match &self.data {
+            #[cfg(unix)]
+            // SAFETY: 映射在 self 存续期间有效，长度为 len
+            Data::Map { ptr, len } => unsafe {
+                std::slice::from_raw_parts(*ptr as *const u8, *len)
+            },
+            Data::Owned(bytes) => bytes,
+        };
+        &all[self.start..]
+    }
+
+    /// 按行切分，与 [`BufRead::lines`](std::io::BufRead::lines) 相同：去掉行尾的 `\n` 或 `\r\n`，
+    /// 以换行结尾时没有最后的空行，不是 UTF-8 的行为错误
+    pub fn lines(&self) -> impl Iterator<Item = io::Result<&str>> {
+        let all = self.bytes();
+        let bytes = all.strip_suffix(b"\n").unwrap_or(all);
+        let lines = (!all.is_empty()).then(|| bytes.split(|b| *b == b'\n'));
+        lines.into_iter().flatten().map(|line| {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            str::from_utf8(line).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "stream did not contain valid UTF-8",
+                )
+            })
+        })
+    }
+}
+
+#[cfg(unix)]
+fn map(file: &File, len: usize) -> io::Result<Data> {
+    use std::os::unix::io::AsRawFd;
+
+    // SAFETY: 只读的私有映射，在 Drop 中解除
+    let ptr = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            len,
+            libc::PROT_READ,
+            libc::MAP_PRIVATE,
+            file.as_raw_fd(),
+            0,
+        )
+    };
+    if ptr == libc::MAP_FAILED {
+        return Err(io::Error::last_os_error());
+    }
+    // 只是提示内核顺序预读，失败也无妨
+    // SAFETY: 范围正是刚建立的映射
+    unsafe { libc::madvise(ptr, len, libc::MADV_SEQUENTIAL) };
+    Ok(Data::Map { ptr, len })
+}
+
+#[cfg(not(unix))]
+fn map(mut file: &File, len: usize) -> io::Result<Data> {
+    use std::io::Read;
+
+    let mut bytes = Vec::with_capacity(len);
+    file.rewind()?;
+    file.read_to_end(&mut bytes)?;
+    Ok(Data::Owned(bytes))
+}
+
+impl Drop for Mapped {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Data::Map { ptr, len } = self.data {
+            // SAFETY: ptr 和 len 来自成功的 mmap，之后不再使用
+            unsafe { libc::munmap(ptr, len) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::{BufRead, Read};
+    use std::process;
+
+    use super::Mapped;
+
+    #[test]
+    fn test_lines() {
+        let path = env::temp_dir().join(format!("superdo-mapped-{}.sdm", process::id()));
+        for text in ["", "\n", "a", "a\nb", "a\r\nb\n", "a\n\nb\n\n", "数独\n"] {
+            fs::write(&path, text).unwrap();
+            let mapped = Mapped::open(&File::open(&path).unwrap()).unwrap().unwrap();
+            let lines: Vec<&str> = mapped.lines().map(Result::unwrap).collect();
+            let expected: Vec<String> = text.as_bytes().lines().map(Result::unwrap).collect();
+            assert_eq!(lines, expected, "{:?}", text);
+        }
+
+        // 从已读过的位置开始
+        fs::write(&path, "skip\nkeep\n").unwrap();
+        let mut file = File::open(&path).unwrap();
+        file.read_exact(&mut [0; 5]).unwrap();
+        let mapped = Mapped::open(&file).unwrap().unwrap();
+        assert_eq!(mapped.bytes(), b"keep\n");
+
+        fs::write(&path, b"ok\n\xff\n").unwrap();
+        let mapped = Mapped::open(&File::open(&path).unwrap()).unwrap().unwrap();
+        let lines: Vec<_> = mapped.lines().collect();
+        assert!(lines[0].is_ok() && lines[1].is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
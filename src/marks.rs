@@ -0,0 +1,153 @@
+//! 笔记标记
+//!
+//! 按照变体解题者常用的标注方式，角标使用 Snyder 记法：某数字在 3x3 小格中只剩两个位置时，
+//! 在这两个位置标注；中心标记列出该位置剩余的全部候选数字。
+
+use std::fmt;
+
+use crate::constraint::Unit;
+use crate::json::Json;
+use crate::SudokuBoard;
+
+/// 棋盘上的笔记标记
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PencilMarks {
+    /// 已填数字，0 表示空位
+    pub values: [[u32; 9]; 9],
+    /// 角标（Snyder 记法）
+    pub corner: [[Vec<u32>; 9]; 9],
+    /// 中心标记
+    pub center: [[Vec<u32>; 9]; 9],
+}
+
+/// 每个位置输出的宽度，足够放下 9 个候选数字
+const WIDTH: usize = 9;
+
+impl PencilMarks {
+    /// 由棋盘当前的候选数字得到标记
+    pub fn from_board(board: &SudokuBoard) -> Self {
+        let mut marks = Self {
+            values: board.grid(),
+            corner: Default::default(),
+            center: Default::default(),
+        };
+        for row in 0..9 {
+            for col in 0..9 {
+                let pos = board.get(row, col);
+                if pos.val == 0 {
+                    let mut digits: Vec<u32> = pos.digits.iter().cloned().collect();
+                    digits.sort();
+                    marks.center[row][col] = digits;
+                }
+            }
+        }
+        for n in 0..9 {
+            let cells = Unit::block(n).cells;
+            for digit in 1..10 {
+                let spots: Vec<_> = cells
+                    .iter()
+                    .filter(|&&(r, c)| {
+                        let pos = board.get(r, c);
+                        pos.val == 0 && pos.digits.contains(&digit)
+                    })
+                    .collect();
+                let placed = cells.iter().any(|&(r, c)| board.get(r, c).val == digit);
+                if spots.len() == 2 && !placed {
+                    for &&(r, c) in &spots {
+                        marks.corner[r][c].push(digit);
+                    }
+                }
+            }
+        }
+        marks
+    }
+
+    /// 输出为 JSON，已填位置只有 value，空位给出 corner、center
+    pub fn to_json(&self) -> Json {
+        let mut cells = vec![];
+        for row in 0..9 {
+            for col in 0..9 {
+                let mut items = vec![(
+                    "cell".to_string(),
+                    Json::from(format!("r{}c{}", row + 1, col + 1)),
+                )];
+                match self.values[row][col] {
+                    0 => {
+                        items.push(("corner".to_string(), self.corner[row][col].clone().into()));
+                        items.push(("center".to_string(), self.center[row][col].clone().into()));
+                    }
+                    val => items.push(("value".to_string(), val.into())),
+                }
+                cells.push(Json::Object(items));
+            }
+        }
+        Json::Array(cells)
+    }
+}
+
+/// 以文本网格输出：每个位置两行，第一行为角标，第二行为中心标记，已填数字显示为 `[5]`
+impl fmt::Display for PencilMarks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let border = format!("+{}", format!("{}+", "-".repeat(WIDTH * 3 + 4)).repeat(3));
+        let join = |digits: &[u32]| digits.iter().map(|d| d.to_string()).collect::<String>();
+        writeln!(f, "{}", border)?;
+        for row in 0..9 {
+            let mut top = String::from("|");
+            let mut bottom = String::from("|");
+            for col in 0..9 {
+                let (corner, center) = match self.values[row][col] {
+                    0 => (join(&self.corner[row][col]), join(&self.center[row][col])),
+                    val => (String::new(), format!("[{}]", val)),
+                };
+                top.push_str(&format!(" {:<WIDTH$}", corner));
+                bottom.push_str(&format!(" {:^WIDTH$}", center));
+                if col % 3 == 2 {
+                    top.push_str(" |");
+                    bottom.push_str(" |");
+                }
+            }
+            writeln!(f, "{}", top)?;
+            writeln!(f, "{}", bottom)?;
+            if row % 3 == 2 {
+                writeln!(f, "{}", border)?;
+            } else {
+                writeln!(
+                    f,
+                    "|{}",
+                    format!("{}|", " ".repeat(WIDTH * 3 + 4)).repeat(3)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::PencilMarks;
+    use crate::SudokuBoard;
+
+    #[test]
+    fn test_pencil_marks() {
+        let mut grid = [[0; 9]; 9];
+        grid[0] = [1, 2, 3, 4, 5, 6, 7, 0, 0];
+        grid[1] = [4, 5, 6, 0, 0, 0, 0, 0, 0];
+        grid[2] = [7, 0, 0, 0, 0, 0, 0, 0, 0];
+        let board = SudokuBoard::new_with(&grid, Arc::default());
+        let marks = PencilMarks::from_board(&board);
+
+        assert_eq!(marks.center[0][7], vec![8, 9]);
+        // 第一个小格中 8、9 只剩 (2,1)、(2,2) 两个位置
+        assert_eq!(marks.corner[2][1], vec![8, 9]);
+        assert!(marks.corner[0][0].is_empty());
+
+        let text = marks.to_string();
+        assert!(text.contains("[1]"));
+        assert_eq!(text.lines().count(), 28);
+        let json = marks.to_json().to_string();
+        assert!(json.starts_with(r#"[{"cell":"r1c1","value":1}"#));
+        assert!(json.contains(r#"{"cell":"r1c8","corner":[],"center":[8,9]}"#));
+    }
+}
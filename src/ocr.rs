@@ -0,0 +1,308 @@
+//! 从截图中识别题目
+//!
+//! 按 Otsu 阈值二值化后，由暗像素最多的行和列找出网格的外框，均分为 9x9 个格子。
+//! 格子中心的笔画缩放到 5x7 点阵，与内置的数字模板逐一比较，取最接近的一个。
+//! 最接近的模板差距较大或与次接近的模板难以区分时，该格记为不确定。
+
+use crate::board::Grid;
+use crate::constraint::Cell;
+use crate::image::Image;
+
+/// 1 到 9 的 5x7 点阵模板
+const TEMPLATES: [[&str; 7]; 9] = [
+    [
+        "..#..", ".##..", "..#..", "..#..", "..#..", "..#..", ".###.",
+    ],
+    [
+        ".###.", "#...#", "....#", "...#.", "..#..", ".#...", "#####",
+    ],
+    [
+        "#####", "...#.", "..#..", "...#.", "....#", "#...#", ".###.",
+    ],
+    [
+        "...#.", "..##.", ".#.#.", "#..#.", "#####", "...#.", "...#.",
+    ],
+    [
+        "#####", "#....", "####.", "....#", "....#", "#...#", ".###.",
+    ],
+    [
+        "..##.", ".#...", "#....", "####.", "#...#", "#...#", ".###.",
+    ],
+    [
+        "#####", "....#", "...#.", "..#..", ".#...", ".#...", ".#...",
+    ],
+    [
+        ".###.", "#...#", "#...#", ".###.", "#...#", "#...#", ".###.",
+    ],
+    [
+        ".###.", "#...#", "#...#", ".####", "....#", "...#.", ".##..",
+    ],
+];
+
+/// 网格线所在的行（列）中暗像素至少占最多的一行（列）的比例
+const LINE_RATIO: f64 = 0.6;
+
+/// 格子四周略去的比例，避开网格线
+const CELL_MARGIN: f64 = 0.08;
+
+/// 格子中暗像素至少占这个比例的行（列）是网格线的残留，不算作笔画
+const RESIDUE_RATIO: f64 = 0.8;
+
+/// 暗像素少于格子中心面积的这个比例时视为空格
+const EMPTY_RATIO: f64 = 0.02;
+
+/// 与最接近模板的差距超过此值时不确定
+const MAX_DISTANCE: f64 = 0.15;
+
+/// 最接近与次接近的模板差距之差小于此值时不确定
+const MIN_MARGIN: f64 = 0.03;
+
+/// 识别结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recognized {
+    /// 识别出的题目，0 为空格
+    pub grid: Grid,
+    /// 识别不确定的格子
+    pub uncertain: Vec<Cell>,
+}
+
+/// 识别截图中的题目，找不到网格时返回错误
+pub fn recognize(image: &Image) -> Result<Recognized, String> {
+    let threshold = otsu(image);
+    let dark = |x: usize, y: usize| image.get(x, y) < threshold;
+    let rows: Vec<usize> = (0..image.height)
+        .map(|y| (0..image.width).filter(|&x| dark(x, y)).count())
+        .collect();
+    let cols: Vec<usize> = (0..image.width)
+        .map(|x| (0..image.height).filter(|&y| dark(x, y)).count())
+        .collect();
+    let not_found = || "no sudoku grid found in the image".to_string();
+    let (top, bottom) = lines(&rows).ok_or_else(not_found)?;
+    let (left, right) = lines(&cols).ok_or_else(not_found)?;
+    let (width, height) = ((right - left) as f64, (bottom - top) as f64);
+    // 网格大致为正方形，每格至少几个像素
+    if width < 45.0 || height < 45.0 || !(0.8..1.25).contains(&(width / height)) {
+        return Err(not_found());
+    }
+    let mut recognized = Recognized {
+        grid: [[0; 9]; 9],
+        uncertain: vec![],
+    };
+    for row in 0..9 {
+        for col in 0..9 {
+            let x0 = left as f64 + width * (col as f64 + CELL_MARGIN) / 9.0;
+            let x1 = left as f64 + width * (col as f64 + 1.0 - CELL_MARGIN) / 9.0;
+            let y0 = top as f64 + height * (row as f64 + CELL_MARGIN) / 9.0;
+            let y1 = top as f64 + height * (row as f64 + 1.0 - CELL_MARGIN) / 9.0;
+            let (xs, ys) = (
+                x0.ceil() as usize..x1 as usize,
+                y0.ceil() as usize..y1 as usize,
+            );
+            let residue = |n: usize, len: usize| n as f64 >= len as f64 * RESIDUE_RATIO;
+            let line_rows = edges(
+                ys.clone()
+                    .map(|y| residue(xs.clone().filter(|&x| dark(x, y)).count(), xs.len()))
+                    .collect(),
+            );
+            let line_cols = edges(
+                xs.clone()
+                    .map(|x| residue(ys.clone().filter(|&y| dark(x, y)).count(), ys.len()))
+                    .collect(),
+            );
+            let mut ink = vec![];
+            for x in xs.clone() {
+                for y in ys.clone() {
+                    if dark(x, y) && !line_cols[x - xs.start] && !line_rows[y - ys.start] {
+                        ink.push((x, y));
+                    }
+                }
+            }
+            if (ink.len() as f64) < (x1 - x0) * (y1 - y0) * EMPTY_RATIO {
+                continue;
+            }
+            let (digit, sure) = classify(&ink);
+            recognized.grid[row][col] = digit;
+            if !sure {
+                recognized.uncertain.push((row, col));
+            }
+        }
+    }
+    Ok(recognized)
+}
+
+/// 使类间方差最大的阈值，低于阈值的像素为暗
+pub(crate) fn otsu(image: &Image) -> u8 {
+    let mut histogram = [0usize; 256];
+    for &v in &image.pixels {
+        histogram[v as usize] += 1;
+    }
+    let total = image.pixels.len() as f64;
+    let sum: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(v, &n)| v as f64 * n as f64)
+        .sum();
+    let (mut best, mut threshold) = (0.0, 128);
+    let (mut count, mut partial) = (0.0, 0.0);
+    for (v, &n) in histogram.iter().enumerate() {
+        count += n as f64;
+        partial += v as f64 * n as f64;
+        if count == 0.0 || count == total {
+            continue;
+        }
+        let (mean_dark, mean_light) = (partial / count, (sum - partial) / (total - count));
+        let variance = count * (total - count) * (mean_dark - mean_light).powi(2);
+        if variance > best {
+            best = variance;
+            threshold = v as u8 + 1;
+        }
+    }
+    threshold
+}
+
+/// 只保留从两端开始连续的标记，网格线的残留只会出现在格子边上
+fn edges(mut marks: Vec<bool>) -> Vec<bool> {
+    let start = marks.iter().position(|&m| !m).unwrap_or(marks.len());
+    let end = marks.iter().rposition(|&m| !m).map_or(0, |i| i + 1);
+    for mark in marks.iter_mut().take(end).skip(start) {
+        *mark = false;
+    }
+    marks
+}
+
+/// 暗像素足够多的第一行和最后一行
+fn lines(profile: &[usize]) -> Option<(usize, usize)> {
+    let max = *profile.iter().max()?;
+    if max == 0 {
+        return None;
+    }
+    let is_line = |&n: &usize| n as f64 >= max as f64 * LINE_RATIO;
+    let first = profile.iter().position(is_line)?;
+    let last = profile.iter().rposition(is_line)?;
+    Some((first, last))
+}
+
+/// 把笔画缩放到 5x7 点阵后与模板比较，返回最接近的数字和是否确定
+fn classify(ink: &[(usize, usize)]) -> (u32, bool) {
+    let x0 = ink.iter().map(|p| p.0).min().unwrap();
+    let x1 = ink.iter().map(|p| p.0).max().unwrap() + 1;
+    let y0 = ink.iter().map(|p| p.1).min().unwrap();
+    let y1 = ink.iter().map(|p| p.1).max().unwrap() + 1;
+    // 数字 1 这样窄的笔画居中放到 5:7 的框中，不拉伸
+    let (h, w) = ((y1 - y0) as f64, (x1 - x0) as f64);
+    let w = w.max(h * 5.0 / 7.0);
+    let left = (x0 + x1) as f64 / 2.0 - w / 2.0;
+    // 每个像素按中心落在的点计入
+    let dot = |x: usize, y: usize| {
+        let r = ((y - y0) as f64 + 0.5) * 7.0 / h;
+        let c = (x as f64 + 0.5 - left) * 5.0 / w;
+        (r as usize, (c as usize).min(4))
+    };
+    let (mut dark, mut area) = ([[0.0; 5]; 7], [[0.0; 5]; 7]);
+    for y in y0..y1 {
+        for x in (left.max(0.0) as usize)..(left + w).ceil() as usize {
+            let (r, c) = dot(x, y);
+            area[r][c] += 1.0;
+        }
+    }
+    for &(x, y) in ink {
+        let (r, c) = dot(x, y);
+        dark[r][c] += 1.0;
+    }
+    let mut sampled = [[0.0; 5]; 7];
+    for r in 0..7 {
+        for c in 0..5 {
+            sampled[r][c] = dark[r][c] / f64::max(area[r][c], 1.0);
+        }
+    }
+    let mut distances: Vec<(f64, u32)> = TEMPLATES
+        .iter()
+        .zip(1..)
+        .map(|(template, digit)| {
+            let mut d = 0.0;
+            for (line, row) in template.iter().zip(&sampled) {
+                for (c, v) in line.bytes().zip(row) {
+                    let expected = if c == b'#' { 1.0 } else { 0.0 };
+                    d += (v - expected) * (v - expected);
+                }
+            }
+            (d / 35.0, digit)
+        })
+        .collect();
+    distances.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let (best, second) = (distances[0], distances[1]);
+    (
+        best.1,
+        best.0 <= MAX_DISTANCE && second.0 - best.0 >= MIN_MARGIN,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{recognize, TEMPLATES};
+    use crate::board::parse_grid;
+    use crate::image::Image;
+
+    const PUZZLE: &str =
+        "530070000600195000098000060800060003400803001700020006060000280000419005000080079";
+
+    /// 画出网格和数字，每格 size 像素，数字的每个点为 dot 像素
+    fn draw(puzzle: &str, size: usize, dot: usize) -> Image {
+        let margin = 30;
+        let mut image = Image::new(size * 9 + margin * 2, size * 9 + margin * 2 + 40);
+        // 网格上方的标题文字
+        for x in margin..margin + 100 {
+            for y in 10..20 {
+                if x % 7 < 4 {
+                    image.set(x, y, 40);
+                }
+            }
+        }
+        let top = margin + 40;
+        for i in 0..=9 {
+            let thick = if i % 3 == 0 { 3 } else { 1 };
+            for t in 0..thick {
+                for p in 0..size * 9 + 2 {
+                    image.set(margin + p, top + i * size + t, 0);
+                    image.set(margin + i * size + t, top + p, 0);
+                }
+            }
+        }
+        let grid = parse_grid(puzzle).unwrap();
+        for (row, line) in grid.iter().enumerate() {
+            for (col, &digit) in line.iter().enumerate() {
+                if digit == 0 {
+                    continue;
+                }
+                let x = margin + col * size + (size - 5 * dot) / 2;
+                let y = top + row * size + (size - 7 * dot) / 2;
+                for (r, bits) in TEMPLATES[digit as usize - 1].iter().enumerate() {
+                    for (c, bit) in bits.bytes().enumerate() {
+                        if bit != b'#' {
+                            continue;
+                        }
+                        for dy in 0..dot {
+                            for dx in 0..dot {
+                                image.set(x + c * dot + dx, y + r * dot + dy, 20);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn test_recognize() {
+        for (size, dot) in [(40, 4), (24, 2)] {
+            let recognized = recognize(&draw(PUZZLE, size, dot)).unwrap();
+            assert_eq!(recognized.grid, parse_grid(PUZZLE).unwrap(), "{}", size);
+            assert_eq!(recognized.uncertain, vec![]);
+        }
+        let every = "123456789".repeat(9);
+        let recognized = recognize(&draw(&every, 30, 3)).unwrap();
+        assert_eq!(recognized.grid, parse_grid(&every).unwrap());
+        assert!(recognize(&Image::new(200, 200)).is_err());
+    }
+}
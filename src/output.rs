@@ -0,0 +1,156 @@
+//! 解的输出
+//!
+//! 并行搜索的各个分支都可能找到解。[`Printer`] 把要输出的文本经通道交给唯一的写线程，
+//! 每条记录整体写出，不同分支的输出不会交错；也可以暂存一道题目的解，排序后再写出，
+//! 或者只写出 key 不同的记录。
+
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+/// 写线程收到的消息
+enum Event {
+    /// 一条完整的记录，排序时按 key 的顺序写出
+    Record { key: String, text: String },
+    /// 写出暂存的记录并刷新，然后应答
+    Flush(Sender<()>),
+}
+
+/// 输出到同一个写线程，可以在线程间共享和克隆；所有克隆都丢弃后写线程退出
+#[derive(Debug, Clone)]
+pub struct Printer {
+    tx: Sender<Event>,
+}
+
+impl Printer {
+    /// 写到 out。ordered 为真时暂存记录，到 [`flush`](Self::flush) 时按 key 排序写出；
+    /// dedupe 为真时丢弃 key 与此前的记录相同的记录
+    pub fn new<W: Write + Send + 'static>(mut out: W, ordered: bool, dedupe: bool) -> Self {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut pending = vec![];
+            let mut seen = HashSet::new();
+            // 写入失败（如管道已关闭）后丢弃余下的输出
+            let mut failed = false;
+            let mut write = |out: &mut W, text: &str| {
+                if !failed {
+                    if let Err(e) = out.write_all(text.as_bytes()) {
+                        log::error!("writing output: {}", e);
+                        failed = true;
+                    }
+                }
+            };
+            for event in rx {
+                match event {
+                    Event::Record { key, .. } if dedupe && !seen.insert(key.clone()) => {}
+                    Event::Record { key, text } if ordered => pending.push((key, text)),
+                    Event::Record { text, .. } => write(&mut out, &text),
+                    Event::Flush(ack) => {
+                        pending.sort();
+                        for (_, text) in pending.drain(..) {
+                            write(&mut out, &text);
+                        }
+                        let _ = out.flush();
+                        let _ = ack.send(());
+                    }
+                }
+            }
+        });
+        Self { tx }
+    }
+
+    /// 写到标准输出
+    pub fn stdout(ordered: bool, dedupe: bool) -> Self {
+        Self::new(io::stdout(), ordered, dedupe)
+    }
+
+    /// 输出一条记录，text 含末尾的换行
+    pub fn print(&self, key: String, text: String) {
+        let _ = self.tx.send(Event::Record { key, text });
+    }
+
+    /// 等到此前的记录都已写出
+    pub fn flush(&self) {
+        let (ack, done) = mpsc::channel();
+        if self.tx.send(Event::Flush(ack)).is_ok() {
+            let _ = done.recv();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use super::Printer;
+
+    /// 可在测试中读取的输出
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Shared {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_printer() {
+        // 多个线程同时输出多行的记录，每条记录保持完整
+        let out = Shared::default();
+        let printer = Printer::new(out.clone(), false, false);
+        thread::scope(|s| {
+            for t in 0..8 {
+                let printer = printer.clone();
+                s.spawn(move || {
+                    for i in 0..100 {
+                        printer.print(String::new(), format!("{0}-{1}\n{0}-{1}\n", t, i));
+                    }
+                });
+            }
+        });
+        printer.flush();
+        let text = out.text();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 1600);
+        assert!(lines.chunks(2).all(|pair| pair[0] == pair[1]));
+
+        // 排序时到 flush 才写出
+        let out = Shared::default();
+        let printer = Printer::new(out.clone(), true, false);
+        for key in ["c", "a", "b"] {
+            printer.print(key.to_string(), format!("{}\n", key));
+        }
+        assert_eq!(out.text(), "");
+        printer.flush();
+        assert_eq!(out.text(), "a\nb\nc\n");
+        printer.print("0".to_string(), "0\n".to_string());
+        printer.flush();
+        assert_eq!(out.text(), "a\nb\nc\n0\n");
+
+        // 去重在排序之前，跨过 flush 也有效
+        let out = Shared::default();
+        let printer = Printer::new(out.clone(), true, true);
+        for key in ["b", "a", "b"] {
+            printer.print(key.to_string(), format!("{}\n", key));
+        }
+        printer.flush();
+        printer.print("a".to_string(), "a\n".to_string());
+        printer.flush();
+        assert_eq!(out.text(), "a\nb\n");
+    }
+}
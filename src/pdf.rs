@@ -0,0 +1,208 @@
+//! 生成简单的 PDF 文档
+//!
+//! 只支持线条、矩形和内置 Helvetica 字体的文字，足够排版题目册。坐标以点（1/72 英寸）为单位，
+//! 原点在页面左上角，写入时再换算为 PDF 的左下角原点。
+
+use std::fmt::Write;
+
+/// A4 纸的宽度
+pub const A4_WIDTH: f64 = 595.0;
+/// A4 纸的高度
+pub const A4_HEIGHT: f64 = 842.0;
+
+/// Helvetica 中数字的宽度（相对字号）
+const DIGIT_WIDTH: f64 = 0.556;
+
+/// 一页的绘图指令
+#[derive(Debug, Clone)]
+pub struct Page {
+    /// 页面高度，用于换算纵坐标
+    height: f64,
+    /// 内容流
+    content: String,
+}
+
+impl Page {
+    /// 线段
+    pub fn line(&mut self, from: (f64, f64), to: (f64, f64), width: f64) {
+        let _ = writeln!(
+            self.content,
+            "{:.2} w {:.2} {:.2} m {:.2} {:.2} l S",
+            width,
+            from.0,
+            self.height - from.1,
+            to.0,
+            self.height - to.1
+        );
+    }
+
+    /// 以灰度填充的矩形，gray 为 0（黑）到 1（白）
+    pub fn fill_rect(&mut self, (x, y): (f64, f64), (w, h): (f64, f64), gray: f64) {
+        let _ = writeln!(
+            self.content,
+            "{:.2} g {:.2} {:.2} {:.2} {:.2} re f 0 g",
+            gray,
+            x,
+            self.height - y - h,
+            w,
+            h
+        );
+    }
+
+    /// 文字，(x, y) 为基线左端
+    pub fn text(&mut self, (x, y): (f64, f64), size: f64, text: &str) {
+        let escaped = text
+            .replace('\\', "\\\\")
+            .replace('(', "\\(")
+            .replace(')', "\\)");
+        let _ = writeln!(
+            self.content,
+            "BT /F1 {:.2} Tf {:.2} {:.2} Td ({}) Tj ET",
+            size,
+            x,
+            self.height - y,
+            escaped
+        );
+    }
+
+    /// 以 (x, y) 为中心写一个数字，gray 为灰度
+    pub fn digit(&mut self, (x, y): (f64, f64), size: f64, digit: u32, gray: f64) {
+        let _ = writeln!(self.content, "{:.2} g", gray);
+        // 数字高度约为字号的 0.7
+        self.text(
+            (x - size * DIGIT_WIDTH / 2.0, y + size * 0.35),
+            size,
+            &digit.to_string(),
+        );
+        self.content.push_str("0 g\n");
+    }
+}
+
+/// PDF 文档
+#[derive(Debug, Clone)]
+pub struct Document {
+    /// 页面宽度
+    width: f64,
+    /// 页面高度
+    height: f64,
+    /// 所有页面
+    pages: Vec<Page>,
+}
+
+impl Document {
+    /// 指定页面大小的空文档
+    pub fn new(width: f64, height: f64) -> Self {
+        Self {
+            width,
+            height,
+            pages: vec![],
+        }
+    }
+
+    /// 增加一页并返回该页
+    pub fn add_page(&mut self) -> &mut Page {
+        self.pages.push(Page {
+            height: self.height,
+            content: String::new(),
+        });
+        self.pages.last_mut().unwrap()
+    }
+
+    /// 页数
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// 是否没有页面
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    /// 输出为 PDF 文件内容
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // 对象编号：1 目录，2 页面树，3 字体，之后每页依次为页面和内容流
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                (0..self.pages.len())
+                    .map(|i| format!("{} 0 R", 4 + i * 2))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                self.pages.len()
+            ),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+        ];
+        for (i, page) in self.pages.iter().enumerate() {
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+                self.width,
+                self.height,
+                5 + i * 2
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{}endstream",
+                page.content.len(),
+                page.content
+            ));
+        }
+
+        let mut out = String::from("%PDF-1.4\n");
+        let mut offsets = vec![];
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            let _ = write!(out, "{} 0 obj\n{}\nendobj\n", i + 1, object);
+        }
+        let xref = out.len();
+        let _ = writeln!(out, "xref\n0 {}\n0000000000 65535 f ", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(out, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            out,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        );
+        out.into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Document, A4_HEIGHT, A4_WIDTH};
+
+    #[test]
+    fn test_document() {
+        let mut doc = Document::new(A4_WIDTH, A4_HEIGHT);
+        let page = doc.add_page();
+        page.line((10.0, 10.0), (100.0, 10.0), 1.0);
+        page.text((10.0, 30.0), 12.0, "Puzzle (1)");
+        doc.add_page().digit((50.0, 50.0), 20.0, 7, 0.0);
+        assert_eq!(doc.len(), 2);
+
+        let bytes = doc.to_bytes();
+        let text = String::from_utf8(bytes).unwrap();
+        assert!(text.starts_with("%PDF-1.4\n"));
+        assert!(text.ends_with("%%EOF\n"));
+        assert!(text.contains("/Count 2"));
+        assert!(text.contains(r"(Puzzle \(1\)) Tj"));
+        assert!(text.contains("10.00 832.00 m 100.00 832.00 l S"));
+
+        // xref 中的偏移指向各个对象
+        let xref: usize = text
+            .rsplit("startxref\n")
+            .next()
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(text[xref..].starts_with("xref"));
+        let first = text[xref..].lines().nth(3).unwrap();
+        let offset: usize = first[..10].parse().unwrap();
+        assert!(text[offset..].starts_with("1 0 obj"));
+    }
+}
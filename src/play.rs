@@ -0,0 +1,764 @@
+//! 终端中的游戏
+//!
+//! `superdo play` 的前端：在终端中显示棋盘，按键经 [`Keymap`] 转为 [`Action`]，修改交给
+//! [`Session`] 执行，可撤销、重做。每次修改后都把进度写入文件，关闭终端后可以接着玩。
+//! 画面上显示已用时间和提示次数，解出后显示分数，成绩记入 [`BestTimes`]。按
+//! [`CheckMode`] 检查时，填错的数字和无数可填的空位以醒目的样式显示。候选数字标记可以在
+//! 自动和手动维护之间切换，见 [`MarkMode`]。
+
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::constraint::Cell;
+use crate::history::Edit;
+use crate::keys::{Action, Key, Keymap};
+use crate::scores::{BestTimes, HINT_PENALTY};
+use crate::session::{CheckMode, MarkMode, Session};
+use crate::theme::{Role, Theme};
+
+/// 清屏并移到左上角
+const CLEAR: &str = "\x1b[H\x1b[2J";
+
+/// 没有修改时，至少每隔这么久保存一次进度，记下已用时间
+const AUTOSAVE: Duration = Duration::from_secs(15);
+
+/// 处理按键之后是否继续
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    Quit,
+}
+
+/// 一局游戏的前端状态
+#[derive(Debug)]
+pub struct Game {
+    session: Session,
+    keymap: Keymap,
+    /// 配色，None 时不用颜色
+    theme: Option<Theme>,
+    /// 光标位置
+    cursor: Cell,
+    /// 数字键是否标注候选数字
+    pencil: bool,
+    /// 进度文件，None 时不保存
+    path: Option<PathBuf>,
+    /// 上次保存的时刻
+    saved: Instant,
+    /// 状态行的消息
+    message: String,
+    /// 最佳用时
+    best_times: BestTimes,
+    /// 最佳用时的记录文件，None 时不保存
+    best_path: Option<PathBuf>,
+    /// 本局的成绩是否已记入最佳用时，继续已解出的一局时不再记入
+    recorded: bool,
+}
+
+impl Game {
+    /// 开始或继续一局游戏
+    pub fn new(
+        session: Session,
+        keymap: Keymap,
+        theme: Option<Theme>,
+        path: Option<PathBuf>,
+    ) -> Self {
+        let recorded = session.is_solved();
+        Self {
+            session,
+            keymap,
+            theme,
+            cursor: (0, 0),
+            pencil: false,
+            path,
+            saved: Instant::now(),
+            message: String::new(),
+            best_times: BestTimes::default(),
+            best_path: None,
+            recorded,
+        }
+    }
+
+    /// 设置最佳用时及其记录文件，解出后记入
+    pub fn set_best_times(&mut self, best_times: BestTimes, path: Option<PathBuf>) {
+        self.best_times = best_times;
+        self.best_path = path;
+    }
+
+    /// 最佳用时
+    pub fn best_times(&self) -> &BestTimes {
+        &self.best_times
+    }
+
+    /// 当前的一局
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// 光标位置
+    pub fn cursor(&self) -> Cell {
+        self.cursor
+    }
+
+    /// 状态行的消息
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// 处理一个按键，没有绑定的按键被忽略。Ctrl-C 总是保存并退出
+    pub fn key(&mut self, key: Key) -> Flow {
+        match self.keymap.action(key) {
+            Some(action) => self.act(action),
+            None if key == Key::Ctrl('c') => self.act(Action::Quit),
+            None => Flow::Continue,
+        }
+    }
+
+    /// 执行一个操作
+    pub fn act(&mut self, action: Action) -> Flow {
+        self.message.clear();
+        let (row, col) = self.cursor;
+        let edited = match action {
+            Action::Up => self.move_by(-1, 0),
+            Action::Down => self.move_by(1, 0),
+            Action::Left => self.move_by(0, -1),
+            Action::Right => self.move_by(0, 1),
+            Action::Pencil => {
+                self.pencil = !self.pencil;
+                false
+            }
+            Action::Marks => {
+                self.toggle_marks();
+                false
+            }
+            Action::Digit(val) => self.digit(val),
+            Action::Clear => self.edit(Edit::Unassign { row, col }),
+            Action::Undo => match self.session.undo() {
+                Some(edit) => {
+                    self.message = format!("undid {}", edit);
+                    true
+                }
+                None => {
+                    self.message = "nothing to undo".to_string();
+                    false
+                }
+            },
+            Action::Redo => match self.session.redo() {
+                Ok(Some(edit)) => {
+                    self.message = format!("redid {}", edit);
+                    true
+                }
+                Ok(None) => {
+                    self.message = "nothing to redo".to_string();
+                    false
+                }
+                Err(e) => {
+                    self.message = e;
+                    false
+                }
+            },
+            Action::Hint => {
+                self.hint();
+                false
+            }
+            Action::Save => {
+                self.save();
+                false
+            }
+            Action::Quit => {
+                self.session.pause();
+                self.save();
+                return Flow::Quit;
+            }
+        };
+        if edited {
+            if self.session.is_solved() {
+                self.solved();
+            } else {
+                self.session.resume();
+            }
+            self.save();
+        }
+        Flow::Continue
+    }
+
+    /// 切换候选数字标记的维护方式，之前的修改不能再撤销
+    fn toggle_marks(&mut self) {
+        let marks = match self.session.mark_mode() {
+            MarkMode::Auto => MarkMode::Manual,
+            MarkMode::Manual => MarkMode::Auto,
+        };
+        self.session.set_mark_mode(marks);
+        self.message = format!("{} marks; undo history cleared", marks.name());
+        self.save();
+    }
+
+    /// 提示：光标移到最容易填出的空位，显示填入的数字和所用技巧
+    fn hint(&mut self) {
+        let Some(hint) = self.session.hint() else {
+            self.message = "no empty cell left".to_string();
+            return;
+        };
+        self.cursor = hint.cell;
+        let (row, col) = hint.cell;
+        self.message = match (hint.digit, hint.technique) {
+            (Some(digit), Some(technique)) => format!(
+                "hint: r{}c{} is {} by {}",
+                row + 1,
+                col + 1,
+                digit,
+                technique.name()
+            ),
+            _ => format!(
+                "hint: r{}c{} has the fewest candidates ({})",
+                row + 1,
+                col + 1,
+                hint.candidates
+            ),
+        };
+        let _ = write!(self.message, " (+{}s)", HINT_PENALTY.as_secs());
+    }
+
+    /// 解出：停止计时，显示分数，第一次解出时记入最佳用时
+    fn solved(&mut self) {
+        self.session.pause();
+        let time = self.session.best_time();
+        self.message = format!(
+            "solved in {} with {} hint(s), score {}",
+            clock(self.session.elapsed()),
+            time.hints,
+            time.score
+        );
+        if self.recorded {
+            return;
+        }
+        self.recorded = true;
+        let difficulty = self.session.difficulty();
+        if let Some(rank) = self.best_times.record(difficulty, time) {
+            let _ = write!(
+                self.message,
+                ", best time #{} for {}",
+                rank,
+                difficulty.name()
+            );
+            if let Some(path) = &self.best_path {
+                if let Err(e) = self.best_times.save(path) {
+                    self.message = e;
+                }
+            }
+        }
+    }
+
+    /// 定时调用：隔一段时间保存一次已用时间
+    pub fn tick(&mut self) {
+        if self.saved.elapsed() >= AUTOSAVE {
+            self.save();
+        }
+    }
+
+    /// 光标移动一格，到边上时绕到另一边
+    fn move_by(&mut self, dr: isize, dc: isize) -> bool {
+        let (row, col) = self.cursor;
+        self.cursor = (
+            (row as isize + dr).rem_euclid(9) as usize,
+            (col as isize + dc).rem_euclid(9) as usize,
+        );
+        false
+    }
+
+    /// 填入数字或切换候选数字，已填其他数字时改填
+    fn digit(&mut self, val: u32) -> bool {
+        let (row, col) = self.cursor;
+        if self.pencil {
+            let edit = if self.session.board().get(row, col).digits.contains(&val) {
+                Edit::RemoveCandidate { row, col, val }
+            } else {
+                Edit::AddCandidate { row, col, val }
+            };
+            return self.edit(edit);
+        }
+        match self.session.board().get(row, col).val {
+            current if current == val => false,
+            0 => self.edit(Edit::Assign { row, col, val }),
+            _ => {
+                // 改填分两步，可以分别撤销
+                self.edit(Edit::Unassign { row, col }) && self.edit(Edit::Assign { row, col, val })
+            }
+        }
+    }
+
+    /// 执行修改，出错时显示原因
+    fn edit(&mut self, edit: Edit) -> bool {
+        match self.session.apply(edit) {
+            Ok(changed) => changed,
+            Err(e) => {
+                self.message = e;
+                false
+            }
+        }
+    }
+
+    /// 写入进度文件
+    fn save(&mut self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        self.saved = Instant::now();
+        if let Err(e) = self.session.save(path) {
+            self.message = e;
+        }
+    }
+
+    /// 按键说明，列出各操作绑定的第一个按键
+    fn help(&self) -> String {
+        let mut parts = vec![];
+        let first = |action| self.keymap.keys(action).first().map(Key::to_string);
+        let moves: Vec<String> = [Action::Up, Action::Down, Action::Left, Action::Right]
+            .into_iter()
+            .filter_map(first)
+            .collect();
+        if !moves.is_empty() {
+            parts.push(format!("{} move", moves.join("/")));
+        }
+        for (action, label) in [
+            (Action::Pencil, "pencil"),
+            (Action::Marks, "auto/manual marks"),
+            (Action::Hint, "hint"),
+            (Action::Clear, "clear"),
+            (Action::Undo, "undo"),
+            (Action::Redo, "redo"),
+            (Action::Save, "save"),
+            (Action::Quit, "quit"),
+        ] {
+            if let Some(key) = first(action) {
+                parts.push(format!("{} {}", key, label));
+            }
+        }
+        parts.join(", ")
+    }
+
+    /// 一帧画面：棋盘，光标处用方括号标出，下面是光标处的候选数字、消息和按键说明
+    pub fn render(&self) -> String {
+        let board = self.session.board();
+        let puzzle = self.session.puzzle();
+        let mistakes = self.session.mistakes();
+        let paint = |role, text: String| match self.theme {
+            Some(theme) => theme.paint(role, text),
+            None => text,
+        };
+        let mut s = String::new();
+        for row in 0..9 {
+            if row % 3 == 0 {
+                s.push_str("+-------+-------+-------+\n");
+            }
+            for col in 0..9 {
+                let cursor = self.cursor == (row, col);
+                let after_cursor = self.cursor == (row, col.wrapping_sub(1)) && col % 3 != 0;
+                if col % 3 == 0 {
+                    s.push('|');
+                }
+                s.push(if cursor {
+                    '['
+                } else if after_cursor {
+                    ']'
+                } else {
+                    ' '
+                });
+                let val = board.get(row, col).val;
+                let wrong = mistakes.contains(&(row, col));
+                let text = match val {
+                    // 无数可填的空位不用颜色也能看出
+                    0 if wrong => paint(Role::Removed, "!".to_string()),
+                    0 => paint(Role::Muted, ".".to_string()),
+                    _ if wrong => paint(Role::Removed, val.to_string()),
+                    _ if puzzle[row][col] != 0 => val.to_string(),
+                    _ => paint(Role::Added, val.to_string()),
+                };
+                s.push_str(&text);
+                if col % 3 == 2 {
+                    s.push(if cursor { ']' } else { ' ' });
+                }
+            }
+            s.push_str("|\n");
+        }
+        s.push_str("+-------+-------+-------+\n");
+
+        let (row, col) = self.cursor;
+        let _ = write!(s, "r{}c{}  ", row + 1, col + 1);
+        let pos = board.get(row, col);
+        if puzzle[row][col] != 0 {
+            s.push_str("given");
+        } else if pos.val != 0 {
+            s.push_str("entered");
+        } else {
+            let mut digits: Vec<u32> = pos.digits.iter().cloned().collect();
+            digits.sort();
+            let digits: Vec<String> = digits.iter().map(u32::to_string).collect();
+            let _ = write!(s, "candidates {}", digits.join(" "));
+        }
+        let _ = writeln!(
+            s,
+            "  [{}, {} marks]",
+            if self.pencil { "pencil" } else { "digits" },
+            self.session.mark_mode().name()
+        );
+        let difficulty = self.session.difficulty();
+        let _ = write!(
+            s,
+            "{}  time {}  hints {}",
+            difficulty.name(),
+            clock(self.session.elapsed()),
+            self.session.hints()
+        );
+        if let Some(best) = self.best_times.get(difficulty).first() {
+            let _ = write!(s, "  best {}", clock(best.score_time()));
+        }
+        let check = self.session.check_mode();
+        if check != CheckMode::Off {
+            let _ = write!(s, "  mistakes {} ({})", mistakes.len(), check.name());
+        }
+        s.push('\n');
+        let _ = writeln!(s, "{}", self.message);
+        let _ = writeln!(s, "{}", self.help());
+        s
+    }
+}
+
+/// 显示为 `分:秒`，超过一小时时为 `时:分:秒`
+fn clock(time: Duration) -> String {
+    let secs = time.as_secs();
+    match secs / 3600 {
+        0 => format!("{}:{:02}", secs / 60, secs % 60),
+        hours => format!("{}:{:02}:{:02}", hours, secs / 60 % 60, secs % 60),
+    }
+}
+
+/// 把终端输入的字节转为按键，识别方向键等转义序列，无法识别的序列被忽略
+pub fn parse_keys(input: &[u8]) -> Vec<Key> {
+    let mut keys = vec![];
+    let text = String::from_utf8_lossy(input);
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let key = match c {
+            '\x1b' => match chars.peek() {
+                Some('[') | Some('O') => {
+                    chars.next();
+                    // 参数之后以字母或 `~` 结尾
+                    let mut params = String::new();
+                    let end = loop {
+                        match chars.next() {
+                            Some(c) if c.is_ascii_digit() || c == ';' => params.push(c),
+                            other => break other,
+                        }
+                    };
+                    match (end, params.as_str()) {
+                        (Some('A'), _) => Key::Up,
+                        (Some('B'), _) => Key::Down,
+                        (Some('C'), _) => Key::Right,
+                        (Some('D'), _) => Key::Left,
+                        (Some('~'), "3") => Key::Delete,
+                        _ => continue,
+                    }
+                }
+                _ => Key::Esc,
+            },
+            '\r' | '\n' => Key::Enter,
+            '\t' => Key::Tab,
+            '\x7f' | '\x08' => Key::Backspace,
+            '\x01'..='\x1a' => Key::Ctrl((c as u8 - 1 + b'a') as char),
+            c if c.is_control() => continue,
+            c => Key::Char(c),
+        };
+        keys.push(key);
+    }
+    keys
+}
+
+/// 终端的原始模式：逐个读取按键、不回显，读取每隔一秒超时一次以便刷新计时。
+/// 析构时恢复原来的设置
+struct RawMode {
+    #[cfg(unix)]
+    original: Option<libc::termios>,
+}
+
+impl RawMode {
+    fn enable() -> Self {
+        #[cfg(unix)]
+        {
+            // SAFETY: termios 由 tcgetattr 填满后才使用
+            let original = unsafe {
+                let mut termios = std::mem::zeroed::<libc::termios>();
+                (libc::tcgetattr(libc::STDIN_FILENO, &mut termios) == 0).then_some(termios)
+            };
+            if let Some(original) = original {
+                let mut raw = original;
+                // Ctrl-C、Ctrl-Z、Ctrl-S 等作为按键读入
+                raw.c_iflag &= !(libc::IXON | libc::ICRNL | libc::BRKINT | libc::ISTRIP);
+                raw.c_lflag &= !(libc::ECHO | libc::ICANON | libc::ISIG | libc::IEXTEN);
+                raw.c_cc[libc::VMIN] = 0;
+                raw.c_cc[libc::VTIME] = 10;
+                // SAFETY: raw 是有效的 termios
+                unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &raw) };
+            }
+            Self { original }
+        }
+        #[cfg(not(unix))]
+        Self {}
+    }
+
+    /// 读取超时是否表现为读到 0 字节，否则 0 字节表示输入结束
+    fn timeouts(&self) -> bool {
+        #[cfg(unix)]
+        return self.original.is_some();
+        #[cfg(not(unix))]
+        false
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(original) = &self.original {
+            // SAFETY: original 来自 tcgetattr
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, original) };
+        }
+    }
+}
+
+/// 在终端中进行游戏，直到退出或输入结束
+pub fn run(game: &mut Game) -> io::Result<()> {
+    let raw = RawMode::enable();
+    let mut stdin = io::stdin();
+    let mut out = io::stdout();
+    // 换到备用屏幕并隐藏光标，退出时恢复
+    write!(out, "\x1b[?1049h\x1b[?25l")?;
+    let result = (|| {
+        let mut buf = [0; 64];
+        loop {
+            write!(out, "{}{}", CLEAR, game.render().replace('\n', "\r\n"))?;
+            out.flush()?;
+            let n = stdin.read(&mut buf)?;
+            if n == 0 {
+                if !raw.timeouts() {
+                    game.act(Action::Quit);
+                    return Ok(());
+                }
+                game.tick();
+                continue;
+            }
+            for key in parse_keys(&buf[..n]) {
+                if game.key(key) == Flow::Quit {
+                    return Ok(());
+                }
+            }
+        }
+    })();
+    write!(out, "\x1b[?25h\x1b[?1049l")?;
+    out.flush()?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::process;
+
+    use std::time::Duration;
+
+    use super::{clock, parse_keys, Flow, Game};
+    use crate::board::parse_grid;
+    use crate::keys::{Action, Key, Keymap, Preset};
+    use crate::rating::Difficulty;
+    use crate::scores::BestTimes;
+    use crate::session::{CheckMode, MarkMode, Session};
+    use crate::theme::{Role, Theme};
+
+    const PUZZLE: &str =
+        "530070000600195000098000060800060003400803001700020006060000280000419005000080079";
+
+    fn game() -> Game {
+        let session = Session::new(parse_grid(PUZZLE).unwrap());
+        Game::new(session, Keymap::default(), None, None)
+    }
+
+    fn press(game: &mut Game, keys: &str) -> Flow {
+        let mut flow = Flow::Continue;
+        for key in parse_keys(keys.as_bytes()) {
+            flow = game.key(key);
+        }
+        flow
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(
+            parse_keys(b"5\x1b[A\x1b[D\x1bOB\x1b[3~\x7f\x1a\r\x1b"),
+            [
+                Key::Char('5'),
+                Key::Up,
+                Key::Left,
+                Key::Down,
+                Key::Delete,
+                Key::Backspace,
+                Key::Ctrl('z'),
+                Key::Enter,
+                Key::Esc,
+            ]
+        );
+        // 无法识别的序列被忽略
+        assert_eq!(parse_keys(b"\x1b[15~q"), [Key::Char('q')]);
+    }
+
+    #[test]
+    fn test_play() {
+        let mut game = game();
+        // 光标绕到最后一列
+        press(&mut game, "\x1b[D");
+        assert_eq!(game.cursor(), (0, 8));
+        press(&mut game, "\x1b[C\x1b[C\x1b[C");
+        assert_eq!(game.cursor(), (0, 2));
+        press(&mut game, "5");
+        assert!(game.message().contains("conflicts"));
+        press(&mut game, "4");
+        assert_eq!(game.session().board().get(0, 2).val, 4);
+        // 改填后可以分两步撤销
+        press(&mut game, "1");
+        assert_eq!(game.session().board().get(0, 2).val, 1);
+        press(&mut game, "\x1a");
+        assert_eq!(game.session().board().get(0, 2).val, 0);
+        press(&mut game, "\x1a");
+        assert_eq!(game.session().board().get(0, 2).val, 4);
+        press(&mut game, "\x19");
+        assert_eq!(game.session().board().get(0, 2).val, 0);
+        assert!(game.message().starts_with("redid"));
+
+        // 标注模式切换候选数字
+        press(&mut game, "p");
+        assert!(game.session().board().get(0, 2).digits.contains(&4));
+        press(&mut game, "4");
+        assert!(!game.session().board().get(0, 2).digits.contains(&4));
+        let frame = game.render();
+        assert!(frame.starts_with("+-------+-------+-------+\n| 5 3[.]| . 7 . | . . . |\n"));
+        assert!(frame.contains("r1c3  candidates 1 2  [pencil, auto marks]\n"));
+        assert!(frame.contains("q quit"));
+        // 给定数字不能修改
+        press(&mut game, "\x1b[Dp4");
+        assert!(game.message().contains("given"));
+        assert_eq!(press(&mut game, "q"), Flow::Quit);
+    }
+
+    #[test]
+    fn test_hint() {
+        // 只差两个数字
+        let mut puzzle = parse_grid(
+            "534678912672195348198342567859761423426853791713924856961537284287419635345286179",
+        )
+        .unwrap();
+        puzzle[4][4] = 0;
+        puzzle[8][8] = 0;
+        let mut game = Game::new(Session::new(puzzle), Keymap::default(), None, None);
+        let mut best_times = BestTimes::default();
+        best_times.record(Difficulty::Easy, game.session().best_time());
+        game.set_best_times(best_times, None);
+        assert!(game
+            .render()
+            .contains("easy  time 0:00  hints 0  best 0:00\n"));
+
+        press(&mut game, "h");
+        assert_eq!(game.cursor(), (4, 4));
+        assert!(game.message().starts_with("hint: r5c5 is 5 by "));
+        assert!(game.message().ends_with("(+30s)"));
+        press(&mut game, "5h");
+        assert_eq!(game.cursor(), (8, 8));
+        assert_eq!(game.session().hints(), 2);
+        press(&mut game, "9");
+        assert!(game.session().is_solved());
+        assert!(game.message().contains("with 2 hint(s)"));
+        // 罚时一分钟，排在之前的记录之后
+        assert!(game.message().ends_with("best time #2 for easy"));
+        assert_eq!(game.best_times().get(Difficulty::Easy).len(), 2);
+        press(&mut game, "h");
+        assert_eq!(game.message(), "no empty cell left");
+
+        assert_eq!(clock(Duration::from_secs(65)), "1:05");
+    }
+
+    #[test]
+    fn test_mistakes() {
+        let mut session = Session::new(parse_grid(PUZZLE).unwrap());
+        session.set_check_mode(CheckMode::Solution);
+        let mut game = Game::new(session, Keymap::default(), Some(Theme::Dark), None);
+        // r1c3 应为 4
+        press(&mut game, "\x1b[C\x1b[C1");
+        let frame = game.render();
+        assert!(frame.contains(&Theme::Dark.paint(Role::Removed, "1")));
+        assert!(frame.contains("  mistakes 1 (solution)\n"));
+        press(&mut game, "4");
+        assert!(game.render().contains("  mistakes 0 (solution)\n"));
+
+        // 按规则只标出无数可填的空位
+        let mut session = Session::new(parse_grid(PUZZLE).unwrap());
+        session.set_check_mode(CheckMode::Rules);
+        let mut game = Game::new(session, Keymap::default(), None, None);
+        press(&mut game, "\x1b[C\x1b[C\x1b[C2\x1b[B\x1b[D4");
+        assert!(game.render().contains("  mistakes 0 (rules)\n"));
+        // r1c3 只剩 1 可填，r3c1 填 1 后无数可填
+        press(&mut game, "\x1b[B\x1b[D\x1b[D1");
+        let frame = game.render();
+        assert!(frame.contains("| 5 3 ! | 2 7 . |"));
+        assert!(frame.contains("  mistakes 1 (rules)\n"));
+        assert_eq!(clock(Duration::from_secs(3725)), "1:02:05");
+    }
+
+    #[test]
+    fn test_marks() {
+        let mut game = game();
+        press(&mut game, "\x1b[C\x1b[C4a");
+        assert_eq!(game.session().mark_mode(), MarkMode::Manual);
+        assert!(game.message().starts_with("manual marks"));
+        assert!(game.session().board().get(0, 3).digits.is_empty());
+        assert!(game.render().contains("[digits, manual marks]"));
+        // 切换后不能撤销之前的修改
+        press(&mut game, "\x1a");
+        assert_eq!(game.session().board().get(0, 2).val, 4);
+        press(&mut game, "\x1b[Cp6");
+        assert_eq!(game.session().board().get(0, 3).digits.len(), 1);
+        press(&mut game, "a");
+        assert_eq!(game.session().mark_mode(), MarkMode::Auto);
+        assert!(game.session().board().get(0, 3).digits.contains(&2));
+    }
+
+    #[test]
+    fn test_keymap() {
+        let mut keymap = Keymap::preset(Preset::Vim);
+        keymap.bind(Action::Hint, &[Key::Char('?'), Key::Ctrl('g')]);
+        let session = Session::new(parse_grid(PUZZLE).unwrap());
+        let mut game = Game::new(session, keymap, None, None);
+        press(&mut game, "ll4");
+        assert_eq!(game.session().board().get(0, 2).val, 4);
+        press(&mut game, "u\x07");
+        assert_eq!(game.session().board().get(0, 2).val, 0);
+        assert_eq!(game.session().hints(), 1);
+        assert!(game
+            .render()
+            .contains(", m pencil, a auto/manual marks, ? hint, "));
+    }
+
+    #[test]
+    fn test_save() {
+        let path = env::temp_dir().join(format!("superdo-play-{}.json", process::id()));
+        let session = Session::new(parse_grid(PUZZLE).unwrap());
+        let mut game = Game::new(session, Keymap::default(), None, Some(path.clone()));
+        // 每次修改后都保存
+        press(&mut game, "\x1b[C\x1b[C4");
+        let saved = Session::load(&path).unwrap();
+        assert_eq!(saved.board().get(0, 2).val, 4);
+        assert_eq!(press(&mut game, "\x1b[B\x1b[C\x03"), Flow::Quit);
+        let mut resumed = Game::new(Session::load(&path).unwrap(), Keymap::default(), None, None);
+        assert_eq!(resumed.session().board().get(0, 2).val, 4);
+        press(&mut resumed, "\x1b[C\x1b[C\x1b[C7");
+        assert_eq!(resumed.session().board().get(0, 3).val, 0);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,566 @@
+//! QR 码的编码和解码
+//!
+//! 编码使用 qrcode crate，限于解码支持的版本 1 到 3，固定使用 M 级纠错，足以容纳 81 位数字的题目。
+//! 解码只支持版本 1 到 3 中只有一个纠错块的规格，假定码图没有旋转和倾斜，如截图或摆正的扫描件，
+//! 损坏的码字用 Reed-Solomon 纠错。
+
+use qrcode::{Color, QrCode, Version};
+
+use crate::image::Image;
+use crate::ocr::otsu;
+
+/// 纠错级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EcLevel {
+    L,
+    M,
+    Q,
+    H,
+}
+
+impl EcLevel {
+    /// 格式信息中的两位
+    fn bits(self) -> u32 {
+        match self {
+            EcLevel::L => 1,
+            EcLevel::M => 0,
+            EcLevel::Q => 3,
+            EcLevel::H => 2,
+        }
+    }
+}
+
+/// 只有一个纠错块的规格：版本、纠错级别、数据码字数、纠错码字数
+const BLOCKS: [(usize, EcLevel, usize, usize); 10] = [
+    (1, EcLevel::L, 19, 7),
+    (1, EcLevel::M, 16, 10),
+    (1, EcLevel::Q, 13, 13),
+    (1, EcLevel::H, 9, 17),
+    (2, EcLevel::L, 34, 10),
+    (2, EcLevel::M, 28, 16),
+    (2, EcLevel::Q, 22, 22),
+    (2, EcLevel::H, 16, 28),
+    (3, EcLevel::L, 55, 15),
+    (3, EcLevel::M, 44, 26),
+];
+
+/// 字母数字模式的字符表
+const ALPHANUMERIC: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// 码图四周的空白，以模块计
+pub const QUIET_ZONE: usize = 4;
+
+/// 模块矩阵，按行排列，true 为深色
+pub type Modules = Vec<Vec<bool>>;
+
+/// GF(256) 上的乘法，本原多项式为 x^8 + x^4 + x^3 + x^2 + 1
+fn gf_mul(a: u8, b: u8) -> u8 {
+    let (mut a, mut b, mut r) = (a as u16, b, 0u16);
+    while b != 0 {
+        if b & 1 != 0 {
+            r ^= a;
+        }
+        a <<= 1;
+        if a & 0x100 != 0 {
+            a ^= 0x11d;
+        }
+        b >>= 1;
+    }
+    r as u8
+}
+
+fn gf_pow(a: u8, n: usize) -> u8 {
+    (0..n).fold(1, |r, _| gf_mul(r, a))
+}
+
+fn gf_inv(a: u8) -> u8 {
+    gf_pow(a, 254)
+}
+
+/// 多项式在 x 处的值，系数高次在前
+fn eval(poly: &[u8], x: u8) -> u8 {
+    poly.iter().fold(0, |r, &c| gf_mul(r, x) ^ c)
+}
+
+/// 就地纠正码字中的错误，错误超过纠错能力时返回错误
+fn correct(codewords: &mut [u8], ec: usize) -> Result<(), String> {
+    let syndromes: Vec<u8> = (0..ec).map(|j| eval(codewords, gf_pow(2, j))).collect();
+    if syndromes.iter().all(|&s| s == 0) {
+        return Ok(());
+    }
+    // Berlekamp-Massey 求错误位置多项式，系数低次在前
+    let (mut lambda, mut b) = (vec![1u8], vec![1u8]);
+    let (mut errors, mut shift, mut last) = (0, 1, 1u8);
+    for k in 0..ec {
+        let mut d = syndromes[k];
+        for i in 1..=errors.min(lambda.len() - 1) {
+            d ^= gf_mul(lambda[i], syndromes[k - i]);
+        }
+        if d == 0 {
+            shift += 1;
+            continue;
+        }
+        let coef = gf_mul(d, gf_inv(last));
+        let mut next = lambda.clone();
+        next.resize(next.len().max(b.len() + shift), 0);
+        for (i, &c) in b.iter().enumerate() {
+            next[i + shift] ^= gf_mul(coef, c);
+        }
+        if 2 * errors <= k {
+            b = std::mem::replace(&mut lambda, next);
+            errors = k + 1 - errors;
+            last = d;
+            shift = 1;
+        } else {
+            lambda = next;
+            shift += 1;
+        }
+    }
+    let too_many = || "too many errors to correct in the QR code".to_string();
+    // Chien 搜索：位置 p 对应 x 的次数 n-1-p
+    let n = codewords.len();
+    let low_first = |poly: &[u8], x: u8| poly.iter().rev().fold(0, |r, &c| gf_mul(r, x) ^ c);
+    let positions: Vec<usize> = (0..n)
+        .filter(|&p| low_first(&lambda, gf_inv(gf_pow(2, n - 1 - p))) == 0)
+        .collect();
+    if positions.len() != errors {
+        return Err(too_many());
+    }
+    // Forney 算法求错误值，Ω(x) = S(x)Λ(x) mod x^ec
+    let mut omega = vec![0u8; ec];
+    for (i, &s) in syndromes.iter().enumerate() {
+        for (j, &l) in lambda.iter().enumerate() {
+            if i + j < ec {
+                omega[i + j] ^= gf_mul(s, l);
+            }
+        }
+    }
+    let derivative: Vec<u8> = lambda
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, &c)| if i % 2 == 1 { c } else { 0 })
+        .collect();
+    for p in positions {
+        let x = gf_pow(2, n - 1 - p);
+        let inv = gf_inv(x);
+        let denominator = low_first(&derivative, inv);
+        if denominator == 0 {
+            return Err(too_many());
+        }
+        codewords[p] ^= gf_mul(gf_mul(x, low_first(&omega, inv)), gf_inv(denominator));
+    }
+    if (0..ec).any(|j| eval(codewords, gf_pow(2, j)) != 0) {
+        return Err(too_many());
+    }
+    Ok(())
+}
+
+/// 15 位格式信息
+fn format_bits(level: EcLevel, mask: usize) -> u32 {
+    let data = level.bits() << 3 | mask as u32;
+    let mut rem = data;
+    for _ in 0..10 {
+        rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+    }
+    (data << 10 | rem) ^ 0x5412
+}
+
+/// 掩码为真时翻转该模块
+fn masked(mask: usize, x: usize, y: usize) -> bool {
+    match mask {
+        0 => (x + y).is_multiple_of(2),
+        1 => y.is_multiple_of(2),
+        2 => x.is_multiple_of(3),
+        3 => (x + y).is_multiple_of(3),
+        4 => (x / 3 + y / 2).is_multiple_of(2),
+        5 => x * y % 2 + x * y % 3 == 0,
+        6 => (x * y % 2 + x * y % 3).is_multiple_of(2),
+        _ => ((x + y) % 2 + x * y % 3).is_multiple_of(2),
+    }
+}
+
+/// 码图，含功能图形的位置
+struct Symbol {
+    size: usize,
+    modules: Modules,
+    /// 定位、定时、校正图形和格式信息所在的模块
+    function: Vec<Vec<bool>>,
+}
+
+impl Symbol {
+    /// 画好功能图形，格式信息位置先留空
+    fn new(version: usize) -> Self {
+        let size = version * 4 + 17;
+        let mut symbol = Self {
+            size,
+            modules: vec![vec![false; size]; size],
+            function: vec![vec![false; size]; size],
+        };
+        for i in 0..size {
+            symbol.set_function(6, i, i % 2 == 0);
+            symbol.set_function(i, 6, i % 2 == 0);
+        }
+        // 定位图形连同四周的分隔带
+        for (cx, cy) in [(3, 3), (size - 4, 3), (3, size - 4)] {
+            for dy in -4i32..=4 {
+                for dx in -4i32..=4 {
+                    let (x, y) = (cx as i32 + dx, cy as i32 + dy);
+                    if (0..size as i32).contains(&x) && (0..size as i32).contains(&y) {
+                        let dist = dx.abs().max(dy.abs());
+                        symbol.set_function(x as usize, y as usize, dist != 2 && dist != 4);
+                    }
+                }
+            }
+        }
+        if version > 1 {
+            let c = size - 7;
+            for dy in -2i32..=2 {
+                for dx in -2i32..=2 {
+                    let (x, y) = ((c as i32 + dx) as usize, (c as i32 + dy) as usize);
+                    symbol.set_function(x, y, dx.abs().max(dy.abs()) != 1);
+                }
+            }
+        }
+        symbol.draw_format(0);
+        symbol
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y][x] = dark;
+        self.function[y][x] = true;
+    }
+
+    /// 格式信息两份的位置，第 i 个元素是第 i 位所在的 (x, y)
+    fn format_positions(&self) -> [[(usize, usize); 15]; 2] {
+        let size = self.size;
+        let mut first = [(0, 0); 15];
+        let mut second = [(0, 0); 15];
+        for i in 0..15 {
+            first[i] = match i {
+                0..=5 => (8, i),
+                6 => (8, 7),
+                7 => (8, 8),
+                8 => (7, 8),
+                _ => (14 - i, 8),
+            };
+            second[i] = match i {
+                0..=7 => (size - 1 - i, 8),
+                _ => (8, size - 15 + i),
+            };
+        }
+        [first, second]
+    }
+
+    fn draw_format(&mut self, bits: u32) {
+        for copy in self.format_positions() {
+            for (i, (x, y)) in copy.into_iter().enumerate() {
+                self.set_function(x, y, bits >> i & 1 == 1);
+            }
+        }
+        // 始终为深色的模块
+        let size = self.size;
+        self.set_function(8, size - 8, true);
+    }
+
+    /// 按之字形顺序依次经过的数据模块
+    fn data_positions(&self) -> Vec<(usize, usize)> {
+        let mut positions = vec![];
+        let mut right = self.size as i32 - 1;
+        while right >= 1 {
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vert in 0..self.size {
+                for j in 0..2 {
+                    let x = (right - j) as usize;
+                    let y = if upward { self.size - 1 - vert } else { vert };
+                    if !self.function[y][x] {
+                        positions.push((x, y));
+                    }
+                }
+            }
+            right -= 2;
+        }
+        positions
+    }
+
+    fn apply_mask(&mut self, mask: usize) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                if !self.function[y][x] && masked(mask, x, y) {
+                    self.modules[y][x] ^= true;
+                }
+            }
+        }
+    }
+}
+
+/// 把文本编码为 QR 码，使用能放下的最小版本和 M 级纠错
+pub fn encode(text: &str) -> Result<Modules, String> {
+    let code = (1..=3)
+        .find_map(|v| QrCode::with_version(text, Version::Normal(v), qrcode::EcLevel::M).ok())
+        .ok_or_else(|| format!("`{}` is too long for a version 3 QR code", text))?;
+    let colors = code.to_colors();
+    Ok(colors
+        .chunks(code.width())
+        .map(|row| row.iter().map(|&c| c == Color::Dark).collect())
+        .collect())
+}
+
+/// 画成图片，每个模块 scale 像素，四周留出空白
+pub fn to_image(modules: &Modules, scale: usize) -> Image {
+    let size = (modules.len() + QUIET_ZONE * 2) * scale;
+    let mut image = Image::new(size, size);
+    for (y, row) in modules.iter().enumerate() {
+        for (x, _) in row.iter().enumerate().filter(|(_, &dark)| dark) {
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    let (px, py) = ((x + QUIET_ZONE) * scale + dx, (y + QUIET_ZONE) * scale + dy);
+                    image.set(px, py, 0);
+                }
+            }
+        }
+    }
+    image
+}
+
+/// 识别图片中的 QR 码，返回其中的文本
+pub fn decode(image: &Image) -> Result<String, String> {
+    let threshold = otsu(image);
+    let dark = |x: usize, y: usize| image.get(x, y) < threshold;
+    let not_found = || "no QR code found in the image".to_string();
+    // 深色像素的外框即码图的外框
+    let (mut left, mut top, mut right, mut bottom) = (usize::MAX, usize::MAX, 0, 0);
+    for y in 0..image.height {
+        for x in 0..image.width {
+            if dark(x, y) {
+                (left, top) = (left.min(x), top.min(y));
+                (right, bottom) = (right.max(x), bottom.max(y));
+            }
+        }
+    }
+    if left > right {
+        return Err(not_found());
+    }
+    // 左上角定位图形的顶边宽 7 个模块
+    let run = (left..=right).take_while(|&x| dark(x, top)).count();
+    let (width, height) = (right - left + 1, bottom - top + 1);
+    let size = (width as f64 * 7.0 / run as f64).round() as usize;
+    let version = match size {
+        21 | 25 | 29 => (size - 17) / 4,
+        s if s > 29 && (s - 17) % 4 == 0 => {
+            return Err(format!("QR code version {} is not supported", (s - 17) / 4))
+        }
+        _ => return Err(not_found()),
+    };
+    let mut symbol = Symbol::new(version);
+    for y in 0..size {
+        for x in 0..size {
+            let px = left + ((x as f64 + 0.5) * width as f64 / size as f64) as usize;
+            let py = top + ((y as f64 + 0.5) * height as f64 / size as f64) as usize;
+            symbol.modules[y][x] = dark(px, py);
+        }
+    }
+    // 取与两份格式信息最接近的合法值
+    let copies = symbol.format_positions().map(|copy| {
+        copy.iter().enumerate().fold(0, |bits, (i, &(x, y))| {
+            bits | (symbol.modules[y][x] as u32) << i
+        })
+    });
+    let (distance, level, mask) = [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H]
+        .into_iter()
+        .flat_map(|level| (0..8).map(move |mask| (level, mask)))
+        .map(|(level, mask)| {
+            let bits = format_bits(level, mask);
+            let distance = copies
+                .iter()
+                .map(|c| (c ^ bits).count_ones())
+                .min()
+                .unwrap();
+            (distance, level, mask)
+        })
+        .min_by_key(|t| t.0)
+        .unwrap();
+    if distance > 3 {
+        return Err("unreadable QR format information".to_string());
+    }
+    let &(_, _, data, ec) = BLOCKS
+        .iter()
+        .find(|b| b.0 == version && b.1 == level)
+        .ok_or_else(|| {
+            format!(
+                "QR code version {} with {:?} error correction is not supported",
+                version, level
+            )
+        })?;
+    symbol.apply_mask(mask);
+    let bits: Vec<bool> = symbol
+        .data_positions()
+        .into_iter()
+        .map(|(x, y)| symbol.modules[y][x])
+        .collect();
+    let mut codewords: Vec<u8> = bits
+        .chunks(8)
+        .take(data + ec)
+        .map(|byte| byte.iter().fold(0, |v, &b| v << 1 | b as u8))
+        .collect();
+    correct(&mut codewords, ec)?;
+    segments(&codewords[..data])
+}
+
+/// 读取数据码字中的各段
+fn segments(codewords: &[u8]) -> Result<String, String> {
+    let bits: Vec<bool> = codewords
+        .iter()
+        .flat_map(|&c| (0..8).rev().map(move |i| c >> i & 1 == 1))
+        .collect();
+    let mut position = 0;
+    let mut read = |n: usize| -> Result<u32, String> {
+        let chunk = bits
+            .get(position..position + n)
+            .ok_or("truncated QR data")?;
+        position += n;
+        Ok(chunk.iter().fold(0, |v, &b| v << 1 | b as u32))
+    };
+    let mut text = vec![];
+    // 剩余不足 4 位时没有结束符
+    while let Ok(mode) = read(4) {
+        match mode {
+            0 => break,
+            0b0001 => {
+                let mut count = read(10)? as usize;
+                while count > 0 {
+                    let n = count.min(3);
+                    let value = read(n * 3 + 1)?;
+                    text.extend(format!("{:0width$}", value, width = n).bytes());
+                    count -= n;
+                }
+            }
+            0b0010 => {
+                let mut count = read(9)? as usize;
+                while count > 0 {
+                    let n = count.min(2);
+                    let value = read(n * 5 + 1)? as usize;
+                    let chars = if n == 2 {
+                        vec![value / 45, value % 45]
+                    } else {
+                        vec![value]
+                    };
+                    for c in chars {
+                        text.push(*ALPHANUMERIC.get(c).ok_or("invalid QR data")?);
+                    }
+                    count -= n;
+                }
+            }
+            0b0100 => {
+                let count = read(8)?;
+                for _ in 0..count {
+                    text.push(read(8)? as u8);
+                }
+            }
+            mode => return Err(format!("QR mode {:04b} is not supported", mode)),
+        }
+    }
+    String::from_utf8(text).map_err(|_| "QR data is not UTF-8 text".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{correct, decode, encode, format_bits, gf_mul, to_image, EcLevel};
+
+    const PUZZLE: &str =
+        "530070000600195000098000060800060003400803001700020006060000280000419005000080079";
+
+    /// 生成多项式 (x - α^0)(x - α^1)...(x - α^(degree-1)) 除首项外的系数，高次在前
+    fn generator(degree: usize) -> Vec<u8> {
+        let mut g = vec![0; degree];
+        g[degree - 1] = 1;
+        let mut root = 1;
+        for _ in 0..degree {
+            for j in 0..degree {
+                g[j] = gf_mul(g[j], root);
+                if j + 1 < degree {
+                    g[j] ^= g[j + 1];
+                }
+            }
+            root = gf_mul(root, 2);
+        }
+        g
+    }
+
+    /// 纠错码字：数据多项式除以生成多项式的余数
+    fn ec_codewords(data: &[u8], degree: usize) -> Vec<u8> {
+        let g = generator(degree);
+        let mut r = vec![0; degree];
+        for &b in data {
+            let factor = b ^ r.remove(0);
+            r.push(0);
+            for (x, &c) in r.iter_mut().zip(&g) {
+                *x ^= gf_mul(c, factor);
+            }
+        }
+        r
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format_bits(EcLevel::M, 0), 0b101010000010010);
+        assert_eq!(format_bits(EcLevel::L, 4), 0b110011000101111);
+    }
+
+    #[test]
+    fn test_correct() {
+        // 常见教程中 HELLO WORLD 的 1-M 码字
+        let data = [
+            32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17,
+        ];
+        let ec = ec_codewords(&data, 10);
+        assert_eq!(ec, [196, 35, 39, 119, 235, 215, 231, 226, 93, 23]);
+        let mut codewords = data.to_vec();
+        codewords.extend(ec);
+        let clean = codewords.clone();
+        for p in [0, 9, 18, 25, 3] {
+            codewords[p] ^= 0x5a;
+        }
+        correct(&mut codewords, 10).unwrap();
+        assert_eq!(codewords, clean);
+
+        let data: Vec<u8> = (0..44).map(|i| (i * 37 % 256) as u8).collect();
+        let mut codewords = data.clone();
+        codewords.extend(ec_codewords(&data, 26));
+        let clean = codewords.clone();
+        for (i, p) in [0, 7, 20, 33, 41, 50, 60, 69].into_iter().enumerate() {
+            codewords[p] ^= 0x5a + i as u8;
+        }
+        correct(&mut codewords, 26).unwrap();
+        assert_eq!(codewords, clean);
+        // 超过 13 个错误
+        for p in 0..14 {
+            codewords[p * 5] ^= 0xff;
+        }
+        assert!(correct(&mut codewords, 26).is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for text in [PUZZLE, "HELLO WORLD", "superdo: 1.2.0"] {
+            let modules = encode(text).unwrap();
+            let decoded = decode(&to_image(&modules, 3)).unwrap();
+            assert_eq!(decoded, text);
+        }
+        // 使用能放下的最小版本
+        assert_eq!(encode("HELLO WORLD").unwrap().len(), 21);
+        let modules = encode(PUZZLE).unwrap();
+        assert_eq!(modules.len(), 29);
+        // 损坏部分数据模块后仍可读出
+        let mut damaged = modules.clone();
+        for (x, y) in [(20, 20), (21, 20), (12, 25), (15, 10), (25, 14)] {
+            damaged[y][x] ^= true;
+        }
+        assert_eq!(decode(&to_image(&damaged, 4)).unwrap(), PUZZLE);
+        assert!(encode(&"1".repeat(200)).is_err());
+        assert!(decode(&crate::image::Image::new(50, 50)).is_err());
+    }
+}
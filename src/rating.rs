@@ -0,0 +1,861 @@
+//! 按人工解题技巧评定难度
+//!
+//! 使用行、列、3x3 小格以及变体附加的区域（如对角线）和同组关系（如无马步）进行推理，其它
+//! 约束不参与：每一步从最简单的技巧开始尝试，记录用到的技巧，最难的技巧决定难度。所有技巧
+//! 都无法继续时需要猜测，难度为 evil。
+//!
+//! 分数参照 HoDoKu：每一步按所用技巧计分并累加，需要猜测时再加上固定的分数。
+//!
+//! 另外提供近似的 Sudoku Explainer（SE）评分：按 SE 的顺序尝试技巧，取用到的技巧中 SE 分值
+//! 最高者。唯余法不区分小格与行列，超出已实现技巧的题目没有 SE 评分。
+
+use clap::ValueEnum;
+
+use std::sync::Arc;
+
+use crate::board::{Grid, SudokuBoard};
+use crate::constraint::{Cell, Unit};
+use crate::json::Json;
+use crate::solver::solutions;
+use crate::variant::Variant;
+
+/// 难度
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Difficulty {
+    /// Singles only
+    Easy,
+    /// Locked candidates and pairs
+    Medium,
+    /// Triples and quads
+    Hard,
+    /// Fish (X-wing, swordfish, jellyfish)
+    Expert,
+    /// Needs guessing beyond the technique ladder
+    Evil,
+}
+
+/// 评分方式
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scale {
+    /// Total cost of the technique ladder steps (HoDoKu-like)
+    #[default]
+    Ladder,
+    /// Approximate Sudoku Explainer rating, e.g. 3.2 for an X-wing
+    Se,
+}
+
+impl Difficulty {
+    /// 名称
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+            Difficulty::Expert => "expert",
+            Difficulty::Evil => "evil",
+        }
+    }
+}
+
+/// 解题技巧，按难度从低到高排列
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Technique {
+    /// A cell with a single candidate
+    NakedSingle,
+    /// A digit with a single place in a row, column or box
+    HiddenSingle,
+    /// Pointing and claiming
+    LockedCandidates,
+    /// Two cells of a unit holding the same two candidates
+    NakedPair,
+    /// Two digits confined to the same two cells of a unit
+    HiddenPair,
+    /// Three cells of a unit holding three candidates in total
+    NakedTriple,
+    /// Three digits confined to the same three cells of a unit
+    HiddenTriple,
+    /// Four cells of a unit holding four candidates in total
+    NakedQuad,
+    /// Four digits confined to the same four cells of a unit
+    HiddenQuad,
+    /// Fish of size 2
+    #[value(alias = "xwing")]
+    XWing,
+    /// Fish of size 3
+    Swordfish,
+    /// Fish of size 4
+    Jellyfish,
+}
+
+impl Technique {
+    /// 按难度排列的所有技巧
+    pub const ALL: [Technique; 12] = [
+        Technique::NakedSingle,
+        Technique::HiddenSingle,
+        Technique::LockedCandidates,
+        Technique::NakedPair,
+        Technique::HiddenPair,
+        Technique::NakedTriple,
+        Technique::HiddenTriple,
+        Technique::NakedQuad,
+        Technique::HiddenQuad,
+        Technique::XWing,
+        Technique::Swordfish,
+        Technique::Jellyfish,
+    ];
+
+    /// 名称
+    pub fn name(self) -> &'static str {
+        match self {
+            Technique::NakedSingle => "naked-single",
+            Technique::HiddenSingle => "hidden-single",
+            Technique::LockedCandidates => "locked-candidates",
+            Technique::NakedPair => "naked-pair",
+            Technique::HiddenPair => "hidden-pair",
+            Technique::NakedTriple => "naked-triple",
+            Technique::HiddenTriple => "hidden-triple",
+            Technique::NakedQuad => "naked-quad",
+            Technique::HiddenQuad => "hidden-quad",
+            Technique::XWing => "x-wing",
+            Technique::Swordfish => "swordfish",
+            Technique::Jellyfish => "jellyfish",
+        }
+    }
+
+    /// 按 SE 分值从低到高排列的所有技巧
+    pub const SE_ORDER: [Technique; 12] = [
+        Technique::HiddenSingle,
+        Technique::NakedSingle,
+        Technique::LockedCandidates,
+        Technique::NakedPair,
+        Technique::XWing,
+        Technique::HiddenPair,
+        Technique::NakedTriple,
+        Technique::Swordfish,
+        Technique::HiddenTriple,
+        Technique::NakedQuad,
+        Technique::Jellyfish,
+        Technique::HiddenQuad,
+    ];
+
+    /// SE 分值，乘以 10 以便比较
+    pub fn se_rating(self) -> u32 {
+        match self {
+            Technique::HiddenSingle => 15,
+            Technique::NakedSingle => 23,
+            Technique::LockedCandidates => 26,
+            Technique::NakedPair => 30,
+            Technique::XWing => 32,
+            Technique::HiddenPair => 34,
+            Technique::NakedTriple => 36,
+            Technique::Swordfish => 38,
+            Technique::HiddenTriple => 40,
+            Technique::NakedQuad => 50,
+            Technique::Jellyfish => 52,
+            Technique::HiddenQuad => 54,
+        }
+    }
+
+    /// 每使用一次的分数
+    pub fn cost(self) -> u32 {
+        match self {
+            Technique::NakedSingle => 4,
+            Technique::HiddenSingle => 14,
+            Technique::LockedCandidates => 50,
+            Technique::NakedPair => 60,
+            Technique::HiddenPair => 70,
+            Technique::NakedTriple => 80,
+            Technique::HiddenTriple => 100,
+            Technique::NakedQuad => 120,
+            Technique::HiddenQuad => 150,
+            Technique::XWing => 140,
+            Technique::Swordfish => 150,
+            Technique::Jellyfish => 160,
+        }
+    }
+
+    /// 所属难度
+    pub fn difficulty(self) -> Difficulty {
+        match self {
+            Technique::NakedSingle | Technique::HiddenSingle => Difficulty::Easy,
+            Technique::LockedCandidates | Technique::NakedPair | Technique::HiddenPair => {
+                Difficulty::Medium
+            }
+            Technique::NakedTriple
+            | Technique::HiddenTriple
+            | Technique::NakedQuad
+            | Technique::HiddenQuad => Difficulty::Hard,
+            Technique::XWing | Technique::Swordfish | Technique::Jellyfish => Difficulty::Expert,
+        }
+    }
+}
+
+/// 评定结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rating {
+    /// 难度
+    pub difficulty: Difficulty,
+    /// 用到的最难技巧
+    pub hardest: Option<Technique>,
+    /// 依次使用的技巧
+    pub steps: Vec<Technique>,
+    /// 是否仅靠技巧解出
+    pub solved: bool,
+    /// 分数，各步分数之和，需要猜测时另加 [`GUESS_COST`]
+    pub score: u32,
+}
+
+impl Rating {
+    /// 难度名称
+    pub fn grade(&self) -> &'static str {
+        self.difficulty.name()
+    }
+}
+
+/// 技巧无法继续、需要猜测时另加的分数
+pub const GUESS_COST: u32 = 2000;
+
+/// 数字对应的位
+fn bit(digit: u32) -> u16 {
+    1 << digit
+}
+
+/// 从 items 中选出 k 个的所有组合
+fn combinations(items: &[usize], k: usize) -> Vec<Vec<usize>> {
+    if k == 0 {
+        return vec![vec![]];
+    }
+    let mut result = vec![];
+    for i in 0..items.len() {
+        for mut rest in combinations(&items[i + 1..], k - 1) {
+            rest.insert(0, items[i]);
+            result.push(rest);
+        }
+    }
+    result
+}
+
+/// 以位掩码表示候选数字的棋盘
+#[derive(Debug, Clone)]
+pub(crate) struct Candidates {
+    /// 已填数字，按 row * 9 + col 排列
+    values: [u32; 81],
+    /// 候选数字，第 d 位表示数字 d
+    cands: [u16; 81],
+    /// 所有区域
+    units: Vec<[usize; 9]>,
+    /// 每个位置的同组位置
+    peers: Vec<Vec<usize>>,
+}
+
+impl Candidates {
+    /// 由题目和规则初始化，题目本身冲突时返回 None
+    pub(crate) fn new(grid: &Grid, variant: &Variant) -> Option<Self> {
+        let mut board = Self {
+            values: [0; 81],
+            cands: [0; 81],
+            units: variant
+                .find::<Unit>()
+                .map(|unit| std::array::from_fn(|j| unit.cells[j].0 * 9 + unit.cells[j].1))
+                .collect(),
+            peers: (0..81)
+                .map(|i| {
+                    let peers = variant.peers().of(i / 9, i % 9);
+                    peers.iter().map(|&(r, c)| r * 9 + c).collect()
+                })
+                .collect(),
+        };
+        for i in 0..81 {
+            for digit in 1..10 {
+                if variant.allows(i / 9, i % 9, digit) {
+                    board.cands[i] |= bit(digit);
+                }
+            }
+        }
+        for i in 0..81 {
+            let digit = grid[i / 9][i % 9];
+            if digit != 0 {
+                if board.cands[i] & bit(digit) == 0 {
+                    return None;
+                }
+                board.place(i, digit);
+            }
+        }
+        Some(board)
+    }
+
+    /// 填入数字，并从同组位置中去除该候选
+    fn place(&mut self, cell: usize, digit: u32) {
+        self.values[cell] = digit;
+        self.cands[cell] = 0;
+        for &i in &self.peers[cell] {
+            self.cands[i] &= !bit(digit);
+        }
+    }
+
+    /// 去除一组位置上的候选，返回是否有变化
+    fn eliminate(&mut self, cells: impl IntoIterator<Item = usize>, mask: u16) -> bool {
+        let mut changed = false;
+        for i in cells {
+            if self.cands[i] & mask != 0 {
+                self.cands[i] &= !mask;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// 是否已填满
+    fn solved(&self) -> bool {
+        self.values.iter().all(|v| *v != 0)
+    }
+
+    /// 是否有空位没有候选
+    fn broken(&self) -> bool {
+        (0..81).any(|i| self.values[i] == 0 && self.cands[i] == 0)
+    }
+
+    /// 空位 i 能否直接用唯余法或摒除法填入，返回所用技巧和数字
+    fn single_at(&self, i: usize) -> Option<(Technique, u32)> {
+        if self.values[i] != 0 {
+            return None;
+        }
+        if self.cands[i].count_ones() == 1 {
+            return Some((Technique::NakedSingle, self.cands[i].trailing_zeros()));
+        }
+        for unit in self.units.iter().filter(|unit| unit.contains(&i)) {
+            for digit in 1..10 {
+                if self.places(unit, digit) == [i] {
+                    return Some((Technique::HiddenSingle, digit));
+                }
+            }
+        }
+        None
+    }
+
+    /// 区域内数字 digit 可填的位置
+    fn places(&self, unit: &[usize; 9], digit: u32) -> Vec<usize> {
+        unit.iter()
+            .cloned()
+            .filter(|i| self.cands[*i] & bit(digit) != 0)
+            .collect()
+    }
+
+    /// 使用某种技巧推进一步，返回是否有变化
+    pub(crate) fn apply(&mut self, technique: Technique) -> bool {
+        match technique {
+            Technique::NakedSingle => self.naked_single(),
+            Technique::HiddenSingle => self.hidden_single(),
+            Technique::LockedCandidates => self.locked_candidates(),
+            Technique::NakedPair => self.naked_subset(2),
+            Technique::HiddenPair => self.hidden_subset(2),
+            Technique::NakedTriple => self.naked_subset(3),
+            Technique::HiddenTriple => self.hidden_subset(3),
+            Technique::NakedQuad => self.naked_subset(4),
+            Technique::HiddenQuad => self.hidden_subset(4),
+            Technique::XWing => self.fish(2),
+            Technique::Swordfish => self.fish(3),
+            Technique::Jellyfish => self.fish(4),
+        }
+    }
+
+    fn naked_single(&mut self) -> bool {
+        for i in 0..81 {
+            if self.values[i] == 0 && self.cands[i].count_ones() == 1 {
+                self.place(i, self.cands[i].trailing_zeros());
+                return true;
+            }
+        }
+        false
+    }
+
+    fn hidden_single(&mut self) -> bool {
+        for u in 0..self.units.len() {
+            let unit = self.units[u];
+            for digit in 1..10 {
+                if let [cell] = self.places(&unit, digit)[..] {
+                    self.place(cell, digit);
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// 小格内某数字只在同一行（列）时，排除该行（列）其余位置；反之亦然
+    fn locked_candidates(&mut self) -> bool {
+        let units = self.units.clone();
+        for digit in 1..10 {
+            for a in &units {
+                let places = self.places(a, digit);
+                if places.len() < 2 {
+                    continue;
+                }
+                for b in &units {
+                    if a == b {
+                        continue;
+                    }
+                    if places.iter().all(|p| b.contains(p)) {
+                        let rest = b.iter().cloned().filter(|i| !a.contains(i));
+                        if self.eliminate(rest, bit(digit)) {
+                            return true;
+                        }
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// n 个位置的候选合计只有 n 个数字，排除区域内其余位置的这些数字
+    fn naked_subset(&mut self, n: usize) -> bool {
+        for u in 0..self.units.len() {
+            let unit = self.units[u];
+            let free: Vec<usize> = unit
+                .iter()
+                .cloned()
+                .filter(|i| self.values[*i] == 0)
+                .collect();
+            if free.len() <= n {
+                continue;
+            }
+            for cells in combinations(&free, n) {
+                let mask = cells.iter().fold(0, |m, i| m | self.cands[*i]);
+                if mask.count_ones() as usize == n {
+                    let rest = free.iter().cloned().filter(|i| !cells.contains(i));
+                    if self.eliminate(rest, mask) {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// n 个数字只能填在区域内相同的 n 个位置，排除这些位置的其余候选
+    fn hidden_subset(&mut self, n: usize) -> bool {
+        for u in 0..self.units.len() {
+            let unit = self.units[u];
+            let digits: Vec<usize> = (1..10)
+                .filter(|d| !unit.iter().any(|i| self.values[*i] == *d))
+                .map(|d| d as usize)
+                .collect();
+            if digits.len() <= n {
+                continue;
+            }
+            for combo in combinations(&digits, n) {
+                let mut cells: Vec<usize> = vec![];
+                for d in &combo {
+                    for p in self.places(&unit, *d as u32) {
+                        if !cells.contains(&p) {
+                            cells.push(p);
+                        }
+                    }
+                }
+                if cells.len() == n {
+                    let keep = combo.iter().fold(0, |m, d| m | bit(*d as u32));
+                    if self.eliminate(cells, !keep & 0b11_1111_1110) {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// 鱼：数字在 n 行中只出现在相同的 n 列，排除这些列其余行的该数字；行列互换亦然
+    fn fish(&mut self, n: usize) -> bool {
+        for digit in 1..10 {
+            for by_row in [true, false] {
+                let at = |line: usize, k: usize| if by_row { line * 9 + k } else { k * 9 + line };
+                // 每条线上数字可填的位置
+                let lines: Vec<(usize, Vec<usize>)> = (0..9)
+                    .map(|line| {
+                        let ks = (0..9)
+                            .filter(|k| self.cands[at(line, *k)] & bit(digit) != 0)
+                            .collect::<Vec<usize>>();
+                        (line, ks)
+                    })
+                    .filter(|(_, ks)| (2..=n).contains(&ks.len()))
+                    .collect();
+                let indices: Vec<usize> = (0..lines.len()).collect();
+                for combo in combinations(&indices, n) {
+                    let mut cover: Vec<usize> = vec![];
+                    for i in &combo {
+                        for k in &lines[*i].1 {
+                            if !cover.contains(k) {
+                                cover.push(*k);
+                            }
+                        }
+                    }
+                    if cover.len() != n {
+                        continue;
+                    }
+                    let base: Vec<usize> = combo.iter().map(|i| lines[*i].0).collect();
+                    let rest: Vec<usize> = (0..9)
+                        .filter(|line| !base.contains(line))
+                        .flat_map(|line| cover.iter().map(move |k| (line, *k)))
+                        .map(|(line, k)| at(line, k))
+                        .collect();
+                    if self.eliminate(rest, bit(digit)) {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+}
+
+/// 按给定顺序尝试技巧进行推理，返回用到的技巧以及是否解出
+fn replay(grid: &Grid, variant: &Variant, order: &[Technique]) -> (Vec<Technique>, bool) {
+    let mut steps = vec![];
+    let solved = match Candidates::new(grid, variant) {
+        Some(mut board) => 'solve: loop {
+            if board.solved() {
+                break true;
+            }
+            if board.broken() {
+                break false;
+            }
+            for &technique in order {
+                if board.apply(technique) {
+                    steps.push(technique);
+                    continue 'solve;
+                }
+            }
+            break false;
+        },
+        None => false,
+    };
+    (steps, solved)
+}
+
+/// 空位的提示信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellHint {
+    /// 位置
+    pub cell: Cell,
+    /// 候选数字的个数
+    pub candidates: u32,
+    /// 能填出该位置的最简单技巧：唯余法、摒除法，或先用该技巧排除后再用唯余法或摒除法填入。
+    /// 现有技巧都不能填出时为 None
+    pub technique: Option<Technique>,
+    /// 填入的数字
+    pub digit: Option<u32>,
+}
+
+impl CellHint {
+    /// 输出为 JSON，不能填出时 technique 和 digit 为 null
+    pub fn to_json(&self) -> Json {
+        Json::Object(vec![
+            (
+                "cell".to_string(),
+                format!("r{}c{}", self.cell.0 + 1, self.cell.1 + 1).into(),
+            ),
+            ("candidates".to_string(), self.candidates.into()),
+            (
+                "technique".to_string(),
+                self.technique.map_or(Json::Null, |t| t.name().into()),
+            ),
+            (
+                "digit".to_string(),
+                self.digit.map_or(Json::Null, Json::from),
+            ),
+        ])
+    }
+}
+
+/// 按从易到难排列所有空位：能用越简单的技巧填出的位置越靠前，同一技巧下候选数字越少越靠前。
+/// 题目本身冲突时返回空列表
+pub fn rank_cells(grid: &Grid, variant: &Variant) -> Vec<CellHint> {
+    let Some(board) = Candidates::new(grid, variant) else {
+        return vec![];
+    };
+    // 每种排除类技巧用尽后的候选状态
+    let eliminated: Vec<(Technique, Candidates)> = Technique::ALL
+        .into_iter()
+        .filter(|t| !matches!(t, Technique::NakedSingle | Technique::HiddenSingle))
+        .map(|t| {
+            let mut after = board.clone();
+            while after.apply(t) {}
+            (t, after)
+        })
+        .collect();
+    let mut hints: Vec<CellHint> = (0..81)
+        .filter(|&i| board.values[i] == 0)
+        .map(|i| {
+            let found = board.single_at(i).or_else(|| {
+                eliminated
+                    .iter()
+                    .find_map(|(t, after)| after.single_at(i).map(|(_, d)| (*t, d)))
+            });
+            CellHint {
+                cell: (i / 9, i % 9),
+                candidates: board.cands[i].count_ones(),
+                technique: found.map(|(t, _)| t),
+                digit: found.map(|(_, d)| d),
+            }
+        })
+        .collect();
+    hints.sort_by_key(|h| {
+        let order = h
+            .technique
+            .and_then(|t| Technique::ALL.iter().position(|x| *x == t))
+            .unwrap_or(usize::MAX);
+        (order, h.candidates, h.cell)
+    });
+    hints
+}
+
+/// 按标准规则评定题目难度
+pub fn rate(grid: &Grid) -> Rating {
+    rate_variant(grid, &Variant::standard())
+}
+
+/// 按变体规则评定题目难度，附加的区域和同组关系参与推理，其它约束不参与
+pub fn rate_variant(grid: &Grid, variant: &Variant) -> Rating {
+    let (steps, solved) = replay(grid, variant, &Technique::ALL);
+    let hardest = steps.iter().max().cloned();
+    let difficulty = if solved {
+        hardest
+            .map(Technique::difficulty)
+            .unwrap_or(Difficulty::Easy)
+    } else {
+        Difficulty::Evil
+    };
+    let score = steps.iter().map(|t| t.cost()).sum::<u32>() + if solved { 0 } else { GUESS_COST };
+    Rating {
+        difficulty,
+        hardest,
+        steps,
+        solved,
+        score,
+    }
+}
+
+/// 开始解题时能否使用某种技巧
+pub fn available_at_start(grid: &Grid, variant: &Variant, technique: Technique) -> bool {
+    Candidates::new(grid, variant).is_some_and(|mut board| board.apply(technique))
+}
+
+/// 按技巧阶梯推理时第一个填入数字的位置，推理无法填入任何数字时返回 None
+pub fn first_placement(grid: &Grid, variant: &Variant) -> Option<(usize, usize)> {
+    let mut board = Candidates::new(grid, variant)?;
+    'solve: loop {
+        let before = board.values;
+        for technique in Technique::ALL {
+            if board.apply(technique) {
+                if let Some(i) = (0..81).find(|&i| board.values[i] != before[i]) {
+                    return Some((i / 9, i % 9));
+                }
+                continue 'solve;
+            }
+        }
+        return None;
+    }
+}
+
+/// 只用唯余法和摒除法（naked / hidden single）能否解出
+fn singles_solve(grid: &Grid, variant: &Variant) -> bool {
+    replay(
+        grid,
+        variant,
+        &[Technique::NakedSingle, Technique::HiddenSingle],
+    )
+    .1
+}
+
+/// 后门大小：至少需要猜对几个位置，之后只用唯余法和摒除法就能解出。与解题技巧无关，
+/// 可作为结构上的难度指标。题目无解或超过 max 时返回 None
+pub fn backdoor_size(grid: &Grid, variant: &Variant, max: usize) -> Option<usize> {
+    let board = SudokuBoard::new_with(grid, Arc::new(variant.clone()));
+    let solution = solutions(&board, 1).pop()?;
+    let empty: Vec<usize> = (0..81).filter(|&i| grid[i / 9][i % 9] == 0).collect();
+    for k in 0..=max.min(empty.len()) {
+        for cells in combinations(&empty, k) {
+            let mut guessed = *grid;
+            for i in cells {
+                guessed[i / 9][i % 9] = solution[i / 9][i % 9];
+            }
+            if singles_solve(&guessed, variant) {
+                return Some(k);
+            }
+        }
+    }
+    None
+}
+
+/// 后门位置：给出该位置的正确数字后，只用唯余法和摒除法就能解出。题目无解时返回 None，
+/// 不需要猜测就能解出时返回空列表
+pub fn backdoor_cells(grid: &Grid, variant: &Variant) -> Option<Vec<(Cell, u32)>> {
+    let board = SudokuBoard::new_with(grid, Arc::new(variant.clone()));
+    let solution = solutions(&board, 1).pop()?;
+    if singles_solve(grid, variant) {
+        return Some(vec![]);
+    }
+    let mut cells = vec![];
+    for row in 0..9 {
+        for col in 0..9 {
+            if grid[row][col] != 0 {
+                continue;
+            }
+            let mut guessed = *grid;
+            guessed[row][col] = solution[row][col];
+            if singles_solve(&guessed, variant) {
+                cells.push(((row, col), solution[row][col]));
+            }
+        }
+    }
+    Some(cells)
+}
+
+/// 近似的 SE 评分（乘以 10），无法仅靠已实现的技巧解出时返回 None
+pub fn rate_se(grid: &Grid) -> Option<u32> {
+    match replay(grid, &Variant::standard(), &Technique::SE_ORDER) {
+        (steps, true) => Some(steps.iter().map(|t| t.se_rating()).max().unwrap_or(0)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        available_at_start, backdoor_cells, backdoor_size, first_placement, rank_cells, rate,
+        rate_se, Difficulty, Technique, GUESS_COST,
+    };
+    use crate::variant::Variant;
+
+    /// 将 81 个字符转为棋盘
+    fn grid(s: &str) -> [[u32; 9]; 9] {
+        let digits: Vec<u32> = s.chars().map(|c| c.to_digit(10).unwrap_or(0)).collect();
+        std::array::from_fn(|r| std::array::from_fn(|c| digits[r * 9 + c]))
+    }
+
+    #[test]
+    fn test_rate_easy() {
+        let rating = rate(&grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        ));
+        assert!(rating.solved);
+        assert_eq!(rating.difficulty, Difficulty::Easy);
+        assert_eq!(rating.steps.len(), 51);
+        assert_eq!(rating.grade(), "easy");
+        let cost =
+            |t: Technique| t.cost() * rating.steps.iter().filter(|s| **s == t).count() as u32;
+        assert_eq!(
+            rating.score,
+            cost(Technique::NakedSingle) + cost(Technique::HiddenSingle)
+        );
+    }
+
+    #[test]
+    fn test_rate_harder() {
+        // 需要 X-wing 的题目
+        let rating = rate(&grid(
+            "100000569492056108056109240009640801064010000218035604040500016905061402621000005",
+        ));
+        assert!(rating.solved);
+        assert_eq!(rating.hardest, Some(Technique::XWing));
+        assert_eq!(rating.difficulty, Difficulty::Expert);
+
+        // 需要猜测的题目
+        let rating = rate(&grid(
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+        ));
+        assert!(!rating.solved);
+        assert_eq!(rating.difficulty, Difficulty::Evil);
+        assert!(rating.score >= GUESS_COST);
+    }
+
+    #[test]
+    fn test_start() {
+        let variant = Variant::standard();
+        let easy = grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        );
+        assert!(available_at_start(&easy, &variant, Technique::NakedSingle));
+        // r5c5 只剩 5 一个候选
+        assert_eq!(first_placement(&easy, &variant), Some((4, 4)));
+
+        let evil = grid(
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+        );
+        assert!(!available_at_start(&evil, &variant, Technique::NakedSingle));
+        assert!(first_placement(&evil, &variant).is_none());
+    }
+
+    #[test]
+    fn test_backdoor_size() {
+        let variant = Variant::standard();
+        let easy = grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        );
+        assert_eq!(backdoor_size(&easy, &variant, 2), Some(0));
+        let xwing = grid(
+            "100000569492056108056109240009640801064010000218035604040500016905061402621000005",
+        );
+        assert_eq!(backdoor_size(&xwing, &variant, 2), Some(1));
+        let mut broken = easy;
+        broken[0][2] = 5;
+        assert_eq!(backdoor_size(&broken, &variant, 2), None);
+
+        assert_eq!(backdoor_cells(&easy, &variant), Some(vec![]));
+        let cells = backdoor_cells(&xwing, &variant).unwrap();
+        assert!(!cells.is_empty());
+        for ((row, col), digit) in cells {
+            assert_eq!(xwing[row][col], 0);
+            let mut given = xwing;
+            given[row][col] = digit;
+            assert_eq!(backdoor_size(&given, &variant, 0), Some(0));
+        }
+        assert_eq!(backdoor_cells(&broken, &variant), None);
+    }
+
+    #[test]
+    fn test_rate_se() {
+        // 只需唯余法
+        let se = rate_se(&grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        ));
+        assert!(se.unwrap() <= 23);
+        let se = rate_se(&grid(
+            "100000569492056108056109240009640801064010000218035604040500016905061402621000005",
+        ));
+        assert_eq!(se, Some(32));
+        let se = rate_se(&grid(
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+        ));
+        assert_eq!(se, None);
+    }
+
+    #[test]
+    fn test_rank_cells() {
+        let variant = Variant::standard();
+        let easy = grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        );
+        let hints = rank_cells(&easy, &variant);
+        assert_eq!(hints.len(), 51);
+        assert!(hints[0]
+            .technique
+            .is_some_and(|t| t <= Technique::HiddenSingle));
+        // 排序：能填出的在前，同一技巧下候选数字少的在前
+        let order = |t: Option<Technique>| {
+            t.and_then(|t| Technique::ALL.iter().position(|x| *x == t))
+                .unwrap_or(usize::MAX)
+        };
+        assert!(hints.windows(2).all(|w| {
+            (order(w[0].technique), w[0].candidates) <= (order(w[1].technique), w[1].candidates)
+        }));
+        // 给出的数字与解一致
+        let solution = grid(
+            "534678912672195348198342567859761423426853791713924856961537284287419635345286179",
+        );
+        for hint in &hints {
+            if let Some(digit) = hint.digit {
+                assert_eq!(solution[hint.cell.0][hint.cell.1], digit);
+            }
+        }
+    }
+}
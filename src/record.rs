@@ -0,0 +1,310 @@
+//! 求解过程的记录与回放
+//!
+//! 记录从给定数字开始的每一轮推理中填入的数字和排除的候选，推理停滞时记录猜测。只保留通向第一个解的
+//! 猜测，走入死路的分支不记录。排除的候选中，由同一轮填入的数字直接导致的不单独记录。
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::board::{grid_line, parse_grid, Grid, SudokuBoard};
+use crate::constraint::{parse_cell, Cell};
+use crate::json::Json;
+use crate::solver::most_constrained;
+use crate::variant::Variant;
+
+/// 事件的种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// 推理填入数字
+    Place,
+    /// 推理排除候选
+    Eliminate,
+    /// 推理停滞时猜测
+    Guess,
+}
+
+impl EventKind {
+    /// 名称
+    pub fn name(self) -> &'static str {
+        match self {
+            EventKind::Place => "place",
+            EventKind::Eliminate => "eliminate",
+            EventKind::Guess => "guess",
+        }
+    }
+
+    /// 由名称得到种类
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "place" => Some(EventKind::Place),
+            "eliminate" => Some(EventKind::Eliminate),
+            "guess" => Some(EventKind::Guess),
+            _ => None,
+        }
+    }
+}
+
+/// 一个事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Event {
+    /// 第几轮推理，从 1 开始
+    pub step: usize,
+    /// 种类
+    pub kind: EventKind,
+    /// 位置
+    pub cell: Cell,
+    /// 数字
+    pub digit: u32,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (row, col) = self.cell;
+        match self.kind {
+            EventKind::Place => write!(f, "r{}c{} = {}", row + 1, col + 1, self.digit),
+            EventKind::Eliminate => write!(f, "r{}c{} <> {}", row + 1, col + 1, self.digit),
+            EventKind::Guess => write!(f, "r{}c{} = {} (guess)", row + 1, col + 1, self.digit),
+        }
+    }
+}
+
+/// 一道题目的求解记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recording {
+    /// 题目给定的数字
+    pub givens: Grid,
+    /// 依次发生的事件
+    pub events: Vec<Event>,
+    /// 是否解出
+    pub solved: bool,
+}
+
+impl Recording {
+    /// 求解并记录
+    pub fn record(givens: &Grid, variant: Arc<Variant>) -> Self {
+        let board = SudokuBoard::new_with(givens, variant);
+        let mut events = vec![];
+        let solved = walk(board, &mut events);
+        Self {
+            givens: *givens,
+            events,
+            solved,
+        }
+    }
+
+    /// 输出为 JSON
+    pub fn to_json(&self) -> Json {
+        let events: Vec<Json> = self
+            .events
+            .iter()
+            .map(|e| {
+                Json::Object(vec![
+                    ("step".to_string(), e.step.into()),
+                    ("kind".to_string(), e.kind.name().into()),
+                    (
+                        "cell".to_string(),
+                        format!("r{}c{}", e.cell.0 + 1, e.cell.1 + 1).into(),
+                    ),
+                    ("digit".to_string(), e.digit.into()),
+                ])
+            })
+            .collect();
+        Json::Object(vec![
+            ("givens".to_string(), grid_line(&self.givens).into()),
+            ("solved".to_string(), self.solved.into()),
+            ("events".to_string(), Json::Array(events)),
+        ])
+    }
+
+    /// 从 JSON 读取
+    pub fn from_json(json: &Json) -> Result<Self, String> {
+        let givens = json
+            .get("givens")
+            .and_then(Json::as_str)
+            .ok_or("missing givens")?;
+        let givens = parse_grid(givens)?;
+        let solved = json.get("solved").and_then(Json::as_bool).unwrap_or(false);
+        let mut events = vec![];
+        for item in json.get("events").and_then(Json::as_array).unwrap_or(&[]) {
+            let field = |key: &str| item.get(key).ok_or(format!("event without {}", key));
+            let kind = field("kind")?.as_str().and_then(EventKind::from_name);
+            let cell = field("cell")?.as_str().and_then(parse_cell);
+            let digit = field("digit")?.as_u32().filter(|d| (1..10).contains(d));
+            let step = field("step")?.as_u32();
+            match (kind, cell, digit, step) {
+                (Some(kind), Some(cell), Some(digit), Some(step)) => events.push(Event {
+                    step: step as usize,
+                    kind,
+                    cell,
+                    digit,
+                }),
+                _ => return Err(format!("invalid event {}", item)),
+            }
+        }
+        Ok(Self {
+            givens,
+            events,
+            solved,
+        })
+    }
+}
+
+/// 推理直到停滞，需要时猜测，记录通向解的事件。返回是否解出
+fn walk(mut board: SudokuBoard, events: &mut Vec<Event>) -> bool {
+    loop {
+        let before = board.clone();
+        let result = board.step();
+        let step = events.last().map_or(0, |e| e.step) + 1;
+        record_step(&before, &board, step, events);
+        match result {
+            Some(true) => return true,
+            Some(false) => break,
+            None => {}
+        }
+    }
+    let Some((row, col)) = most_constrained(&board) else {
+        return false;
+    };
+    let mut digits: Vec<u32> = board.get(row, col).digits.iter().cloned().collect();
+    digits.sort();
+    for digit in digits {
+        let len = events.len();
+        let step = events.last().map_or(0, |e| e.step) + 1;
+        events.push(Event {
+            step,
+            kind: EventKind::Guess,
+            cell: (row, col),
+            digit,
+        });
+        let mut guess = board.clone();
+        guess.set(digit, row, col);
+        if walk(guess, events) {
+            return true;
+        }
+        // 死路，丢弃这个分支的记录
+        events.truncate(len);
+    }
+    false
+}
+
+/// 比较一轮推理前后的棋盘，记录填入的数字和不由填入直接导致的排除
+fn record_step(before: &SudokuBoard, after: &SudokuBoard, step: usize, events: &mut Vec<Event>) {
+    let mut placed = vec![];
+    for row in 0..9 {
+        for col in 0..9 {
+            let val = after.get(row, col).val;
+            if before.get(row, col).val == 0 && val != 0 {
+                placed.push(((row, col), val));
+                events.push(Event {
+                    step,
+                    kind: EventKind::Place,
+                    cell: (row, col),
+                    digit: val,
+                });
+            }
+        }
+    }
+    let peers = after.variant().peers();
+    for row in 0..9 {
+        for col in 0..9 {
+            if after.get(row, col).val != 0 {
+                continue;
+            }
+            let mut removed: Vec<u32> = before
+                .get(row, col)
+                .digits
+                .difference(&after.get(row, col).digits)
+                .cloned()
+                .collect();
+            removed.sort();
+            for digit in removed {
+                let implied = placed
+                    .iter()
+                    .any(|(cell, d)| *d == digit && peers.of(row, col).contains(cell));
+                if !implied {
+                    events.push(Event {
+                        step,
+                        kind: EventKind::Eliminate,
+                        cell: (row, col),
+                        digit,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// 带 3x3 小格边框的盘面，空位显示为 `.`
+pub fn pretty(grid: &Grid) -> String {
+    let border = "+-------+-------+-------+";
+    let mut lines = vec![border.to_string()];
+    for row in 0..9 {
+        let mut line = String::from("|");
+        for col in 0..9 {
+            match grid[row][col] {
+                0 => line.push_str(" ."),
+                d => line.push_str(&format!(" {}", d)),
+            }
+            if col % 3 == 2 {
+                line.push_str(" |");
+            }
+        }
+        lines.push(line);
+        if row % 3 == 2 {
+            lines.push(border.to_string());
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{pretty, EventKind, Recording};
+    use crate::board::parse_grid;
+    use crate::json::Json;
+    use crate::variant::Variant;
+
+    #[test]
+    fn test_record() {
+        let givens = parse_grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        )
+        .unwrap();
+        let recording = Recording::record(&givens, Arc::new(Variant::standard()));
+        assert!(recording.solved);
+        let placed = recording
+            .events
+            .iter()
+            .filter(|e| e.kind == EventKind::Place)
+            .count();
+        assert_eq!(placed, 51);
+
+        // 按顺序填入后得到解
+        let mut grid = givens;
+        for e in &recording.events {
+            if e.kind != EventKind::Eliminate {
+                grid[e.cell.0][e.cell.1] = e.digit;
+            }
+        }
+        assert!(Variant::standard().check(&grid));
+        assert!(grid.iter().flatten().all(|d| *d != 0));
+
+        let text = recording.to_json().to_string();
+        let parsed = Recording::from_json(&Json::parse(&text).unwrap()).unwrap();
+        assert_eq!(parsed, recording);
+    }
+
+    #[test]
+    fn test_record_guess() {
+        let givens = parse_grid(
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+        )
+        .unwrap();
+        let recording = Recording::record(&givens, Arc::new(Variant::standard()));
+        assert!(recording.solved);
+        assert!(recording.events.iter().any(|e| e.kind == EventKind::Guess));
+        assert!(pretty(&givens).starts_with("+-------+-------+-------+\n| 8 . . |"));
+    }
+}
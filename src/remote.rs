@@ -0,0 +1,250 @@
+//! `superdo serve` 的客户端
+//!
+//! 把题目发给远程的服务求解、计数或评级，结果在本地按所选格式输出，
+//! 配置较低的机器可以把耗时的列举交给更强的机器。
+
+use std::time::Duration;
+
+use crate::board::{grid_line, parse_grid, Grid};
+use crate::bot::Webhook;
+use crate::fetch::http_post;
+use crate::json::Json;
+
+/// 服务返回的错误或连接失败
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteError {
+    /// 错误类型，与 `--format json` 的错误对象相同；连接失败时为 `unreachable`
+    pub kind: String,
+    pub message: String,
+    /// 错误对象中的其他字段
+    pub details: Vec<(String, Json)>,
+}
+
+impl RemoteError {
+    fn new(kind: &str, message: String) -> Self {
+        Self {
+            kind: kind.to_string(),
+            message,
+            details: vec![],
+        }
+    }
+
+    /// 是否因连接或响应格式有误而失败，而非服务对这道题目报错
+    pub fn is_transport(&self) -> bool {
+        matches!(self.kind.as_str(), "unreachable" | "bad_response")
+    }
+}
+
+/// 远程评级的结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteRating {
+    pub grade: String,
+    pub score: u32,
+    /// 用到的最难技巧，没有时为 None
+    pub hardest: Option<String>,
+    /// 不经猜测即可解出
+    pub solved: bool,
+    pub steps: Vec<String>,
+}
+
+/// 远程服务，地址形如 `http://solver:8080`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Client {
+    /// 服务的地址，不含末尾的 `/`
+    base: String,
+    /// 等待响应的时间，None 为一直等待，由服务自己的计算时间限制兜底
+    pub timeout: Option<Duration>,
+    /// 服务要求认证时使用的令牌
+    pub token: Option<String>,
+}
+
+impl Client {
+    /// 检查地址，只支持 http://
+    pub fn new(host: &str) -> Result<Self, String> {
+        Webhook::parse(host)?;
+        Ok(Self {
+            base: host.trim_end_matches('/').to_string(),
+            timeout: None,
+            token: None,
+        })
+    }
+
+    /// 列出至多 limit 个解
+    pub fn solve(&self, givens: &Grid, limit: usize) -> Result<Vec<Grid>, RemoteError> {
+        let body = self.call("solve", givens, Some(("limit", limit)))?;
+        body.get("solutions")
+            .and_then(Json::as_array)
+            .and_then(|found| {
+                found
+                    .iter()
+                    .map(|s| s.as_str().and_then(|s| parse_grid(s).ok()))
+                    .collect()
+            })
+            .ok_or_else(|| bad_response("solutions"))
+    }
+
+    /// 统计解的个数，数到 cap 为止（未给出时为服务的上限），返回个数和是否已达到上限
+    pub fn count(&self, givens: &Grid, cap: Option<usize>) -> Result<(usize, bool), RemoteError> {
+        let body = self.call("count", givens, cap.map(|cap| ("cap", cap)))?;
+        let count = body.get("count").and_then(Json::as_u32);
+        let capped = body.get("capped").and_then(Json::as_bool);
+        match (count, capped) {
+            (Some(count), Some(capped)) => Ok((count as usize, capped)),
+            _ => Err(bad_response("count")),
+        }
+    }
+
+    /// 按人工解题技巧评级
+    pub fn rate(&self, givens: &Grid) -> Result<RemoteRating, RemoteError> {
+        let body = self.call("rate", givens, None)?;
+        let text = |key: &str| body.get(key).and_then(Json::as_str).map(str::to_string);
+        let steps: Option<Vec<String>> =
+            body.get("steps")
+                .and_then(Json::as_array)
+                .and_then(|steps| {
+                    steps
+                        .iter()
+                        .map(|s| s.as_str().map(str::to_string))
+                        .collect()
+                });
+        match (
+            text("grade"),
+            body.get("score").and_then(Json::as_u32),
+            body.get("solved").and_then(Json::as_bool),
+            steps,
+        ) {
+            (Some(grade), Some(score), Some(solved), Some(steps)) => Ok(RemoteRating {
+                grade,
+                score,
+                hardest: text("hardest"),
+                solved,
+                steps,
+            }),
+            _ => Err(bad_response("rate")),
+        }
+    }
+
+    /// POST 一道题目到 `/v1/{endpoint}`，返回成功的响应体
+    fn call(
+        &self,
+        endpoint: &str,
+        givens: &Grid,
+        extra: Option<(&str, usize)>,
+    ) -> Result<Json, RemoteError> {
+        let mut request = vec![("puzzle".to_string(), grid_line(givens).into())];
+        request.extend(extra.map(|(key, n)| (key.to_string(), n.into())));
+        let url = format!("{}/v1/{}", self.base, endpoint);
+        let authorization = self.token.as_ref().map(|token| format!("Bearer {}", token));
+        let headers: Vec<(&str, &str)> = authorization
+            .iter()
+            .map(|value| ("Authorization", value.as_str()))
+            .collect();
+        let body = Json::Object(request).to_string();
+        let (status, body) = http_post(&url, &headers, &body, self.timeout)
+            .map_err(|e| RemoteError::new("unreachable", e))?;
+        let body = Json::parse(&body).map_err(|_| {
+            RemoteError::new(
+                "bad_response",
+                format!("{}: server answered {} without a JSON body", url, status),
+            )
+        })?;
+        if status == 200 {
+            return Ok(body);
+        }
+        // 错误对象 `{"error": {"kind": ..., "message": ..., ...}}`
+        let Some(Json::Object(fields)) = body.get("error") else {
+            return Err(RemoteError::new(
+                "bad_response",
+                format!("{}: server answered {}", url, status),
+            ));
+        };
+        let mut error = RemoteError::new("remote", format!("server answered {}", status));
+        for (key, value) in fields {
+            match (key.as_str(), value.as_str()) {
+                ("kind", Some(kind)) => error.kind = kind.to_string(),
+                ("message", Some(message)) => error.message = message.to_string(),
+                _ => error.details.push((key.clone(), value.clone())),
+            }
+        }
+        Err(error)
+    }
+}
+
+fn bad_response(endpoint: &str) -> RemoteError {
+    RemoteError::new(
+        "bad_response",
+        format!("unexpected response from /v1/{}", endpoint),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::Client;
+    use crate::auth::ApiToken;
+    use crate::board::{grid_line, parse_grid};
+    use crate::server::{Server, ServerOptions};
+
+    const PUZZLE: &str =
+        "530070000600195000098000060800060003400803001700020006060000280000419005000080079";
+
+    #[test]
+    fn test_client() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = Arc::new(Server::new(ServerOptions::default()));
+        thread::spawn(move || server.serve(listener));
+        let client = Client::new(&format!("http://{}/", addr)).unwrap();
+
+        let givens = parse_grid(PUZZLE).unwrap();
+        let found = client.solve(&givens, 2).unwrap();
+        assert_eq!(
+            found.iter().map(grid_line).collect::<Vec<_>>(),
+            ["534678912672195348198342567859761423426853791713924856961537284287419635345286179"]
+        );
+        // 去掉 r1c1 和 r1c2，两个解
+        let two = parse_grid(&format!("00{}", &PUZZLE[2..])).unwrap();
+        assert_eq!(client.count(&two, None).unwrap(), (2, false));
+        assert_eq!(client.count(&two, Some(1)).unwrap(), (1, true));
+        let rating = client.rate(&givens).unwrap();
+        assert!(rating.solved && rating.steps.len() > 1);
+
+        // 服务报告的错误
+        let conflict = parse_grid(&format!("55{}", &PUZZLE[2..])).unwrap();
+        let error = client.solve(&conflict, 1).unwrap_err();
+        assert_eq!(error.kind, "conflict");
+        assert!(!error.is_transport() && !error.details.is_empty());
+        let error = client.solve(&givens, 100_000).unwrap_err();
+        assert_eq!(error.kind, "invalid_request");
+
+        // 连接失败
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", closed.local_addr().unwrap());
+        drop(closed);
+        let error = Client::new(&url).unwrap().solve(&givens, 1).unwrap_err();
+        assert!(error.is_transport() && error.message.starts_with(&url));
+        assert!(Client::new("https://solver:8080").is_err());
+    }
+
+    #[test]
+    fn test_token() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = Arc::new(Server::new(ServerOptions {
+            tokens: ApiToken::parse_list("s3cret:1").unwrap(),
+            ..Default::default()
+        }));
+        thread::spawn(move || server.serve(listener));
+        let mut client = Client::new(&format!("http://{}", addr)).unwrap();
+        let givens = parse_grid(PUZZLE).unwrap();
+        assert_eq!(client.solve(&givens, 1).unwrap_err().kind, "unauthorized");
+        client.token = Some("wrong".to_string());
+        assert_eq!(client.solve(&givens, 1).unwrap_err().kind, "unauthorized");
+        client.token = Some("s3cret".to_string());
+        assert_eq!(client.solve(&givens, 1).unwrap().len(), 1);
+        assert_eq!(client.solve(&givens, 1).unwrap_err().kind, "rate_limited");
+    }
+}
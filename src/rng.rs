@@ -0,0 +1,135 @@
+//! 简单的伪随机数生成器（SplitMix64），用于生成题目，相同种子得到相同结果
+//!
+//! 每日题目的种子由 UTC 日期和可选的命名空间得到，同一天所有人生成相同的题目。
+
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 日期（UTC）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Date {
+    /// 年
+    pub year: i64,
+    /// 月，1~12
+    pub month: u32,
+    /// 日，1~31
+    pub day: u32,
+}
+
+impl Date {
+    /// 1970-01-01 之后第 days 天
+    pub fn from_days(days: i64) -> Self {
+        // 参见 Howard Hinnant 的 civil_from_days
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        Self { year, month, day }
+    }
+
+    /// 今天
+    pub fn today() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self::from_days((secs / 86_400) as i64)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// 伪随机数生成器
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// 使用指定种子
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// 使用当前时间作为种子
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Self::new(nanos)
+    }
+
+    /// 每日题目的种子，同一日期和命名空间得到相同的种子
+    pub fn daily(date: Date, namespace: &str) -> Self {
+        // FNV-1a
+        let key = format!("{}/{}", date, namespace);
+        let seed = key.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+            (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+        Self::new(seed)
+    }
+
+    /// 下一个随机数
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// [0, n) 之间的随机数
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// 随机打乱
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i + 1);
+            items.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Date, Rng};
+
+    #[test]
+    fn test_rng() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        assert_eq!(a.next_u64(), b.next_u64());
+        assert!((0..100).all(|_| a.below(9) < 9));
+
+        let mut items: Vec<u32> = (1..10).collect();
+        a.shuffle(&mut items);
+        items.sort();
+        assert_eq!(items, (1..10).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn test_daily() {
+        assert_eq!(Date::from_days(0).to_string(), "1970-01-01");
+        assert_eq!(Date::from_days(11_016).to_string(), "2000-02-29");
+        assert_eq!(Date::from_days(20_742).to_string(), "2026-10-16");
+
+        let date = Date::from_days(20_742);
+        let next = Date::from_days(20_743);
+        let seed = |date, ns| Rng::daily(date, ns).next_u64();
+        assert_eq!(seed(date, ""), seed(date, ""));
+        assert_ne!(seed(date, ""), seed(next, ""));
+        assert_ne!(seed(date, ""), seed(date, "club"));
+    }
+}
@@ -0,0 +1,224 @@
+//! 计分与最佳用时
+//!
+//! 一局的分数为难度的基础分减去计分用时的秒数，计分用时是已用时间加上每次提示的
+//! [`HINT_PENALTY`]。每个难度保留计分用时最短的 [`BEST_TIMES`] 局，记录在配置目录中。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config::config_dir;
+use crate::json::Json;
+use crate::rating::Difficulty;
+
+/// 每次提示计入的用时
+pub const HINT_PENALTY: Duration = Duration::from_secs(30);
+
+/// 每个难度保留的记录数
+pub const BEST_TIMES: usize = 10;
+
+/// 各难度的基础分
+fn base_points(difficulty: Difficulty) -> u32 {
+    match difficulty {
+        Difficulty::Easy => 1000,
+        Difficulty::Medium => 2000,
+        Difficulty::Hard => 3000,
+        Difficulty::Expert => 4000,
+        Difficulty::Evil => 5000,
+    }
+}
+
+/// 计分用时：已用时间加上提示的罚时
+pub fn score_time(elapsed: Duration, hints: u32) -> Duration {
+    elapsed + HINT_PENALTY * hints
+}
+
+/// 分数，不低于 0
+pub fn score(difficulty: Difficulty, elapsed: Duration, hints: u32) -> u32 {
+    let secs = score_time(elapsed, hints).as_secs();
+    base_points(difficulty).saturating_sub(secs.min(u32::MAX as u64) as u32)
+}
+
+/// 一局的成绩
+#[derive(Debug, Clone, PartialEq)]
+pub struct BestTime {
+    /// 已用秒数，不含罚时
+    pub secs: f64,
+    /// 提示次数
+    pub hints: u32,
+    /// 分数
+    pub score: u32,
+}
+
+impl BestTime {
+    /// 某一难度下一局的成绩
+    pub fn new(difficulty: Difficulty, elapsed: Duration, hints: u32) -> Self {
+        Self {
+            secs: elapsed.as_secs_f64(),
+            hints,
+            score: score(difficulty, elapsed, hints),
+        }
+    }
+
+    /// 计分用时
+    pub fn score_time(&self) -> Duration {
+        score_time(Duration::from_secs_f64(self.secs), self.hints)
+    }
+
+    fn to_json(&self) -> Json {
+        Json::Object(vec![
+            ("secs".to_string(), self.secs.into()),
+            ("hints".to_string(), self.hints.into()),
+            ("score".to_string(), self.score.into()),
+        ])
+    }
+
+    fn from_json(json: &Json) -> Option<Self> {
+        Some(Self {
+            secs: json
+                .get("secs")?
+                .as_f64()
+                .filter(|s| s.is_finite() && *s >= 0.0)?,
+            hints: json.get("hints")?.as_u32()?,
+            score: json.get("score")?.as_u32()?,
+        })
+    }
+}
+
+/// 各难度的最佳用时，按计分用时从短到长排列
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BestTimes {
+    table: Vec<(Difficulty, Vec<BestTime>)>,
+}
+
+impl BestTimes {
+    /// 默认的记录文件，配置目录中的 `best-times.json`
+    pub fn default_path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("best-times.json"))
+    }
+
+    /// 某一难度的记录
+    pub fn get(&self, difficulty: Difficulty) -> &[BestTime] {
+        self.table
+            .iter()
+            .find(|(d, _)| *d == difficulty)
+            .map_or(&[], |(_, times)| times)
+    }
+
+    /// 记入一局的成绩，进入前 [`BEST_TIMES`] 名时返回名次（从 1 开始）
+    pub fn record(&mut self, difficulty: Difficulty, time: BestTime) -> Option<usize> {
+        let index = match self.table.iter().position(|(d, _)| *d == difficulty) {
+            Some(index) => index,
+            None => {
+                self.table.push((difficulty, vec![]));
+                self.table.sort_by_key(|(d, _)| *d);
+                self.table.iter().position(|(d, _)| *d == difficulty)?
+            }
+        };
+        let times = &mut self.table[index].1;
+        let rank = times.partition_point(|t| t.score_time() <= time.score_time());
+        if rank >= BEST_TIMES {
+            return None;
+        }
+        times.insert(rank, time);
+        times.truncate(BEST_TIMES);
+        Some(rank + 1)
+    }
+
+    /// JSON 形式，以难度名称为键
+    pub fn to_json(&self) -> Json {
+        Json::Object(
+            self.table
+                .iter()
+                .map(|(d, times)| {
+                    let times = times.iter().map(BestTime::to_json).collect();
+                    (d.name().to_string(), Json::Array(times))
+                })
+                .collect(),
+        )
+    }
+
+    /// 从 JSON 形式读取，忽略未知的难度和不完整的记录
+    pub fn from_json(json: &Json) -> Self {
+        let mut best = Self::default();
+        for difficulty in [
+            Difficulty::Easy,
+            Difficulty::Medium,
+            Difficulty::Hard,
+            Difficulty::Expert,
+            Difficulty::Evil,
+        ] {
+            let times = json.get(difficulty.name()).and_then(Json::as_array);
+            for time in times.unwrap_or(&[]).iter().filter_map(BestTime::from_json) {
+                best.record(difficulty, time);
+            }
+        }
+        best
+    }
+
+    /// 读取记录文件，文件不存在时为空
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+        let json = Json::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Self::from_json(&json))
+    }
+
+    /// 写入记录文件，必要时创建所在目录
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        path.parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&tmp, format!("{}\n", self.to_json())))
+            .and_then(|_| fs::rename(&tmp, path))
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::process;
+    use std::time::Duration;
+
+    use super::{score, BestTime, BestTimes, BEST_TIMES};
+    use crate::rating::Difficulty;
+
+    #[test]
+    fn test_score() {
+        let minutes = |m: u64| Duration::from_secs(m * 60);
+        assert_eq!(score(Difficulty::Easy, minutes(5), 0), 700);
+        assert_eq!(score(Difficulty::Easy, minutes(5), 2), 640);
+        assert_eq!(score(Difficulty::Easy, minutes(20), 0), 0);
+        assert_eq!(score(Difficulty::Expert, minutes(20), 0), 2800);
+    }
+
+    #[test]
+    fn test_best_times() {
+        let mut best = BestTimes::default();
+        let time = |secs, hints| BestTime::new(Difficulty::Hard, Duration::from_secs(secs), hints);
+        assert_eq!(best.record(Difficulty::Hard, time(300, 0)), Some(1));
+        // 一次提示罚 30 秒
+        assert_eq!(best.record(Difficulty::Hard, time(280, 1)), Some(2));
+        assert_eq!(best.record(Difficulty::Hard, time(200, 0)), Some(1));
+        assert_eq!(best.record(Difficulty::Easy, time(900, 0)), Some(1));
+        assert_eq!(best.get(Difficulty::Hard)[2].hints, 1);
+        assert!(best.get(Difficulty::Medium).is_empty());
+        for _ in 0..BEST_TIMES {
+            best.record(Difficulty::Easy, time(100, 0));
+        }
+        assert_eq!(best.get(Difficulty::Easy).len(), BEST_TIMES);
+        assert_eq!(best.record(Difficulty::Easy, time(100, 0)), None);
+
+        let path = env::temp_dir().join(format!("superdo-best-{}", process::id()));
+        let file = path.join("best-times.json");
+        assert_eq!(BestTimes::load(&file), Ok(BestTimes::default()));
+        best.save(&file).unwrap();
+        assert_eq!(BestTimes::load(&file), Ok(best));
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
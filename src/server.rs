@@ -0,0 +1,1650 @@
+//! HTTP 服务
+//!
+//! 请求和响应都是 JSON：`POST /v1/solve`、`/v1/count`、`/v1/rate`、`/v1/step`、`/v1/generate`，
+//! `GET /v1/schema` 给出各接口请求体的 JSON Schema。失败时返回与 `--format json` 相同的错误对象。
+//! WebSocket 接口 `GET /v1/enumerate?puzzle=...&limit=N` 逐个推送解，并定时推送进度。
+//! `GET /` 是使用这些接口的网页界面。
+//! 每个连接一个线程，限制同时处理的连接数、请求体大小、解的个数和每个请求的计算时间。
+//! 同时计算的请求数也有上限，其余的请求排队等待，队列满了返回 429。
+//! 设置了令牌时 `/v1/` 下的接口需要认证，见 [`crate::auth`]。
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+
+use crate::auth::{ApiToken, Auth};
+use crate::board::{grid_line, parse_grid, Deduction, Grid, SudokuBoard};
+use crate::cache::{CacheKey, SolveCache};
+use crate::generator::{generate, GenerateOptions};
+use crate::json::Json;
+use crate::rating::{rate, Difficulty};
+use crate::rng::Rng;
+use crate::solver::{error_json, solutions_until, Propagating, SearchProgress};
+use crate::variant::Variant;
+use crate::websocket;
+
+/// 请求体的最大字节数
+const MAX_BODY: usize = 64 * 1024;
+
+/// 请求行和请求头合计的最大字节数
+const MAX_HEADER: u64 = 8 * 1024;
+
+/// 读取请求的超时
+pub(crate) const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 列举解时推送进度的间隔
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 网页界面
+const GUI_PAGE: &str = include_str!("gui.html");
+
+/// 请求字段的取值，决定字段的 JSON Schema 和读取方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// 81 个数字的题目，给定数字不能冲突
+    Puzzle,
+    /// 解的个数，从 1 到服务的上限
+    Count,
+    /// 随机种子，小于 2^53 的非负整数
+    Seed,
+    /// 难度的名称
+    Difficulty,
+}
+
+/// 请求体或查询参数中的一个字段。文档和参数的读取都按字段表进行，两者不会不一致
+#[derive(Debug)]
+struct Field {
+    name: &'static str,
+    kind: Kind,
+    required: bool,
+    description: &'static str,
+}
+
+impl Field {
+    /// 字段的 JSON Schema
+    fn schema(&self) -> Json {
+        let mut items: Vec<(String, Json)> = match self.kind {
+            Kind::Puzzle => vec![("type".to_string(), "string".into())],
+            Kind::Count => vec![
+                ("type".to_string(), "integer".into()),
+                ("minimum".to_string(), 1u32.into()),
+            ],
+            Kind::Seed => vec![
+                ("type".to_string(), "integer".into()),
+                ("minimum".to_string(), 0u32.into()),
+            ],
+            Kind::Difficulty => vec![("enum".to_string(), difficulties().into())],
+        };
+        items.push(("description".to_string(), self.description.into()));
+        Json::Object(items)
+    }
+}
+
+/// 题目字段
+const PUZZLE_FIELD: Field = Field {
+    name: "puzzle",
+    kind: Kind::Puzzle,
+    required: true,
+    description: "81 digits, 0 or . for an empty cell",
+};
+
+/// POST 接口的说明及其请求体的字段
+const ENDPOINTS: [(&str, &str, &[Field]); 5] = [
+    (
+        "solve",
+        "List solutions of a standard sudoku",
+        &[
+            PUZZLE_FIELD,
+            Field {
+                name: "limit",
+                kind: Kind::Count,
+                required: false,
+                description: "solutions to list, default 1",
+            },
+        ],
+    ),
+    (
+        "count",
+        "Count solutions up to a cap",
+        &[
+            PUZZLE_FIELD,
+            Field {
+                name: "cap",
+                kind: Kind::Count,
+                required: false,
+                description: "stop counting here, default the server cap",
+            },
+        ],
+    ),
+    (
+        "rate",
+        "Rate a puzzle by the human solving techniques it needs",
+        &[PUZZLE_FIELD],
+    ),
+    (
+        "step",
+        "Run one round of logical deduction",
+        &[Field {
+            name: "puzzle",
+            kind: Kind::Puzzle,
+            required: true,
+            description: "the current board, 0 or . for an empty cell",
+        }],
+    ),
+    (
+        "generate",
+        "Generate a puzzle with a unique solution",
+        &[
+            Field {
+                name: "difficulty",
+                kind: Kind::Difficulty,
+                required: false,
+                description: "the grade of the puzzle, any when omitted",
+            },
+            Field {
+                name: "seed",
+                kind: Kind::Seed,
+                required: false,
+                description: "the same seed gives the same puzzle",
+            },
+        ],
+    ),
+];
+
+/// WebSocket 列举接口的查询参数
+const ENUMERATE_FIELDS: [Field; 2] = [
+    PUZZLE_FIELD,
+    Field {
+        name: "limit",
+        kind: Kind::Count,
+        required: false,
+        description: "solutions to stream, default the server cap",
+    },
+];
+
+/// 难度的名称
+fn difficulties() -> Vec<String> {
+    Difficulty::value_variants()
+        .iter()
+        .filter_map(ValueEnum::to_possible_value)
+        .map(|value| value.get_name().to_string())
+        .collect()
+}
+
+/// 由字段表得出对象的 JSON Schema
+fn object_schema(fields: &[Field]) -> Json {
+    let required: Vec<&str> = fields
+        .iter()
+        .filter(|f| f.required)
+        .map(|f| f.name)
+        .collect();
+    let mut items = vec![("type".to_string(), "object".into())];
+    if !required.is_empty() {
+        items.push(("required".to_string(), required.into()));
+    }
+    let properties = fields
+        .iter()
+        .map(|field| (field.name.to_string(), field.schema()))
+        .collect();
+    items.push(("properties".to_string(), Json::Object(properties)));
+    Json::Object(items)
+}
+
+/// 各接口请求体的 JSON Schema
+fn schema() -> Json {
+    Json::Object(
+        ENDPOINTS
+            .iter()
+            .map(|(name, _, fields)| (name.to_string(), object_schema(fields)))
+            .collect(),
+    )
+}
+
+/// 各接口成功时响应体的 JSON Schema
+const RESPONSES: &str = r#"{
+  "solve": {
+    "type": "object",
+    "properties": {
+      "solutions": {"type": "array", "items": {"type": "string"}, "description": "81 digits each"}
+    }
+  },
+  "count": {
+    "type": "object",
+    "properties": {
+      "count": {"type": "integer"},
+      "capped": {"type": "boolean", "description": "counting stopped at the cap"}
+    }
+  },
+  "rate": {
+    "type": "object",
+    "properties": {
+      "grade": {"enum": ["easy", "medium", "hard", "expert", "evil"]},
+      "score": {"type": "integer"},
+      "hardest": {"type": "string", "nullable": true},
+      "solved": {"type": "boolean", "description": "solved by the techniques without guessing"},
+      "steps": {"type": "array", "items": {"type": "string"}}
+    }
+  },
+  "step": {
+    "type": "object",
+    "properties": {
+      "status": {"enum": ["progress", "solved", "stuck", "contradiction"]},
+      "cells": {
+        "type": "array",
+        "items": {
+          "type": "object",
+          "properties": {
+            "cell": {"type": "string", "description": "r1c1 to r9c9"},
+            "value": {"type": "integer"},
+            "candidates": {"type": "array", "items": {"type": "integer"}}
+          }
+        }
+      }
+    }
+  },
+  "generate": {
+    "type": "object",
+    "properties": {
+      "puzzle": {"type": "string"},
+      "solution": {"type": "string"},
+      "clues": {"type": "integer"},
+      "difficulty": {"enum": ["easy", "medium", "hard", "expert", "evil"]}
+    }
+  }
+}"#;
+
+/// 错误对象的 JSON Schema
+const ERROR: &str = r#"{
+  "type": "object",
+  "required": ["error"],
+  "properties": {
+    "error": {
+      "type": "object",
+      "required": ["kind", "message"],
+      "properties": {"kind": {"type": "string"}, "message": {"type": "string"}},
+      "additionalProperties": true
+    }
+  }
+}"#;
+
+/// 由各接口的请求和响应的 JSON Schema 生成 OpenAPI 3 文档
+pub fn openapi() -> Json {
+    let requests = schema();
+    let responses = Json::parse(RESPONSES).unwrap();
+    let object = |items: Vec<(&str, Json)>| {
+        Json::Object(
+            items
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    };
+    let content =
+        |schema: Json| object(vec![("application/json", object(vec![("schema", schema)]))]);
+    let error = |description: &str| {
+        object(vec![
+            ("description", description.into()),
+            (
+                "content",
+                content(object(vec![("$ref", "#/components/schemas/Error".into())])),
+            ),
+        ])
+    };
+    let mut paths = vec![];
+    for (name, summary, _) in ENDPOINTS {
+        let post = object(vec![
+            ("summary", summary.into()),
+            ("operationId", name.into()),
+            (
+                "requestBody",
+                object(vec![
+                    ("required", true.into()),
+                    ("content", content(requests.get(name).unwrap().clone())),
+                ]),
+            ),
+            (
+                "responses",
+                object(vec![
+                    (
+                        "200",
+                        object(vec![
+                            ("description", "OK".into()),
+                            ("content", content(responses.get(name).unwrap().clone())),
+                        ]),
+                    ),
+                    ("400", error("Invalid request")),
+                    ("401", error("Missing or unknown bearer token")),
+                    ("422", error("Conflicting givens or no puzzle found")),
+                    (
+                        "429",
+                        error(
+                            "Too many requests are waiting, or the token's rate limit is exceeded",
+                        ),
+                    ),
+                    ("504", error("The request took too long")),
+                ]),
+            ),
+        ]);
+        paths.push((format!("/v1/{}", name), object(vec![("post", post)])));
+    }
+    let schema = object(vec![
+        ("summary", "JSON Schemas of the request bodies".into()),
+        ("operationId", "schema".into()),
+        (
+            "responses",
+            object(vec![(
+                "200",
+                object(vec![
+                    ("description", "OK".into()),
+                    ("content", content(object(vec![("type", "object".into())]))),
+                ]),
+            )]),
+        ),
+    ]);
+    paths.push(("/v1/schema".to_string(), object(vec![("get", schema)])));
+    let parameter = |name: &str, schema: Json, required: bool| {
+        object(vec![
+            ("name", name.into()),
+            ("in", "query".into()),
+            ("required", required.into()),
+            ("schema", schema),
+        ])
+    };
+    let enumerate = object(vec![
+        (
+            "summary",
+            "WebSocket streaming solutions, progress and a final done or error message".into(),
+        ),
+        ("operationId", "enumerate".into()),
+        (
+            "parameters",
+            ENUMERATE_FIELDS
+                .iter()
+                .map(|field| parameter(field.name, field.schema(), field.required))
+                .collect::<Vec<_>>()
+                .into(),
+        ),
+        (
+            "responses",
+            object(vec![
+                (
+                    "101",
+                    object(vec![(
+                        "description",
+                        "Switching to the WebSocket protocol".into(),
+                    )]),
+                ),
+                ("400", error("Invalid request")),
+                ("401", error("Missing or unknown bearer token")),
+                ("426", error("Not a WebSocket upgrade")),
+                (
+                    "429",
+                    error("Too many requests are waiting, or the token's rate limit is exceeded"),
+                ),
+            ]),
+        ),
+    ]);
+    paths.push((
+        "/v1/enumerate".to_string(),
+        object(vec![("get", enumerate)]),
+    ));
+    object(vec![
+        ("openapi", "3.0.3".into()),
+        (
+            "info",
+            object(vec![
+                ("title", "superdo".into()),
+                ("version", env!("CARGO_PKG_VERSION").into()),
+            ]),
+        ),
+        ("paths", Json::Object(paths)),
+        // 令牌是可选的，取决于服务的设置
+        (
+            "security",
+            Json::Array(vec![
+                object(vec![("bearer", Json::Array(vec![]))]),
+                object(vec![]),
+            ]),
+        ),
+        (
+            "components",
+            object(vec![
+                (
+                    "schemas",
+                    object(vec![("Error", Json::parse(ERROR).unwrap())]),
+                ),
+                (
+                    "securitySchemes",
+                    object(vec![(
+                        "bearer",
+                        object(vec![("type", "http".into()), ("scheme", "bearer".into())]),
+                    )]),
+                ),
+            ]),
+        ),
+    ])
+}
+
+/// 服务的限制
+#[derive(Debug, Clone)]
+pub struct ServerOptions {
+    /// 每个请求最多列出或统计的解的个数
+    pub max_solutions: usize,
+    /// 每个请求的计算时间上限
+    pub timeout: Duration,
+    /// 同时处理的连接数上限
+    pub max_connections: usize,
+    /// 同时计算的请求数上限
+    pub max_jobs: usize,
+    /// 排队等待计算的请求数上限，再多的请求返回 429
+    pub queue: usize,
+    /// 接受的令牌，为空时不需要认证
+    pub tokens: Vec<ApiToken>,
+    /// 缓存求解结果的题目数，等价的题目共用一个条目，为 0 时不缓存
+    pub cache: usize,
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        Self {
+            max_solutions: 100,
+            timeout: Duration::from_secs(10),
+            max_connections: 64,
+            max_jobs: num_cpus::get(),
+            queue: 16,
+            tokens: vec![],
+            cache: 1024,
+        }
+    }
+}
+
+/// 计算的名额，同时发出的名额有上限，其余的申请排队等待
+#[derive(Debug)]
+struct Jobs {
+    /// 正在计算和正在排队的请求数
+    state: Mutex<(usize, usize)>,
+    freed: Condvar,
+    max_jobs: usize,
+    queue: usize,
+}
+
+impl Jobs {
+    /// 申请一个名额，最多等到 deadline。队列已满时返回 429，等待超时返回 504
+    fn acquire(self: &Arc<Self>, deadline: Instant) -> Result<Job, Response> {
+        let mut state = self.state.lock().unwrap();
+        if state.0 >= self.max_jobs {
+            if state.1 >= self.queue {
+                return Err(Response::error(
+                    429,
+                    "too_many_requests",
+                    "too many requests are waiting, try again later",
+                ));
+            }
+            state.1 += 1;
+            while state.0 >= self.max_jobs {
+                let now = Instant::now();
+                if now >= deadline {
+                    state.1 -= 1;
+                    return Err(Response::error(
+                        504,
+                        "timeout",
+                        "the request waited too long in the queue",
+                    ));
+                }
+                state = self.freed.wait_timeout(state, deadline - now).unwrap().0;
+            }
+            state.1 -= 1;
+        }
+        state.0 += 1;
+        Ok(Job(self.clone()))
+    }
+}
+
+/// 一个计算名额，计算结束后释放
+#[derive(Debug)]
+struct Job(Arc<Jobs>);
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        self.0.state.lock().unwrap().0 -= 1;
+        self.0.freed.notify_one();
+    }
+}
+
+/// 响应
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    /// HTTP 状态码
+    pub status: u16,
+    /// 响应体
+    pub body: Json,
+}
+
+impl Response {
+    pub(crate) fn ok(body: Json) -> Self {
+        Self { status: 200, body }
+    }
+
+    pub(crate) fn error(status: u16, kind: &str, message: &str) -> Self {
+        Self {
+            status,
+            body: error_json(kind, message, vec![]),
+        }
+    }
+}
+
+/// HTTP 服务
+#[derive(Debug)]
+pub struct Server {
+    options: ServerOptions,
+    /// 求解结果
+    cache: Arc<SolveCache>,
+    /// 正在处理的连接数
+    active: AtomicUsize,
+    /// 计算的名额
+    jobs: Arc<Jobs>,
+    auth: Auth,
+}
+
+impl Server {
+    /// 创建服务
+    pub fn new(options: ServerOptions) -> Self {
+        Self {
+            cache: Arc::new(SolveCache::new(options.cache)),
+            active: AtomicUsize::new(0),
+            jobs: Arc::new(Jobs {
+                state: Mutex::default(),
+                freed: Condvar::new(),
+                max_jobs: options.max_jobs.max(1),
+                queue: options.queue,
+            }),
+            auth: Auth::new(options.tokens.clone()),
+            options,
+        }
+    }
+
+    /// 在 listener 上接受连接，直到出错
+    pub fn serve(self: Arc<Self>, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let mut stream = stream?;
+            if self.active.fetch_add(1, Ordering::SeqCst) >= self.options.max_connections {
+                self.active.fetch_sub(1, Ordering::SeqCst);
+                let busy = Response::error(503, "busy", "too many connections");
+                let _ = write_response(&mut stream, &busy);
+                continue;
+            }
+            let server = self.clone();
+            thread::spawn(move || {
+                if let Err(e) = server.connection(&mut stream) {
+                    log::debug!("connection: {}", e);
+                }
+                server.active.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+        Ok(())
+    }
+
+    /// 处理一个连接上的一个请求
+    fn connection(&self, stream: &mut TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let response = match read_request(stream)? {
+            Ok(request) => {
+                log::info!("{} {}", request.method, request.path);
+                let (path, query) = request.path.split_once('?').unwrap_or((&request.path, ""));
+                if path.starts_with("/v1/") {
+                    if let Err(response) = self.auth.check(request.authorization.as_deref()) {
+                        return write_response(stream, &response);
+                    }
+                }
+                match (path, &request.websocket_key) {
+                    ("/v1/enumerate", Some(key)) if request.method == "GET" => {
+                        let deadline = Instant::now() + self.options.timeout;
+                        match self.enumerate_request(query).and_then(|(givens, limit)| {
+                            Ok((givens, limit, self.jobs.acquire(deadline)?))
+                        }) {
+                            Ok((givens, limit, job)) => {
+                                websocket::write_handshake(stream, key)?;
+                                return self.enumerate(stream, givens, limit, job);
+                            }
+                            Err(response) => response,
+                        }
+                    }
+                    ("/v1/enumerate", _) => {
+                        Response::error(426, "upgrade_required", "use a WebSocket connection")
+                    }
+                    ("/", _) if request.method == "GET" => {
+                        return write_http(stream, 200, "text/html; charset=utf-8", GUI_PAGE);
+                    }
+                    _ => self.handle(&request.method, path, &request.body),
+                }
+            }
+            Err(response) => response,
+        };
+        write_response(stream, &response)
+    }
+
+    /// 读取列举请求的参数 puzzle 和 limit，limit 缺省时为服务的上限
+    fn enumerate_request(&self, query: &str) -> Result<(Grid, usize), Response> {
+        let request: Vec<(String, Json)> = query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(key, value)| {
+                // 个数按数字读取
+                let count = ENUMERATE_FIELDS
+                    .iter()
+                    .any(|field| field.name == key && field.kind == Kind::Count);
+                let value = match count {
+                    true => value.parse::<u32>().map_or(Json::Null, Json::from),
+                    false => value.into(),
+                };
+                (key.to_string(), value)
+            })
+            .collect();
+        let params = self.params(&ENUMERATE_FIELDS, &Json::Object(request))?;
+        let limit = params.count("limit").unwrap_or(self.options.max_solutions);
+        Ok((params.puzzle(), limit))
+    }
+
+    /// 在 WebSocket 上逐个推送解 `{"solution": ..., "index": n}`，每隔一段时间推送
+    /// `{"progress": {"found": n, "elapsed_ms": t}}`，结束时推送 `{"done": {"count": n, "capped": ...}}`
+    /// 或超时的错误对象，然后关闭连接。客户端断开后停止列举，列举结束后释放 job
+    fn enumerate(
+        &self,
+        stream: &mut TcpStream,
+        givens: Grid,
+        limit: usize,
+        job: Job,
+    ) -> io::Result<()> {
+        let start = Instant::now();
+        let progress = Arc::new(SearchProgress::default());
+        let (tx, rx) = mpsc::channel();
+        let worker = {
+            let progress = progress.clone();
+            thread::spawn(move || {
+                let _job = job;
+                let board = SudokuBoard::new_with(&givens, Arc::new(Variant::standard()));
+                let found = AtomicUsize::new(0);
+                let tx = Mutex::new(tx);
+                let emit = |board: &SudokuBoard| {
+                    let n = found.fetch_add(1, Ordering::Relaxed) + 1;
+                    if n > limit {
+                        return false;
+                    }
+                    tx.lock().unwrap().send(board.grid()).is_ok() && n < limit
+                };
+                Propagating::default().solve_with(board, &emit, &progress);
+            })
+        };
+        let mut count = 0;
+        let result = loop {
+            if start.elapsed() > self.options.timeout {
+                break error_json("timeout", "the enumeration took too long", vec![]);
+            }
+            let message = match rx.recv_timeout(PROGRESS_INTERVAL) {
+                Ok(solution) => {
+                    count += 1;
+                    Json::Object(vec![
+                        ("solution".to_string(), grid_line(&solution).into()),
+                        ("index".to_string(), count.into()),
+                    ])
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    let progress = Json::Object(vec![
+                        ("found".to_string(), count.into()),
+                        (
+                            "elapsed_ms".to_string(),
+                            (start.elapsed().as_millis() as usize).into(),
+                        ),
+                    ]);
+                    Json::Object(vec![("progress".to_string(), progress)])
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    let done = Json::Object(vec![
+                        ("count".to_string(), count.into()),
+                        ("capped".to_string(), (count == limit).into()),
+                    ]);
+                    break Json::Object(vec![("done".to_string(), done)]);
+                }
+            };
+            if let Err(e) = websocket::write_text(stream, &message.to_string()) {
+                progress.cancel();
+                return Err(e);
+            }
+        };
+        // 超时后取消搜索，工作线程在正在推理的分支结束后退出并释放 job
+        progress.cancel();
+        let _ = worker.join();
+        websocket::write_text(stream, &result.to_string())?;
+        websocket::write_close(stream)
+    }
+
+    /// 处理一个请求，不检查令牌
+    pub fn handle(&self, method: &str, path: &str, body: &str) -> Response {
+        if path == "/v1/schema" {
+            return match method {
+                "GET" => Response::ok(schema()),
+                _ => Response::error(405, "method_not_allowed", "use GET"),
+            };
+        }
+        let Some((endpoint, _, fields)) = path
+            .strip_prefix("/v1/")
+            .and_then(|name| ENDPOINTS.iter().find(|(endpoint, ..)| *endpoint == name))
+        else {
+            return Response::error(404, "not_found", &format!("no endpoint {}", path));
+        };
+        if method != "POST" {
+            return Response::error(405, "method_not_allowed", "use POST");
+        }
+        let request = match Json::parse(body) {
+            Ok(request @ Json::Object(_)) => request,
+            Ok(_) => return invalid("request body must be a JSON object"),
+            Err(e) => return invalid(&format!("invalid JSON: {}", e)),
+        };
+        let params = match self.params(fields, &request) {
+            Ok(params) => params,
+            Err(response) => return response,
+        };
+        match *endpoint {
+            "solve" => self.solve(&params),
+            "count" => self.count(&params),
+            "rate" => {
+                let givens = params.puzzle();
+                self.run(move |_| {
+                    let rating = rate(&givens);
+                    let steps: Vec<&str> = rating.steps.iter().map(|t| t.name()).collect();
+                    Some(Json::Object(vec![
+                        ("grade".to_string(), rating.grade().into()),
+                        ("score".to_string(), rating.score.into()),
+                        (
+                            "hardest".to_string(),
+                            rating.hardest.map_or(Json::Null, |t| t.name().into()),
+                        ),
+                        ("solved".to_string(), rating.solved.into()),
+                        ("steps".to_string(), steps.into()),
+                    ]))
+                })
+            }
+            "step" => {
+                let givens = params.puzzle();
+                self.run(move |_| {
+                    let mut board = SudokuBoard::new_with(&givens, Arc::new(Variant::standard()));
+                    let status = match board.advance() {
+                        None => "progress",
+                        Some(Deduction::Solved) => "solved",
+                        Some(Deduction::Stuck) => "stuck",
+                        Some(Deduction::Contradiction) => "contradiction",
+                    };
+                    Some(Json::Object(vec![
+                        ("status".to_string(), status.into()),
+                        ("cells".to_string(), board.snapshot()),
+                    ]))
+                })
+            }
+            _ => self.generate(&params),
+        }
+    }
+
+    fn solve(&self, params: &Params) -> Response {
+        let givens = params.puzzle();
+        let limit = params
+            .count("limit")
+            .unwrap_or(1)
+            .min(self.options.max_solutions);
+        let cache = self.cache.clone();
+        // 规范形式的计算也算在计算时间内
+        self.run(move |deadline| {
+            let key = CacheKey::new(&givens);
+            let found = match key.as_ref().and_then(|key| cache.get(key, limit)) {
+                Some(found) => found,
+                None => {
+                    let board = SudokuBoard::new_with(&givens, Arc::new(Variant::standard()));
+                    let found = solutions_until(&board, limit, Some(deadline))?;
+                    if let Some(key) = &key {
+                        cache.insert(key, &found, found.len() < limit);
+                    }
+                    found
+                }
+            };
+            let found: Vec<String> = found.iter().map(grid_line).collect();
+            Some(Json::Object(vec![("solutions".to_string(), found.into())]))
+        })
+    }
+
+    fn count(&self, params: &Params) -> Response {
+        let givens = params.puzzle();
+        let cap = params.count("cap").unwrap_or(self.options.max_solutions);
+        self.run(move |deadline| {
+            let board = SudokuBoard::new_with(&givens, Arc::new(Variant::standard()));
+            let count = solutions_until(&board, cap, Some(deadline))?.len();
+            Some(Json::Object(vec![
+                ("count".to_string(), count.into()),
+                ("capped".to_string(), (count == cap).into()),
+            ]))
+        })
+    }
+
+    fn generate(&self, params: &Params) -> Response {
+        let difficulty = params.difficulty();
+        let mut rng = params.seed().map_or_else(Rng::from_time, Rng::new);
+        self.run(move |deadline| {
+            let options = GenerateOptions {
+                difficulty,
+                deadline: Some(deadline),
+                ..Default::default()
+            };
+            match generate(Arc::new(Variant::standard()), &options, &mut rng) {
+                Some(generated) => Some(generated.to_json()),
+                None if options.expired() => None,
+                None => Some(error_json(
+                    "no_puzzle",
+                    "no puzzle satisfies the options",
+                    vec![],
+                )),
+            }
+        })
+    }
+
+    /// 按字段表读取请求中的参数，缺少必需的字段或取值无效时返回 400，给定数字冲突时返回 422
+    fn params(&self, fields: &[Field], request: &Json) -> Result<Params, Response> {
+        let mut params = vec![];
+        for field in fields {
+            let value = match request.get(field.name) {
+                None if !field.required => continue,
+                value => self.value(field, value)?,
+            };
+            params.push((field.name, value));
+        }
+        Ok(Params(params))
+    }
+
+    /// 读取一个字段的值，value 为 None 表示请求中没有这个字段
+    fn value(&self, field: &Field, value: Option<&Json>) -> Result<Value, Response> {
+        let name = field.name;
+        match field.kind {
+            Kind::Puzzle => puzzle(name, value).map(|givens| Value::Puzzle(Box::new(givens))),
+            Kind::Count => {
+                let max = self.options.max_solutions;
+                match value.and_then(Json::as_u32) {
+                    Some(n) if n >= 1 && n as usize <= max => Ok(Value::Count(n as usize)),
+                    _ => Err(invalid(&format!(
+                        "{} must be an integer between 1 and {}",
+                        name, max
+                    ))),
+                }
+            }
+            Kind::Seed => match value.and_then(Json::as_f64) {
+                Some(seed) if seed >= 0.0 && seed.fract() == 0.0 && seed < 2f64.powi(53) => {
+                    Ok(Value::Seed(seed as u64))
+                }
+                _ => Err(invalid(&format!("{} must be a non-negative integer", name))),
+            },
+            Kind::Difficulty => {
+                match value
+                    .and_then(Json::as_str)
+                    .map(|s| Difficulty::from_str(s, true))
+                {
+                    Some(Ok(difficulty)) => Ok(Value::Difficulty(difficulty)),
+                    _ => Err(invalid(&format!(
+                        "{} must be one of {}",
+                        name,
+                        difficulties().join(", ")
+                    ))),
+                }
+            }
+        }
+    }
+
+    /// 取得计算名额后在单独的线程中计算，排队的时间也算在计算时间内。计算到 work 收到的
+    /// 截止时刻应尽快放弃，超时后返回 504，计算线程随即退出并释放名额
+    fn run(&self, work: impl FnOnce(Instant) -> Option<Json> + Send + 'static) -> Response {
+        let deadline = Instant::now() + self.options.timeout;
+        let job = match self.jobs.acquire(deadline) {
+            Ok(job) => job,
+            Err(response) => return response,
+        };
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let body = work(deadline);
+            drop(job);
+            let _ = tx.send(body);
+        });
+        match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(Some(body)) if body.get("error").is_some() => Response { status: 422, body },
+            Ok(Some(body)) => Response::ok(body),
+            Ok(None) | Err(_) => Response::error(504, "timeout", "the request took too long"),
+        }
+    }
+}
+
+/// 请求无效
+fn invalid(message: &str) -> Response {
+    Response::error(400, "invalid_request", message)
+}
+
+/// 读取字段的值
+#[derive(Debug)]
+enum Value {
+    Puzzle(Box<Grid>),
+    Count(usize),
+    Seed(u64),
+    Difficulty(Difficulty),
+}
+
+/// 按字段表读出的请求参数，请求中没有的可选字段不在其中
+#[derive(Debug)]
+struct Params(Vec<(&'static str, Value)>);
+
+impl Params {
+    fn get(&self, name: &str) -> Option<&Value> {
+        self.0
+            .iter()
+            .find(|(field, _)| *field == name)
+            .map(|(_, value)| value)
+    }
+
+    /// 题目，各接口的字段表中都是必需的
+    fn puzzle(&self) -> Grid {
+        match self.get("puzzle") {
+            Some(Value::Puzzle(givens)) => **givens,
+            _ => panic!("the field table has no puzzle"),
+        }
+    }
+
+    fn count(&self, name: &str) -> Option<usize> {
+        match self.get(name) {
+            Some(Value::Count(n)) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn seed(&self) -> Option<u64> {
+        match self.get("seed") {
+            Some(Value::Seed(seed)) => Some(*seed),
+            _ => None,
+        }
+    }
+
+    fn difficulty(&self) -> Option<Difficulty> {
+        match self.get("difficulty") {
+            Some(Value::Difficulty(difficulty)) => Some(*difficulty),
+            _ => None,
+        }
+    }
+}
+
+/// 读取题目字段，给定数字冲突时返回 422
+fn puzzle(name: &str, value: Option<&Json>) -> Result<Grid, Response> {
+    let text = value
+        .and_then(Json::as_str)
+        .ok_or_else(|| invalid(&format!("{} must be a string of 81 digits", name)))?;
+    let givens = parse_grid(text).map_err(|e| invalid(&e))?;
+    match Variant::standard().conflicts(&givens).first() {
+        Some(conflict) => Err(Response {
+            status: 422,
+            body: error_json("conflict", &conflict.to_string(), conflict.details()),
+        }),
+        None => Ok(givens),
+    }
+}
+
+/// 一个 HTTP 请求
+pub(crate) struct Request {
+    pub(crate) method: String,
+    pub(crate) path: String,
+    /// 请求升级为 WebSocket 时的 `Sec-WebSocket-Key`
+    pub(crate) websocket_key: Option<String>,
+    /// `Authorization` 请求头
+    pub(crate) authorization: Option<String>,
+    pub(crate) body: String,
+}
+
+/// 读取请求行、请求头和请求体，格式有误或请求头过大时返回要发送的错误响应
+pub(crate) fn read_request(stream: &TcpStream) -> io::Result<Result<Request, Response>> {
+    let mut reader = BufReader::new(stream).take(MAX_HEADER);
+    // 读到上限仍没有读完一行时，请求头过大
+    let too_large = || {
+        let message = format!(
+            "request line and headers are larger than {} bytes",
+            MAX_HEADER
+        );
+        Ok(Err(Response::error(431, "too_large", &message)))
+    };
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.ends_with('\n') && reader.limit() == 0 {
+        return too_large();
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Ok(Err(invalid("malformed request line")));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+    let mut length = 0;
+    let (mut upgrade, mut websocket_key) = (false, None);
+    let mut authorization = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            if reader.limit() == 0 {
+                return too_large();
+            }
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let (name, value) = (name.trim().to_ascii_lowercase(), value.trim());
+            match name.as_str() {
+                "content-length" => match value.parse() {
+                    Ok(n) => length = n,
+                    Err(_) => return Ok(Err(invalid("invalid Content-Length"))),
+                },
+                "upgrade" => upgrade = value.eq_ignore_ascii_case("websocket"),
+                "sec-websocket-key" => websocket_key = Some(value.to_string()),
+                "authorization" => authorization = Some(value.to_string()),
+                _ => {}
+            }
+        }
+    }
+    if length > MAX_BODY {
+        let message = format!("request body is larger than {} bytes", MAX_BODY);
+        return Ok(Err(Response::error(413, "too_large", &message)));
+    }
+    let mut body = vec![0; length];
+    reader.into_inner().read_exact(&mut body)?;
+    match String::from_utf8(body) {
+        Ok(body) => Ok(Ok(Request {
+            method,
+            path,
+            websocket_key: websocket_key.filter(|_| upgrade),
+            authorization,
+            body,
+        })),
+        Err(_) => Ok(Err(invalid("request body is not UTF-8"))),
+    }
+}
+
+pub(crate) fn write_response(stream: &mut TcpStream, response: &Response) -> io::Result<()> {
+    write_http(
+        stream,
+        response.status,
+        "application/json",
+        &response.body.to_string(),
+    )
+}
+
+fn write_http(
+    stream: &mut TcpStream,
+    status: u16,
+    content_type: &str,
+    body: &str,
+) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        426 => "Upgrade Required",
+        429 => "Too Many Requests",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "",
+    };
+    let challenge = match status {
+        401 => "WWW-Authenticate: Bearer\r\n",
+        _ => "",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\n{}Content-Type: {}\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        reason,
+        challenge,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use super::{openapi, Server, ServerOptions, MAX_HEADER};
+    use crate::json::Json;
+
+    const PUZZLE: &str =
+        "530070000600195000098000060800060003400803001700020006060000280000419005000080079";
+    const SOLUTION: &str =
+        "534678912672195348198342567859761423426853791713924856961537284287419635345286179";
+
+    fn kind(body: &Json) -> Option<&str> {
+        body.get("error")
+            .and_then(|e| e.get("kind"))
+            .and_then(Json::as_str)
+    }
+
+    #[test]
+    fn test_handle() {
+        let server = Server::new(ServerOptions {
+            max_solutions: 5,
+            ..Default::default()
+        });
+        let request = format!(r#"{{"puzzle": "{}"}}"#, PUZZLE);
+        let solved = server.handle("POST", "/v1/solve", &request);
+        assert_eq!(solved.status, 200);
+        assert_eq!(
+            solved.body.to_string(),
+            format!(r#"{{"solutions":["{}"]}}"#, SOLUTION)
+        );
+        // 第二次来自缓存，等价的题目也是
+        assert_eq!(server.handle("POST", "/v1/solve", &request), solved);
+        assert_eq!(server.cache.len(), 1);
+        let swapped = |line: &str| -> String {
+            line.chars()
+                .map(|c| match c {
+                    '1' => '2',
+                    '2' => '1',
+                    c => c,
+                })
+                .collect()
+        };
+        let relabeled = server.handle(
+            "POST",
+            "/v1/solve",
+            &format!(r#"{{"puzzle": "{}"}}"#, swapped(PUZZLE)),
+        );
+        assert_eq!(
+            relabeled.body.to_string(),
+            format!(r#"{{"solutions":["{}"]}}"#, swapped(SOLUTION))
+        );
+        assert_eq!(server.cache.len(), 1);
+
+        let counted = server.handle("POST", "/v1/count", &request);
+        assert_eq!(counted.body.to_string(), r#"{"count":1,"capped":false}"#);
+        let empty = format!(r#"{{"puzzle": "{}"}}"#, "0".repeat(81));
+        let counted = server.handle("POST", "/v1/count", &empty);
+        assert_eq!(counted.body.to_string(), r#"{"count":5,"capped":true}"#);
+
+        let rated = server.handle("POST", "/v1/rate", &request);
+        assert_eq!(rated.body.get("grade").and_then(Json::as_str), Some("easy"));
+        let generated = server.handle(
+            "POST",
+            "/v1/generate",
+            r#"{"difficulty": "easy", "seed": 3}"#,
+        );
+        assert_eq!(generated.status, 200);
+        assert_eq!(
+            generated,
+            server.handle(
+                "POST",
+                "/v1/generate",
+                r#"{"difficulty": "easy", "seed": 3}"#
+            )
+        );
+
+        // 校验
+        let too_many = format!(r#"{{"puzzle": "{}", "limit": 6}}"#, PUZZLE);
+        let cases = [
+            (
+                "POST",
+                "/v1/solve",
+                too_many.as_str(),
+                400,
+                "invalid_request",
+            ),
+            ("POST", "/v1/solve", "[1]", 400, "invalid_request"),
+            ("POST", "/v1/solve", "{", 400, "invalid_request"),
+            (
+                "POST",
+                "/v1/rate",
+                r#"{"puzzle": "123"}"#,
+                400,
+                "invalid_request",
+            ),
+            (
+                "POST",
+                "/v1/generate",
+                r#"{"seed": -1}"#,
+                400,
+                "invalid_request",
+            ),
+            (
+                "POST",
+                "/v1/generate",
+                r#"{"difficulty": "x"}"#,
+                400,
+                "invalid_request",
+            ),
+            ("GET", "/v1/solve", "", 405, "method_not_allowed"),
+            ("POST", "/v2/solve", "{}", 404, "not_found"),
+        ];
+        for (method, path, body, status, error) in cases {
+            let response = server.handle(method, path, body);
+            assert_eq!(response.status, status, "{} {}", path, body);
+            assert_eq!(kind(&response.body), Some(error));
+        }
+        let conflict = format!(r#"{{"puzzle": "55{}"}}"#, &PUZZLE[2..]);
+        let response = server.handle("POST", "/v1/solve", &conflict);
+        assert_eq!(response.status, 422);
+        assert_eq!(kind(&response.body), Some("conflict"));
+
+        let schema = server.handle("GET", "/v1/schema", "");
+        assert!(schema.body.get("generate").is_some());
+    }
+
+    #[test]
+    fn test_timeout() {
+        let server = Server::new(ServerOptions {
+            max_solutions: 1000,
+            timeout: Duration::ZERO,
+            ..Default::default()
+        });
+        let empty = format!(r#"{{"puzzle": "{}", "cap": 1000}}"#, "0".repeat(81));
+        let response = server.handle("POST", "/v1/count", &empty);
+        assert_eq!(response.status, 504);
+        assert_eq!(kind(&response.body), Some("timeout"));
+
+        // 超时后计算随即放弃，名额很快释放
+        let server = Server::new(ServerOptions {
+            max_solutions: 1_000_000,
+            timeout: Duration::from_millis(100),
+            max_jobs: 1,
+            ..Default::default()
+        });
+        let released = || {
+            let start = Instant::now();
+            while *server.jobs.state.lock().unwrap() != (0, 0) {
+                assert!(start.elapsed() < Duration::from_secs(2), "job not released");
+                thread::sleep(Duration::from_millis(10));
+            }
+        };
+        let empty = "0".repeat(81);
+        let requests = [
+            (
+                "/v1/count",
+                format!(r#"{{"puzzle": "{}", "cap": 1000000}}"#, empty),
+            ),
+            (
+                "/v1/solve",
+                format!(r#"{{"puzzle": "{}", "limit": 1000000}}"#, empty),
+            ),
+        ];
+        for (path, body) in &requests {
+            let response = server.handle("POST", path, body);
+            assert_eq!(response.status, 504, "{}", path);
+            released();
+        }
+        // 生成可能在超时前完成，无论如何名额都应释放
+        server.handle("POST", "/v1/generate", r#"{"difficulty": "evil"}"#);
+        released();
+    }
+
+    #[test]
+    fn test_queue() {
+        let server = Server::new(ServerOptions {
+            timeout: Duration::from_secs(1),
+            max_jobs: 1,
+            queue: 1,
+            ..Default::default()
+        });
+        let request = format!(r#"{{"puzzle": "{}"}}"#, PUZZLE);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let job = server.jobs.acquire(deadline).unwrap();
+        // 唯一的名额被占用，请求排队直到超时
+        let response = server.handle("POST", "/v1/count", &request);
+        assert_eq!(response.status, 504);
+        assert_eq!(kind(&response.body), Some("timeout"));
+        // 队列也满了时立即返回 429
+        let server = Arc::new(server);
+        let waiting = {
+            let server = server.clone();
+            let request = request.clone();
+            thread::spawn(move || server.handle("POST", "/v1/count", &request))
+        };
+        while server.jobs.state.lock().unwrap().1 == 0 {
+            thread::yield_now();
+        }
+        let response = server.handle("POST", "/v1/rate", &request);
+        assert_eq!(response.status, 429);
+        assert_eq!(kind(&response.body), Some("too_many_requests"));
+        // 名额释放后排队的请求得到计算
+        drop(job);
+        assert_eq!(waiting.join().unwrap().status, 200);
+        assert_eq!(server.handle("POST", "/v1/rate", &request).status, 200);
+        assert_eq!(*server.jobs.state.lock().unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn test_serve() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = Arc::new(Server::new(ServerOptions::default()));
+        thread::spawn(move || server.serve(listener));
+
+        let body = format!(r#"{{"puzzle": "{}"}}"#, PUZZLE);
+        // 查询参数不影响路由
+        for path in ["/v1/solve", "/v1/solve?x=1"] {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            write!(
+                stream,
+                "POST {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+                path,
+                body.len(),
+                body
+            )
+            .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+            assert!(response.ends_with(&format!(r#"{{"solutions":["{}"]}}"#, SOLUTION)));
+        }
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "POST /v1/solve HTTP/1.1\r\nContent-Length: 100000\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 413 "), "{}", response);
+
+        // 读到上限仍没有读完请求头时不再继续读取
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let head = "GET / HTTP/1.1\r\nX-Padding: ";
+        let padding = "a".repeat(MAX_HEADER as usize - head.len());
+        write!(stream, "{}{}", head, padding).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 431 "), "{}", response);
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET /{}", "a".repeat(MAX_HEADER as usize - 5)).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 431 "), "{}", response);
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET / HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.contains("Content-Type: text/html"));
+        assert!(response.ends_with("</html>\n"));
+    }
+
+    #[test]
+    fn test_step() {
+        let server = Server::new(ServerOptions::default());
+        let step = |puzzle: &str| {
+            let response = server.handle(
+                "POST",
+                "/v1/step",
+                &format!(r#"{{"puzzle": "{}"}}"#, puzzle),
+            );
+            assert_eq!(response.status, 200, "{}", response.body);
+            response.body
+        };
+        let body = step(PUZZLE);
+        assert_eq!(body.get("status").and_then(Json::as_str), Some("progress"));
+        let cells = body.get("cells").and_then(Json::as_array).unwrap();
+        assert_eq!(cells.len(), 81);
+        assert!(cells.iter().any(|cell| cell.get("candidates").is_some()));
+        let body = step(&"0".repeat(81));
+        assert_eq!(body.get("status").and_then(Json::as_str), Some("stuck"));
+        // r1c9 只能是 9，但 r2c9 已是 9
+        let mut puzzle = format!("12345678{}", "0".repeat(73));
+        puzzle.replace_range(17..18, "9");
+        let body = step(&puzzle);
+        assert_eq!(
+            body.get("status").and_then(Json::as_str),
+            Some("contradiction")
+        );
+        // 没有空位被排除干净，但第 3 个小格已无处可填 1
+        let body = step(
+            "530070400600195000098000060800060003400803001700020006060000280000419005000080079",
+        );
+        assert_eq!(
+            body.get("status").and_then(Json::as_str),
+            Some("contradiction")
+        );
+        assert_eq!(
+            step(SOLUTION).get("status").and_then(Json::as_str),
+            Some("solved")
+        );
+    }
+
+    /// 解析服务端发送的帧，返回文本帧的内容
+    fn text_frames(mut data: &[u8]) -> Vec<String> {
+        let mut texts = vec![];
+        while data.len() >= 2 {
+            let (opcode, mut len, mut at) = (data[0] & 0x0f, (data[1] & 0x7f) as usize, 2);
+            if len == 126 {
+                len = u16::from_be_bytes([data[2], data[3]]) as usize;
+                at = 4;
+            }
+            if opcode == 1 {
+                texts.push(String::from_utf8(data[at..at + len].to_vec()).unwrap());
+            }
+            data = &data[at + len..];
+        }
+        texts
+    }
+
+    #[test]
+    fn test_enumerate() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = Arc::new(Server::new(ServerOptions {
+            max_solutions: 10,
+            ..Default::default()
+        }));
+        thread::spawn(move || server.serve(listener));
+
+        let request = |path: &str, upgrade: bool| {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            let headers = if upgrade {
+                "Upgrade: websocket\r\nConnection: Upgrade\r\n\
+                 Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n"
+            } else {
+                ""
+            };
+            write!(stream, "GET {} HTTP/1.1\r\n{}\r\n", path, headers).unwrap();
+            let mut response = vec![];
+            stream.read_to_end(&mut response).unwrap();
+            response
+        };
+
+        // 去掉 r8c9、r9c8、r9c9 后有 3 个解
+        let several: String = PUZZLE
+            .char_indices()
+            .map(|(i, c)| if [71, 79, 80].contains(&i) { '0' } else { c })
+            .collect();
+        let response = request(&format!("/v1/enumerate?puzzle={}", several), true);
+        let end = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        let head = String::from_utf8(response[..end].to_vec()).unwrap();
+        assert!(head.starts_with("HTTP/1.1 101 "), "{}", head);
+        assert!(head.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+        let texts = text_frames(&response[end..]);
+        let solutions = texts.iter().filter(|t| t.contains("solution")).count();
+        assert_eq!(
+            texts.last().unwrap(),
+            &format!(r#"{{"done":{{"count":{},"capped":false}}}}"#, solutions)
+        );
+        assert_eq!(solutions, 3);
+
+        let response = request(
+            &format!("/v1/enumerate?puzzle={}&limit=1", "0".repeat(81)),
+            true,
+        );
+        let texts = text_frames(
+            &response[response.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4..],
+        );
+        assert_eq!(
+            texts.last().unwrap(),
+            r#"{"done":{"count":1,"capped":true}}"#
+        );
+
+        // 参数有误或不是 WebSocket 时返回普通的错误响应
+        let response = request(&format!("/v1/enumerate?puzzle={}&limit=11", PUZZLE), true);
+        assert!(response.starts_with(b"HTTP/1.1 400 "));
+        let response = request(&format!("/v1/enumerate?puzzle={}", PUZZLE), false);
+        assert!(response.starts_with(b"HTTP/1.1 426 "));
+    }
+
+    #[test]
+    fn test_enumerate_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = Arc::new(Server::new(ServerOptions {
+            max_solutions: 1_000_000,
+            timeout: Duration::from_millis(100),
+            max_jobs: 1,
+            ..Default::default()
+        }));
+        thread::spawn({
+            let server = server.clone();
+            move || server.serve(listener)
+        });
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "GET /v1/enumerate?puzzle={}&limit=1000000 HTTP/1.1\r\nUpgrade: websocket\r\n\
+             Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+             Sec-WebSocket-Version: 13\r\n\r\n",
+            "0".repeat(81)
+        )
+        .unwrap();
+        let mut response = vec![];
+        stream.read_to_end(&mut response).unwrap();
+        let texts = text_frames(
+            &response[response.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4..],
+        );
+        let last = Json::parse(texts.last().unwrap()).unwrap();
+        assert_eq!(kind(&last), Some("timeout"));
+        // 搜索已取消，关闭连接前释放 job
+        assert_eq!(*server.jobs.state.lock().unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn test_openapi() {
+        let doc = openapi();
+        assert_eq!(doc.get("openapi").and_then(Json::as_str), Some("3.0.3"));
+        let paths = doc.get("paths").unwrap();
+        for path in [
+            "/v1/solve",
+            "/v1/count",
+            "/v1/rate",
+            "/v1/step",
+            "/v1/generate",
+        ] {
+            let post = paths.get(path).and_then(|p| p.get("post")).unwrap();
+            let schema = post
+                .get("requestBody")
+                .and_then(|b| b.get("content"))
+                .and_then(|c| c.get("application/json"))
+                .and_then(|c| c.get("schema"))
+                .unwrap();
+            assert_eq!(schema.get("type").and_then(Json::as_str), Some("object"));
+            assert!(post.get("responses").and_then(|r| r.get("200")).is_some());
+        }
+        assert!(paths.get("/v1/enumerate").is_some());
+        // 文档中的响应与实际的响应一致
+        let server = Server::new(ServerOptions::default());
+        let response = server.handle(
+            "POST",
+            "/v1/count",
+            &format!(r#"{{"puzzle": "{}"}}"#, PUZZLE),
+        );
+        let properties = paths
+            .get("/v1/count")
+            .and_then(|p| p.get("post"))
+            .and_then(|p| p.get("responses"))
+            .and_then(|r| r.get("200"))
+            .and_then(|r| r.get("content"))
+            .and_then(|c| c.get("application/json"))
+            .and_then(|c| c.get("schema"))
+            .and_then(|s| s.get("properties"))
+            .unwrap();
+        let Json::Object(fields) = &response.body else {
+            panic!("{}", response.body);
+        };
+        for (key, _) in fields {
+            assert!(properties.get(key).is_some(), "{}", key);
+        }
+    }
+
+    #[test]
+    fn test_documented_fields() {
+        // 文档中的每个字段都按其 schema 取一个合法的值，一起发送时都应被接受
+        let server = Server::new(ServerOptions::default());
+        let Some(Json::Object(paths)) = openapi().get("paths").cloned() else {
+            panic!("no paths");
+        };
+        let sample = |schema: &Json| match schema.get("type").and_then(Json::as_str) {
+            Some("string") => Json::from(PUZZLE),
+            Some("integer") => Json::from(2u32),
+            _ => schema.get("enum").and_then(Json::as_array).unwrap()[0].clone(),
+        };
+        let mut posted = 0;
+        for (path, item) in &paths {
+            let Some(post) = item.get("post") else {
+                continue;
+            };
+            let schema = post
+                .get("requestBody")
+                .and_then(|b| b.get("content"))
+                .and_then(|c| c.get("application/json"))
+                .and_then(|c| c.get("schema"))
+                .unwrap();
+            let Some(Json::Object(properties)) = schema.get("properties") else {
+                panic!("{}", schema);
+            };
+            let body: Vec<(String, Json)> = properties
+                .iter()
+                .map(|(name, schema)| (name.clone(), sample(schema)))
+                .collect();
+            let body = Json::Object(body).to_string();
+            let response = server.handle("POST", path, &body);
+            assert_eq!(response.status, 200, "{} {} {}", path, body, response.body);
+            posted += 1;
+        }
+        assert_eq!(posted, 5);
+
+        // 列举接口的查询参数同样按文档读取
+        let enumerate = paths.iter().find(|(path, _)| path == "/v1/enumerate");
+        let parameters = enumerate
+            .and_then(|(_, item)| item.get("get"))
+            .and_then(|get| get.get("parameters"))
+            .and_then(Json::as_array)
+            .unwrap();
+        let query: Vec<String> = parameters
+            .iter()
+            .map(|parameter| {
+                let name = parameter.get("name").and_then(Json::as_str).unwrap();
+                let value = sample(parameter.get("schema").unwrap());
+                format!(
+                    "{}={}",
+                    name,
+                    value.as_str().map_or(value.to_string(), str::to_string)
+                )
+            })
+            .collect();
+        assert!(server.enumerate_request(&query.join("&")).is_ok());
+    }
+}
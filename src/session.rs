@@ -0,0 +1,590 @@
+//! 游戏进度
+//!
+//! [`Session`] 保存一局游戏的题目、玩家填入的数字、候选数字标记和已用时间，玩家的修改经
+//! [`History`] 执行，可撤销、重做。进度可以写入文件，之后读回继续，未完成的题目不会因为
+//! 关闭终端而丢失。解出后按难度、用时和提示次数计分，见 [`crate::scores`]。开始时预先求解，
+//! 可按 [`CheckMode`] 标出填错的数字。候选数字标记可以自动维护，也可以完全由玩家标注，
+//! 见 [`MarkMode`]。目前只支持标准规则。
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+
+use crate::board::{grid_line, parse_grid, Grid, SudokuBoard};
+use crate::constraint::Cell;
+use crate::history::{Edit, History};
+use crate::json::Json;
+use crate::rating::{rank_cells, rate, CellHint, Difficulty};
+use crate::scores::{self, BestTime};
+use crate::solver::solutions;
+use crate::variant::Variant;
+
+/// 填错数字的检查方式
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CheckMode {
+    /// No checking
+    #[default]
+    Off,
+    /// Flag entries that differ from the unique solution
+    Solution,
+    /// Flag only what the rules rule out: empty cells left without any possible digit
+    Rules,
+}
+
+impl CheckMode {
+    /// 名称
+    pub fn name(self) -> &'static str {
+        match self {
+            CheckMode::Off => "off",
+            CheckMode::Solution => "solution",
+            CheckMode::Rules => "rules",
+        }
+    }
+}
+
+/// 候选数字标记的维护方式
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MarkMode {
+    /// Fill in every candidate and update them as digits are entered and cleared
+    #[default]
+    Auto,
+    /// Start with no marks; only the player adds them
+    Manual,
+}
+
+impl MarkMode {
+    /// 名称
+    pub fn name(self) -> &'static str {
+        match self {
+            MarkMode::Auto => "auto",
+            MarkMode::Manual => "manual",
+        }
+    }
+}
+
+/// 一局游戏
+#[derive(Debug, Clone)]
+pub struct Session {
+    /// 题目的给定数字
+    puzzle: Grid,
+    /// 当前棋盘，含玩家填入的数字和候选数字标记
+    board: SudokuBoard,
+    /// 玩家的修改
+    history: History,
+    /// 之前累计的时间，不含本次计时
+    elapsed: Duration,
+    /// 本次开始计时的时刻，暂停时为 None
+    running: Option<Instant>,
+    /// 题目的难度
+    difficulty: Difficulty,
+    /// 已用的提示次数
+    hints: u32,
+    /// 唯一解，题目无解或有多个解时为 None
+    solution: Option<Grid>,
+    /// 填错数字的检查方式
+    check: CheckMode,
+    /// 候选数字标记的维护方式
+    marks: MarkMode,
+}
+
+impl Session {
+    /// 开始一局新游戏，预先求解，立即开始计时
+    pub fn new(puzzle: Grid) -> Self {
+        let board = SudokuBoard::new_with(&puzzle, Arc::new(Variant::standard()));
+        let solution = match solutions(&board, 2)[..] {
+            [solution] => Some(solution),
+            _ => None,
+        };
+        Self {
+            puzzle,
+            board,
+            history: History::new(),
+            elapsed: Duration::ZERO,
+            running: Some(Instant::now()),
+            difficulty: rate(&puzzle).difficulty,
+            hints: 0,
+            solution,
+            check: CheckMode::default(),
+            marks: MarkMode::default(),
+        }
+    }
+
+    /// 题目的给定数字
+    pub fn puzzle(&self) -> &Grid {
+        &self.puzzle
+    }
+
+    /// 当前棋盘
+    pub fn board(&self) -> &SudokuBoard {
+        &self.board
+    }
+
+    /// 修改历史
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// 执行玩家的一次修改，返回是否有变化；不能修改给定数字
+    pub fn apply(&mut self, edit: Edit) -> Result<bool, String> {
+        let (row, col) = edit.cell();
+        if self.puzzle[row][col] != 0 {
+            return Err(format!("r{}c{} is a given", row + 1, col + 1));
+        }
+        let changed = self.history.apply(&mut self.board, edit)?;
+        self.after(edit);
+        Ok(changed)
+    }
+
+    /// 手动标记时清除数字后不自动填入候选
+    fn after(&mut self, edit: Edit) {
+        if let (MarkMode::Manual, Edit::Unassign { row, col }) = (self.marks, edit) {
+            self.clear_marks(row, col);
+        }
+    }
+
+    /// 清除空位的全部候选数字标记
+    fn clear_marks(&mut self, row: usize, col: usize) {
+        for d in 1..10 {
+            self.board.remove_candidate(d, row, col);
+        }
+    }
+
+    /// 撤销最近一次修改
+    pub fn undo(&mut self) -> Option<Edit> {
+        self.history.undo(&mut self.board)
+    }
+
+    /// 重做最近撤销的修改
+    pub fn redo(&mut self) -> Result<Option<Edit>, String> {
+        let edit = self.history.redo(&mut self.board)?;
+        if let Some(edit) = edit {
+            self.after(edit);
+        }
+        Ok(edit)
+    }
+
+    /// 候选数字标记的维护方式
+    pub fn mark_mode(&self) -> MarkMode {
+        self.marks
+    }
+
+    /// 切换候选数字标记的维护方式：自动时填入全部候选，手动时清空标记。
+    /// 切换改写了所有标记，之前的修改不能再撤销
+    pub fn set_mark_mode(&mut self, marks: MarkMode) {
+        if marks == self.marks {
+            return;
+        }
+        self.marks = marks;
+        for (row, col) in (0..81).map(|i| (i / 9, i % 9)) {
+            match marks {
+                MarkMode::Auto => {
+                    for d in 1..10 {
+                        // 与已填数字冲突的候选不填入
+                        let _ = self.board.add_candidate(d, row, col);
+                    }
+                }
+                MarkMode::Manual => self.clear_marks(row, col),
+            }
+        }
+        self.history = History::new();
+    }
+
+    /// 题目的难度
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    /// 当前盘面上最容易填出的空位，计入提示次数；没有空位时为 None
+    pub fn hint(&mut self) -> Option<CellHint> {
+        let hint = rank_cells(&self.board.grid(), self.board.variant())
+            .into_iter()
+            .next()?;
+        self.hints += 1;
+        Some(hint)
+    }
+
+    /// 已用的提示次数
+    pub fn hints(&self) -> u32 {
+        self.hints
+    }
+
+    /// 是否已填满且符合规则
+    pub fn is_solved(&self) -> bool {
+        let grid = self.board.grid();
+        grid.iter().flatten().all(|d| *d != 0) && self.board.variant().check(&grid)
+    }
+
+    /// 按难度、已用时间和提示次数计算的分数
+    pub fn score(&self) -> u32 {
+        scores::score(self.difficulty, self.elapsed(), self.hints)
+    }
+
+    /// 本局的成绩，用于记入最佳用时
+    pub fn best_time(&self) -> BestTime {
+        BestTime::new(self.difficulty, self.elapsed(), self.hints)
+    }
+
+    /// 唯一解，题目无解或有多个解时为 None
+    pub fn solution(&self) -> Option<&Grid> {
+        self.solution.as_ref()
+    }
+
+    /// 填错数字的检查方式
+    pub fn check_mode(&self) -> CheckMode {
+        self.check
+    }
+
+    /// 设置填错数字的检查方式
+    pub fn set_check_mode(&mut self, check: CheckMode) {
+        self.check = check;
+    }
+
+    /// 按检查方式应标出的位置，按行列顺序。
+    ///
+    /// [`CheckMode::Solution`] 标出与唯一解不同的填入数字，没有唯一解时按 [`CheckMode::Rules`]
+    /// 检查。填入的数字不会与同组位置重复，因此 [`CheckMode::Rules`] 标出的是已填数字使之无数可填的空位
+    pub fn mistakes(&self) -> Vec<Cell> {
+        let grid = self.board.grid();
+        let cells = (0..81).map(|i| (i / 9, i % 9));
+        match (self.check, &self.solution) {
+            (CheckMode::Off, _) => vec![],
+            (CheckMode::Solution, Some(solution)) => cells
+                .filter(|&(r, c)| grid[r][c] != 0 && grid[r][c] != solution[r][c])
+                .collect(),
+            (CheckMode::Solution | CheckMode::Rules, _) => {
+                let peers = self.board.variant().peers();
+                cells
+                    .filter(|&(r, c)| {
+                        grid[r][c] == 0
+                            && (1..10)
+                                .all(|d| peers.of(r, c).iter().any(|&(pr, pc)| grid[pr][pc] == d))
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// 已用时间，不含暂停的时间
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed + self.running.map_or(Duration::ZERO, |start| start.elapsed())
+    }
+
+    /// 暂停计时
+    pub fn pause(&mut self) {
+        if let Some(start) = self.running.take() {
+            self.elapsed += start.elapsed();
+        }
+    }
+
+    /// 继续计时
+    pub fn resume(&mut self) {
+        self.running.get_or_insert_with(Instant::now);
+    }
+
+    /// JSON 形式：题目、当前数字、每个空位的候选数字、已用秒数、提示次数、检查方式和标记方式。
+    /// 修改历史不保存
+    pub fn to_json(&self) -> Json {
+        let marks: Vec<String> = (0..81)
+            .map(|i| {
+                let pos = self.board.get(i / 9, i % 9);
+                let mut digits: Vec<u32> = pos.digits.iter().cloned().collect();
+                digits.sort();
+                digits.iter().map(u32::to_string).collect()
+            })
+            .collect();
+        Json::Object(vec![
+            ("puzzle".to_string(), grid_line(&self.puzzle).into()),
+            ("entries".to_string(), grid_line(&self.board.grid()).into()),
+            ("marks".to_string(), marks.into()),
+            ("elapsed".to_string(), self.elapsed().as_secs_f64().into()),
+            ("hints".to_string(), self.hints.into()),
+            ("check".to_string(), self.check.name().into()),
+            ("mark_mode".to_string(), self.marks.name().into()),
+        ])
+    }
+
+    /// 从 JSON 形式恢复，恢复后开始计时
+    pub fn from_json(json: &Json) -> Result<Self, String> {
+        let grid = |key: &str| {
+            json.get(key)
+                .and_then(Json::as_str)
+                .ok_or_else(|| format!("session has no `{}`", key))
+                .and_then(parse_grid)
+        };
+        let puzzle = grid("puzzle")?;
+        let entries = grid("entries")?;
+        let mut session = Self::new(puzzle);
+        for row in 0..9 {
+            for col in 0..9 {
+                let (given, entry) = (puzzle[row][col], entries[row][col]);
+                if given != 0 && entry != given {
+                    return Err(format!(
+                        "entry at r{}c{} replaces a given",
+                        row + 1,
+                        col + 1
+                    ));
+                }
+                if given == 0 && entry != 0 {
+                    session.board.assign(entry, row, col)?;
+                }
+            }
+        }
+
+        let marks = json
+            .get("marks")
+            .and_then(Json::as_array)
+            .filter(|marks| marks.len() == 81)
+            .ok_or("session has no `marks` for the 81 cells")?;
+        for (i, mark) in marks.iter().enumerate() {
+            let (row, col) = (i / 9, i % 9);
+            if session.board.get(row, col).val != 0 {
+                continue;
+            }
+            let digits: HashSet<u32> = mark
+                .as_str()
+                .ok_or("marks must be strings of digits")?
+                .chars()
+                .map(|c| c.to_digit(10).filter(|d| *d != 0))
+                .collect::<Option<_>>()
+                .ok_or_else(|| format!("invalid marks at r{}c{}", row + 1, col + 1))?;
+            for d in 1..10 {
+                if digits.contains(&d) {
+                    session.board.add_candidate(d, row, col)?;
+                } else {
+                    session.board.remove_candidate(d, row, col);
+                }
+            }
+        }
+
+        session.elapsed = json
+            .get("elapsed")
+            .and_then(Json::as_f64)
+            .filter(|secs| secs.is_finite() && *secs >= 0.0)
+            .map_or(Duration::ZERO, Duration::from_secs_f64);
+        session.hints = json.get("hints").and_then(Json::as_u32).unwrap_or(0);
+        session.check = match json.get("check").and_then(Json::as_str) {
+            Some(check) => CheckMode::from_str(check, true)
+                .map_err(|_| format!("unknown check mode `{}`", check))?,
+            None => CheckMode::default(),
+        };
+        session.marks = match json.get("mark_mode").and_then(Json::as_str) {
+            Some(marks) => MarkMode::from_str(marks, true)
+                .map_err(|_| format!("unknown mark mode `{}`", marks))?,
+            None => MarkMode::default(),
+        };
+        Ok(session)
+    }
+
+    /// 读取进度文件
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let json = Json::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::from_json(&json).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// 写入进度文件，先写临时文件再改名，中途被打断也不会损坏之前的进度
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, format!("{}\n", self.to_json()))
+            .and_then(|_| fs::rename(&tmp, path))
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::process;
+    use std::time::Duration;
+
+    use super::{CheckMode, MarkMode, Session};
+    use crate::board::parse_grid;
+    use crate::history::Edit;
+    use crate::json::Json;
+    use crate::rating::Difficulty;
+
+    #[test]
+    fn test_session() {
+        let puzzle = parse_grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        )
+        .unwrap();
+        let mut session = Session::new(puzzle);
+        let given = Edit::Assign {
+            row: 0,
+            col: 0,
+            val: 5,
+        };
+        assert!(session.apply(given).is_err());
+        let assign = Edit::Assign {
+            row: 0,
+            col: 2,
+            val: 4,
+        };
+        let remove = Edit::RemoveCandidate {
+            row: 0,
+            col: 3,
+            val: 6,
+        };
+        assert_eq!(session.apply(assign), Ok(true));
+        assert_eq!(session.apply(remove), Ok(true));
+        session.pause();
+        let elapsed = session.elapsed();
+        assert_eq!(session.elapsed(), elapsed);
+
+        let path = env::temp_dir().join(format!("superdo-session-{}.json", process::id()));
+        session.save(&path).unwrap();
+        let loaded = Session::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.puzzle(), session.puzzle());
+        assert_eq!(loaded.board().grid(), session.board().grid());
+        assert_eq!(loaded.board().state_hash(), session.board().state_hash());
+        assert!(loaded.elapsed() >= elapsed);
+        assert!(!loaded.history().can_undo());
+
+        let json = session.to_json();
+        assert_eq!(json.get("entries").unwrap().as_str().unwrap()[..3], *"534");
+        assert_eq!(
+            json.get("marks").unwrap().as_array().unwrap()[3],
+            "2".into()
+        );
+        // 填入的数字不能覆盖给定数字，候选不能与已填数字冲突
+        let mut bad = json.to_string().replacen("\"534", "\"634", 1);
+        assert!(Session::from_json(&Json::parse(&bad).unwrap()).is_err());
+        bad = json.to_string().replacen("\"2\"", "\"25\"", 1);
+        assert!(Session::from_json(&Json::parse(&bad).unwrap()).is_err());
+
+        session.resume();
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(session.elapsed() > elapsed);
+    }
+
+    #[test]
+    fn test_score() {
+        let puzzle = parse_grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        )
+        .unwrap();
+        let mut session = Session::new(puzzle);
+        assert_eq!(session.difficulty(), Difficulty::Easy);
+        assert_eq!(session.score(), 1000);
+        let hint = session.hint().unwrap();
+        assert_eq!(session.hints(), 1);
+        assert_eq!(session.score(), 970);
+
+        // 按提示填完
+        while let Some(hint) = session.hint() {
+            let (row, col) = hint.cell;
+            let val = hint.digit.unwrap();
+            session.apply(Edit::Assign { row, col, val }).unwrap();
+        }
+        assert!(session.is_solved());
+        assert!(session.hints() > 40);
+        assert_eq!(session.score(), 0);
+        assert!(hint.digit.is_some());
+
+        let loaded = Session::from_json(&session.to_json()).unwrap();
+        assert_eq!(loaded.hints(), session.hints());
+        assert!(loaded.is_solved());
+        assert_eq!(loaded.best_time().hints, session.hints());
+    }
+
+    #[test]
+    fn test_mistakes() {
+        let puzzle = parse_grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        )
+        .unwrap();
+        let mut session = Session::new(puzzle);
+        assert_eq!(session.solution().unwrap()[0][2], 4);
+        // r1c3 应为 4
+        session
+            .apply(Edit::Assign {
+                row: 0,
+                col: 2,
+                val: 1,
+            })
+            .unwrap();
+        assert!(session.mistakes().is_empty());
+        session.set_check_mode(CheckMode::Solution);
+        assert_eq!(session.mistakes(), vec![(0, 2)]);
+        session.set_check_mode(CheckMode::Rules);
+        assert!(session.mistakes().is_empty());
+
+        // r1c4 只剩 2、6，填入 2、6 的同组位置后无数可填，r9c6 也一样
+        for (row, col, val) in [(0, 5, 2), (8, 3, 6)] {
+            session.apply(Edit::Assign { row, col, val }).unwrap();
+        }
+        assert_eq!(session.mistakes(), vec![(0, 3), (8, 5)]);
+        session.set_check_mode(CheckMode::Solution);
+        assert_eq!(session.mistakes(), vec![(0, 2), (0, 5), (8, 3)]);
+
+        let loaded = Session::from_json(&session.to_json()).unwrap();
+        assert_eq!(loaded.check_mode(), CheckMode::Solution);
+        assert_eq!(loaded.mistakes(), session.mistakes());
+
+        // 没有唯一解时按规则检查
+        let mut open = Session::new([[0; 9]; 9]);
+        assert_eq!(open.solution(), None);
+        open.set_check_mode(CheckMode::Solution);
+        open.apply(Edit::Assign {
+            row: 0,
+            col: 0,
+            val: 1,
+        })
+        .unwrap();
+        assert!(open.mistakes().is_empty());
+    }
+
+    #[test]
+    fn test_mark_mode() {
+        let puzzle = parse_grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        )
+        .unwrap();
+        let mut session = Session::new(puzzle);
+        assert_eq!(session.mark_mode(), MarkMode::Auto);
+        let assign = Edit::Assign {
+            row: 0,
+            col: 2,
+            val: 4,
+        };
+        session.apply(assign).unwrap();
+
+        session.set_mark_mode(MarkMode::Manual);
+        assert!(!session.history().can_undo());
+        assert!(session.board().get(0, 3).digits.is_empty());
+        let mark = |val| Edit::AddCandidate {
+            row: 0,
+            col: 3,
+            val,
+        };
+        assert_eq!(session.apply(mark(2)), Ok(true));
+        // 已填的 4 不能标注
+        assert!(session.apply(mark(4)).is_err());
+        // 清除数字后不自动填入候选，已有的手动标记仍随之更新
+        assert_eq!(session.apply(Edit::Unassign { row: 0, col: 2 }), Ok(true));
+        assert!(session.board().get(0, 2).digits.is_empty());
+        session.undo();
+        assert_eq!(session.board().grid()[0][2], 4);
+        assert_eq!(session.redo(), Ok(Some(Edit::Unassign { row: 0, col: 2 })));
+        assert!(session.board().get(0, 2).digits.is_empty());
+        assert_eq!(session.board().get(0, 3).digits.len(), 1);
+
+        let loaded = Session::from_json(&session.to_json()).unwrap();
+        assert_eq!(loaded.mark_mode(), MarkMode::Manual);
+        assert_eq!(loaded.board().state_hash(), session.board().state_hash());
+
+        session.set_mark_mode(MarkMode::Auto);
+        assert_eq!(session.board().get(0, 2).digits.len(), 3);
+        assert_eq!(
+            session.board().get(0, 2).digits,
+            Session::new(puzzle).board().get(0, 2).digits
+        );
+    }
+}
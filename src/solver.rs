@@ -0,0 +1,2093 @@
+//! 求解
+
+use std::any::Any;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::AddAssign;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::board::{grid_hash, grid_line, Alphabet, Deduction, Grid, SudokuBoard};
+use crate::constraint::{Cell, Propagation, Unit};
+use crate::json::Json;
+use crate::marks::PencilMarks;
+use crate::output::Printer;
+use crate::rng::Rng;
+use crate::svg;
+use crate::variant::Variant;
+
+/// 结果输出格式
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Plain digits, one row per line
+    Text,
+    /// One SVG image per solution
+    Svg,
+    /// One JSON object per line
+    Json,
+    /// One 81-digit line per grid (.sdm)
+    Sdm,
+    /// Comma-separated rows
+    Csv,
+}
+
+/// 求解算法
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SolverKind {
+    /// Candidate propagation with parallel guessing
+    #[default]
+    Propagate,
+    /// Serial backtracking over the cells in row order
+    Backtrack,
+}
+
+impl SolverKind {
+    /// 对应的求解器
+    pub fn solver(self) -> Box<dyn Solver> {
+        match self {
+            SolverKind::Propagate => Box::new(Propagating::default()),
+            SolverKind::Backtrack => Box::new(Backtracking),
+        }
+    }
+}
+
+/// 一次求解的搜索统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SolveStats {
+    /// 尝试填入的猜测
+    pub guesses: usize,
+    /// 走入的死路：推理出现矛盾，如某个空位没有可填的数字
+    pub dead_ends: usize,
+    /// 放弃猜测、退回上一层重新选择的次数：猜测之后走入死路或达到猜测深度上限的分支。
+    /// 推理本身出现的矛盾不经猜测，只计入 dead_ends
+    pub backtracks: usize,
+    /// 猜测深度达到上限而放弃的分支，不为 0 时可能漏掉了解
+    pub cut_off: usize,
+}
+
+impl AddAssign for SolveStats {
+    fn add_assign(&mut self, other: Self) {
+        self.guesses += other.guesses;
+        self.dead_ends += other.dead_ends;
+        self.backtracks += other.backtracks;
+        self.cut_off += other.cut_off;
+    }
+}
+
+/// 求解器
+pub trait Solver: Send + Sync {
+    /// 从 board 开始求解，每找到一个解调用一次 emit，emit 返回 false 时停止。返回搜索统计
+    fn solve(&self, board: SudokuBoard, emit: &(dyn Fn(&SudokuBoard) -> bool + Sync))
+        -> SolveStats;
+
+    /// 与 [`Solver::solve`] 相同，同时把求解过程中的事件交给 observer。
+    /// 默认只报告找到的解，内置的求解器报告全部事件
+    fn solve_observed(
+        &self,
+        board: SudokuBoard,
+        emit: &(dyn Fn(&SudokuBoard) -> bool + Sync),
+        observer: &dyn SolverObserver,
+    ) -> SolveStats {
+        self.solve(board, &|board| {
+            observer.on_solution(board);
+            emit(board)
+        })
+    }
+}
+
+/// 求解过程的观察者，用于性能分析、可视化和记录求解过程，不必改动求解器。
+///
+/// 各方法默认什么都不做。并行搜索中会从多个线程同时调用，调用的先后与线程调度有关
+pub trait SolverObserver: Sync {
+    /// 推理填入数字
+    fn on_assign(&self, _cell: Cell, _digit: u32) {}
+
+    /// 推理排除候选数字，包括由填入的数字（推理填入的或猜测的）直接导致的
+    fn on_eliminate(&self, _cell: Cell, _digit: u32) {}
+
+    /// 在分支的第 depth 层（从 1 开始）猜测填入数字，回溯求解的每次填数都是猜测。
+    /// 猜测的位置不再另外报告 on_assign 和 on_eliminate
+    fn on_guess(&self, _depth: usize, _cell: Cell, _digit: u32) {}
+
+    /// 放弃第 depth 层（从 1 开始）的猜测：分支走入死路或达到猜测深度上限。
+    /// 次数与 [`SolveStats::backtracks`] 相同
+    fn on_backtrack(&self, _depth: usize) {}
+
+    /// 找到一个解，在交给 emit 之前调用
+    fn on_solution(&self, _board: &SudokuBoard) {}
+}
+
+/// 候选数字传播，推理停滞时在自由位置上并行猜测
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Propagating {
+    /// 每个分支最多叠加的猜测个数，超出的分支放弃并计入 [`SolveStats::cut_off`]
+    pub max_depth: Option<usize>,
+    /// 给出种子时按随机顺序逐个尝试候选，不再并行；相同种子总是先找到同一个解
+    pub shuffle: Option<u64>,
+}
+
+impl Solver for Propagating {
+    fn solve(
+        &self,
+        board: SudokuBoard,
+        emit: &(dyn Fn(&SudokuBoard) -> bool + Sync),
+    ) -> SolveStats {
+        self.solve_with(board, emit, &SearchProgress::default())
+    }
+
+    fn solve_observed(
+        &self,
+        board: SudokuBoard,
+        emit: &(dyn Fn(&SudokuBoard) -> bool + Sync),
+        observer: &dyn SolverObserver,
+    ) -> SolveStats {
+        self.search(board, emit, &SearchProgress::default(), Some(observer))
+    }
+}
+
+impl Propagating {
+    /// 求解，搜索进度实时记录在 progress 中，可由其他线程读取
+    pub fn solve_with(
+        &self,
+        board: SudokuBoard,
+        emit: &(dyn Fn(&SudokuBoard) -> bool + Sync),
+        progress: &SearchProgress,
+    ) -> SolveStats {
+        self.search(board, emit, progress, None)
+    }
+
+    fn search(
+        &self,
+        board: SudokuBoard,
+        emit: &(dyn Fn(&SudokuBoard) -> bool + Sync),
+        progress: &SearchProgress,
+        observer: Option<&dyn SolverObserver>,
+    ) -> SolveStats {
+        let search = Search {
+            stop: AtomicBool::new(false),
+            max_depth: self.max_depth.unwrap_or(usize::MAX),
+            shuffle: self.shuffle,
+            emit,
+            progress,
+            observer,
+        };
+        resolve(board, vec![], &search);
+        SolveStats {
+            guesses: progress.guesses.load(Ordering::Relaxed),
+            dead_ends: progress.dead_ends.load(Ordering::Relaxed),
+            backtracks: progress.backtracks.load(Ordering::Relaxed),
+            cut_off: progress.cut_off.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// 按行优先顺序逐格回溯
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Backtracking;
+
+impl Solver for Backtracking {
+    fn solve(
+        &self,
+        board: SudokuBoard,
+        emit: &(dyn Fn(&SudokuBoard) -> bool + Sync),
+    ) -> SolveStats {
+        self.solve_observed(board, emit, &())
+    }
+
+    fn solve_observed(
+        &self,
+        board: SudokuBoard,
+        emit: &(dyn Fn(&SudokuBoard) -> bool + Sync),
+        observer: &dyn SolverObserver,
+    ) -> SolveStats {
+        let mut stats = SolveStats::default();
+        let variant = board.variant().clone();
+        let mut grid = board.grid();
+        let empty = grid.map(|row| row.map(|d| d == 0));
+        // 回溯栈
+        let mut stack = Vec::with_capacity(81);
+        stack.push((0, 0));
+        while backtrack(
+            &mut grid, &empty, &variant, &mut stack, &mut stats, observer,
+        ) {
+            // 回溯只检查与新填数字相关的约束，没有空位时给定数字本身也需检查
+            if !variant.check(&grid) {
+                continue;
+            }
+            let solution = SudokuBoard::new_with(&grid, variant.clone());
+            observer.on_solution(&solution);
+            if !emit(&solution) {
+                break;
+            }
+        }
+        stats
+    }
+}
+
+/// 并行搜索的进度，各分支共享
+#[derive(Debug, Default)]
+pub struct SearchProgress {
+    /// 尝试填入的猜测
+    pub guesses: AtomicUsize,
+    /// 走到死路的分支
+    pub dead_ends: AtomicUsize,
+    /// 放弃的猜测分支，包括走到死路和达到深度上限的
+    pub backtracks: AtomicUsize,
+    /// 猜测深度达到上限而放弃的分支
+    pub cut_off: AtomicUsize,
+    /// 尚未结束的分支，包括等待子分支的
+    pub active: AtomicUsize,
+    /// 解出的分支
+    pub solutions: AtomicUsize,
+    /// 置位后所有分支尽快结束
+    cancelled: AtomicBool,
+    /// 是否需要某个分支交出当前盘面
+    wanted: AtomicBool,
+    /// 某个分支最近交出的盘面
+    board: Mutex<Option<SudokuBoard>>,
+}
+
+impl SearchProgress {
+    /// 取出上次调用以来某个分支交出的盘面，并请求下一个
+    pub fn watch(&self) -> Option<SudokuBoard> {
+        let board = self.board.lock().unwrap().take();
+        self.wanted.store(true, Ordering::Relaxed);
+        board
+    }
+
+    /// 请求搜索尽快结束，可由其他线程调用。正在推理的分支推理完后结束，不再尝试新的猜测
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// 有请求时交出盘面，没有请求时不复制
+    fn publish(&self, board: &SudokuBoard) {
+        if self.wanted.swap(false, Ordering::Relaxed) {
+            *self.board.lock().unwrap() = Some(board.clone());
+        }
+    }
+}
+
+/// 用求解器列出至多 cap 个解，排序后返回
+pub fn collect(solver: &dyn Solver, board: SudokuBoard, cap: usize) -> Vec<Grid> {
+    let found = Mutex::new(vec![]);
+    if cap > 0 {
+        solver.solve(board, &|board| {
+            let mut found = found.lock().unwrap();
+            found.push(board.grid());
+            found.len() < cap
+        });
+    }
+    let mut found = found.into_inner().unwrap();
+    found.sort();
+    found
+}
+
+/// 用所有求解器各列出至多 cap 个解并比较，一致时返回解的个数，不一致时返回说明。
+/// 解的个数达到 cap 时各求解器列出的解可能不同，只比较个数
+pub fn cross_check(board: &SudokuBoard, cap: usize) -> Result<usize, String> {
+    let kinds = [SolverKind::Propagate, SolverKind::Backtrack];
+    let results: Vec<Vec<Grid>> = kinds
+        .iter()
+        .map(|kind| collect(kind.solver().as_ref(), board.clone(), cap))
+        .collect();
+    let (first, rest) = results.split_first().unwrap();
+    for (kind, found) in kinds[1..].iter().zip(rest) {
+        let same = if first.len() >= cap {
+            found.len() >= cap
+        } else {
+            found == first
+        };
+        if !same {
+            let missing = first.iter().find(|g| !found.contains(g));
+            let extra = found.iter().find(|g| !first.contains(g));
+            let mut message = format!(
+                "{:?} found {} solutions, {:?} found {}",
+                kinds[0],
+                first.len(),
+                kind,
+                found.len()
+            );
+            if let Some(grid) = missing {
+                message.push_str(&format!("; only {:?}: {}", kinds[0], grid_line(grid)));
+            }
+            if let Some(grid) = extra {
+                message.push_str(&format!("; only {:?}: {}", kind, grid_line(grid)));
+            }
+            return Err(message);
+        }
+    }
+    Ok(first.len())
+}
+
+/// 求解上下文，负责校验、计数和输出每个解
+pub struct ResolveCtx {
+    /// 结果分隔符
+    pub sep: String,
+    /// 输出格式
+    pub format: OutputFormat,
+    /// 题目给定的数字
+    pub givens: Grid,
+    /// 是否求解所有结果
+    pub all: bool,
+    /// 求解所有结果时至多输出的个数
+    pub limit: Option<usize>,
+    /// 输出前是否校验每个解
+    pub verify: bool,
+    /// 结果总数
+    pub total: AtomicUsize,
+    /// 已输出的解的哈希，并行的分支解出同一个解时只输出一次
+    pub seen: Mutex<HashSet<u64>>,
+    /// 解经由唯一的写线程输出，搜索结束后须 flush
+    pub out: Printer,
+    /// 字母数独以字母输出（SVG 除外）
+    pub alphabet: Option<Alphabet>,
+    /// 题目在输入中的标识，JSON 和 CSV 的每个解都带上
+    pub label: Label,
+}
+
+/// 题目在输入中的标识，批量求解时用来把结果对应回输入
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Label {
+    /// `# name` 注释行或 CSV 第一列给出的名称
+    pub id: Option<String>,
+    /// 题目开始的行号，从 1 开始
+    pub line: Option<usize>,
+}
+
+impl Label {
+    /// JSON 对象中的 `id` 和 `line` 字段，没有的省略
+    pub fn fields(&self) -> Vec<(String, Json)> {
+        let mut fields = vec![];
+        fields.extend(self.id.as_deref().map(|id| ("id".to_string(), id.into())));
+        fields.extend(self.line.map(|line| ("line".to_string(), line.into())));
+        fields
+    }
+}
+
+impl ResolveCtx {
+    /// 需要输出的解的个数
+    pub fn wanted(&self) -> usize {
+        if self.all {
+            self.limit.unwrap_or(usize::MAX)
+        } else {
+            1
+        }
+    }
+
+    /// 校验并输出一个解，返回是否还需要更多的解
+    pub fn emit(&self, board: &SudokuBoard) -> bool {
+        if self.verify {
+            if let Err(e) = verify(&board.grid(), &self.givens, board.variant()) {
+                log::error!("solver produced an invalid solution: {}", e);
+                return true;
+            }
+        }
+        if !self.seen.lock().unwrap().insert(board.state_hash()) {
+            return true;
+        }
+        // 并行的分支可能同时解出，超出个数的解不输出
+        let counted = self
+            .total
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+                (n < self.wanted()).then_some(n + 1)
+            });
+        let Ok(n) = counted else {
+            return false;
+        };
+        let line = grid_line(&board.grid());
+        let spell = |text: String| match &self.alphabet {
+            Some(alphabet) => alphabet.spell(&text),
+            None => text,
+        };
+        let text = match self.format {
+            OutputFormat::Text => format!("{}\n{}\n", self.sep, spell(board.to_string())),
+            OutputFormat::Svg => format!("{}\n", svg::render(board, &self.givens)),
+            OutputFormat::Json => format!("{}\n", solution_json(spell(line.clone()), &self.label)),
+            OutputFormat::Sdm => format!("{}\n", spell(line.clone())),
+            OutputFormat::Csv => format!(
+                "{},{}{}\n",
+                spell(grid_line(&self.givens)),
+                spell(line.clone()),
+                self.label
+                    .id
+                    .as_deref()
+                    .map_or(String::new(), |id| format!(",{}", csv_field(id)))
+            ),
+        };
+        self.out.print(line, text);
+        n + 1 < self.wanted()
+    }
+}
+
+/// 一次并行搜索中各分支共享的状态
+struct Search<'a> {
+    /// 置位后所有分支尽快结束
+    stop: AtomicBool,
+    /// 每个分支最多叠加的猜测个数
+    max_depth: usize,
+    /// 随机顺序的种子
+    shuffle: Option<u64>,
+    emit: &'a (dyn Fn(&SudokuBoard) -> bool + Sync),
+    progress: &'a SearchProgress,
+    /// 观察者，没有时不比较推理前后的盘面
+    observer: Option<&'a dyn SolverObserver>,
+}
+
+impl Search<'_> {
+    /// 是否应结束搜索：输出方不再需要解，或搜索已被取消
+    fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed) || self.progress.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// 什么都不做的观察者
+impl SolverObserver for () {}
+
+/// 比较前后的盘面，报告填入的数字和排除的候选，跳过 skip 位置（猜测的位置）
+fn observe_step(
+    before: &SudokuBoard,
+    after: &SudokuBoard,
+    observer: &dyn SolverObserver,
+    skip: Option<Cell>,
+) {
+    for row in 0..9 {
+        for col in 0..9 {
+            let (was, now) = (before.get(row, col), after.get(row, col));
+            if was.val != 0 || skip == Some((row, col)) {
+                continue;
+            }
+            let mut removed: Vec<u32> = was
+                .digits
+                .iter()
+                .filter(|d| now.val != **d && !now.digits.contains(d))
+                .cloned()
+                .collect();
+            removed.sort_unstable();
+            for digit in removed {
+                observer.on_eliminate((row, col), digit);
+            }
+            if now.val != 0 {
+                observer.on_assign((row, col), now.val);
+            }
+        }
+    }
+}
+
+/// 候选数字传播求解，q 为已猜测的数字
+fn resolve(board: SudokuBoard, q: Vec<(usize, usize, u32)>, search: &Search) {
+    let Search {
+        stop,
+        emit,
+        progress,
+        observer,
+        ..
+    } = search;
+    if search.stopped() {
+        return;
+    }
+    progress.active.fetch_add(1, Ordering::Relaxed);
+    let mut board = board;
+    let before = observer.map(|_| board.clone());
+    let result = board.deduce();
+    if let (Some(before), Some(observer)) = (&before, observer) {
+        observe_step(before, &board, *observer, None);
+    }
+    progress.publish(&board);
+    if result == Deduction::Solved {
+        log::debug!("q: {:?}", q);
+        progress.solutions.fetch_add(1, Ordering::Relaxed);
+        if let Some(observer) = observer {
+            observer.on_solution(&board);
+        }
+        if !emit(&board) {
+            stop.store(true, Ordering::Relaxed);
+        }
+    } else if result == Deduction::Contradiction {
+        // 死路，包括约束推出的矛盾
+        progress.dead_ends.fetch_add(1, Ordering::Relaxed);
+        abandon(&q, search);
+    } else if q.len() >= search.max_depth {
+        log::debug!("cut off at depth {}: {:?}", q.len(), q);
+        progress.cut_off.fetch_add(1, Ordering::Relaxed);
+        abandon(&q, search);
+    } else {
+        // 固定某个自由参数
+        let (free_row, free_col, _) = q.last().cloned().unwrap_or((0, 0, 0));
+        let free_pos = free_row * 9 + free_col;
+        let mut found_free = false;
+        for row in free_row..9 {
+            for col in 0..9 {
+                let cur_pos = row * 9 + col;
+                if cur_pos < free_pos {
+                    continue;
+                }
+                let pos = board.get(row, col);
+                if pos.val == 0 {
+                    // 找到一个自由参数
+                    found_free = true;
+                    log::debug!("free pos: ({},{})={} {:?}", row, col, pos.val, pos.digits);
+                    let guess = |digit: u32| {
+                        let mut board2 = board.clone();
+                        board2.set(digit, row, col);
+                        let mut q2 = q.clone();
+                        q2.push((row, col, digit));
+                        progress.guesses.fetch_add(1, Ordering::Relaxed);
+                        if let Some(observer) = observer {
+                            observer.on_guess(q2.len(), (row, col), digit);
+                            // 填入猜测的数字时从同组位置排除的候选
+                            observe_step(&board, &board2, *observer, Some((row, col)));
+                        }
+                        (board2, q2)
+                    };
+                    match search.shuffle {
+                        // 顺序只由种子和盘面决定，与线程的调度无关
+                        Some(seed) => {
+                            let mut digits: Vec<u32> = pos.digits.iter().copied().collect();
+                            digits.sort_unstable();
+                            Rng::new(seed ^ grid_hash(&board.grid())).shuffle(&mut digits);
+                            for digit in digits {
+                                if search.stopped() {
+                                    break;
+                                }
+                                let (board2, q2) = guess(digit);
+                                resolve(board2, q2, search);
+                            }
+                        }
+                        // 在 scope 中并行尝试，返回前所有分支都已结束
+                        None => rayon::scope(|s| {
+                            for digit in pos.digits.clone() {
+                                let (board2, q2) = guess(digit);
+                                s.spawn(move |_| {
+                                    resolve(board2, q2, search);
+                                });
+                            }
+                        }),
+                    }
+                }
+                if found_free {
+                    break;
+                }
+            }
+            if found_free {
+                break;
+            }
+        }
+    }
+    progress.active.fetch_sub(1, Ordering::Relaxed);
+}
+
+/// 放弃一个分支，根上没有猜测可以放弃
+fn abandon(q: &[(usize, usize, u32)], search: &Search) {
+    if q.is_empty() {
+        return;
+    }
+    search.progress.backtracks.fetch_add(1, Ordering::Relaxed);
+    if let Some(observer) = search.observer {
+        observer.on_backtrack(q.len());
+    }
+}
+
+/// 检查解是否填满、与给定数字一致且满足所有约束
+pub fn verify(solution: &Grid, givens: &Grid, variant: &Variant) -> Result<(), String> {
+    for row in 0..9 {
+        for col in 0..9 {
+            let (val, given) = (solution[row][col], givens[row][col]);
+            if val == 0 {
+                return Err(format!("r{}c{} is empty", row + 1, col + 1));
+            }
+            if given != 0 && val != given {
+                return Err(format!(
+                    "r{}c{} is {} but the given is {}",
+                    row + 1,
+                    col + 1,
+                    val,
+                    given
+                ));
+            }
+            if !variant.allows(row, col, val) {
+                return Err(format!(
+                    "r{}c{}={} breaks its parity mark",
+                    row + 1,
+                    col + 1,
+                    val
+                ));
+            }
+        }
+    }
+    match variant.conflicts(solution).first() {
+        Some(conflict) => Err(conflict.to_string()),
+        None => Ok(()),
+    }
+}
+
+/// 输出解是否唯一，sdm 中为注释，svg 中为 XML 注释，csv 中写到标准错误以免混入表格
+pub fn print_uniqueness(format: OutputFormat, unique: bool) {
+    let verdict = if unique {
+        "unique"
+    } else {
+        "multiple solutions exist"
+    };
+    match format {
+        OutputFormat::Text => println!("{}", verdict),
+        OutputFormat::Csv => eprintln!("{}", verdict),
+        OutputFormat::Svg => println!("<!-- {} -->", verdict),
+        OutputFormat::Json => println!(
+            "{}",
+            Json::Object(vec![("unique".to_string(), unique.into())])
+        ),
+        OutputFormat::Sdm => println!("# {}", verdict),
+    }
+}
+
+/// 输出解的个数，capped 表示已达到计数上限，实际可能更多
+pub fn print_count(format: OutputFormat, count: usize, capped: bool) {
+    let text = format!(
+        "{}{} solution{}",
+        if capped { "at least " } else { "" },
+        count,
+        if count == 1 { "" } else { "s" }
+    );
+    match format {
+        OutputFormat::Text | OutputFormat::Csv => println!("{}", text),
+        OutputFormat::Svg => println!("<!-- {} -->", text),
+        OutputFormat::Json => println!(
+            "{}",
+            Json::Object(vec![
+                ("count".to_string(), count.into()),
+                ("exact".to_string(), (!capped).into()),
+            ])
+        ),
+        OutputFormat::Sdm => println!("# {}", text),
+    }
+}
+
+/// 不经猜测、只靠推理能填入的空位个数
+pub fn logic_cells(givens: &Grid, variant: Arc<Variant>) -> usize {
+    let mut board = SudokuBoard::new_with(givens, variant);
+    board.solve();
+    let filled = |grid: &Grid| grid.iter().flatten().filter(|d| **d != 0).count();
+    filled(&board.grid()) - filled(givens)
+}
+
+/// 输出推理和猜测各填入的空位个数，csv 中写到标准错误以免混入表格
+pub fn print_breakdown(format: OutputFormat, givens: usize, logic: usize) {
+    let guessed = 81 - givens - logic;
+    let text = format!("givens {}, logic {}, guessed {}", givens, logic, guessed);
+    match format {
+        OutputFormat::Text => println!("{}", text),
+        OutputFormat::Csv => eprintln!("{}", text),
+        OutputFormat::Svg => println!("<!-- {} -->", text),
+        OutputFormat::Json => println!(
+            "{}",
+            Json::Object(vec![
+                ("givens".to_string(), givens.into()),
+                ("logic".to_string(), logic.into()),
+                ("guessed".to_string(), guessed.into()),
+            ])
+        ),
+        OutputFormat::Sdm => println!("# {}", text),
+    }
+}
+
+/// 错误对象 `{"error": {"kind": ..., "message": ..., ...}}`
+pub fn error_json(kind: &str, message: &str, details: Vec<(String, Json)>) -> Json {
+    let mut error = vec![
+        ("kind".to_string(), kind.into()),
+        ("message".to_string(), message.into()),
+    ];
+    error.extend(details);
+    Json::Object(vec![("error".to_string(), Json::Object(error))])
+}
+
+/// JSON 格式下输出错误对象，便于脚本处理；其他格式的错误只写入日志
+pub fn print_error(format: OutputFormat, kind: &str, message: &str, details: Vec<(String, Json)>) {
+    if format == OutputFormat::Json {
+        println!("{}", error_json(kind, message, details));
+    }
+}
+
+/// 输出诊断信息：JSON 格式下作为对象写到标准输出，其他格式下以文本写到标准错误，不混入结果
+fn print_diagnostic(format: OutputFormat, text: &str, json: Json) {
+    match format {
+        OutputFormat::Json => println!("{}", json),
+        _ => eprintln!("{}", text),
+    }
+}
+
+/// 输出搜索统计
+pub fn print_stats(format: OutputFormat, stats: &SolveStats) {
+    let text = format!(
+        "guesses {}, dead ends {}, backtracks {}",
+        stats.guesses, stats.dead_ends, stats.backtracks
+    );
+    let json = Json::Object(vec![
+        ("guesses".to_string(), stats.guesses.into()),
+        ("dead_ends".to_string(), stats.dead_ends.into()),
+        ("backtracks".to_string(), stats.backtracks.into()),
+    ]);
+    print_diagnostic(format, &text, json);
+}
+
+/// 输出一道题目的求解用时，以毫秒为单位
+pub fn print_time(format: OutputFormat, elapsed: Duration) {
+    let ms = (elapsed.as_secs_f64() * 1e6).round() / 1e3;
+    let json = Json::Object(vec![("time_ms".to_string(), ms.into())]);
+    print_diagnostic(format, &format!("time {:.3} ms", ms), json);
+}
+
+/// 解的 JSON 形式，81 个数字（或字母）按行排列，并带上题目的标识
+fn solution_json(line: String, label: &Label) -> Json {
+    let mut fields = vec![("solution".to_string(), line.into())];
+    fields.extend(label.fields());
+    Json::Object(fields)
+}
+
+/// CSV 的一个字段，含逗号、引号或换行时加引号
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// 逐步推理，每一步输出角标和中心标记，直到推理无法继续。返回是否已解出
+pub fn trace(board: &mut SudokuBoard, format: OutputFormat) -> bool {
+    let print = |step: usize, marks: &PencilMarks| match format {
+        OutputFormat::Json => {
+            let json = Json::Object(vec![
+                ("step".to_string(), step.into()),
+                ("cells".to_string(), marks.to_json()),
+            ]);
+            println!("{}", json);
+        }
+        _ => println!("step {}\n{}", step, marks),
+    };
+    let mut marks = PencilMarks::from_board(board);
+    print(0, &marks);
+    let mut step = 0;
+    loop {
+        let result = board.step();
+        let next = PencilMarks::from_board(board);
+        // 最后一轮没有变化时不再重复输出
+        if next != marks {
+            step += 1;
+            print(step, &next);
+            marks = next;
+        }
+        if let Some(solved) = result {
+            return solved;
+        }
+    }
+}
+
+/// 推理 steps 轮之后的棋盘，推理提前结束时为结束时的棋盘
+pub fn snapshot_at(board: &SudokuBoard, steps: usize) -> SudokuBoard {
+    let mut board = board.clone();
+    for _ in 0..steps {
+        if board.step().is_some() {
+            break;
+        }
+    }
+    board
+}
+
+/// 候选数字最少的空位，有空位已无候选数字或没有空位时返回 None
+pub(crate) fn most_constrained(board: &SudokuBoard) -> Option<(usize, usize)> {
+    let mut best: Option<(usize, usize, usize)> = None;
+    for row in 0..9 {
+        for col in 0..9 {
+            let pos = board.get(row, col);
+            if pos.val != 0 {
+                continue;
+            }
+            let n = pos.digits.len();
+            if n == 0 {
+                return None;
+            }
+            if best.is_none_or(|(_, _, m)| n < m) {
+                best = Some((row, col, n));
+            }
+        }
+    }
+    best.map(|(row, col, _)| (row, col))
+}
+
+/// 按求解的顺序推理，停滞时猜测，直到找到第一个解。对每个被排除的候选用计数求解器确认：在这一轮之前的
+/// 盘面上填入该候选后没有任何补全。返回确认过的排除个数，发现不可靠的排除时返回说明
+pub fn audit(givens: &Grid, variant: Arc<Variant>) -> Result<usize, String> {
+    let mut checked = 0;
+    audit_branch(SudokuBoard::new_with(givens, variant), &mut checked)?;
+    Ok(checked)
+}
+
+/// 审查一个分支，返回是否解出
+fn audit_branch(mut board: SudokuBoard, checked: &mut usize) -> Result<bool, String> {
+    let variant = board.variant().clone();
+    let result = loop {
+        let before = board.clone();
+        let result = board.advance();
+        let grid = before.grid();
+        for row in 0..9 {
+            for col in 0..9 {
+                let (old, new) = (before.get(row, col), board.get(row, col));
+                if old.val != 0 {
+                    continue;
+                }
+                let mut removed: Vec<u32> = old
+                    .digits
+                    .iter()
+                    .cloned()
+                    .filter(|d| {
+                        if new.val != 0 {
+                            new.val != *d
+                        } else {
+                            !new.digits.contains(d)
+                        }
+                    })
+                    .collect();
+                removed.sort();
+                for digit in removed {
+                    *checked += 1;
+                    let mut guess = grid;
+                    guess[row][col] = digit;
+                    // 与已填数字直接矛盾的候选无需求解
+                    if !variant.allows(row, col, digit)
+                        || variant
+                            .peers()
+                            .of(row, col)
+                            .iter()
+                            .any(|&(r, c)| grid[r][c] == digit)
+                        || !variant.consistent(&guess, row, col)
+                    {
+                        continue;
+                    }
+                    let guess = SudokuBoard::new_with(&guess, variant.clone());
+                    if count_solutions(&guess, 1) > 0 {
+                        return Err(format!(
+                            "eliminated {} from r{}c{} on {}, but a completion uses it",
+                            digit,
+                            row + 1,
+                            col + 1,
+                            grid_line(&grid)
+                        ));
+                    }
+                }
+            }
+        }
+        if let Some(result) = result {
+            break result;
+        }
+    };
+    match result {
+        Deduction::Solved => return Ok(true),
+        // 推理发现矛盾的分支没有解，不再往下猜测
+        Deduction::Contradiction => return Ok(false),
+        Deduction::Stuck => {}
+    }
+    let Some((row, col)) = most_constrained(&board) else {
+        return Ok(false);
+    };
+    let mut digits: Vec<u32> = board.get(row, col).digits.iter().cloned().collect();
+    digits.sort();
+    for digit in digits {
+        let mut guess = board.clone();
+        guess.set(digit, row, col);
+        if audit_branch(guess, checked)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// 统计解的个数，找到 cap 个解即停止
+pub fn count_solutions(board: &SudokuBoard, cap: usize) -> usize {
+    solutions(board, cap).len()
+}
+
+/// 列出至多 cap 个解
+pub fn solutions(board: &SudokuBoard, cap: usize) -> Vec<Grid> {
+    solutions_until(board, cap, None).unwrap_or_default()
+}
+
+/// 与 [`solutions`] 相同，但到 deadline 仍未搜索完时放弃，返回 None
+pub fn solutions_until(
+    board: &SudokuBoard,
+    cap: usize,
+    deadline: Option<Instant>,
+) -> Option<Vec<Grid>> {
+    fn search(
+        board: &SudokuBoard,
+        cap: usize,
+        deadline: Option<Instant>,
+        found: &mut Vec<Grid>,
+    ) -> Option<()> {
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return None;
+        }
+        let mut board = board.clone();
+        match board.deduce() {
+            Deduction::Solved => {
+                found.push(board.grid());
+                return Some(());
+            }
+            // 推理发现矛盾的分支没有解，不再往下猜测
+            Deduction::Contradiction => return Some(()),
+            Deduction::Stuck => {}
+        }
+        // 在候选数字最少的空位上猜测
+        let Some((row, col)) = most_constrained(&board) else {
+            return Some(());
+        };
+        let mut digits: Vec<u32> = board.get(row, col).digits.iter().cloned().collect();
+        digits.sort();
+        for digit in digits {
+            let mut guess = board.clone();
+            guess.set(digit, row, col);
+            search(&guess, cap, deadline, found)?;
+            if found.len() >= cap {
+                break;
+            }
+        }
+        Some(())
+    }
+    let mut found = vec![];
+    if cap > 0 {
+        search(board, cap, deadline, &mut found)?;
+    }
+    Some(found)
+}
+
+/// 平均每个分片分到的子树个数，越多各分片的工作量越均衡
+const SHARD_SPREAD: usize = 16;
+
+/// 搜索树顶部的一个分片，共 count 个，index 从 1 开始
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    pub index: usize,
+    pub count: usize,
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    /// 解析 `2/8` 形式的分片
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid shard `{}`, expected I/N with 1 <= I <= N", s);
+        let (index, count) = s.split_once('/').ok_or_else(invalid)?;
+        let index = index.trim().parse().map_err(|_| invalid())?;
+        let count = count.trim().parse().map_err(|_| invalid())?;
+        if index < 1 || index > count {
+            return Err(invalid());
+        }
+        Ok(Self { index, count })
+    }
+}
+
+impl Shard {
+    /// 逐层展开搜索树的顶部：推理后在候选最少的空位上按数字从小到大猜测，直到子树足够多。
+    /// 子树按展开的顺序编号，返回编号除以 count 余 index - 1 的子树。展开只取决于题目，
+    /// 各子树互不相交，所有分片的解合起来恰好是全部的解
+    pub fn subtrees(&self, board: &SudokuBoard) -> Vec<SudokuBoard> {
+        let mut frontier = vec![board.clone()];
+        let mut expanded = true;
+        while expanded && frontier.len() < self.count * SHARD_SPREAD {
+            expanded = false;
+            let mut next = vec![];
+            for mut board in frontier {
+                match board.deduce() {
+                    Deduction::Solved => {
+                        next.push(board);
+                        continue;
+                    }
+                    // 矛盾的子树没有解，不再分给任何分片
+                    Deduction::Contradiction => continue,
+                    Deduction::Stuck => {}
+                }
+                let Some((row, col)) = most_constrained(&board) else {
+                    continue;
+                };
+                let mut digits: Vec<u32> = board.get(row, col).digits.iter().cloned().collect();
+                digits.sort();
+                for digit in digits {
+                    let mut guess = board.clone();
+                    guess.set(digit, row, col);
+                    next.push(guess);
+                }
+                expanded = true;
+            }
+            frontier = next;
+        }
+        frontier
+            .into_iter()
+            .skip(self.index - 1)
+            .step_by(self.count)
+            .collect()
+    }
+}
+
+/// 不经猜测的推理中最早发现的矛盾
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Contradiction {
+    /// 空位的候选数字被全部排除，givens 和 deduced 为已有数字的同组位置
+    Empty {
+        cell: Cell,
+        givens: Vec<(Cell, u32)>,
+        deduced: Vec<(Cell, u32)>,
+    },
+    /// 某个约束无法满足。digit 为区域内已无处可填的数字，cells 为约束涉及的位置
+    Violated {
+        constraint: String,
+        digit: Option<u32>,
+        cells: Vec<Cell>,
+    },
+}
+
+impl fmt::Display for Contradiction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |cells: &[(Cell, u32)]| {
+            cells
+                .iter()
+                .map(|((r, c), d)| format!("r{}c{}={}", r + 1, c + 1, d))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        match self {
+            Contradiction::Empty {
+                cell,
+                givens,
+                deduced,
+            } => {
+                write!(f, "no candidates left for r{}c{}", cell.0 + 1, cell.1 + 1)?;
+                if !givens.is_empty() {
+                    write!(f, "; givens {}", join(givens))?;
+                }
+                if !deduced.is_empty() {
+                    write!(f, "; deduced {}", join(deduced))?;
+                }
+                Ok(())
+            }
+            Contradiction::Violated {
+                constraint,
+                digit: Some(digit),
+                ..
+            } => write!(f, "no place for {} in {}", digit, constraint),
+            Contradiction::Violated { constraint, .. } => {
+                write!(f, "constraint cannot be satisfied: {}", constraint)
+            }
+        }
+    }
+}
+
+impl Contradiction {
+    /// 结构化的错误信息，行列从 1 开始
+    pub fn details(&self) -> Vec<(String, Json)> {
+        match self {
+            Contradiction::Empty { cell, .. } => vec![
+                ("row".to_string(), (cell.0 + 1).into()),
+                ("col".to_string(), (cell.1 + 1).into()),
+            ],
+            Contradiction::Violated {
+                constraint,
+                digit,
+                cells,
+            } => {
+                let mut details = vec![("constraint".to_string(), constraint.as_str().into())];
+                if let Some(digit) = digit {
+                    details.push(("digit".to_string(), (*digit).into()));
+                }
+                let cells = cells
+                    .iter()
+                    .map(|(r, c)| format!("r{}c{}", r + 1, c + 1).into())
+                    .collect();
+                details.push(("cells".to_string(), Json::Array(cells)));
+                details
+            }
+        }
+    }
+}
+
+/// 从给定数字开始推理（不猜测），返回最早发现的矛盾。推理停滞或解出时返回 None
+pub fn contradiction(givens: &Grid, variant: Arc<Variant>) -> Option<Contradiction> {
+    let mut board = SudokuBoard::new_with(givens, variant.clone());
+    loop {
+        // 每一轮之后检查是否有空位已无候选数字
+        for row in 0..9 {
+            for col in 0..9 {
+                let pos = board.get(row, col);
+                if pos.val != 0 || !pos.digits.is_empty() {
+                    continue;
+                }
+                let (mut from_givens, mut deduced) = (vec![], vec![]);
+                let mut peers = variant.peers().of(row, col).to_vec();
+                peers.sort();
+                for (r, c) in peers {
+                    let val = board.get(r, c).val;
+                    if givens[r][c] != 0 {
+                        from_givens.push(((r, c), val));
+                    } else if val != 0 {
+                        deduced.push(((r, c), val));
+                    }
+                }
+                return Some(Contradiction::Empty {
+                    cell: (row, col),
+                    givens: from_givens,
+                    deduced,
+                });
+            }
+        }
+        match board.step() {
+            None => {}
+            Some(true) => return None,
+            Some(false) => {
+                if board.exhausted() {
+                    continue;
+                }
+                // 找出无法满足的约束
+                return variant
+                    .constraints()
+                    .iter()
+                    .find(|c| {
+                        !c.check(&board.grid())
+                            || c.propagate(&mut board.clone()) == Propagation::Contradiction
+                    })
+                    .map(|c| {
+                        let unit = (c.as_ref() as &dyn Any).downcast_ref::<Unit>();
+                        // 区域内既未填入、也不再是任何空位候选的数字
+                        let digit = unit.and_then(|unit| {
+                            (1..10).find(|d| {
+                                unit.cells.iter().all(|&(r, c)| {
+                                    let pos = board.get(r, c);
+                                    pos.val != *d && (pos.val != 0 || !pos.digits.contains(d))
+                                })
+                            })
+                        });
+                        Contradiction::Violated {
+                            constraint: c.to_string(),
+                            digit,
+                            cells: unit.map_or_else(|| c.cells(), |unit| unit.cells.clone()),
+                        }
+                    });
+            }
+        }
+    }
+}
+
+/// 回溯法找一个解
+pub fn brute_force(
+    board: &mut Grid,
+    empty: &[[bool; 9]; 9],
+    variant: &Variant,
+    stack: &mut Vec<(usize, usize)>,
+) -> bool {
+    backtrack(
+        board,
+        empty,
+        variant,
+        stack,
+        &mut SolveStats::default(),
+        &(),
+    )
+}
+
+/// 回溯法找一个解，同时累计搜索统计
+fn backtrack(
+    board: &mut Grid,
+    empty: &[[bool; 9]; 9],
+    variant: &Variant,
+    stack: &mut Vec<(usize, usize)>,
+    stats: &mut SolveStats,
+    observer: &dyn SolverObserver,
+) -> bool {
+    // 无法回溯或缺少初始值
+    if stack.is_empty() {
+        return false;
+    }
+
+    let (mut row, mut col) = stack.pop().unwrap(); // 当前行列
+
+    // 为 (row,col) 查找下一个能用的值并填入
+    let next_digit = |board: &mut Grid, row: usize, col: usize| -> Option<u32> {
+        // 注意，使用 +1 来越过旧值
+        for digit in (board[row][col] + 1)..10 {
+            // 符合奇偶标记，且所有同组位置（行、列、3x3 小格及附加约束）唯一
+            if !variant.allows(row, col, digit)
+                || variant
+                    .peers()
+                    .of(row, col)
+                    .iter()
+                    .any(|&(r, c)| board[r][c] == digit)
+            {
+                continue;
+            }
+            // 其余约束
+            board[row][col] = digit;
+            if variant.consistent(board, row, col) {
+                return Some(digit);
+            }
+        }
+        None
+    };
+
+    'back_trace: while row < 9 {
+        while col < 9 {
+            // 空位处理
+            if board[row][col] == 0 || empty[row][col] {
+                let fresh = board[row][col] == 0;
+                match next_digit(board, row, col) {
+                    Some(digit) => {
+                        // 找到可能值，压栈
+                        stats.guesses += 1;
+                        board[row][col] = digit;
+                        stack.push((row, col));
+                        observer.on_guess(stack.len(), (row, col), digit);
+                    }
+                    None => {
+                        // 一个值都填不进去即为死路
+                        if fresh {
+                            stats.dead_ends += 1;
+                        }
+                        // 当前复位，向前回溯
+                        board[row][col] = 0;
+                        match stack.pop() {
+                            Some((prev_row, prev_col)) => {
+                                stats.backtracks += 1;
+                                observer.on_backtrack(stack.len() + 1);
+                                row = prev_row;
+                                col = prev_col;
+                                continue 'back_trace;
+                            }
+                            None => {
+                                // 已不能回溯，失败
+                                return false;
+                            }
+                        }
+                    }
+                }
+            }
+            // 成功找到结果，打印出来，然后向前回溯
+            if row == 8 && col == 8 && board[row][col] != 0 {
+                return true;
+            }
+            col += 1;
+        }
+        row += 1;
+        col = 0;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::{
+        audit, audit_branch, brute_force, collect, contradiction, count_solutions, cross_check,
+        csv_field, logic_cells, solutions, trace, verify, Backtracking, Contradiction, Label,
+        OutputFormat, Propagating, ResolveCtx, SearchProgress, Shard, SolveStats, Solver,
+        SolverObserver,
+    };
+    use crate::board::SudokuBoard;
+    use crate::board::{parse_grid, Deduction, Grid};
+    use crate::constraint::Cell;
+    use crate::constraint::{Arrow, Cage, Pair};
+    use crate::json::Json;
+    use crate::output::Printer;
+    use crate::variant::{ConstraintKind, Parity, Variant};
+
+    #[test]
+    fn test_sudoku_3() {
+        let mut board = [
+            [0, 4, 0, 6, 1, 0, 9, 2, 5],
+            [0, 5, 1, 0, 0, 0, 7, 4, 6],
+            [9, 2, 6, 0, 0, 0, 8, 1, 3],
+            [0, 8, 0, 0, 5, 0, 0, 7, 1],
+            [0, 9, 0, 1, 0, 0, 0, 3, 2],
+            [0, 1, 3, 4, 7, 0, 5, 9, 8],
+            [0, 0, 0, 0, 0, 0, 1, 8, 9],
+            [1, 6, 2, 8, 0, 0, 3, 5, 7],
+            [8, 0, 9, 0, 0, 1, 2, 6, 4],
+        ];
+        let mut empty = [[false; 9]; 9];
+        for i in 0..9 {
+            for j in 0..9 {
+                empty[i][j] = board[i][j] == 0;
+            }
+        }
+        let mut stack = Vec::with_capacity(81);
+        stack.push((0, 0));
+        let variant = Variant::default();
+        let resolved = brute_force(&mut board, &empty, &variant, &mut stack);
+        assert!(resolved);
+
+        let board2 = [
+            [7_u32, 4, 8, 6, 1, 3, 9, 2, 5],
+            [3, 5, 1, 9, 2, 8, 7, 4, 6],
+            [9, 2, 6, 7, 4, 5, 8, 1, 3],
+            [2, 8, 4, 3, 5, 9, 6, 7, 1],
+            [5, 9, 7, 1, 8, 6, 4, 3, 2],
+            [6, 1, 3, 4, 7, 2, 5, 9, 8],
+            [4, 3, 5, 2, 6, 7, 1, 8, 9],
+            [1, 6, 2, 8, 9, 4, 3, 5, 7],
+            [8, 7, 9, 5, 3, 1, 2, 6, 4],
+        ];
+        assert_eq!(board, board2);
+
+        let resolved = brute_force(&mut board, &empty, &variant, &mut stack);
+        assert!(!resolved);
+    }
+
+    #[test]
+    fn test_sudoku_anti_knight() {
+        let variant = Arc::new(Variant::with_constraints(&[ConstraintKind::AntiKnight]));
+
+        // 候选数传播：马步位置不能再使用相同数字
+        let mut board = [[0; 9]; 9];
+        board[4][4] = 5;
+        let sudoku = SudokuBoard::new_with(&board, variant.clone());
+        assert!(!sudoku.get(2, 3).digits.contains(&5));
+        assert!(sudoku.get(2, 2).digits.contains(&5));
+
+        // 回溯：找到的解满足所有同组约束
+        board[0][0] = 1;
+        let mut empty = [[false; 9]; 9];
+        for i in 0..9 {
+            for j in 0..9 {
+                empty[i][j] = board[i][j] == 0;
+            }
+        }
+        let mut stack = vec![(0, 0)];
+        assert!(brute_force(&mut board, &empty, &variant, &mut stack));
+        assert!(variant.check(&board));
+    }
+
+    #[test]
+    fn test_sudoku_variants() {
+        let mut variant = Variant::with_constraints(&[ConstraintKind::OddEven]);
+        variant.parity[0][0] = Some(Parity::Odd);
+        variant.parity[0][1] = Some(Parity::Even);
+        variant.add("r1c4-r1c5-r1c6".parse::<Arrow>().unwrap());
+        variant.add("v:r2c1-r2c2".parse::<Pair>().unwrap());
+        variant.add_negative_xv();
+
+        // 回溯找到的解满足奇偶标记、箭头以及所有二元约束
+        let mut board = [[0; 9]; 9];
+        board[0][2] = 1;
+        let empty = board.map(|row| row.map(|d| d == 0));
+        let mut stack = vec![(0, 0)];
+        assert!(brute_force(&mut board, &empty, &variant, &mut stack));
+        assert_eq!(board[0][0] % 2, 1);
+        assert_eq!(board[0][1] % 2, 0);
+        assert_eq!(board[0][4] + board[0][5], board[0][3]);
+        assert_eq!(board[1][0] + board[1][1], 5);
+        assert!(variant.check(&board));
+    }
+
+    #[test]
+    fn test_trace() {
+        let grid = [
+            [0, 4, 0, 6, 1, 0, 9, 2, 5],
+            [0, 5, 1, 0, 0, 0, 7, 4, 6],
+            [9, 2, 6, 0, 0, 0, 8, 1, 3],
+            [0, 8, 0, 0, 5, 0, 0, 7, 1],
+            [0, 9, 0, 1, 0, 0, 0, 3, 2],
+            [0, 1, 3, 4, 7, 0, 5, 9, 8],
+            [0, 0, 0, 0, 0, 0, 1, 8, 9],
+            [1, 6, 2, 8, 0, 0, 3, 5, 7],
+            [8, 0, 9, 0, 0, 1, 2, 6, 4],
+        ];
+        let mut board = SudokuBoard::new_with(&grid, Arc::default());
+        assert!(trace(&mut board, OutputFormat::Json));
+        assert!(!board.exhausted());
+        assert_eq!(board.grid()[0][0], 7);
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        let empty = SudokuBoard::new_with(&[[0; 9]; 9], Arc::default());
+        assert_eq!(count_solutions(&empty, 2), 2);
+
+        let mut grid = [
+            [0, 4, 6, 9, 0, 3, 0, 0, 0],
+            [0, 0, 3, 0, 5, 0, 0, 6, 0],
+            [9, 0, 0, 0, 0, 2, 0, 0, 3],
+            [0, 0, 5, 0, 0, 6, 0, 0, 0],
+            [8, 0, 0, 0, 0, 0, 0, 1, 0],
+            [0, 1, 0, 7, 8, 0, 2, 0, 0],
+            [0, 0, 0, 0, 0, 0, 0, 5, 0],
+            [0, 8, 1, 3, 0, 0, 0, 0, 7],
+            [0, 0, 0, 8, 0, 0, 1, 0, 4],
+        ];
+        let board = SudokuBoard::new_with(&grid, Arc::default());
+        assert_eq!(count_solutions(&board, 2), 1);
+
+        // 同一行出现两个 4，无解
+        grid[0][0] = 4;
+        let board = SudokuBoard::new_with(&grid, Arc::default());
+        assert_eq!(count_solutions(&board, 2), 0);
+
+        // 推理发现第 8 行无处可填 3，不应在矛盾之下继续猜测
+        let grid = parse_grid(
+            "000000000000000040000900000070000000000290060000000030130000070800000406600030000",
+        )
+        .unwrap();
+        let board = SudokuBoard::new_with(&grid, Arc::new(Variant::standard()));
+        assert_eq!(count_solutions(&board, 2), 0);
+    }
+
+    #[test]
+    fn test_contradiction() {
+        let mut grid = [[0; 9]; 9];
+        grid[0] = [1, 2, 3, 4, 5, 6, 7, 8, 0];
+        grid[4][8] = 9;
+        let variant = Arc::new(Variant::standard());
+        let found = contradiction(&grid, variant.clone()).unwrap();
+        let Contradiction::Empty { cell, givens, .. } = &found else {
+            panic!("{:?}", found);
+        };
+        assert_eq!(*cell, (0, 8));
+        assert_eq!(givens.len(), 9);
+        assert!(found
+            .to_string()
+            .starts_with("no candidates left for r1c9; givens"));
+        assert_eq!(
+            Json::Object(found.details()).to_string(),
+            r#"{"row":1,"col":9}"#
+        );
+
+        // 有解的题目没有矛盾
+        grid[4][8] = 0;
+        assert_eq!(contradiction(&grid, variant.clone()), None);
+
+        // 区域内无处可填的数字
+        let grid = parse_grid(
+            "530070400600195000098000060800060003400803001700020006060000280000419005000080079",
+        )
+        .unwrap();
+        let found = contradiction(&grid, variant).unwrap();
+        assert_eq!(found.to_string(), "no place for 1 in box 3");
+        assert_eq!(
+            Json::Object(found.details()).to_string(),
+            r#"{"constraint":"box 3","digit":1,"cells":["r1c7","r1c8","r1c9","r2c7","r2c8","r2c9","r3c7","r3c8","r3c9"]}"#
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut givens = [[0; 9]; 9];
+        givens[0][0] = 5;
+        let board = SudokuBoard::new_with(&givens, Arc::new(Variant::standard()));
+        let solution = super::solutions(&board, 1)[0];
+        let variant = Variant::standard();
+        assert_eq!(verify(&solution, &givens, &variant), Ok(()));
+
+        let mut wrong = solution;
+        wrong[0][0] = 0;
+        assert_eq!(
+            verify(&wrong, &givens, &variant),
+            Err("r1c1 is empty".to_string())
+        );
+        givens[0][0] = 6;
+        assert!(verify(&solution, &givens, &variant).is_err());
+
+        // 交换两个数字后行中出现重复
+        let mut wrong = solution;
+        wrong[0][1] = wrong[0][2];
+        assert!(verify(&wrong, &[[0; 9]; 9], &variant)
+            .unwrap_err()
+            .starts_with("duplicate"));
+    }
+
+    /// 求解器找到的所有解，排序后返回
+    fn all_solutions(solver: &dyn Solver, givens: &Grid, variant: &Variant) -> Vec<Grid> {
+        let board = SudokuBoard::new_with(givens, Arc::new(variant.clone()));
+        collect(solver, board, usize::MAX)
+    }
+
+    #[test]
+    fn test_solvers_agree() {
+        const PUZZLE: &str =
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079";
+        const SOLUTION: &str =
+            "534678912672195348198342567859761423426853791713924856961537284287419635345286179";
+        let unique = parse_grid(PUZZLE).unwrap();
+        let mut several = unique;
+        several[8][7] = 0;
+        several[8][8] = 0;
+        several[7][8] = 0;
+        // 再去掉几个给定数字，由奇偶标记限制
+        let mut sparse = several;
+        for (row, col) in [(0, 0), (0, 1), (1, 0), (4, 0), (4, 8)] {
+            sparse[row][col] = 0;
+        }
+        let mut broken = [[0; 9]; 9];
+        broken[0] = [1, 2, 3, 4, 5, 6, 7, 8, 0];
+        broken[4][8] = 9;
+        let solution = parse_grid(SOLUTION).unwrap();
+
+        // 奇偶标记与唯一解一致
+        let mut odd_even = Variant::with_constraints(&[ConstraintKind::OddEven]);
+        for row in 0..9 {
+            for col in 0..9 {
+                if sparse[row][col] == 0 {
+                    odd_even.parity[row][col] = Some(if solution[row][col] % 2 == 1 {
+                        Parity::Odd
+                    } else {
+                        Parity::Even
+                    });
+                }
+            }
+        }
+        // 给定数字已填满但不满足笼子
+        let mut caged = Variant::standard();
+        caged.add("9=r1c1-r1c2".parse::<Cage>().unwrap());
+
+        let corpus = [
+            (unique, Variant::standard(), 1),
+            (several, Variant::standard(), 3),
+            (sparse, odd_even, 11),
+            (broken, Variant::standard(), 0),
+            (solution, Variant::standard(), 1),
+            (solution, caged, 0),
+        ];
+        for (givens, variant, count) in &corpus {
+            let a = all_solutions(&Propagating::default(), givens, variant);
+            let b = all_solutions(&Backtracking, givens, variant);
+            assert_eq!(a.len(), *count, "{:?}", givens);
+            assert_eq!(a, b, "{:?}", givens);
+            let board = SudokuBoard::new_with(givens, Arc::new(variant.clone()));
+            assert_eq!(cross_check(&board, 100), Ok(*count));
+        }
+        // 达到上限时只比较个数
+        let board = SudokuBoard::new_with(&several, Arc::new(Variant::standard()));
+        assert_eq!(cross_check(&board, 2), Ok(2));
+    }
+
+    #[test]
+    fn test_logic_cells() {
+        let variant = Arc::new(Variant::standard());
+        let easy = parse_grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        )
+        .unwrap();
+        assert_eq!(logic_cells(&easy, variant.clone()), 81 - 30);
+        assert_eq!(logic_cells(&[[0; 9]; 9], variant), 0);
+    }
+
+    #[test]
+    fn test_solve_stats() {
+        let variant = Arc::new(Variant::standard());
+        let easy = parse_grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        )
+        .unwrap();
+        // 纯推理即可解出
+        let board = SudokuBoard::new_with(&easy, variant.clone());
+        assert_eq!(
+            Propagating::default().solve(board, &|_| true),
+            SolveStats::default()
+        );
+        // 逐格回溯需要猜测，且不会无故退回
+        let board = SudokuBoard::new_with(&easy, variant.clone());
+        let stats = Backtracking.solve(board, &|_| true);
+        assert!(stats.guesses >= 51);
+        assert!(stats.dead_ends <= stats.backtracks);
+
+        let hard = parse_grid(
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+        )
+        .unwrap();
+        let board = SudokuBoard::new_with(&hard, variant.clone());
+        let stats = Propagating::default().solve(board.clone(), &|_| true);
+        assert!(stats.guesses > 0 && stats.dead_ends > 0);
+        // 根上推理没有矛盾，每个死路都是放弃的猜测
+        assert_eq!(stats.dead_ends, stats.backtracks);
+        // 达到深度上限而放弃的分支也是回溯
+        let shallow = Propagating {
+            max_depth: Some(2),
+            ..Default::default()
+        };
+        let stats = shallow.solve(board, &|_| true);
+        assert!(stats.cut_off > 0);
+        assert_eq!(stats.backtracks, stats.dead_ends + stats.cut_off);
+
+        // 无解时每个分支都是死路
+        let mut broken = [[0; 9]; 9];
+        broken[0] = [1, 2, 3, 4, 5, 6, 7, 8, 0];
+        broken[4][8] = 9;
+        let board = SudokuBoard::new_with(&broken, variant);
+        let stats = Backtracking.solve(board, &|_| false);
+        assert!(stats.dead_ends > 0);
+    }
+
+    #[test]
+    fn test_audit() {
+        let variant = Arc::new(Variant::standard());
+        let givens = parse_grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        )
+        .unwrap();
+        assert!(audit(&givens, variant.clone()).unwrap() > 0);
+        // 多解时需要猜测，各分支中的推理同样可靠
+        let mut several = parse_grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        )
+        .unwrap();
+        several[8][7] = 0;
+        several[8][8] = 0;
+        several[7][8] = 0;
+        assert!(audit(&several, variant.clone()).is_ok());
+        // 一开始就无解
+        let mut broken = [[0; 9]; 9];
+        broken[0] = [1, 2, 3, 4, 5, 6, 7, 8, 0];
+        broken[4][8] = 9;
+        assert_eq!(audit(&broken, variant.clone()), Ok(0));
+        // 推理发现第 8 行无处可填 3，分支立即判为无解，不再往下猜测
+        let givens = parse_grid(
+            "000000000000000040000900000070000000000290060000000030130000070800000406600030000",
+        )
+        .unwrap();
+        let mut checked = 0;
+        let board = SudokuBoard::new_with(&givens, variant);
+        assert_eq!(audit_branch(board, &mut checked), Ok(false));
+    }
+
+    #[test]
+    fn test_emit_dedup() {
+        let solution = parse_grid(
+            "534678912672195348198342567859761423426853791713924856961537284287419635345286179",
+        )
+        .unwrap();
+        let ctx = ResolveCtx {
+            sep: String::new(),
+            format: OutputFormat::Sdm,
+            givens: solution,
+            all: true,
+            limit: None,
+            verify: true,
+            total: Default::default(),
+            seen: Default::default(),
+            out: Printer::new(io::sink(), false, false),
+            alphabet: None,
+            label: Label::default(),
+        };
+        let board = SudokuBoard::new_with(&solution, Arc::new(Variant::standard()));
+        assert!(ctx.emit(&board));
+        assert!(ctx.emit(&board.clone()));
+        assert_eq!(ctx.total.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_shard() {
+        assert_eq!("2/8".parse::<Shard>(), Ok(Shard { index: 2, count: 8 }));
+        for bad in ["0/4", "5/4", "1", "a/b"] {
+            assert!(bad.parse::<Shard>().is_err(), "{}", bad);
+        }
+
+        // 清空最后两行，有 240 个解，分片的解不重不漏
+        let mut givens = parse_grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        )
+        .unwrap();
+        for row in givens.iter_mut().skip(7) {
+            *row = [0; 9];
+        }
+        let board = SudokuBoard::new_with(&givens, Arc::new(Variant::standard()));
+        let mut all = solutions(&board, usize::MAX);
+        assert_eq!(all.len(), 240);
+        let mut sharded = vec![];
+        for index in 1..=5 {
+            let shard = Shard { index, count: 5 };
+            let subtrees = shard.subtrees(&board);
+            assert!(!subtrees.is_empty());
+            // 每次展开相同
+            let again = shard.subtrees(&board);
+            assert_eq!(
+                subtrees.iter().map(|b| b.state_hash()).collect::<Vec<_>>(),
+                again.iter().map(|b| b.state_hash()).collect::<Vec<_>>()
+            );
+            for subtree in &subtrees {
+                sharded.extend(solutions(subtree, usize::MAX));
+            }
+        }
+        all.sort();
+        sharded.sort();
+        assert_eq!(sharded, all);
+
+        // 推理即矛盾的题目没有子树
+        let givens = parse_grid(
+            "000000000000000040000900000070000000000290060000000030130000070800000406600030000",
+        )
+        .unwrap();
+        let board = SudokuBoard::new_with(&givens, Arc::new(Variant::standard()));
+        for index in 1..=4 {
+            assert!(Shard { index, count: 4 }.subtrees(&board).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_cancel() {
+        let empty = SudokuBoard::new_with(&[[0; 9]; 9], Arc::new(Variant::standard()));
+        let progress = SearchProgress::default();
+        progress.cancel();
+        let stats = Propagating::default().solve_with(empty.clone(), &|_| panic!(), &progress);
+        assert_eq!(stats, SolveStats::default());
+
+        // 其他线程取消后，列举所有解的搜索也很快结束
+        let progress = SearchProgress::default();
+        thread::scope(|s| {
+            s.spawn(|| {
+                thread::sleep(Duration::from_millis(50));
+                progress.cancel();
+            });
+            Propagating::default().solve_with(empty, &|_| true, &progress);
+        });
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let mut givens = parse_grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        )
+        .unwrap();
+        let board = SudokuBoard::new_with(&givens, Arc::new(Variant::standard()));
+        // 只靠推理即可解出，不需要猜测
+        let strict = Propagating {
+            max_depth: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(strict.solve(board, &|_| true), SolveStats::default());
+
+        // 清空最后两行，有 240 个解
+        for row in givens.iter_mut().skip(7) {
+            *row = [0; 9];
+        }
+        let board = SudokuBoard::new_with(&givens, Arc::new(Variant::standard()));
+        let found = AtomicUsize::new(0);
+        let count = |_: &SudokuBoard| {
+            found.fetch_add(1, Ordering::Relaxed);
+            true
+        };
+        let shallow = Propagating {
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        let stats = shallow.solve(board.clone(), &count);
+        assert!(stats.cut_off > 0);
+        assert!(found.load(Ordering::Relaxed) < 240);
+        found.store(0, Ordering::Relaxed);
+        let stats = Propagating::default().solve(board, &count);
+        assert_eq!(stats.cut_off, 0);
+        assert_eq!(found.load(Ordering::Relaxed), 240);
+    }
+
+    #[test]
+    fn test_contradiction_is_dead_end() {
+        // r1c7 填 4 后第一行的 4 无处可放，推理即可证明无解，不是猜测深度不够
+        let givens = parse_grid(
+            "530070400600195000098000060800060003400803001700020006060000280000419005000080079",
+        )
+        .unwrap();
+        let mut board = SudokuBoard::new_with(&givens, Arc::new(Variant::standard()));
+        assert!(!board.exhausted());
+        let strict = Propagating {
+            max_depth: Some(0),
+            ..Default::default()
+        };
+        let stats = strict.solve(board.clone(), &|_| panic!("no solution expected"));
+        assert_eq!(stats.cut_off, 0);
+        assert_eq!(stats.dead_ends, 1);
+        // 没有猜测就不需要回溯
+        assert_eq!(stats.backtracks, 0);
+        assert_eq!(board.deduce(), Deduction::Contradiction);
+    }
+
+    #[test]
+    fn test_shuffle() {
+        // 清空最后两行，有 240 个解
+        let mut givens = parse_grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        )
+        .unwrap();
+        for row in givens.iter_mut().skip(7) {
+            *row = [0; 9];
+        }
+        let first = |seed: u64| {
+            let solver = Propagating {
+                shuffle: Some(seed),
+                ..Default::default()
+            };
+            let board = SudokuBoard::new_with(&givens, Arc::new(Variant::standard()));
+            let found = std::sync::Mutex::new(None);
+            solver.solve(board, &|board| {
+                found.lock().unwrap().get_or_insert(board.grid());
+                false
+            });
+            found.into_inner().unwrap().unwrap()
+        };
+        // 相同种子得到同一个解，不同种子得到不同的解
+        assert_eq!(first(7), first(7));
+        let distinct: std::collections::HashSet<Grid> = (0..20).map(first).collect();
+        assert!(distinct.len() > 1);
+
+        // 打乱时仍能列出全部的解
+        let solver = Propagating {
+            shuffle: Some(1),
+            ..Default::default()
+        };
+        let found = AtomicUsize::new(0);
+        let board = SudokuBoard::new_with(&givens, Arc::new(Variant::standard()));
+        solver.solve(board, &|_| {
+            found.fetch_add(1, Ordering::Relaxed);
+            true
+        });
+        assert_eq!(found.load(Ordering::Relaxed), 240);
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("easy 1"), "easy 1");
+        assert_eq!(csv_field("a, b"), "\"a, b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_label() {
+        assert!(Label::default().fields().is_empty());
+        let label = Label {
+            id: Some("easy".to_string()),
+            line: Some(3),
+        };
+        let fields: Vec<String> = label
+            .fields()
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect();
+        assert_eq!(fields, [r#"id="easy""#, "line=3"]);
+    }
+
+    /// 统计各种事件的观察者
+    #[derive(Default)]
+    struct Counter {
+        assigned: AtomicUsize,
+        eliminated: AtomicUsize,
+        guesses: AtomicUsize,
+        backtracks: AtomicUsize,
+        solutions: AtomicUsize,
+    }
+
+    impl SolverObserver for Counter {
+        fn on_assign(&self, _cell: Cell, _digit: u32) {
+            self.assigned.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn on_eliminate(&self, _cell: Cell, _digit: u32) {
+            self.eliminated.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn on_guess(&self, depth: usize, _cell: Cell, _digit: u32) {
+            assert!(depth >= 1);
+            self.guesses.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn on_backtrack(&self, depth: usize) {
+            assert!(depth >= 1);
+            self.backtracks.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn on_solution(&self, _board: &SudokuBoard) {
+            self.solutions.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// 一层盘面：数字和空位的候选位掩码
+    #[derive(Clone)]
+    struct Layer {
+        values: Grid,
+        candidates: [[u16; 9]; 9],
+    }
+
+    impl Layer {
+        /// 从 cell 中去掉候选 digit，该位置须为空位且有此候选
+        fn take(&mut self, (row, col): Cell, digit: u32) {
+            assert_eq!(
+                self.values[row][col],
+                0,
+                "r{}c{} is filled",
+                row + 1,
+                col + 1
+            );
+            let bit = 1 << digit;
+            assert!(
+                self.candidates[row][col] & bit != 0,
+                "r{}c{} has no candidate {}",
+                row + 1,
+                col + 1,
+                digit
+            );
+            self.candidates[row][col] &= !bit;
+        }
+
+        fn place(&mut self, cell: Cell, digit: u32) {
+            self.take(cell, digit);
+            self.values[cell.0][cell.1] = digit;
+            self.candidates[cell.0][cell.1] = 0;
+        }
+
+        /// 已填的数字都已从同行、同列、同宫的空位中排除
+        fn check_peers(&self) {
+            for (row, col) in (0..81).map(|i| (i / 9, i % 9)) {
+                let digit = self.values[row][col];
+                if digit == 0 {
+                    continue;
+                }
+                for (r, c) in (0..81).map(|i| (i / 9, i % 9)) {
+                    let peer = r == row || c == col || (r / 3, c / 3) == (row / 3, col / 3);
+                    assert!(
+                        !peer || self.candidates[r][c] & 1 << digit == 0,
+                        "r{}c{} keeps candidate {} of r{}c{}",
+                        r + 1,
+                        c + 1,
+                        digit,
+                        row + 1,
+                        col + 1
+                    );
+                }
+            }
+        }
+    }
+
+    /// 只凭事件重建各层盘面的观察者，检查事件与盘面一致。只用于逐个尝试分支的搜索
+    struct Replayer {
+        layers: std::sync::Mutex<Vec<Layer>>,
+        backtracks: AtomicUsize,
+    }
+
+    impl Replayer {
+        fn new(board: &SudokuBoard) -> Self {
+            let candidates = std::array::from_fn(|row| {
+                std::array::from_fn(|col| {
+                    let pos = board.get(row, col);
+                    match pos.val {
+                        0 => pos.digits.iter().fold(0, |mask, d| mask | 1 << d),
+                        _ => 0,
+                    }
+                })
+            });
+            let layer = Layer {
+                values: board.grid(),
+                candidates,
+            };
+            Self {
+                layers: std::sync::Mutex::new(vec![layer]),
+                backtracks: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    impl SolverObserver for Replayer {
+        fn on_assign(&self, cell: Cell, digit: u32) {
+            self.layers
+                .lock()
+                .unwrap()
+                .last_mut()
+                .unwrap()
+                .place(cell, digit);
+        }
+
+        fn on_eliminate(&self, cell: Cell, digit: u32) {
+            self.layers
+                .lock()
+                .unwrap()
+                .last_mut()
+                .unwrap()
+                .take(cell, digit);
+        }
+
+        fn on_guess(&self, depth: usize, cell: Cell, digit: u32) {
+            // 解出的分支没有回溯，下一个猜测直接回到父层
+            let mut layers = self.layers.lock().unwrap();
+            assert!(
+                layers.len() >= depth,
+                "guess at depth {} skips a layer",
+                depth
+            );
+            layers.truncate(depth);
+            layers.last().unwrap().check_peers();
+            let mut layer = layers.last().unwrap().clone();
+            layer.place(cell, digit);
+            layers.push(layer);
+        }
+
+        fn on_backtrack(&self, depth: usize) {
+            let mut layers = self.layers.lock().unwrap();
+            assert_eq!(layers.len(), depth + 1, "backtrack from another depth");
+            layers.pop();
+            self.backtracks.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn on_solution(&self, board: &SudokuBoard) {
+            let layers = self.layers.lock().unwrap();
+            layers.last().unwrap().check_peers();
+            assert_eq!(layers.last().unwrap().values, board.grid());
+        }
+    }
+
+    #[test]
+    fn test_observer() {
+        let load = |n: &AtomicUsize| n.load(Ordering::Relaxed);
+        let givens = parse_grid(
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079",
+        )
+        .unwrap();
+        let variant = Arc::new(Variant::standard());
+        let blanks = givens.iter().flatten().filter(|d| **d == 0).count();
+
+        // 只靠推理解出，没有猜测
+        let counter = Counter::default();
+        let board = SudokuBoard::new_with(&givens, variant.clone());
+        Propagating::default().solve_observed(board, &|_| true, &counter);
+        assert_eq!(load(&counter.assigned), blanks);
+        assert!(load(&counter.eliminated) > 0);
+        assert_eq!(load(&counter.guesses), 0);
+        assert_eq!(load(&counter.solutions), 1);
+
+        // 清空最后两行后有 240 个解
+        let mut open = givens;
+        open[7] = [0; 9];
+        open[8] = [0; 9];
+        for solver in [&Propagating::default() as &dyn Solver, &Backtracking] {
+            let counter = Counter::default();
+            let board = SudokuBoard::new_with(&open, variant.clone());
+            let stats = solver.solve_observed(board, &|_| true, &counter);
+            assert_eq!(load(&counter.solutions), 240);
+            assert_eq!(load(&counter.guesses), stats.guesses);
+            assert_eq!(load(&counter.backtracks), stats.backtracks);
+        }
+
+        // 只凭事件即可重建每个分支的盘面，包括走入死路和达到深度上限的分支
+        let hard = parse_grid(
+            "800000000003600000070090200050007000000045700000100030001000068008500010090000400",
+        )
+        .unwrap();
+        for (givens, max_depth) in [(open, None), (hard, None), (hard, Some(3))] {
+            let board = SudokuBoard::new_with(&givens, variant.clone());
+            let replayer = Replayer::new(&board);
+            let solver = Propagating {
+                max_depth,
+                shuffle: Some(7),
+            };
+            let stats = solver.solve_observed(board, &|_| true, &replayer);
+            assert_eq!(load(&replayer.backtracks), stats.backtracks);
+            assert!(givens == open || stats.backtracks > 0);
+            assert_eq!(stats.cut_off > 0, max_depth.is_some());
+        }
+    }
+}
@@ -0,0 +1,356 @@
+//! SVG 输出
+
+use std::fmt::Write;
+
+use crate::constraint::{Arrow, Cage, Pair, PairKind, Thermo};
+use crate::qr::QUIET_ZONE;
+use crate::variant::Parity;
+use crate::SudokuBoard;
+
+/// 每个小格的边长
+const CELL: f64 = 50.0;
+/// 四周留白
+const MARGIN: f64 = 10.0;
+
+/// 小格中心坐标
+fn center((row, col): (usize, usize)) -> (f64, f64) {
+    (
+        MARGIN + CELL * (col as f64 + 0.5),
+        MARGIN + CELL * (row as f64 + 0.5),
+    )
+}
+
+/// 将棋盘输出为 SVG 图片，题目给定的数字加粗，其余为求解得到的数字
+pub fn render(board: &SudokuBoard, givens: &[[u32; 9]; 9]) -> String {
+    let size = CELL * 9.0 + MARGIN * 2.0;
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}" viewBox="0 0 {size} {size}">"#
+    );
+    let _ = writeln!(
+        s,
+        r#"<rect x="0" y="0" width="{size}" height="{size}" fill="white"/>"#
+    );
+
+    // 奇偶标记：奇数为圆形，偶数为方形
+    for row in 0..9 {
+        for col in 0..9 {
+            let (x, y) = center((row, col));
+            match board.variant().parity[row][col] {
+                Some(Parity::Odd) => {
+                    let _ = writeln!(
+                        s,
+                        r##"<circle cx="{x}" cy="{y}" r="{}" fill="#dddddd"/>"##,
+                        CELL * 0.4
+                    );
+                }
+                Some(Parity::Even) => {
+                    let _ = writeln!(
+                        s,
+                        r##"<rect x="{}" y="{}" width="{}" height="{}" fill="#dddddd"/>"##,
+                        x - CELL * 0.4,
+                        y - CELL * 0.4,
+                        CELL * 0.8,
+                        CELL * 0.8
+                    );
+                }
+                None => {}
+            }
+        }
+    }
+
+    // 温度计
+    for thermo in board.variant().find::<Thermo>() {
+        render_thermo(&mut s, thermo);
+    }
+
+    // 箭头
+    for arrow in board.variant().find::<Arrow>() {
+        render_arrow(&mut s, arrow);
+    }
+
+    // 杀手笼子
+    for cage in board.variant().find::<Cage>() {
+        render_cage(&mut s, cage);
+    }
+
+    // 格线，3x3 小格边界加粗
+    for i in 0..10 {
+        let width = if i % 3 == 0 { 3 } else { 1 };
+        let p = MARGIN + CELL * i as f64;
+        let end = MARGIN + CELL * 9.0;
+        let _ = writeln!(
+            s,
+            r#"<line x1="{p}" y1="{MARGIN}" x2="{p}" y2="{end}" stroke="black" stroke-width="{width}"/>"#
+        );
+        let _ = writeln!(
+            s,
+            r#"<line x1="{MARGIN}" y1="{p}" x2="{end}" y2="{p}" stroke="black" stroke-width="{width}"/>"#
+        );
+    }
+
+    // XV 标记和黑白点画在两格交界处
+    for pair in board.variant().find::<Pair>() {
+        let (ax, ay) = center(pair.a);
+        let (bx, by) = center(pair.b);
+        let (x, y) = ((ax + bx) / 2.0, (ay + by) / 2.0);
+        let fill = match pair.kind {
+            PairKind::White => Some("white"),
+            PairKind::Black => Some("black"),
+            _ => None,
+        };
+        if let Some(fill) = fill {
+            let _ = writeln!(
+                s,
+                r#"<circle cx="{x}" cy="{y}" r="{}" fill="{fill}" stroke="black" stroke-width="1"/>"#,
+                CELL * 0.1
+            );
+        }
+        if let Some(c) = pair.kind.to_char() {
+            let _ = writeln!(
+                s,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="white"/>"#,
+                x - CELL * 0.15,
+                y - CELL * 0.15,
+                CELL * 0.3,
+                CELL * 0.3
+            );
+            let _ = writeln!(
+                s,
+                r#"<text x="{x}" y="{}" font-size="{}" font-family="sans-serif" font-weight="bold" text-anchor="middle">{c}</text>"#,
+                y + CELL * 0.1,
+                CELL * 0.3
+            );
+        }
+    }
+
+    // 数字
+    for row in 0..9 {
+        for col in 0..9 {
+            let val = board.get(row, col).val;
+            if val == 0 {
+                continue;
+            }
+            let (x, y) = center((row, col));
+            let (weight, color) = if givens[row][col] != 0 {
+                ("bold", "black")
+            } else {
+                ("normal", "#1f4fbf")
+            };
+            let _ = writeln!(
+                s,
+                r#"<text x="{x}" y="{}" font-size="{}" font-family="sans-serif" font-weight="{weight}" fill="{color}" text-anchor="middle">{val}</text>"#,
+                y + CELL * 0.35 * 0.8,
+                CELL * 0.7
+            );
+        }
+    }
+
+    s.push_str("</svg>");
+    s
+}
+
+/// QR 码，每个模块为边长 1 的方块，由查看器缩放
+pub fn qr(modules: &[Vec<bool>]) -> String {
+    let size = modules.len() + QUIET_ZONE * 2;
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{0}" viewBox="0 0 {1} {1}" shape-rendering="crispEdges">"#,
+        size * 8,
+        size
+    );
+    let _ = writeln!(
+        s,
+        r#"<rect x="0" y="0" width="{size}" height="{size}" fill="white"/>"#
+    );
+    // 所有深色模块合成一条路径
+    s.push_str(r#"<path fill="black" d=""#);
+    for (y, row) in modules.iter().enumerate() {
+        for (x, _) in row.iter().enumerate().filter(|(_, &dark)| dark) {
+            let _ = write!(s, "M{},{}h1v1h-1z", x + QUIET_ZONE, y + QUIET_ZONE);
+        }
+    }
+    s.push_str("\"/>\n</svg>");
+    s
+}
+
+/// 温度计：球部为实心圆，沿各格中心画粗线
+fn render_thermo(s: &mut String, thermo: &Thermo) {
+    let (bx, by) = center(thermo.cells[0]);
+    let _ = writeln!(
+        s,
+        r##"<circle cx="{bx}" cy="{by}" r="{}" fill="#cccccc"/>"##,
+        CELL * 0.35
+    );
+    let line = thermo
+        .cells
+        .iter()
+        .map(|&p| {
+            let (x, y) = center(p);
+            format!("{x},{y}")
+        })
+        .collect::<Vec<String>>()
+        .join(" ");
+    let _ = writeln!(
+        s,
+        r##"<polyline points="{line}" fill="none" stroke="#cccccc" stroke-width="{}" stroke-linecap="round" stroke-linejoin="round"/>"##,
+        CELL * 0.3
+    );
+}
+
+/// 箭头：圆圈加上穿过箭身各格中心的折线，末端画出箭尖
+fn render_arrow(s: &mut String, arrow: &Arrow) {
+    let radius = CELL * 0.4;
+    let (cx, cy) = center(arrow.circle);
+    let _ = writeln!(
+        s,
+        r##"<circle cx="{cx}" cy="{cy}" r="{radius}" fill="none" stroke="#888888" stroke-width="2"/>"##
+    );
+
+    let mut points: Vec<(f64, f64)> = arrow.shaft.iter().map(|&p| center(p)).collect();
+    // 折线从圆圈边缘开始
+    let (fx, fy) = points[0];
+    let len = ((fx - cx).powi(2) + (fy - cy).powi(2)).sqrt().max(1.0);
+    points.insert(
+        0,
+        (cx + (fx - cx) / len * radius, cy + (fy - cy) / len * radius),
+    );
+    let line = points
+        .iter()
+        .map(|(x, y)| format!("{x},{y}"))
+        .collect::<Vec<String>>()
+        .join(" ");
+    let _ = writeln!(
+        s,
+        r##"<polyline points="{line}" fill="none" stroke="#888888" stroke-width="2"/>"##
+    );
+
+    // 箭尖
+    let (ex, ey) = points[points.len() - 1];
+    let (px, py) = points[points.len() - 2];
+    let len = ((ex - px).powi(2) + (ey - py).powi(2)).sqrt().max(1.0);
+    let (dx, dy) = ((ex - px) / len, (ey - py) / len);
+    let head = CELL * 0.2;
+    for side in [-1.0, 1.0] {
+        let hx = ex - dx * head - dy * head * side;
+        let hy = ey - dy * head + dx * head * side;
+        let _ = writeln!(
+            s,
+            r##"<line x1="{ex}" y1="{ey}" x2="{hx}" y2="{hy}" stroke="#888888" stroke-width="2"/>"##
+        );
+    }
+}
+
+/// 杀手笼子：沿笼子外边界向内缩进的虚线，左上角标注和
+fn render_cage(s: &mut String, cage: &Cage) {
+    let inset = CELL * 0.08;
+    for &(row, col) in &cage.cells {
+        let x1 = MARGIN + CELL * col as f64 + inset;
+        let y1 = MARGIN + CELL * row as f64 + inset;
+        let x2 = x1 + CELL - inset * 2.0;
+        let y2 = y1 + CELL - inset * 2.0;
+        let inside =
+            |r: isize, c: isize| r >= 0 && c >= 0 && cage.cells.contains(&(r as usize, c as usize));
+        let (r, c) = (row as isize, col as isize);
+        // 与笼内相邻的一侧不画边界，并将线延伸到格子边缘
+        let left = if inside(r, c - 1) { x1 - inset } else { x1 };
+        let right = if inside(r, c + 1) { x2 + inset } else { x2 };
+        let top = if inside(r - 1, c) { y1 - inset } else { y1 };
+        let bottom = if inside(r + 1, c) { y2 + inset } else { y2 };
+        let mut edges = vec![];
+        if !inside(r - 1, c) {
+            edges.push((left, y1, right, y1));
+        }
+        if !inside(r + 1, c) {
+            edges.push((left, y2, right, y2));
+        }
+        if !inside(r, c - 1) {
+            edges.push((x1, top, x1, bottom));
+        }
+        if !inside(r, c + 1) {
+            edges.push((x2, top, x2, bottom));
+        }
+        for (ax, ay, bx, by) in edges {
+            let _ = writeln!(
+                s,
+                r#"<line x1="{ax}" y1="{ay}" x2="{bx}" y2="{by}" stroke="black" stroke-width="1" stroke-dasharray="4,3"/>"#
+            );
+        }
+    }
+
+    if let (Some(sum), Some(&(row, col))) = (cage.sum, cage.cells.iter().min()) {
+        let _ = writeln!(
+            s,
+            r#"<text x="{}" y="{}" font-size="{}" font-family="sans-serif">{sum}</text>"#,
+            MARGIN + CELL * col as f64 + inset * 1.5,
+            MARGIN + CELL * row as f64 + inset * 1.5 + CELL * 0.2,
+            CELL * 0.22
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::constraint::{Arrow, Cage, Pair, Thermo};
+    use crate::variant::{Parity, Variant};
+    use crate::SudokuBoard;
+
+    #[test]
+    fn test_render_svg() {
+        let mut variant = Variant::default();
+        variant.parity[8][8] = Some(Parity::Even);
+        variant.add("r1c1-r2c2-r3c3".parse::<Arrow>().unwrap());
+        let mut givens = [[0; 9]; 9];
+        givens[0][0] = 7;
+        let board = SudokuBoard::new_with(&givens, Arc::new(variant));
+
+        let svg = super::render(&board, &givens);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.ends_with("</svg>"));
+        assert!(svg.contains("<polyline"));
+        assert!(svg.contains(r#"font-weight="bold" fill="black" text-anchor="middle">7<"#));
+        // 20 条格线 + 2 条箭尖
+        assert_eq!(svg.matches("<line").count(), 22);
+    }
+
+    #[test]
+    fn test_qr() {
+        let modules = crate::qr::encode("HELLO WORLD").unwrap();
+        let svg = super::qr(&modules);
+        assert!(svg.contains(r#"viewBox="0 0 29 29""#));
+        assert!(svg.ends_with("</svg>"));
+        let dark = modules.iter().flatten().filter(|&&d| d).count();
+        assert_eq!(svg.matches("h1v1h-1z").count(), dark);
+        assert!(svg.contains("M4,4h1v1h-1z"));
+    }
+
+    #[test]
+    fn test_render_cage() {
+        let mut variant = Variant::default();
+        variant.add("15=r1c1-r1c2-r2c1".parse::<Cage>().unwrap());
+        let board = SudokuBoard::new_with(&[[0; 9]; 9], Arc::new(variant));
+
+        let svg = super::render(&board, &[[0; 9]; 9]);
+        // L 形笼子共 8 条外边
+        assert_eq!(svg.matches("stroke-dasharray").count(), 8);
+        assert!(svg.contains(">15</text>"));
+    }
+
+    #[test]
+    fn test_render_thermo_and_dots() {
+        let mut variant = Variant::default();
+        variant.add("r1c1-r1c2-r1c3".parse::<Thermo>().unwrap());
+        variant.add("w:r2c1-r2c2".parse::<Pair>().unwrap());
+        variant.add("b:r3c1-r3c2".parse::<Pair>().unwrap());
+        let board = SudokuBoard::new_with(&[[0; 9]; 9], Arc::new(variant));
+
+        let svg = super::render(&board, &[[0; 9]; 9]);
+        assert!(svg.contains("stroke-linecap"));
+        assert!(svg.contains(r#"fill="white" stroke="black""#));
+        assert!(svg.contains(r#"fill="black" stroke="black""#));
+    }
+}
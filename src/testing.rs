@@ -0,0 +1,68 @@
+//! 供属性测试使用的辅助函数
+//!
+//! 所有函数都是确定的：相同的输入得到相同的结果，便于 proptest、quickcheck 等工具缩小反例。
+
+use std::sync::Arc;
+
+use crate::board::{grid_hash, Grid};
+use crate::generator::random_solution;
+use crate::rng::Rng;
+use crate::solver::verify;
+use crate::variant::Variant;
+
+/// 由种子得到一个随机的标准数独终盘
+pub fn random_full_grid(seed: u64) -> Grid {
+    random_solution(Arc::new(Variant::standard()), &mut Rng::new(seed))
+        .expect("standard sudoku always has a solution")
+}
+
+/// 随机挖去 n 个已填位置（不保证解唯一），位置由盘面本身决定
+pub fn dig(grid: &Grid, n: usize) -> Grid {
+    let seed = grid_hash(grid);
+    let mut cells: Vec<(usize, usize)> = (0..81)
+        .map(|i| (i / 9, i % 9))
+        .filter(|&(r, c)| grid[r][c] != 0)
+        .collect();
+    Rng::new(seed).shuffle(&mut cells);
+    let mut dug = *grid;
+    for (r, c) in cells.into_iter().take(n) {
+        dug[r][c] = 0;
+    }
+    dug
+}
+
+/// solution 是否为 givens 在标准规则下的一个解
+pub fn is_valid_solution(givens: &Grid, solution: &Grid) -> bool {
+    verify(solution, givens, &Variant::standard()).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{dig, is_valid_solution, random_full_grid};
+    use crate::board::SudokuBoard;
+    use crate::solver::solutions;
+    use crate::variant::Variant;
+
+    #[test]
+    fn test_round_trip() {
+        let variant = Arc::new(Variant::standard());
+        for seed in 0..20 {
+            let full = random_full_grid(seed);
+            assert_eq!(full, random_full_grid(seed));
+            assert!(is_valid_solution(&full, &full));
+
+            let puzzle = dig(&full, 50);
+            assert_eq!(puzzle, dig(&full, 50));
+            assert_eq!(puzzle.iter().flatten().filter(|d| **d == 0).count(), 50);
+
+            let board = SudokuBoard::new_with(&puzzle, variant.clone());
+            let solution = solutions(&board, 1).pop().unwrap();
+            assert!(is_valid_solution(&puzzle, &solution));
+        }
+        let mut broken = random_full_grid(1);
+        broken[0].swap(0, 1);
+        assert!(!is_valid_solution(&[[0; 9]; 9], &broken));
+    }
+}
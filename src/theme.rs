@@ -0,0 +1,83 @@
+//! 终端配色
+//!
+//! 终端输出不直接写颜色代码，而是按用途（[`Role`]）向 [`Theme`] 取样式，换一套配色不用改输出的代码。
+//! 是否使用颜色仍由 `--color` 决定，不用颜色时不调用这里。
+
+/// 配色方案
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Theme {
+    /// For dark terminal backgrounds
+    #[default]
+    Dark,
+    /// For light terminal backgrounds
+    Light,
+    /// Bold colors on solid backgrounds
+    HighContrast,
+    /// No colors, only bold, dim, underline and strike-through
+    Monochrome,
+}
+
+/// 样式的用途
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// 题目中的空位
+    Blank,
+    /// 次要的信息，如实时显示中空位的候选数字个数
+    Muted,
+    /// 对比中原有的数值
+    Removed,
+    /// 对比中新的数值
+    Added,
+}
+
+impl Theme {
+    /// 用途对应的 SGR 参数
+    fn sgr(self, role: Role) -> &'static str {
+        match (self, role) {
+            (Theme::Dark, Role::Blank) => "1;33",
+            (Theme::Dark, Role::Muted) => "2",
+            (Theme::Dark, Role::Removed) => "31",
+            (Theme::Dark, Role::Added) => "32",
+            (Theme::Light, Role::Blank) => "1;34",
+            (Theme::Light, Role::Muted) => "90",
+            (Theme::Light, Role::Removed) => "31",
+            (Theme::Light, Role::Added) => "32",
+            (Theme::HighContrast, Role::Blank) => "1;30;103",
+            (Theme::HighContrast, Role::Muted) => "1;96",
+            (Theme::HighContrast, Role::Removed) => "1;97;41",
+            (Theme::HighContrast, Role::Added) => "1;30;102",
+            (Theme::Monochrome, Role::Blank) => "1",
+            (Theme::Monochrome, Role::Muted) => "2",
+            (Theme::Monochrome, Role::Removed) => "9",
+            (Theme::Monochrome, Role::Added) => "4",
+        }
+    }
+
+    /// 以用途对应的样式输出文本
+    pub fn paint(self, role: Role, text: impl std::fmt::Display) -> String {
+        format!("\x1b[{}m{}\x1b[0m", self.sgr(role), text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Role, Theme};
+
+    #[test]
+    fn test_paint() {
+        assert_eq!(Theme::Dark.paint(Role::Blank, '.'), "\x1b[1;33m.\x1b[0m");
+        assert_eq!(Theme::Monochrome.paint(Role::Added, 5), "\x1b[4m5\x1b[0m");
+        // 每套配色的各种用途互不相同
+        let roles = [Role::Blank, Role::Muted, Role::Removed, Role::Added];
+        for theme in [
+            Theme::Dark,
+            Theme::Light,
+            Theme::HighContrast,
+            Theme::Monochrome,
+        ] {
+            let styles: std::collections::HashSet<_> =
+                roles.iter().map(|role| theme.sgr(*role)).collect();
+            assert_eq!(styles.len(), roles.len(), "{:?}", theme);
+        }
+    }
+}
@@ -0,0 +1,496 @@
+//! 数独变体规则
+
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+use clap::ValueEnum;
+
+use crate::board::Grid;
+use crate::constraint::{AntiMove, Cell, Constraint, Pair, PairKind, Unit};
+use crate::json::Json;
+
+/// 附加约束
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstraintKind {
+    /// Cells a knight's move apart must differ
+    AntiKnight,
+    /// Diagonally touching cells must differ (non-touching / miracle sudoku)
+    AntiKing,
+    /// Blank cells may be marked `o` (odd only) or `e` (even only) in the input
+    OddEven,
+    /// Both main diagonals contain 1-9 once (X-sudoku)
+    Diagonal,
+    /// Four extra 3x3 windows contain 1-9 once (hyper / windoku)
+    Hyper,
+}
+
+/// 奇偶标记
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parity {
+    /// 只能填奇数
+    Odd,
+    /// 只能填偶数
+    Even,
+}
+
+impl Parity {
+    /// 从输入字符解析奇偶标记
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            'o' | 'O' => Some(Parity::Odd),
+            'e' | 'E' => Some(Parity::Even),
+            _ => None,
+        }
+    }
+
+    /// 输出时使用的字符
+    pub fn to_char(self) -> char {
+        match self {
+            Parity::Odd => 'o',
+            Parity::Even => 'e',
+        }
+    }
+
+    /// 数字是否符合奇偶要求
+    pub fn allows(self, digit: u32) -> bool {
+        match self {
+            Parity::Odd => digit % 2 == 1,
+            Parity::Even => digit.is_multiple_of(2),
+        }
+    }
+}
+
+/// 给定数字之间的冲突
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Conflict {
+    /// 两个同组位置给出了相同的数字，a 在 b 之前（按行优先）
+    Duplicate { digit: u32, a: Cell, b: Cell },
+    /// 给定数字违反了某个约束
+    Violated(String),
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Conflict::Duplicate { digit, a, b } => {
+                write!(f, "duplicate {} ", digit)?;
+                if a.0 == b.0 {
+                    write!(f, "in row {} at c{} and c{}", a.0 + 1, a.1 + 1, b.1 + 1)
+                } else if a.1 == b.1 {
+                    write!(f, "in column {} at r{} and r{}", a.1 + 1, a.0 + 1, b.0 + 1)
+                } else {
+                    if (a.0 / 3, a.1 / 3) == (b.0 / 3, b.1 / 3) {
+                        write!(f, "in box {} ", a.0 / 3 * 3 + a.1 / 3 + 1)?;
+                    }
+                    write!(
+                        f,
+                        "at r{}c{} and r{}c{}",
+                        a.0 + 1,
+                        a.1 + 1,
+                        b.0 + 1,
+                        b.1 + 1
+                    )
+                }
+            }
+            Conflict::Violated(ref constraint) => {
+                write!(f, "givens violate constraint: {}", constraint)
+            }
+        }
+    }
+}
+
+impl Conflict {
+    /// 结构化的错误信息，行列从 1 开始
+    pub fn details(&self) -> Vec<(String, Json)> {
+        match *self {
+            Conflict::Duplicate { digit, a, b } => vec![
+                ("digit".to_string(), digit.into()),
+                ("row".to_string(), (a.0 + 1).into()),
+                ("col".to_string(), (a.1 + 1).into()),
+                ("other_row".to_string(), (b.0 + 1).into()),
+                ("other_col".to_string(), (b.1 + 1).into()),
+            ],
+            Conflict::Violated(ref constraint) => {
+                vec![("constraint".to_string(), constraint.as_str().into())]
+            }
+        }
+    }
+}
+
+/// 同组关系表，互为同组的两个位置不能填入相同数字
+#[derive(Debug, Clone)]
+pub struct Peers {
+    /// 每个位置 (row * 9 + col) 的同组位置
+    peers: Vec<Vec<Cell>>,
+}
+
+impl Default for Peers {
+    fn default() -> Self {
+        Self {
+            peers: vec![vec![]; 81],
+        }
+    }
+}
+
+impl Peers {
+    /// 增加一对同组位置，自身和重复的关系会被忽略
+    pub fn add(&mut self, a: Cell, b: Cell) {
+        if a == b || self.of(a.0, a.1).contains(&b) {
+            return;
+        }
+        self.peers[a.0 * 9 + a.1].push(b);
+        self.peers[b.0 * 9 + b.1].push(a);
+    }
+
+    /// 获取某个位置的同组位置
+    pub fn of(&self, row: usize, col: usize) -> &[Cell] {
+        &self.peers[row * 9 + col]
+    }
+}
+
+/// 变体规则：约束集合、由约束汇总的同组关系以及逐个位置的奇偶标记
+#[derive(Debug, Clone)]
+pub struct Variant {
+    /// 奇偶标记
+    pub parity: [[Option<Parity>; 9]; 9],
+    /// 同组关系表
+    peers: Peers,
+    /// 所有约束
+    constraints: Vec<Arc<dyn Constraint>>,
+    /// 每个位置填数后需要检查的约束下标
+    by_cell: Vec<Vec<usize>>,
+}
+
+impl Default for Variant {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl Variant {
+    /// 不含任何约束的规则
+    pub fn empty() -> Self {
+        Self {
+            parity: [[None; 9]; 9],
+            peers: Peers::default(),
+            constraints: vec![],
+            by_cell: vec![vec![]; 81],
+        }
+    }
+
+    /// 标准数独：行、列、3x3 小格
+    pub fn standard() -> Self {
+        let mut variant = Self::empty();
+        for i in 0..9 {
+            variant.add(Unit::row(i));
+            variant.add(Unit::col(i));
+            variant.add(Unit::block(i));
+        }
+        variant
+    }
+
+    /// 标准规则之上叠加附加约束
+    pub fn with_constraints(kinds: &[ConstraintKind]) -> Self {
+        let mut variant = Self::standard();
+        for kind in kinds {
+            variant.add_kind(*kind);
+        }
+        variant
+    }
+
+    /// 增加一种附加约束
+    pub fn add_kind(&mut self, kind: ConstraintKind) {
+        match kind {
+            ConstraintKind::AntiKnight => self.add(AntiMove::knight()),
+            ConstraintKind::AntiKing => self.add(AntiMove::king()),
+            ConstraintKind::OddEven => {}
+            ConstraintKind::Diagonal => {
+                self.add(Unit::diagonal());
+                self.add(Unit::anti_diagonal());
+            }
+            ConstraintKind::Hyper => {
+                for n in 0..4 {
+                    self.add(Unit::window(n));
+                }
+            }
+        }
+    }
+
+    /// 增加一个约束
+    pub fn add(&mut self, constraint: impl Constraint) {
+        self.add_arc(Arc::new(constraint));
+    }
+
+    /// 增加一个已共享的约束
+    pub fn add_arc(&mut self, constraint: Arc<dyn Constraint>) {
+        for (a, b) in constraint.peers() {
+            self.peers.add(a, b);
+        }
+        for (row, col) in constraint.cells() {
+            self.by_cell[row * 9 + col].push(self.constraints.len());
+        }
+        self.constraints.push(constraint);
+    }
+
+    /// 同组关系表
+    pub fn peers(&self) -> &Peers {
+        &self.peers
+    }
+
+    /// 是否只有标准规则（行、列、3x3 小格）
+    pub fn is_standard(&self) -> bool {
+        self.constraints.len() == 27
+            && self.parity.iter().flatten().all(Option::is_none)
+            && (0..9).all(|i| {
+                [Unit::row(i), Unit::col(i), Unit::block(i)]
+                    .iter()
+                    .all(|u| self.find::<Unit>().any(|v| v == u))
+            })
+    }
+
+    /// 所有约束
+    pub fn constraints(&self) -> &[Arc<dyn Constraint>] {
+        &self.constraints
+    }
+
+    /// 指定类型的约束
+    pub fn find<T: Constraint>(&self) -> impl Iterator<Item = &T> {
+        self.constraints
+            .iter()
+            .filter_map(|c| (c.as_ref() as &dyn Any).downcast_ref::<T>())
+    }
+
+    /// 某个位置能否填入该数字（不考虑同组关系）
+    pub fn allows(&self, row: usize, col: usize, digit: u32) -> bool {
+        self.parity[row][col].is_none_or(|p| p.allows(digit))
+    }
+
+    /// 在 (row, col) 填数后，涉及该位置的约束是否仍可能满足（同组关系除外）
+    pub fn consistent(&self, grid: &Grid, row: usize, col: usize) -> bool {
+        self.by_cell[row * 9 + col]
+            .iter()
+            .all(|i| self.constraints[*i].check(grid))
+    }
+
+    /// 已填数字是否满足所有约束
+    pub fn check(&self, grid: &Grid) -> bool {
+        self.constraints.iter().all(|c| c.check(grid))
+    }
+
+    /// 给定数字之间的冲突：先找出同组位置的重复数字，没有重复时再检查其余约束
+    pub fn conflicts(&self, grid: &Grid) -> Vec<Conflict> {
+        let mut conflicts = vec![];
+        for row in 0..9 {
+            for col in 0..9 {
+                let digit = grid[row][col];
+                if digit == 0 {
+                    continue;
+                }
+                let mut later: Vec<Cell> = self
+                    .peers
+                    .of(row, col)
+                    .iter()
+                    .filter(|&&(r, c)| (r, c) > (row, col) && grid[r][c] == digit)
+                    .cloned()
+                    .collect();
+                later.sort();
+                for b in later {
+                    conflicts.push(Conflict::Duplicate {
+                        digit,
+                        a: (row, col),
+                        b,
+                    });
+                }
+            }
+        }
+        if conflicts.is_empty() {
+            conflicts.extend(
+                self.constraints
+                    .iter()
+                    .filter(|c| !c.check(grid))
+                    .map(|c| Conflict::Violated(c.to_string())),
+            );
+        }
+        conflicts
+    }
+
+    /// XV 负约束：所有未标记的相邻两格之和既不是 10 也不是 5
+    pub fn add_negative_xv(&mut self) {
+        let marked: Vec<(Cell, Cell)> = self.find::<Pair>().map(|p| (p.a, p.b)).collect();
+        for row in 0..9 {
+            for col in 0..9 {
+                for b in [(row, col + 1), (row + 1, col)] {
+                    if b.0 > 8 || b.1 > 8 {
+                        continue;
+                    }
+                    let a = (row, col);
+                    if !marked.contains(&(a, b)) && !marked.contains(&(b, a)) {
+                        self.add(Pair {
+                            kind: PairKind::NotXv,
+                            a,
+                            b,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Conflict, ConstraintKind, Parity, Variant};
+    use crate::constraint::{Arrow, Pair};
+    use crate::json::Json;
+
+    #[test]
+    fn test_standard_peers() {
+        let variant = Variant::standard();
+        for row in 0..9 {
+            for col in 0..9 {
+                assert_eq!(variant.peers().of(row, col).len(), 20);
+            }
+        }
+        assert!(variant.peers().of(0, 0).contains(&(2, 2)));
+        assert!(!variant.peers().of(0, 0).contains(&(1, 3)));
+        assert_eq!(variant.constraints().len(), 27);
+    }
+
+    #[test]
+    fn test_anti_knight_peers() {
+        let variant = Variant::with_constraints(&[ConstraintKind::AntiKnight]);
+        let peers = variant.peers();
+        assert!(peers.of(0, 0).contains(&(1, 2)));
+        assert!(peers.of(1, 2).contains(&(0, 0)));
+        assert!(peers.of(4, 4).contains(&(2, 3)));
+        assert!(!peers.of(4, 4).contains(&(2, 2)));
+        // 中心位置的 8 个马步位置都不在标准同组内
+        assert_eq!(peers.of(4, 4).len(), 28);
+    }
+
+    #[test]
+    fn test_anti_king_peers() {
+        let variant = Variant::with_constraints(&[ConstraintKind::AntiKing]);
+        // 同一小格内的对角位置本已同组，跨小格的对角位置为新增
+        assert!(variant.peers().of(2, 2).contains(&(3, 3)));
+        assert!(!Variant::standard().peers().of(2, 2).contains(&(3, 3)));
+        assert_eq!(variant.peers().of(2, 2).len(), 23);
+
+        let both =
+            Variant::with_constraints(&[ConstraintKind::AntiKnight, ConstraintKind::AntiKing]);
+        assert!(both.peers().of(2, 2).contains(&(3, 3)));
+        assert!(both.peers().of(2, 2).contains(&(3, 4)));
+    }
+
+    #[test]
+    fn test_diagonal_peers() {
+        let variant = Variant::with_constraints(&[ConstraintKind::Diagonal]);
+        assert!(variant.peers().of(0, 0).contains(&(8, 8)));
+        assert!(variant.peers().of(0, 8).contains(&(8, 0)));
+        // 中心位置同时在两条对角线上
+        assert_eq!(variant.peers().of(4, 4).len(), 32);
+    }
+
+    #[test]
+    fn test_hyper_peers() {
+        let variant = Variant::with_constraints(&[ConstraintKind::Hyper]);
+        assert!(variant.peers().of(1, 1).contains(&(3, 3)));
+        assert!(!variant.peers().of(1, 1).contains(&(5, 5)));
+        // 窗口外的位置不增加同组位置
+        assert_eq!(variant.peers().of(0, 0).len(), 20);
+        assert!(!variant.is_standard());
+        assert!(Variant::standard().is_standard());
+    }
+
+    #[test]
+    fn test_parity() {
+        assert_eq!(Parity::from_char('o'), Some(Parity::Odd));
+        assert_eq!(Parity::from_char('E'), Some(Parity::Even));
+        assert_eq!(Parity::from_char('x'), None);
+
+        let mut variant = Variant::with_constraints(&[ConstraintKind::OddEven]);
+        variant.parity[0][0] = Some(Parity::Even);
+        assert!(variant.allows(0, 0, 4));
+        assert!(!variant.allows(0, 0, 5));
+        assert!(variant.allows(0, 1, 5));
+    }
+
+    #[test]
+    fn test_find_and_consistent() {
+        let mut variant = Variant::standard();
+        variant.add("r1c1-r1c2-r1c3".parse::<Arrow>().unwrap());
+        variant.add("x:r2c1-r2c2".parse::<Pair>().unwrap());
+        variant.add_negative_xv();
+        assert_eq!(variant.find::<Arrow>().count(), 1);
+        // 9x8x2 对相邻位置，其中一对已标记
+        assert_eq!(variant.find::<Pair>().count(), 144);
+
+        let mut grid = [[0; 9]; 9];
+        grid[0][0] = 5;
+        grid[0][1] = 6;
+        assert!(!variant.consistent(&grid, 0, 1));
+        grid[0][1] = 4;
+        assert!(variant.consistent(&grid, 0, 1));
+        // 未标记的相邻两格之和为 10
+        grid[1][0] = 5;
+        assert!(!variant.consistent(&grid, 1, 0));
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let mut grid = [[0; 9]; 9];
+        grid[2][1] = 5;
+        grid[2][6] = 5;
+        grid[0][0] = 7;
+        grid[1][2] = 7;
+        let conflicts = Variant::standard().conflicts(&grid);
+        let messages: Vec<String> = conflicts.iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "duplicate 7 in box 1 at r1c1 and r2c3",
+                "duplicate 5 in row 3 at c2 and c7"
+            ]
+        );
+        let details = Json::Object(conflicts[1].details());
+        assert_eq!(
+            details.to_string(),
+            r#"{"digit":5,"row":3,"col":2,"other_row":3,"other_col":7}"#
+        );
+
+        let mut grid = [[0; 9]; 9];
+        grid[0][0] = 3;
+        grid[8][8] = 3;
+        assert!(Variant::standard().conflicts(&grid).is_empty());
+        let diagonal = Variant::with_constraints(&[ConstraintKind::Diagonal]);
+        assert_eq!(
+            diagonal.conflicts(&grid),
+            [Conflict::Duplicate {
+                digit: 3,
+                a: (0, 0),
+                b: (8, 8)
+            }]
+        );
+        assert_eq!(
+            diagonal.conflicts(&grid)[0].to_string(),
+            "duplicate 3 at r1c1 and r9c9"
+        );
+
+        // 其余约束的冲突用 r1c1 描述
+        let mut negative = Variant::standard();
+        negative.add_negative_xv();
+        let mut grid = [[0; 9]; 9];
+        grid[0][0] = 1;
+        grid[0][1] = 9;
+        let conflicts = negative.conflicts(&grid);
+        assert_eq!(
+            conflicts[0].to_string(),
+            "givens violate constraint: no X or V between r1c1 and r1c2"
+        );
+        assert_eq!(
+            Json::Object(conflicts[0].details()).to_string(),
+            r#"{"constraint":"no X or V between r1c1 and r1c2"}"#
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! 终端中实时显示并行搜索
+//!
+//! 定时取某个分支的盘面，连同分支数、猜测数和解的个数重绘到终端。
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::solver::SearchProgress;
+use crate::theme::{Role, Theme};
+use crate::SudokuBoard;
+
+/// 重绘间隔
+const REFRESH: Duration = Duration::from_millis(100);
+
+/// 清屏并移到左上角
+const CLEAR: &str = "\x1b[H\x1b[2J";
+
+/// 下标数字，不用颜色时表示候选数字的个数
+const SUBSCRIPTS: [char; 10] = ['₀', '₁', '₂', '₃', '₄', '₅', '₆', '₇', '₈', '₉'];
+
+/// 一帧画面：已填的数字照常显示，空位显示候选数字的个数，有配色时以次要的样式显示，否则以下标显示
+pub fn render(
+    board: Option<&SudokuBoard>,
+    progress: &SearchProgress,
+    elapsed: Duration,
+    theme: Option<Theme>,
+) -> String {
+    let mut s = String::new();
+    for row in 0..9 {
+        if row % 3 == 0 {
+            s.push_str("+-------+-------+-------+\n");
+        }
+        for col in 0..9 {
+            if col % 3 == 0 {
+                s.push_str("| ");
+            }
+            match board.map(|board| board.get(row, col)) {
+                Some(pos) if pos.val != 0 => {
+                    let _ = write!(s, "{} ", pos.val);
+                }
+                Some(pos) if theme.is_some() => {
+                    let count = pos.digits.len();
+                    let _ = write!(s, "{} ", theme.unwrap().paint(Role::Muted, count));
+                }
+                Some(pos) => {
+                    let _ = write!(s, "{} ", SUBSCRIPTS[pos.digits.len().min(9)]);
+                }
+                None => s.push_str(". "),
+            }
+        }
+        s.push_str("|\n");
+    }
+    s.push_str("+-------+-------+-------+\n");
+    match theme {
+        Some(_) => s.push_str("faint digits are candidate counts of empty cells\n\n"),
+        None => s.push_str("subscript digits are candidate counts of empty cells\n\n"),
+    }
+    let load = |n: &AtomicUsize| n.load(Ordering::Relaxed);
+    let _ = writeln!(s, "elapsed    {:.1}s", elapsed.as_secs_f64());
+    let _ = writeln!(s, "branches   {}", load(&progress.active));
+    let _ = writeln!(s, "guesses    {}", load(&progress.guesses));
+    let _ = writeln!(s, "dead ends  {}", load(&progress.dead_ends));
+    let _ = writeln!(s, "solutions  {}", load(&progress.solutions));
+    s
+}
+
+/// 在 out 上重绘搜索进度，直到 done 置位，最后再画一帧
+pub fn run(
+    progress: &SearchProgress,
+    done: &AtomicBool,
+    out: &mut impl Write,
+    theme: Option<Theme>,
+) -> io::Result<()> {
+    let start = Instant::now();
+    let mut board = None;
+    loop {
+        // 没有分支交出新盘面时保留上一个
+        if let Some(latest) = progress.watch() {
+            board = Some(latest);
+        }
+        let finished = done.load(Ordering::Relaxed);
+        write!(
+            out,
+            "{}{}",
+            CLEAR,
+            render(board.as_ref(), progress, start.elapsed(), theme)
+        )?;
+        out.flush()?;
+        if finished {
+            return Ok(());
+        }
+        thread::sleep(REFRESH);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::{render, run};
+    use crate::board::parse_grid;
+    use crate::solver::{Propagating, SearchProgress};
+    use crate::theme::Theme;
+    use crate::{SudokuBoard, Variant};
+
+    #[test]
+    fn test_render() {
+        let progress = SearchProgress::default();
+        let frame = render(None, &progress, Duration::from_millis(1500), None);
+        assert!(frame.starts_with("+-------+-------+-------+\n| . . . | . . . | . . . |\n"));
+        assert!(frame.contains("elapsed    1.5s\n"));
+        assert!(frame.ends_with("solutions  0\n"));
+
+        let mut grid = parse_grid(&"0".repeat(81)).unwrap();
+        grid[0] = [1, 2, 3, 4, 5, 6, 7, 8, 0];
+        let board = SudokuBoard::new_with(&grid, Arc::new(Variant::standard()));
+        let frame = render(Some(&board), &progress, Duration::ZERO, Some(Theme::Dark));
+        assert!(frame.contains("| 1 2 3 | 4 5 6 | 7 8 \x1b[2m1\x1b[0m |\n"));
+        let light = render(Some(&board), &progress, Duration::ZERO, Some(Theme::Light));
+        assert!(light.contains("| 7 8 \x1b[90m1\x1b[0m |\n"));
+        let plain = render(Some(&board), &progress, Duration::ZERO, None);
+        assert!(plain.contains("| 1 2 3 | 4 5 6 | 7 8 ₁ |\n"));
+        assert!(!plain.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_run() {
+        let progress = SearchProgress::default();
+        let board = SudokuBoard::new_with(&[[0; 9]; 9], Arc::new(Variant::standard()));
+        let done = AtomicBool::new(false);
+        let mut out = vec![];
+        std::thread::scope(|s| {
+            s.spawn(|| run(&progress, &done, &mut out, None).unwrap());
+            // 空盘面需要猜测，搜索中有分支交出盘面
+            Propagating::default().solve_with(board, &|_| false, &progress);
+            done.store(true, Ordering::Relaxed);
+        });
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.ends_with("solutions  1\n"), "{}", out);
+        assert_eq!(progress.active.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,150 @@
+//! 供浏览器使用的接口
+//!
+//! 用 wasm-bindgen 导出 [`solve`]、[`generate`] 和 [`hint`]，返回 JavaScript 对象，失败时为
+//! 与 `--format json` 相同的错误对象。另有返回 JSON 文本的同名 `_json` 函数，供其他宿主使用。
+//! wasm32-unknown-unknown 上没有线程和系统时间，这里只使用单线程的求解。构建：
+//! `cargo rustc --release --lib --target wasm32-unknown-unknown --features wasm --crate-type cdylib`，
+//! 再用 `wasm-bindgen --target web` 生成 JavaScript 绑定。
+
+use std::sync::Arc;
+
+use clap::ValueEnum;
+
+use crate::board::{grid_line, parse_grid, Grid, SudokuBoard};
+use crate::generator::{generate as generate_puzzle, GenerateOptions};
+use crate::json::Json;
+use crate::rating::{rank_cells, Difficulty};
+use crate::rng::Rng;
+use crate::solver::{error_json, solutions};
+use crate::variant::Variant;
+use wasm_bindgen::prelude::*;
+
+/// 解析题目并检查给定数字是否冲突
+fn parse(puzzle: &str, variant: &Variant) -> Result<Grid, Json> {
+    let givens = parse_grid(puzzle).map_err(|e| error_json("parse", &e, vec![]))?;
+    match variant.conflicts(&givens).first() {
+        Some(conflict) => Err(error_json(
+            "conflict",
+            &conflict.to_string(),
+            conflict.details(),
+        )),
+        None => Ok(givens),
+    }
+}
+
+/// JSON 文本转为 JavaScript 对象
+fn to_js(json: &str) -> JsValue {
+    js_sys::JSON::parse(json).unwrap_or_else(|_| JsValue::from_str(json))
+}
+
+/// 求解 81 个数字（`0` 或 `.` 为空位）组成的标准数独，返回 `{solution, unique}`
+#[wasm_bindgen]
+pub fn solve(puzzle: &str) -> JsValue {
+    to_js(&solve_json(puzzle))
+}
+
+/// 生成一道指定难度的标准数独，种子取自 `Math.random()`
+#[wasm_bindgen]
+pub fn generate(difficulty: &str) -> JsValue {
+    let seed = (js_sys::Math::random() * u32::MAX as f64) as u64;
+    to_js(&generate_json(difficulty, seed))
+}
+
+/// 当前盘面上最容易填出的空位，返回 `{hint}`
+#[wasm_bindgen]
+pub fn hint(board: &str) -> JsValue {
+    to_js(&hint_json(board))
+}
+
+/// 求解 81 个数字（`0` 或 `.` 为空位）组成的标准数独，返回 `{"solution": ..., "unique": ...}`
+pub fn solve_json(puzzle: &str) -> String {
+    let variant = Arc::new(Variant::standard());
+    let givens = match parse(puzzle, &variant) {
+        Ok(givens) => givens,
+        Err(e) => return e.to_string(),
+    };
+    let found = solutions(&SudokuBoard::new_with(&givens, variant), 2);
+    match found.first() {
+        Some(solution) => Json::Object(vec![
+            ("solution".to_string(), grid_line(solution).into()),
+            ("unique".to_string(), (found.len() == 1).into()),
+        ]),
+        None => error_json("no_solution", "the puzzle has no solution", vec![]),
+    }
+    .to_string()
+}
+
+/// 生成一道指定难度（easy、medium、hard、expert、evil）的标准数独，返回题目、解、给定数字个数和难度
+pub fn generate_json(difficulty: &str, seed: u64) -> String {
+    let difficulty = match Difficulty::from_str(difficulty, true) {
+        Ok(difficulty) => difficulty,
+        Err(_) => {
+            let message = format!("unknown difficulty `{}`", difficulty);
+            return error_json("parse", &message, vec![]).to_string();
+        }
+    };
+    let options = GenerateOptions {
+        difficulty: Some(difficulty),
+        ..Default::default()
+    };
+    let variant = Arc::new(Variant::standard());
+    match generate_puzzle(variant, &options, &mut Rng::new(seed)) {
+        Some(generated) => generated.to_json(),
+        None => error_json("no_puzzle", "no puzzle satisfies the options", vec![]),
+    }
+    .to_string()
+}
+
+/// 当前盘面上最容易填出的空位，返回 `{"hint": ...}`，没有空位时为 null
+pub fn hint_json(board: &str) -> String {
+    let variant = Variant::standard();
+    let grid = match parse(board, &variant) {
+        Ok(grid) => grid,
+        Err(e) => return e.to_string(),
+    };
+    let hint = rank_cells(&grid, &variant).first().map(|h| h.to_json());
+    Json::Object(vec![("hint".to_string(), hint.unwrap_or(Json::Null))]).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_json as generate, hint_json as hint, solve_json as solve};
+    use crate::json::Json;
+
+    const PUZZLE: &str =
+        "530070000600195000098000060800060003400803001700020006060000280000419005000080079";
+
+    #[test]
+    fn test_api() {
+        let solved = Json::parse(&solve(PUZZLE)).unwrap();
+        assert_eq!(
+            solved.get("solution").and_then(Json::as_str),
+            Some(
+                "534678912672195348198342567859761423426853791713924856961537284287419635345286179"
+            )
+        );
+        assert_eq!(solved.get("unique").and_then(Json::as_bool), Some(true));
+        let error = Json::parse(&solve(&PUZZLE.replacen("53", "55", 1))).unwrap();
+        let kind = error.get("error").and_then(|e| e.get("kind"));
+        assert_eq!(kind.and_then(Json::as_str), Some("conflict"));
+        assert!(solve("123").contains("\"parse\""));
+
+        let generated = Json::parse(&generate("easy", 7)).unwrap();
+        assert_eq!(generated, Json::parse(&generate("Easy", 7)).unwrap());
+        assert_eq!(
+            generated.get("difficulty").and_then(Json::as_str),
+            Some("easy")
+        );
+        assert!(generate("trivial", 7).contains("unknown difficulty"));
+
+        let hinted = Json::parse(&hint(PUZZLE)).unwrap();
+        let first = hinted.get("hint").unwrap();
+        assert!(first.get("digit").and_then(Json::as_u32).is_some());
+        assert_eq!(
+            hint(
+                "534678912672195348198342567859761423426853791713924856961537284287419635345286179"
+            ),
+            r#"{"hint":null}"#
+        );
+    }
+}
@@ -0,0 +1,63 @@
+//! 命令行测试
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// 推理即可发现第 8 行无处可填 3 的题目
+const UNIT_CONTRADICTION: &str =
+    "000000000000000040000900000070000000000290060000000030130000070800000406600030000";
+
+/// 从标准输入读入 input 运行 superdo，返回标准输出
+fn superdo(args: &[&str], input: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_superdo"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_count_limit_contradiction() {
+    let output = superdo(
+        &["--count-limit", "2", "--format", "json"],
+        &format!("{}\n", UNIT_CONTRADICTION),
+    );
+    assert_eq!(output.lines().next(), Some(r#"{"count":0,"exact":true}"#));
+}
+
+#[test]
+fn test_unique() {
+    let unique =
+        "530070000600195000098000060800060003400803001700020006060000280000419005000080079";
+    let output = superdo(
+        &["--unique", "--limit", "1", "--format", "json"],
+        &format!("{}\n", unique),
+    );
+    assert_eq!(output.lines().nth(1), Some(r#"{"unique":true}"#));
+
+    // 清空最后一行的 7 和 9 后有两个解
+    let several =
+        "530070000600195000098000060800060003400803001700020006060000280000419005000080000";
+    let output = superdo(
+        &["--unique", "--limit", "1", "--format", "json"],
+        &format!("{}\n", several),
+    );
+    assert_eq!(output.lines().nth(1), Some(r#"{"unique":false}"#));
+
+    // 矛盾的题目没有解，也就不输出是否唯一
+    let output = superdo(
+        &["--unique", "--limit", "1", "--format", "json"],
+        &format!("{}\n", UNIT_CONTRADICTION),
+    );
+    assert!(!output.contains("unique"));
+}